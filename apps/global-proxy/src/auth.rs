@@ -0,0 +1,607 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use http::{
+    HeaderValue, Method, Request, Response, StatusCode, Uri,
+    header::{self, COOKIE},
+};
+use hyper::{Body, Client, body};
+use hyper_rustls::HttpsConnectorBuilder;
+use ring::{
+    hmac,
+    rand::{SecureRandom, SystemRandom},
+    signature::{self, RsaPublicKeyComponents, UnparsedPublicKey},
+};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// Query parameter carrying a signed access token.
+pub const TOKEN_PARAM: &str = "cmux_token";
+/// Cookie holding the proxy session minted from an access token.
+pub const SESSION_COOKIE: &str = "cmux_proxy_session";
+
+/// Clock skew tolerated when checking `exp` and `nbf`.
+const LEEWAY_SECS: u64 = 30;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AuthError {
+    #[error("malformed token")]
+    Malformed,
+    #[error("unsupported algorithm {0}")]
+    UnsupportedAlgorithm(String),
+    #[error("no key matches the token")]
+    UnknownKey,
+    #[error("invalid signature")]
+    BadSignature,
+    #[error("token expired")]
+    Expired,
+    #[error("token not yet valid")]
+    NotYetValid,
+    #[error("invalid key set: {0}")]
+    InvalidKeySet(String),
+    #[error("failed to fetch key set: {0}")]
+    Fetch(String),
+}
+
+/// Claims the proxy understands. `sandbox` names the VM the token grants
+/// access to: the morph id for `port-` and `cmux-` routes, the VM slug for
+/// workspace routes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claims {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    pub sandbox: String,
+    pub exp: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<u64>,
+}
+
+/// Checks access tokens presented to the proxy.
+pub trait TokenVerifier: Send + Sync {
+    fn verify(&self, token: &str) -> Result<Claims, AuthError>;
+}
+
+/// Whether a port can be reached without a token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Public,
+    Private,
+}
+
+#[derive(Clone)]
+pub struct AuthConfig {
+    pub verifier: Arc<dyn TokenVerifier>,
+    /// Key for the session cookies the proxy issues.
+    pub session_key: Vec<u8>,
+    pub session_ttl: Duration,
+    pub default_access: Access,
+    /// Per-port overrides of `default_access`.
+    pub port_access: HashMap<u16, Access>,
+}
+
+impl AuthConfig {
+    /// Every port private, 12 hour sessions signed with a random key.
+    pub fn new(verifier: Arc<dyn TokenVerifier>) -> Self {
+        let mut session_key = vec![0u8; 32];
+        SystemRandom::new()
+            .fill(&mut session_key)
+            .expect("system random source");
+        Self {
+            verifier,
+            session_key,
+            session_ttl: Duration::from_secs(12 * 60 * 60),
+            default_access: Access::Private,
+            port_access: HashMap::new(),
+        }
+    }
+
+    fn access(&self, port: u16) -> Access {
+        self.port_access
+            .get(&port)
+            .copied()
+            .unwrap_or(self.default_access)
+    }
+
+    /// Decides whether `req` may reach `port` on `sandbox`. Valid requests are
+    /// stripped of the token and session cookie before being forwarded.
+    pub(crate) fn check(&self, req: &mut Request<Body>, sandbox: &str, port: u16) -> Decision {
+        if self.access(port) == Access::Public {
+            return Decision::Forward;
+        }
+
+        if let Some((token, rest)) = take_query_param(req.uri(), TOKEN_PARAM) {
+            let claims = match self.verifier.verify(&token) {
+                Ok(claims) if claims.sandbox == sandbox => claims,
+                Ok(_) => return Decision::Respond(denied("Token is not valid for this sandbox")),
+                Err(err) => {
+                    warn!(%err, "rejected access token");
+                    return Decision::Respond(denied("Invalid access token"));
+                }
+            };
+            let path_and_query = match rest {
+                Some(query) => format!("{}?{}", req.uri().path(), query),
+                None => req.uri().path().to_string(),
+            };
+            // Browsers get the session cookie and are sent back to the clean
+            // URL; anything else (websockets, API calls) just goes through.
+            if *req.method() == Method::GET && !is_upgrade(req) {
+                return Decision::Respond(self.session_redirect(&claims, &path_and_query));
+            }
+            if let Ok(uri) = path_and_query.parse::<Uri>() {
+                *req.uri_mut() = uri;
+            }
+            strip_session_cookie(req);
+            return Decision::Forward;
+        }
+
+        let session = session_cookie(req)
+            .and_then(|value| verify_hs256(&self.session_key, &value).ok())
+            .filter(|claims| claims.sandbox == sandbox);
+        if session.is_some() {
+            strip_session_cookie(req);
+            return Decision::Forward;
+        }
+
+        Decision::Respond(denied("Authentication required"))
+    }
+
+    fn session_redirect(&self, claims: &Claims, location: &str) -> Response<Body> {
+        let ttl = self.session_ttl.as_secs();
+        let session = Claims {
+            sub: claims.sub.clone(),
+            sandbox: claims.sandbox.clone(),
+            exp: now() + ttl,
+            nbf: None,
+        };
+        let cookie = format!(
+            "{}={}; Path=/; Max-Age={}; HttpOnly; Secure; SameSite=Lax",
+            SESSION_COOKIE,
+            sign_hs256(&self.session_key, &session),
+            ttl
+        );
+        Response::builder()
+            .status(StatusCode::FOUND)
+            .header(header::LOCATION, location)
+            .header(header::SET_COOKIE, cookie)
+            .header(header::CACHE_CONTROL, "no-store")
+            .body(Body::empty())
+            .unwrap()
+    }
+}
+
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("session_ttl", &self.session_ttl)
+            .field("default_access", &self.default_access)
+            .field("port_access", &self.port_access)
+            .finish_non_exhaustive()
+    }
+}
+
+pub(crate) enum Decision {
+    Forward,
+    Respond(Response<Body>),
+}
+
+/// Verifies tokens against a fixed set of keys.
+pub struct StaticKeyVerifier {
+    keys: Vec<Key>,
+}
+
+impl StaticKeyVerifier {
+    /// HS256 tokens signed with a shared secret.
+    pub fn hs256(secret: &[u8]) -> Self {
+        Self {
+            keys: vec![Key {
+                kid: None,
+                kind: KeyKind::Hmac(secret.to_vec()),
+            }],
+        }
+    }
+
+    /// Keys from a JWKS document (`{"keys": [...]}`).
+    pub fn from_jwks(json: &str) -> Result<Self, AuthError> {
+        Ok(Self {
+            keys: parse_jwks(json)?,
+        })
+    }
+}
+
+impl TokenVerifier for StaticKeyVerifier {
+    fn verify(&self, token: &str) -> Result<Claims, AuthError> {
+        verify_with(&self.keys, token)
+    }
+}
+
+/// Verifies tokens against a JWKS endpoint, refreshed in the background.
+pub struct JwksVerifier {
+    url: Uri,
+    keys: RwLock<Vec<Key>>,
+}
+
+impl JwksVerifier {
+    /// Fetches the key set once; call `spawn_refresh` to keep it current.
+    pub async fn fetch(url: Uri) -> Result<Self, AuthError> {
+        let keys = fetch_jwks(&url).await?;
+        Ok(Self {
+            url,
+            keys: RwLock::new(keys),
+        })
+    }
+
+    /// Refetches the key set every `interval`, keeping the old keys when a
+    /// fetch fails.
+    pub fn spawn_refresh(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                match fetch_jwks(&self.url).await {
+                    // The key set is only ever swapped whole, so a poisoned
+                    // lock still guards a consistent value
+                    Ok(keys) => *self.keys.write().unwrap_or_else(|e| e.into_inner()) = keys,
+                    Err(err) => warn!(%err, url = %self.url, "failed to refresh JWKS"),
+                }
+            }
+        })
+    }
+}
+
+impl TokenVerifier for JwksVerifier {
+    fn verify(&self, token: &str) -> Result<Claims, AuthError> {
+        verify_with(&self.keys.read().unwrap_or_else(|e| e.into_inner()), token)
+    }
+}
+
+async fn fetch_jwks(url: &Uri) -> Result<Vec<Key>, AuthError> {
+    let https = HttpsConnectorBuilder::new()
+        .with_webpki_roots()
+        .https_or_http()
+        .enable_http1()
+        .build();
+    let client: Client<_, Body> = Client::builder().build(https);
+    let response = client
+        .get(url.clone())
+        .await
+        .map_err(|err| AuthError::Fetch(err.to_string()))?;
+    if !response.status().is_success() {
+        return Err(AuthError::Fetch(format!("status {}", response.status())));
+    }
+    let bytes = body::to_bytes(response.into_body())
+        .await
+        .map_err(|err| AuthError::Fetch(err.to_string()))?;
+    let json = std::str::from_utf8(&bytes)
+        .map_err(|_| AuthError::InvalidKeySet("not UTF-8".to_string()))?;
+    parse_jwks(json)
+}
+
+struct Key {
+    kid: Option<String>,
+    kind: KeyKind,
+}
+
+enum KeyKind {
+    Hmac(Vec<u8>),
+    Rsa {
+        n: Vec<u8>,
+        e: Vec<u8>,
+    },
+    /// Uncompressed P-256 point.
+    EcP256(Vec<u8>),
+    Ed25519(Vec<u8>),
+}
+
+impl KeyKind {
+    fn algorithm(&self) -> &'static str {
+        match self {
+            KeyKind::Hmac(_) => "HS256",
+            KeyKind::Rsa { .. } => "RS256",
+            KeyKind::EcP256(_) => "ES256",
+            KeyKind::Ed25519(_) => "EdDSA",
+        }
+    }
+
+    fn verify(&self, message: &[u8], sig: &[u8]) -> bool {
+        match self {
+            KeyKind::Hmac(secret) => {
+                let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+                hmac::verify(&key, message, sig).is_ok()
+            }
+            KeyKind::Rsa { n, e } => RsaPublicKeyComponents { n, e }
+                .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, sig)
+                .is_ok(),
+            KeyKind::EcP256(point) => {
+                UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                    .verify(message, sig)
+                    .is_ok()
+            }
+            KeyKind::Ed25519(public) => UnparsedPublicKey::new(&signature::ED25519, public)
+                .verify(message, sig)
+                .is_ok(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    alg: Option<String>,
+    crv: Option<String>,
+    k: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+fn parse_jwks(json: &str) -> Result<Vec<Key>, AuthError> {
+    let jwks: Jwks =
+        serde_json::from_str(json).map_err(|err| AuthError::InvalidKeySet(err.to_string()))?;
+    let mut keys = Vec::new();
+    for jwk in jwks.keys {
+        let param = |value: &Option<String>, name: &str| {
+            value
+                .as_deref()
+                .and_then(|v| URL_SAFE_NO_PAD.decode(v).ok())
+                .ok_or_else(|| {
+                    AuthError::InvalidKeySet(format!("{} key without {}", jwk.kty, name))
+                })
+        };
+        let kind = match (jwk.kty.as_str(), jwk.crv.as_deref()) {
+            ("oct", _) => KeyKind::Hmac(param(&jwk.k, "k")?),
+            ("RSA", _) => KeyKind::Rsa {
+                n: param(&jwk.n, "n")?,
+                e: param(&jwk.e, "e")?,
+            },
+            ("EC", Some("P-256")) => {
+                let mut point = vec![0x04];
+                point.extend(param(&jwk.x, "x")?);
+                point.extend(param(&jwk.y, "y")?);
+                KeyKind::EcP256(point)
+            }
+            ("OKP", Some("Ed25519")) => KeyKind::Ed25519(param(&jwk.x, "x")?),
+            // Keys we can't use (other curves, encryption keys) are skipped.
+            _ => continue,
+        };
+        if jwk
+            .alg
+            .as_deref()
+            .is_some_and(|alg| alg != kind.algorithm())
+        {
+            continue;
+        }
+        keys.push(Key { kid: jwk.kid, kind });
+    }
+    Ok(keys)
+}
+
+#[derive(Deserialize)]
+struct JwsHeader {
+    alg: String,
+    kid: Option<String>,
+}
+
+fn verify_with(keys: &[Key], token: &str) -> Result<Claims, AuthError> {
+    let mut parts = token.split('.');
+    let (Some(header), Some(payload), Some(sig), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(AuthError::Malformed);
+    };
+    let decode = |part: &str| {
+        URL_SAFE_NO_PAD
+            .decode(part)
+            .map_err(|_| AuthError::Malformed)
+    };
+    let jws: JwsHeader =
+        serde_json::from_slice(&decode(header)?).map_err(|_| AuthError::Malformed)?;
+    if !matches!(jws.alg.as_str(), "HS256" | "RS256" | "ES256" | "EdDSA") {
+        return Err(AuthError::UnsupportedAlgorithm(jws.alg));
+    }
+
+    let message = &token[..header.len() + 1 + payload.len()];
+    let sig = decode(sig)?;
+    let mut candidates = keys
+        .iter()
+        .filter(|key| key.kind.algorithm() == jws.alg)
+        .filter(|key| jws.kid.is_none() || key.kid.is_none() || key.kid == jws.kid)
+        .peekable();
+    if candidates.peek().is_none() {
+        return Err(AuthError::UnknownKey);
+    }
+    if !candidates.any(|key| key.kind.verify(message.as_bytes(), &sig)) {
+        return Err(AuthError::BadSignature);
+    }
+
+    let claims: Claims =
+        serde_json::from_slice(&decode(payload)?).map_err(|_| AuthError::Malformed)?;
+    let now = now();
+    if claims.exp + LEEWAY_SECS <= now {
+        return Err(AuthError::Expired);
+    }
+    if claims.nbf.is_some_and(|nbf| nbf > now + LEEWAY_SECS) {
+        return Err(AuthError::NotYetValid);
+    }
+    Ok(claims)
+}
+
+fn verify_hs256(secret: &[u8], token: &str) -> Result<Claims, AuthError> {
+    verify_with(
+        &[Key {
+            kid: None,
+            kind: KeyKind::Hmac(secret.to_vec()),
+        }],
+        token,
+    )
+}
+
+/// Signs `claims` as an HS256 JWT.
+pub fn sign_hs256(secret: &[u8], claims: &Claims) -> String {
+    let header = URL_SAFE_NO_PAD.encode(br#"{"alg":"HS256","typ":"JWT"}"#);
+    let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims).expect("claims serialize"));
+    let message = format!("{}.{}", header, payload);
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+    let sig = URL_SAFE_NO_PAD.encode(hmac::sign(&key, message.as_bytes()));
+    format!("{}.{}", message, sig)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn denied(message: &str) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .header("content-type", "text/plain")
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from(message.to_string()))
+        .unwrap()
+}
+
+fn is_upgrade(req: &Request<Body>) -> bool {
+    req.headers().contains_key(header::UPGRADE) || req.headers().contains_key("sec-websocket-key")
+}
+
+/// Removes `name` from the query string, returning its (undecoded) value and
+/// the remaining query, if any.
+fn take_query_param(uri: &Uri, name: &str) -> Option<(String, Option<String>)> {
+    let query = uri.query()?;
+    let mut value = None;
+    let rest: Vec<&str> = query
+        .split('&')
+        .filter(|pair| match pair.split_once('=') {
+            Some((key, v)) if key == name => {
+                value.get_or_insert_with(|| v.to_string());
+                false
+            }
+            _ => true,
+        })
+        .collect();
+    let value = value?;
+    let rest = (!rest.is_empty()).then(|| rest.join("&"));
+    Some((value, rest))
+}
+
+fn session_cookie(req: &Request<Body>) -> Option<String> {
+    req.headers()
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|pair| {
+            let (name, value) = pair.trim().split_once('=')?;
+            (name == SESSION_COOKIE).then(|| value.to_string())
+        })
+}
+
+/// Keeps the proxy's session cookie away from the sandbox.
+fn strip_session_cookie(req: &mut Request<Body>) {
+    let others: Vec<String> = req
+        .headers()
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .map(str::trim)
+        .filter(|pair| {
+            !pair.is_empty() && pair.split_once('=').map(|(n, _)| n) != Some(SESSION_COOKIE)
+        })
+        .map(str::to_string)
+        .collect();
+    req.headers_mut().remove(COOKIE);
+    if !others.is_empty()
+        && let Ok(value) = HeaderValue::from_str(&others.join("; "))
+    {
+        req.headers_mut().insert(COOKIE, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    fn claims(exp: u64) -> Claims {
+        Claims {
+            sub: Some("user".to_string()),
+            sandbox: "abc".to_string(),
+            exp,
+            nbf: None,
+        }
+    }
+
+    #[test]
+    fn hs256_tokens_round_trip() {
+        let verifier = StaticKeyVerifier::hs256(b"secret");
+        let token = sign_hs256(b"secret", &claims(now() + 60));
+        assert_eq!(verifier.verify(&token).unwrap(), claims(now() + 60));
+
+        let forged = sign_hs256(b"other", &claims(now() + 60));
+        assert!(matches!(
+            verifier.verify(&forged),
+            Err(AuthError::BadSignature)
+        ));
+        let expired = sign_hs256(b"secret", &claims(now() - 120));
+        assert!(matches!(verifier.verify(&expired), Err(AuthError::Expired)));
+        assert!(matches!(verifier.verify("a.b"), Err(AuthError::Malformed)));
+    }
+
+    #[test]
+    fn jwks_ed25519_keys_verify_tokens() {
+        let rng = SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let jwks = format!(
+            r#"{{"keys":[{{"kty":"OKP","crv":"Ed25519","kid":"k1","x":"{}"}},{{"kty":"EC","crv":"P-384","x":"","y":""}}]}}"#,
+            URL_SAFE_NO_PAD.encode(pair.public_key().as_ref())
+        );
+        let verifier = StaticKeyVerifier::from_jwks(&jwks).unwrap();
+
+        let sign = |kid: &str| {
+            let header = URL_SAFE_NO_PAD.encode(format!(r#"{{"alg":"EdDSA","kid":"{}"}}"#, kid));
+            let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims(now() + 60)).unwrap());
+            let message = format!("{}.{}", header, payload);
+            let sig = URL_SAFE_NO_PAD.encode(pair.sign(message.as_bytes()));
+            format!("{}.{}", message, sig)
+        };
+        assert_eq!(verifier.verify(&sign("k1")).unwrap().sandbox, "abc");
+        assert!(matches!(
+            verifier.verify(&sign("k2")),
+            Err(AuthError::UnknownKey)
+        ));
+        // An HS256 token can't be verified with a public key.
+        let hs = sign_hs256(b"x", &claims(now() + 60));
+        assert!(matches!(verifier.verify(&hs), Err(AuthError::UnknownKey)));
+    }
+
+    #[test]
+    fn query_param_is_taken_out() {
+        let uri: Uri = "/a?x=1&cmux_token=t&y=2".parse().unwrap();
+        assert_eq!(
+            take_query_param(&uri, TOKEN_PARAM),
+            Some(("t".to_string(), Some("x=1&y=2".to_string())))
+        );
+        let uri: Uri = "/a?cmux_token=t".parse().unwrap();
+        assert_eq!(
+            take_query_param(&uri, TOKEN_PARAM),
+            Some(("t".to_string(), None))
+        );
+        let uri: Uri = "/a?x=1".parse().unwrap();
+        assert_eq!(take_query_param(&uri, TOKEN_PARAM), None);
+    }
+}
@@ -1,12 +1,20 @@
 use std::{
-    io::{self, Cursor, Read},
+    cell::RefCell,
+    collections::HashMap,
+    io::{self, Write},
     net::SocketAddr,
-    sync::Arc,
+    path::PathBuf,
+    rc::Rc,
+    sync::{Arc, RwLock},
+    time::Instant,
 };
 
-use brotli::Decompressor;
+use brotli::{CompressorWriter, DecompressorWriter};
 use bytes::Bytes;
-use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::{
+    Compression,
+    write::{GzDecoder, GzEncoder, ZlibDecoder, ZlibEncoder},
+};
 use http::{
     HeaderMap, Method, Request, Response, StatusCode, Uri, Version,
     header::{self, CONNECTION, HeaderValue, UPGRADE},
@@ -14,24 +22,34 @@ use http::{
 };
 use hyper::upgrade::Upgraded;
 use hyper::{
-    Body, Client, body,
+    Body, Client,
+    body::{self, HttpBody},
     client::HttpConnector,
     server::conn::AddrStream,
     service::{make_service_fn, service_fn},
 };
 use hyper_rustls::HttpsConnectorBuilder;
-use lol_html::{HtmlRewriter, Settings, element, html_content::ContentType};
+use lol_html::{HtmlRewriter, OutputSink, Settings, element, html_content::ContentType};
 use tokio::{
     io::{AsyncWriteExt, copy_bidirectional},
-    sync::oneshot,
+    runtime::Handle,
+    sync::{mpsc, oneshot},
     task::JoinHandle,
 };
 use tracing::{error, warn};
-use zstd::stream::read::Decoder as ZstdDecoder;
+use zstd::stream::write::{Decoder as ZstdDecoder, Encoder as ZstdEncoder};
 
 use chrono::Utc;
 use serde_json::{Value, json};
 
+pub mod auth;
+pub mod metrics;
+pub mod routing;
+
+use auth::{AuthConfig, Decision};
+use metrics::{Metrics, NO_ROUTE, OTHER_PORT};
+use routing::{OptionsPolicy, Resolution, ResolvedRoute, RoutingConfig, RoutingError};
+
 type HttpClient = Client<hyper_rustls::HttpsConnector<HttpConnector>, Body>;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -40,8 +58,8 @@ const GIT_COMMIT: &str = match option_env!("GIT_COMMIT") {
     None => "unknown",
 };
 
-const CSP_FRAME_ANCESTORS_PORT_39378: &str = "frame-ancestors 'self' https://cmux.local http://cmux.local https://www.cmux.sh https://cmux.sh https://www.cmux.dev https://cmux.dev http://localhost:5173;";
 const FORWARD_ALL_WEBSOCKET_HEADERS: bool = true;
+const REQUEST_ID_HEADER: &str = "x-request-id";
 
 #[derive(Clone, Debug)]
 pub struct ProxyConfig {
@@ -50,6 +68,17 @@ pub struct ProxyConfig {
     pub backend_scheme: Scheme,
     pub morph_domain_suffix: Option<String>,
     pub workspace_domain_suffix: Option<String>,
+    /// Require a token or session for proxied routes; `None` leaves them open.
+    pub auth: Option<AuthConfig>,
+    /// Subdomain routing rules, used when `routes_file` is unset; `None` uses
+    /// the built-in rules.
+    pub routes: Option<RoutingConfig>,
+    /// JSON routing rules, reloaded whenever the file changes.
+    pub routes_file: Option<PathBuf>,
+    /// Write one JSON line per request to stdout.
+    pub access_log: bool,
+    /// Bearer token required for `/metrics`; `None` disables the endpoint.
+    pub metrics_token: Option<String>,
 }
 
 impl Default for ProxyConfig {
@@ -60,6 +89,11 @@ impl Default for ProxyConfig {
             backend_scheme: Scheme::HTTP,
             morph_domain_suffix: None,
             workspace_domain_suffix: None,
+            auth: None,
+            routes: None,
+            routes_file: None,
+            access_log: false,
+            metrics_token: None,
         }
     }
 }
@@ -68,6 +102,7 @@ pub struct ProxyHandle {
     pub addr: SocketAddr,
     shutdown: Option<oneshot::Sender<()>>,
     task: JoinHandle<()>,
+    reload_task: Option<JoinHandle<()>>,
 }
 
 impl ProxyHandle {
@@ -75,6 +110,9 @@ impl ProxyHandle {
         if let Some(tx) = self.shutdown.take() {
             let _ = tx.send(());
         }
+        if let Some(reload_task) = self.reload_task.take() {
+            reload_task.abort();
+        }
         let _ = self.task.await;
     }
 }
@@ -85,16 +123,42 @@ pub enum ProxyError {
     Io(#[from] std::io::Error),
     #[error("hyper error: {0}")]
     Hyper(#[from] hyper::Error),
+    #[error("routing error: {0}")]
+    Routing(#[from] RoutingError),
 }
 
 struct AppState {
     client: HttpClient,
     backend_host: String,
     backend_scheme: Scheme,
-    morph_domain_suffix: Option<String>,
-    workspace_domain_suffix: Option<String>,
+    auth: Option<AuthConfig>,
+    routes: Arc<RwLock<Arc<RoutingConfig>>>,
+    /// Domain suffixes available to route templates.
+    route_vars: HashMap<&'static str, String>,
+    metrics: Arc<Metrics>,
+    metrics_token: Option<String>,
+    access_log: bool,
 }
 
+/// The route a request resolved to, attached to the request for the
+/// forwarding code and to the response for metrics and access logs.
+#[derive(Clone)]
+struct RouteInfo {
+    route: String,
+    port: u16,
+    upstream: String,
+}
+
+/// Marks responses generated because the upstream could not be reached.
+#[derive(Clone, Copy)]
+struct UpstreamFailure;
+
+/// Marks responses that came from the upstream, as opposed to ones the proxy
+/// made up (auth rejections, upstream failures). Only these are labelled with
+/// their port, since the port comes straight from the client's host name.
+#[derive(Clone, Copy)]
+struct FromUpstream;
+
 pub async fn spawn_proxy(config: ProxyConfig) -> Result<ProxyHandle, ProxyError> {
     let listener = std::net::TcpListener::bind(config.bind_addr)?;
     listener.set_nonblocking(true)?;
@@ -107,12 +171,36 @@ pub async fn spawn_proxy(config: ProxyConfig) -> Result<ProxyHandle, ProxyError>
         .build();
     let client: HttpClient = Client::builder().build(https);
 
+    let routes = match config.routes_file.as_deref() {
+        Some(path) => RoutingConfig::load(path)?,
+        None => match config.routes {
+            Some(routes) => routes,
+            None => RoutingConfig::builtin()?,
+        },
+    };
+    let routes = Arc::new(RwLock::new(Arc::new(routes)));
+    let reload_task = config
+        .routes_file
+        .map(|path| routing::spawn_reloader(path, routes.clone()));
+
+    let route_vars = [
+        ("morph_domain_suffix", config.morph_domain_suffix),
+        ("workspace_domain_suffix", config.workspace_domain_suffix),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some((name, value?)))
+    .collect();
+
     let state = Arc::new(AppState {
         client,
         backend_host: config.backend_host,
         backend_scheme: config.backend_scheme,
-        morph_domain_suffix: config.morph_domain_suffix,
-        workspace_domain_suffix: config.workspace_domain_suffix,
+        auth: config.auth,
+        routes,
+        route_vars,
+        metrics: Arc::new(Metrics::new()),
+        metrics_token: config.metrics_token,
+        access_log: config.access_log,
     });
 
     let make_svc = make_service_fn(move |_conn: &AddrStream| {
@@ -140,10 +228,122 @@ pub async fn spawn_proxy(config: ProxyConfig) -> Result<ProxyHandle, ProxyError>
         addr: local_addr,
         shutdown: Some(shutdown_tx),
         task,
+        reload_task,
     })
 }
 
-async fn handle_request(state: Arc<AppState>, req: Request<Body>) -> Response<Body> {
+async fn handle_request(state: Arc<AppState>, mut req: Request<Body>) -> Response<Body> {
+    let started = Instant::now();
+    let request_id = request_id(req.headers());
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        req.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    let method = req.method().clone();
+    let host = extract_host(&req);
+    let path = req.uri().path().to_string();
+    let upgrade = req
+        .headers()
+        .get(UPGRADE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_ascii_lowercase());
+
+    let mut response = route_request(state.clone(), req).await;
+
+    let info = response.extensions().get::<RouteInfo>().cloned();
+    let (route, port) = match &info {
+        Some(info) if response.extensions().get::<FromUpstream>().is_some() => {
+            (info.route.as_str(), info.port.to_string())
+        }
+        Some(info) => (info.route.as_str(), OTHER_PORT.to_string()),
+        None => (NO_ROUTE, String::new()),
+    };
+    let elapsed = started.elapsed();
+    let status = response.status();
+    state
+        .metrics
+        .observe_request(route, &port, status.as_u16(), elapsed);
+    if response.extensions().get::<UpstreamFailure>().is_some() {
+        state.metrics.upstream_error(route);
+    }
+    if let Some(info) = &info
+        && status != StatusCode::SWITCHING_PROTOCOLS
+        && !response.body().is_end_stream()
+    {
+        let metrics = state.metrics.clone();
+        let route = info.route.clone();
+        let body = std::mem::take(response.body_mut());
+        *response.body_mut() = count_body(body, move |n| metrics.bytes_downstream(&route, n));
+    }
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    if state.access_log {
+        let line = json!({
+            "timestamp": Utc::now().to_rfc3339(),
+            "request_id": request_id,
+            "method": method.as_str(),
+            "host": host,
+            "path": path,
+            "route": info.as_ref().map(|info| info.route.as_str()),
+            "port": info.as_ref().map(|info| info.port),
+            "upstream": info.as_ref().map(|info| info.upstream.as_str()),
+            "status": status.as_u16(),
+            "duration_ms": elapsed.as_secs_f64() * 1000.0,
+            "upgrade": upgrade,
+        });
+        println!("{}", line);
+    }
+
+    response
+}
+
+/// Keeps a client-supplied request id if it is reasonable, otherwise mints one.
+fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= 200)
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Reports the size of each body chunk as it streams through.
+fn count_body(body: Body, count: impl Fn(u64) + Send + Sync + 'static) -> Body {
+    Body::wrap_stream(futures_util::StreamExt::inspect(body, move |chunk| {
+        if let Ok(chunk) = chunk {
+            count(chunk.len() as u64);
+        }
+    }))
+}
+
+/// `/metrics`, for callers presenting the metrics token.
+fn metrics_response(state: &AppState, req: &Request<Body>) -> Response<Body> {
+    let Some(token) = state.metrics_token.as_deref() else {
+        return text_response(StatusCode::NOT_FOUND, "Not found");
+    };
+    let presented = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !constant_time_eq(presented.trim().as_bytes(), token.as_bytes()) {
+        return text_response(StatusCode::UNAUTHORIZED, "Missing or invalid metrics token");
+    }
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/plain; version=0.0.4")
+        .body(Body::from(state.metrics.render()))
+        .unwrap()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn route_request(state: Arc<AppState>, req: Request<Body>) -> Response<Body> {
     if req.uri().path() == "/health" {
         return json_response(
             StatusCode::OK,
@@ -164,11 +364,12 @@ async fn handle_request(state: Arc<AppState>, req: Request<Body>) -> Response<Bo
         }
     };
 
-    if req.uri().path() == "/version" {
+    if matches!(req.uri().path(), "/version" | "/metrics") {
         match parse_cmux_host(&host) {
             Some((Some(_), _)) => {
                 // Requests to subdomains must be proxied; fall through.
             }
+            _ if req.uri().path() == "/metrics" => return metrics_response(&state, &req),
             _ => {
                 return json_response(
                     StatusCode::OK,
@@ -190,130 +391,93 @@ async fn handle_request(state: Arc<AppState>, req: Request<Body>) -> Response<Bo
             return service_worker_response();
         }
 
-        match parse_route(subdomain.unwrap()) {
-            Route::Port(route) => {
-                if is_loop_header(&req) {
-                    return text_response(StatusCode::LOOP_DETECTED, "Loop detected in proxy");
-                }
-
-                if route.port == 39_378 && *req.method() == Method::OPTIONS {
-                    return Response::builder()
-                        .status(StatusCode::NO_CONTENT)
-                        .body(Body::empty())
-                        .unwrap();
-                }
-
-                let target = if let Some(suffix) = state.morph_domain_suffix.clone() {
-                    let host = format!("port-{}-morphvm-{}{}", route.port, route.morph_id, suffix);
-                    Target::Absolute {
-                        scheme: Scheme::HTTPS,
-                        host,
-                        port: None,
-                    }
-                } else {
-                    Target::BackendPort(route.port)
-                };
-
-                let (strip_cors_headers, frame_ancestors) = if route.skip_service_worker {
-                    (true, Some(CSP_FRAME_ANCESTORS_PORT_39378))
-                } else {
-                    (false, None)
-                };
-
-                return forward_request(
-                    state,
-                    req,
-                    target,
-                    ProxyBehavior {
-                        skip_service_worker: route.skip_service_worker,
-                        add_cors: false,
-                        strip_cors_headers,
-                        workspace_header: None,
-                        port_header: None,
-                        frame_ancestors,
-                    },
-                )
-                .await;
+        let routes = state
+            .routes
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let route = match routes.resolve(&subdomain.unwrap(), &state.route_vars) {
+            Some(Resolution::Route(route)) => route,
+            Some(Resolution::Invalid(message)) => {
+                return text_response(StatusCode::BAD_REQUEST, &message);
             }
-            Route::Cmux(route) => {
-                if is_loop_header(&req) {
-                    return text_response(StatusCode::LOOP_DETECTED, "Loop detected in proxy");
-                }
+            Some(Resolution::BadUpstream) => {
+                return text_response(StatusCode::BAD_GATEWAY, "Failed to build upstream URI");
+            }
+            None => return text_response(StatusCode::NOT_FOUND, "No route for this subdomain"),
+        };
 
-                let is_vscode_route = route.port == 39_378;
+        let info = RouteInfo {
+            route: route.name.clone(),
+            port: route.port,
+            upstream: describe_target(&state, &route.target),
+        };
+        let mut req = req;
+        req.extensions_mut().insert(info.clone());
+        let mut response = proxy_route(state, req, route).await;
+        response.extensions_mut().insert(info);
+        return response;
+    }
 
-                if *req.method() == Method::OPTIONS {
-                    if is_vscode_route {
-                        return Response::builder()
-                            .status(StatusCode::NO_CONTENT)
-                            .body(Body::empty())
-                            .unwrap();
-                    }
-                    return cors_response(StatusCode::NO_CONTENT);
-                }
+    text_response(StatusCode::BAD_GATEWAY, "Not a cmux domain")
+}
 
-                let target = if let Some(suffix) = state.morph_domain_suffix.clone() {
-                    let host = format!("port-39379-morphvm-{}{}", route.morph_id, suffix);
-                    Target::Absolute {
-                        scheme: Scheme::HTTPS,
-                        host,
-                        port: None,
-                    }
-                } else {
-                    Target::BackendPort(route.port)
-                };
-
-                return forward_request(
-                    state,
-                    req,
-                    target,
-                    ProxyBehavior {
-                        skip_service_worker: true,
-                        add_cors: !is_vscode_route,
-                        strip_cors_headers: is_vscode_route,
-                        workspace_header: route.workspace_header,
-                        port_header: Some(route.port.to_string()),
-                        frame_ancestors: None,
-                    },
-                )
-                .await;
-            }
-            Route::Workspace(route) => {
-                if is_loop_header(&req) {
-                    return text_response(StatusCode::LOOP_DETECTED, "Loop detected in proxy");
-                }
+async fn proxy_route(
+    state: Arc<AppState>,
+    mut req: Request<Body>,
+    route: ResolvedRoute,
+) -> Response<Body> {
+    if is_loop_header(&req) {
+        return text_response(StatusCode::LOOP_DETECTED, "Loop detected in proxy");
+    }
 
-                let target = if let Some(suffix) = state.workspace_domain_suffix.clone() {
-                    let host = format!("{}{}", route.vm_slug, suffix);
-                    Target::Absolute {
-                        scheme: Scheme::HTTPS,
-                        host,
-                        port: None,
-                    }
-                } else {
-                    Target::BackendPort(route.port)
-                };
-
-                return forward_request(
-                    state,
-                    req,
-                    target,
-                    ProxyBehavior {
-                        skip_service_worker: false,
-                        add_cors: false,
-                        strip_cors_headers: false,
-                        workspace_header: Some(route.workspace),
-                        port_header: Some(route.port.to_string()),
-                        frame_ancestors: None,
-                    },
-                )
-                .await;
+    if *req.method() == Method::OPTIONS {
+        match route.options {
+            OptionsPolicy::Forward => {}
+            OptionsPolicy::NoContent => {
+                return Response::builder()
+                    .status(StatusCode::NO_CONTENT)
+                    .body(Body::empty())
+                    .unwrap();
             }
-            Route::Invalid(resp) => return resp,
+            OptionsPolicy::Cors => return cors_response(StatusCode::NO_CONTENT),
         }
     }
 
-    text_response(StatusCode::BAD_GATEWAY, "Not a cmux domain")
+    if let Decision::Respond(response) = check_auth(&state, &mut req, &route.sandbox, route.port) {
+        return response;
+    }
+
+    if !req.body().is_end_stream() {
+        let metrics = state.metrics.clone();
+        let name = route.name.clone();
+        req = req.map(|body| count_body(body, move |n| metrics.bytes_upstream(&name, n)));
+    }
+
+    let mut response = forward_request(state, req, route.target, route.behavior).await;
+    if response.extensions().get::<UpstreamFailure>().is_none() {
+        response.extensions_mut().insert(FromUpstream);
+    }
+    response
+}
+
+fn describe_target(state: &AppState, target: &Target) -> String {
+    match target {
+        Target::BackendPort(port) => format!("{}:{}", state.backend_host, port),
+        Target::Absolute {
+            host,
+            port: Some(port),
+            ..
+        } => format!("{}:{}", host, port),
+        Target::Absolute { host, .. } => host.clone(),
+    }
+}
+
+fn check_auth(state: &AppState, req: &mut Request<Body>, sandbox: &str, port: u16) -> Decision {
+    match state.auth.as_ref() {
+        Some(auth) => auth.check(req, sandbox, port),
+        None => Decision::Forward,
+    }
 }
 
 #[derive(Clone)]
@@ -328,12 +492,14 @@ enum Target {
 
 #[derive(Clone)]
 struct ProxyBehavior {
+    inject_html: bool,
+    head_fallback: bool,
     skip_service_worker: bool,
     add_cors: bool,
     strip_cors_headers: bool,
     workspace_header: Option<String>,
     port_header: Option<String>,
-    frame_ancestors: Option<&'static str>,
+    frame_ancestors: Option<String>,
 }
 
 async fn forward_request(
@@ -397,7 +563,7 @@ async fn forward_request(
         match format!("{}://{}{}", scheme.as_str(), authority, path_and_query).parse::<Uri>() {
             Ok(uri) => uri,
             Err(_) => {
-                return text_response(StatusCode::BAD_GATEWAY, "Failed to build upstream URI");
+                return upstream_failure("Failed to build upstream URI");
             }
         };
 
@@ -427,7 +593,12 @@ async fn forward_request(
     }
 
     let original_method = req.method().clone();
-    let head_fallback_context = if original_method == Method::HEAD {
+    let accept_encoding = req
+        .headers()
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let head_fallback_context = if original_method == Method::HEAD && behavior.head_fallback {
         Some(HeadFallbackContext {
             headers: req.headers().clone(),
             uri: req.uri().clone(),
@@ -439,7 +610,7 @@ async fn forward_request(
 
     let response = match state.client.request(req).await {
         Ok(resp) => resp,
-        Err(_) => return text_response(StatusCode::BAD_GATEWAY, "Upstream fetch failed"),
+        Err(_) => return upstream_failure("Upstream fetch failed"),
     };
 
     if original_method == Method::HEAD
@@ -454,7 +625,19 @@ async fn forward_request(
         return fallback;
     }
 
-    transform_response(response, behavior).await
+    if original_method == Method::HEAD {
+        // No body to rewrite; keep the upstream payload headers as they are.
+        return forward_response_with_body(
+            response.status(),
+            response.version(),
+            &response.headers().clone(),
+            &behavior,
+            Body::empty(),
+            /* strip_payload_headers */ false,
+        );
+    }
+
+    transform_response(response, behavior, accept_encoding.as_deref())
 }
 
 /// Captures enough of the original HEAD request to retry with GET when the
@@ -480,8 +663,16 @@ async fn handle_head_method_not_allowed(
     *get_request.headers_mut() = context.headers;
     get_request.headers_mut().remove(header::CONTENT_LENGTH);
 
+    let accept_encoding = get_request
+        .headers()
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+
     match state.client.request(get_request).await {
-        Ok(resp) => (transform_head_response_from_get(resp, behavior).await).ok(),
+        Ok(resp) => (transform_head_response_from_get(resp, behavior, accept_encoding.as_deref())
+            .await)
+            .ok(),
         Err(_) => None,
     }
 }
@@ -489,8 +680,9 @@ async fn handle_head_method_not_allowed(
 async fn transform_head_response_from_get(
     response: Response<Body>,
     behavior: ProxyBehavior,
+    accept_encoding: Option<&str>,
 ) -> Result<Response<Body>, hyper::Error> {
-    let transformed_response = transform_response(response, behavior.clone()).await;
+    let transformed_response = transform_response(response, behavior.clone(), accept_encoding);
     let status = transformed_response.status();
     let version = transformed_response.version();
     let headers = transformed_response.headers().clone();
@@ -533,7 +725,7 @@ fn build_head_response(
     if force_cors_headers && !behavior.strip_cors_headers {
         add_cors_headers(&mut new_headers);
     }
-    if let Some(frame_ancestors) = behavior.frame_ancestors
+    if let Some(frame_ancestors) = behavior.frame_ancestors.as_deref()
         && let Ok(value) = HeaderValue::from_str(frame_ancestors)
     {
         new_headers.insert("content-security-policy", value);
@@ -580,10 +772,7 @@ async fn handle_websocket(
         match format!("{}://{}{}", scheme.as_str(), authority, path_and_query).parse::<Uri>() {
             Ok(uri) => uri,
             Err(_) => {
-                return text_response(
-                    StatusCode::BAD_GATEWAY,
-                    "Failed to build upstream websocket URI",
-                );
+                return upstream_failure("Failed to build upstream websocket URI");
             }
         };
 
@@ -597,10 +786,7 @@ async fn handle_websocket(
     {
         Ok(request) => request,
         Err(_) => {
-            return text_response(
-                StatusCode::BAD_GATEWAY,
-                "Failed to prepare upstream WebSocket request",
-            );
+            return upstream_failure("Failed to prepare upstream WebSocket request");
         }
     };
 
@@ -629,14 +815,24 @@ async fn handle_websocket(
             Err(response) => return response,
         };
 
+    let route = req
+        .extensions()
+        .get::<RouteInfo>()
+        .map(|info| info.route.clone())
+        .unwrap_or_else(|| NO_ROUTE.to_string());
     let client_upgrade = hyper::upgrade::on(req);
     let response = build_websocket_response(&backend_headers);
 
     tokio::spawn(async move {
         match client_upgrade.await {
             Ok(client_stream) => {
-                if let Err(err) = tunnel_upgraded(client_stream, backend_stream).await {
-                    warn!(%err, "websocket tunnel error");
+                let _open = state.metrics.tunnel_opened(&route);
+                match tunnel_upgraded(client_stream, backend_stream).await {
+                    Ok((upstream, downstream)) => {
+                        state.metrics.bytes_upstream(&route, upstream);
+                        state.metrics.bytes_downstream(&route, downstream);
+                    }
+                    Err(err) => warn!(%err, "websocket tunnel error"),
                 }
             }
             Err(err) => {
@@ -765,10 +961,7 @@ async fn connect_upstream_websocket(
 ) -> Result<(Upgraded, HeaderMap), Response<Body>> {
     let response = client.request(request).await.map_err(|err| {
         error!(%err, "upstream websocket request error");
-        text_response(
-            StatusCode::BAD_GATEWAY,
-            "Failed to connect to websocket backend",
-        )
+        upstream_failure("Failed to connect to websocket backend")
     })?;
 
     if response.status() != StatusCode::SWITCHING_PROTOCOLS {
@@ -787,10 +980,7 @@ async fn connect_upstream_websocket(
         Ok(upgraded) => Ok((upgraded, headers)),
         Err(err) => {
             error!(%err, "upstream websocket upgrade failed");
-            Err(text_response(
-                StatusCode::BAD_GATEWAY,
-                "Failed to upgrade websocket backend",
-            ))
+            Err(upstream_failure("Failed to upgrade websocket backend"))
         }
     }
 }
@@ -814,87 +1004,103 @@ fn build_websocket_response(headers: &HeaderMap) -> Response<Body> {
     builder.body(Body::empty()).unwrap()
 }
 
-async fn tunnel_upgraded(mut client: Upgraded, mut backend: Upgraded) -> io::Result<()> {
+/// Returns the bytes sent upstream and downstream.
+async fn tunnel_upgraded(mut client: Upgraded, mut backend: Upgraded) -> io::Result<(u64, u64)> {
     let result = copy_bidirectional(&mut client, &mut backend).await;
     let _ = client.shutdown().await;
     let _ = backend.shutdown().await;
-    result.map(|_| ())
+    result
 }
 
-async fn transform_response(response: Response<Body>, behavior: ProxyBehavior) -> Response<Body> {
+/// Rewrites HTML responses as they stream through and forwards everything else
+/// untouched. HTML is decoded, rewritten and re-encoded chunk by chunk, so
+/// streamed server-rendered pages reach the client progressively.
+fn transform_response(
+    response: Response<Body>,
+    behavior: ProxyBehavior,
+    accept_encoding: Option<&str>,
+) -> Response<Body> {
     let status = response.status();
     let version = response.version();
     let headers = response.headers().clone();
     let content_encoding = headers
         .get(header::CONTENT_ENCODING)
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.to_string());
+        .and_then(|v| v.to_str().ok());
 
     let content_type = headers
         .get(header::CONTENT_TYPE)
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
 
-    if content_type.contains("text/html") {
-        match body::to_bytes(response.into_body()).await {
-            Ok(bytes) => {
-                let decoded =
-                    match decode_body_with_encoding(bytes.as_ref(), content_encoding.as_deref()) {
-                        Ok(body) => Bytes::from(body),
-                        Err(err) => {
-                            warn!(%err, "failed to decode upstream body; skipping rewrite");
-                            return forward_response_with_body(
-                                status,
-                                version,
-                                &headers,
-                                &behavior,
-                                Body::from(bytes),
-                                /* strip_payload_headers */ false,
-                            );
-                        }
-                    };
-                match rewrite_html(decoded, behavior.skip_service_worker) {
-                    Ok(body) => {
-                        let mut builder = Response::builder().status(status).version(version);
-                        let mut new_headers =
-                            sanitize_headers(&headers, /* strip_payload_headers */ true);
-                        strip_csp_headers(&mut new_headers);
-                        if behavior.strip_cors_headers {
-                            strip_cors_headers(&mut new_headers);
-                        } else if behavior.add_cors {
-                            add_cors_headers(&mut new_headers);
-                        }
-                        if let Some(frame_ancestors) = behavior.frame_ancestors
-                            && let Ok(value) = HeaderValue::from_str(frame_ancestors)
-                        {
-                            new_headers.insert("content-security-policy", value);
-                        }
-                        new_headers.insert(
-                            header::CONTENT_LENGTH,
-                            HeaderValue::from_str(&body.len().to_string()).unwrap(),
-                        );
-                        let headers_mut = builder.headers_mut().unwrap();
-                        for (name, value) in new_headers.iter() {
-                            headers_mut.insert(name, value.clone());
-                        }
-                        builder.body(Body::from(body)).unwrap()
-                    }
-                    Err(_) => {
-                        text_response(StatusCode::INTERNAL_SERVER_ERROR, "HTML rewrite failed")
-                    }
-                }
-            }
-            Err(_) => text_response(StatusCode::BAD_GATEWAY, "Failed to read upstream body"),
-        }
-    } else {
-        forward_response_with_body(
+    let has_body = !matches!(status, StatusCode::NO_CONTENT | StatusCode::NOT_MODIFIED);
+    if !behavior.inject_html || !content_type.contains("text/html") || !has_body {
+        return forward_response_with_body(
             status,
             version,
             &headers,
             &behavior,
             response.into_body(),
             /* strip_payload_headers */ false,
-        )
+        );
+    }
+
+    let decoder = match BodyDecoder::new(content_encoding) {
+        Ok(decoder) => decoder,
+        Err(err) => {
+            warn!(%err, "cannot decode upstream body; skipping rewrite");
+            return forward_response_with_body(
+                status,
+                version,
+                &headers,
+                &behavior,
+                response.into_body(),
+                /* strip_payload_headers */ false,
+            );
+        }
+    };
+    let encoding = negotiate_encoding(accept_encoding, content_encoding);
+    let body = rewrite_html_stream(
+        response.into_body(),
+        decoder,
+        BodyEncoder::new(encoding),
+        behavior.skip_service_worker,
+    );
+
+    let mut response = forward_response_with_body(
+        status, version, &headers, &behavior, body, /* strip_payload_headers */ true,
+    );
+    let headers = response.headers_mut();
+    if let Some(encoding) = encoding {
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+    }
+    add_vary(headers, "accept-encoding");
+    response
+}
+
+/// Adds `name` to the response's `Vary` header, keeping whatever the upstream
+/// listed there.
+fn add_vary(headers: &mut HeaderMap, name: &str) {
+    let existing: Vec<&str> = headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .collect();
+    if existing
+        .iter()
+        .any(|field| *field == "*" || field.eq_ignore_ascii_case(name))
+    {
+        return;
+    }
+    let vary = existing
+        .into_iter()
+        .chain(std::iter::once(name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if let Ok(value) = HeaderValue::from_str(&vary) {
+        headers.insert(header::VARY, value);
     }
 }
 
@@ -914,7 +1120,7 @@ fn forward_response_with_body(
     } else if behavior.add_cors {
         add_cors_headers(&mut new_headers);
     }
-    if let Some(frame_ancestors) = behavior.frame_ancestors
+    if let Some(frame_ancestors) = behavior.frame_ancestors.as_deref()
         && let Ok(value) = HeaderValue::from_str(frame_ancestors)
     {
         new_headers.insert("content-security-policy", value);
@@ -926,41 +1132,239 @@ fn forward_response_with_body(
     builder.body(body).unwrap()
 }
 
-fn decode_body_with_encoding(bytes: &[u8], encoding: Option<&str>) -> io::Result<Vec<u8>> {
-    match encoding.map(|enc| enc.trim().to_ascii_lowercase()) {
-        None => Ok(bytes.to_vec()),
-        Some(enc) => match enc.as_str() {
-            "" | "identity" => Ok(bytes.to_vec()),
-            "gzip" => {
-                let mut decoder = GzDecoder::new(Cursor::new(bytes));
-                let mut out = Vec::new();
-                decoder.read_to_end(&mut out)?;
-                Ok(out)
+/// Content codings HTML can be re-encoded with, most preferred first.
+const SUPPORTED_ENCODINGS: [&str; 4] = ["br", "zstd", "gzip", "deflate"];
+
+/// Picks the encoding for a rewritten HTML body: the upstream's own encoding
+/// if the client accepts it, otherwise the supported encoding the client
+/// prefers most. `None` means identity.
+fn negotiate_encoding(accept: Option<&str>, upstream: Option<&str>) -> Option<&'static str> {
+    let accepted: Vec<(String, f32)> = accept
+        .unwrap_or("")
+        .split(',')
+        .filter_map(|part| {
+            let mut params = part.split(';');
+            let name = normalize_encoding(params.next()?);
+            if name.is_empty() {
+                return None;
             }
-            "deflate" => {
-                let mut decoder = ZlibDecoder::new(Cursor::new(bytes));
-                let mut out = Vec::new();
-                decoder.read_to_end(&mut out)?;
-                Ok(out)
-            }
-            "br" => {
-                let mut decoder = Decompressor::new(Cursor::new(bytes), 4096);
-                let mut out = Vec::new();
-                decoder.read_to_end(&mut out)?;
-                Ok(out)
-            }
-            "zstd" => {
-                let mut decoder = ZstdDecoder::new(Cursor::new(bytes))?;
-                let mut out = Vec::new();
-                decoder.read_to_end(&mut out)?;
-                Ok(out)
-            }
-            other => Err(io::Error::new(
+            let q = params
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .and_then(|q| q.trim().parse().ok())
+                .unwrap_or(1.0);
+            Some((name, q))
+        })
+        .collect();
+    let quality = |encoding: &str| {
+        accepted
+            .iter()
+            .find(|(name, _)| name == encoding)
+            .or_else(|| accepted.iter().find(|(name, _)| name == "*"))
+            .map_or(0.0, |(_, q)| *q)
+    };
+
+    let upstream = upstream.map(normalize_encoding);
+    if let Some(encoding) = SUPPORTED_ENCODINGS
+        .into_iter()
+        .find(|enc| upstream.as_deref() == Some(*enc))
+        && quality(encoding) > 0.0
+    {
+        return Some(encoding);
+    }
+
+    let mut best: Option<(&'static str, f32)> = None;
+    for encoding in SUPPORTED_ENCODINGS {
+        let q = quality(encoding);
+        if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+            best = Some((encoding, q));
+        }
+    }
+    best.map(|(encoding, _)| encoding)
+}
+
+/// Lowercases a content coding and maps `x-gzip` to `gzip`.
+fn normalize_encoding(encoding: &str) -> String {
+    match encoding.trim().to_ascii_lowercase() {
+        enc if enc == "x-gzip" => "gzip".to_string(),
+        enc => enc,
+    }
+}
+
+/// Incremental decoder for an upstream `content-encoding`.
+enum BodyDecoder {
+    Identity,
+    Gzip(GzDecoder<Vec<u8>>),
+    Deflate(ZlibDecoder<Vec<u8>>),
+    Brotli(Box<DecompressorWriter<Vec<u8>>>),
+    Zstd(ZstdDecoder<'static, Vec<u8>>),
+}
+
+impl BodyDecoder {
+    fn new(encoding: Option<&str>) -> io::Result<Self> {
+        match encoding.map(normalize_encoding).as_deref() {
+            None | Some("" | "identity") => Ok(Self::Identity),
+            Some("gzip") => Ok(Self::Gzip(GzDecoder::new(Vec::new()))),
+            Some("deflate") => Ok(Self::Deflate(ZlibDecoder::new(Vec::new()))),
+            Some("br") => Ok(Self::Brotli(Box::new(DecompressorWriter::new(
+                Vec::new(),
+                4096,
+            )))),
+            Some("zstd") => Ok(Self::Zstd(ZstdDecoder::new(Vec::new())?)),
+            Some(other) => Err(io::Error::new(
                 io::ErrorKind::InvalidData,
                 format!("unsupported content-encoding: {}", other),
             )),
-        },
+        }
+    }
+
+    /// Feeds a chunk of encoded input, returning the output decoded so far.
+    fn decode(&mut self, chunk: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Identity => Ok(chunk.to_vec()),
+            Self::Gzip(decoder) => write_through(decoder, chunk, |d| d.get_mut()),
+            Self::Deflate(decoder) => write_through(decoder, chunk, |d| d.get_mut()),
+            Self::Brotli(decoder) => write_through(decoder.as_mut(), chunk, |d| d.get_mut()),
+            Self::Zstd(decoder) => write_through(decoder, chunk, |d| d.get_mut()),
+        }
+    }
+
+    /// Returns the remaining output, failing if the input was truncated.
+    fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Identity => Ok(Vec::new()),
+            Self::Gzip(decoder) => decoder.finish(),
+            Self::Deflate(decoder) => decoder.finish(),
+            Self::Brotli(decoder) => decoder.into_inner().map_err(|_| {
+                io::Error::new(io::ErrorKind::UnexpectedEof, "truncated brotli stream")
+            }),
+            Self::Zstd(mut decoder) => {
+                decoder.flush()?;
+                Ok(decoder.into_inner())
+            }
+        }
+    }
+}
+
+/// Incremental encoder for the `content-encoding` sent to the client.
+enum BodyEncoder {
+    Identity,
+    Gzip(GzEncoder<Vec<u8>>),
+    Deflate(ZlibEncoder<Vec<u8>>),
+    Brotli(Box<CompressorWriter<Vec<u8>>>),
+    Zstd(ZstdEncoder<'static, Vec<u8>>),
+}
+
+impl BodyEncoder {
+    fn new(encoding: Option<&str>) -> Self {
+        match encoding {
+            Some("gzip") => Self::Gzip(GzEncoder::new(Vec::new(), Compression::fast())),
+            Some("deflate") => Self::Deflate(ZlibEncoder::new(Vec::new(), Compression::fast())),
+            Some("br") => Self::Brotli(Box::new(CompressorWriter::new(Vec::new(), 4096, 5, 22))),
+            Some("zstd") => match ZstdEncoder::new(Vec::new(), 3) {
+                Ok(encoder) => Self::Zstd(encoder),
+                Err(_) => Self::Identity,
+            },
+            _ => Self::Identity,
+        }
+    }
+
+    /// Encodes a chunk and flushes it, so the client can start on it right away.
+    fn encode(&mut self, chunk: Vec<u8>) -> io::Result<Vec<u8>> {
+        if chunk.is_empty() {
+            return Ok(chunk);
+        }
+        match self {
+            Self::Identity => Ok(chunk),
+            Self::Gzip(encoder) => write_through(encoder, &chunk, |e| e.get_mut()),
+            Self::Deflate(encoder) => write_through(encoder, &chunk, |e| e.get_mut()),
+            Self::Brotli(encoder) => write_through(encoder.as_mut(), &chunk, |e| e.get_mut()),
+            Self::Zstd(encoder) => write_through(encoder, &chunk, |e| e.get_mut()),
+        }
+    }
+
+    /// Returns the trailer that ends the encoded stream.
+    fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Identity => Ok(Vec::new()),
+            Self::Gzip(encoder) => encoder.finish(),
+            Self::Deflate(encoder) => encoder.finish(),
+            Self::Brotli(encoder) => Ok(encoder.into_inner()),
+            Self::Zstd(encoder) => encoder.finish(),
+        }
+    }
+}
+
+/// Writes `chunk` through a streaming coder, flushes it and takes whatever
+/// output has accumulated in its buffer.
+fn write_through<W: Write>(
+    writer: &mut W,
+    chunk: &[u8],
+    buffer: impl FnOnce(&mut W) -> &mut Vec<u8>,
+) -> io::Result<Vec<u8>> {
+    writer.write_all(chunk)?;
+    writer.flush()?;
+    Ok(std::mem::take(buffer(writer)))
+}
+
+/// Streams `body` through the HTML rewriter on a blocking thread (lol_html's
+/// rewriter is not `Send`) and returns the rewritten, re-encoded body. Each
+/// upstream chunk is forwarded as soon as it has been processed; errors abort
+/// the response mid-stream since the headers are already on their way.
+fn rewrite_html_stream(
+    body: Body,
+    decoder: BodyDecoder,
+    encoder: BodyEncoder,
+    skip_service_worker: bool,
+) -> Body {
+    let (tx, rx) = mpsc::channel::<io::Result<Bytes>>(16);
+    let runtime = Handle::current();
+    tokio::task::spawn_blocking(move || {
+        if let Err(err) = pump_html(&runtime, body, decoder, encoder, skip_service_worker, &tx) {
+            warn!(%err, "failed to rewrite streamed HTML");
+            let _ = tx.blocking_send(Err(err));
+        }
+    });
+    Body::wrap_stream(futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
+    }))
+}
+
+fn pump_html(
+    runtime: &Handle,
+    mut body: Body,
+    mut decoder: BodyDecoder,
+    mut encoder: BodyEncoder,
+    skip_service_worker: bool,
+    tx: &mpsc::Sender<io::Result<Bytes>>,
+) -> io::Result<()> {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let sink = output.clone();
+    let mut rewriter = html_rewriter(skip_service_worker, move |c: &[u8]| {
+        sink.borrow_mut().extend_from_slice(c)
+    });
+
+    while let Some(chunk) = runtime.block_on(body.data()) {
+        let chunk = chunk.map_err(io::Error::other)?;
+        rewriter
+            .write(&decoder.decode(&chunk)?)
+            .map_err(io::Error::other)?;
+        let encoded = encoder.encode(output.take())?;
+        if !encoded.is_empty() && tx.blocking_send(Ok(Bytes::from(encoded))).is_err() {
+            // The client went away.
+            return Ok(());
+        }
     }
+
+    rewriter
+        .write(&decoder.finish()?)
+        .map_err(io::Error::other)?;
+    rewriter.end().map_err(io::Error::other)?;
+    let mut encoded = encoder.encode(output.take())?;
+    encoded.extend(encoder.finish()?);
+    if !encoded.is_empty() {
+        let _ = tx.blocking_send(Ok(Bytes::from(encoded)));
+    }
+    Ok(())
 }
 
 fn sanitize_headers(headers: &HeaderMap, strip_payload_headers: bool) -> HeaderMap {
@@ -1027,13 +1431,8 @@ fn strip_cors_headers(headers: &mut HeaderMap) {
     }
 }
 
-fn rewrite_html(
-    bytes: Bytes,
-    skip_service_worker: bool,
-) -> Result<Vec<u8>, lol_html::errors::RewritingError> {
-    let mut output = Vec::with_capacity(bytes.len());
-
-    let mut rewriter = HtmlRewriter::new(
+fn html_rewriter<O: OutputSink>(skip_service_worker: bool, sink: O) -> HtmlRewriter<'static, O> {
+    HtmlRewriter::new(
         Settings {
             element_content_handlers: vec![
                 element!("head", move |el| {
@@ -1054,165 +1453,8 @@ fn rewrite_html(
             ],
             ..Settings::default()
         },
-        |c: &[u8]| output.extend_from_slice(c),
-    );
-
-    rewriter.write(&bytes)?;
-    rewriter.end()?;
-    Ok(output)
-}
-
-fn parse_route(subdomain: String) -> Route {
-    if let Some(rest) = subdomain.strip_prefix("port-") {
-        let segments: Vec<&str> = rest.split('-').collect();
-        if segments.len() < 2 {
-            return Route::Invalid(text_response(
-                StatusCode::BAD_REQUEST,
-                "Invalid cmux proxy subdomain",
-            ));
-        }
-
-        let port = match segments[0].parse::<u16>() {
-            Ok(port) => port,
-            Err(_) => {
-                return Route::Invalid(text_response(
-                    StatusCode::BAD_REQUEST,
-                    "Invalid cmux proxy subdomain",
-                ));
-            }
-        };
-
-        let morph_id = segments[1..].join("-");
-        if morph_id.is_empty() {
-            return Route::Invalid(text_response(
-                StatusCode::BAD_REQUEST,
-                "Invalid cmux proxy subdomain",
-            ));
-        }
-
-        return Route::Port(PortRoute {
-            port,
-            morph_id,
-            skip_service_worker: port == 39_378,
-        });
-    }
-
-    if let Some(rest) = subdomain.strip_prefix("cmux-") {
-        let segments: Vec<&str> = rest.split('-').collect();
-        if segments.len() < 2 {
-            return Route::Invalid(text_response(
-                StatusCode::BAD_REQUEST,
-                "Invalid cmux proxy subdomain",
-            ));
-        }
-        let morph_id = segments[0];
-        if morph_id.is_empty() {
-            return Route::Invalid(text_response(
-                StatusCode::BAD_REQUEST,
-                "Missing morph id in cmux proxy subdomain",
-            ));
-        }
-        let port_segment = match segments.last() {
-            Some(seg) => *seg,
-            None => {
-                return Route::Invalid(text_response(
-                    StatusCode::BAD_REQUEST,
-                    "Invalid cmux proxy subdomain",
-                ));
-            }
-        };
-        let port = match port_segment.parse::<u16>() {
-            Ok(port) => port,
-            Err(_) => {
-                return Route::Invalid(text_response(
-                    StatusCode::BAD_REQUEST,
-                    "Invalid port in cmux proxy subdomain",
-                ));
-            }
-        };
-        let scope_segments = &segments[1..segments.len() - 1];
-        let workspace_header = if scope_segments.is_empty()
-            || (scope_segments.len() == 1 && scope_segments[0].eq_ignore_ascii_case("base"))
-        {
-            None
-        } else {
-            Some(scope_segments.join("-"))
-        };
-        return Route::Cmux(CmuxRoute {
-            port,
-            workspace_header,
-            morph_id: morph_id.to_string(),
-        });
-    }
-
-    let parts: Vec<&str> = subdomain.split('-').collect();
-    if parts.len() < 3 {
-        return Route::Invalid(text_response(
-            StatusCode::BAD_REQUEST,
-            "Invalid cmux subdomain",
-        ));
-    }
-
-    let port_segment = parts[parts.len() - 2];
-    let workspace_parts = &parts[..parts.len() - 2];
-    let vm_slug = parts.last().unwrap();
-
-    if workspace_parts.is_empty() {
-        return Route::Invalid(text_response(
-            StatusCode::BAD_REQUEST,
-            "Invalid cmux subdomain",
-        ));
-    }
-
-    let workspace = workspace_parts.join("-");
-
-    let port = match port_segment.parse::<u16>() {
-        Ok(port) => port,
-        Err(_) => {
-            return Route::Invalid(text_response(
-                StatusCode::BAD_REQUEST,
-                "Invalid port in subdomain",
-            ));
-        }
-    };
-
-    if vm_slug.is_empty() {
-        return Route::Invalid(text_response(
-            StatusCode::BAD_REQUEST,
-            "Invalid cmux subdomain",
-        ));
-    }
-
-    Route::Workspace(WorkspaceRoute {
-        workspace,
-        port,
-        vm_slug: vm_slug.to_string(),
-    })
-}
-
-struct PortRoute {
-    port: u16,
-    morph_id: String,
-    skip_service_worker: bool,
-}
-
-struct CmuxRoute {
-    port: u16,
-    workspace_header: Option<String>,
-    morph_id: String,
-}
-
-struct WorkspaceRoute {
-    workspace: String,
-    port: u16,
-    vm_slug: String,
-}
-
-enum Route {
-    Port(PortRoute),
-    Cmux(CmuxRoute),
-    Workspace(WorkspaceRoute),
-    Invalid(Response<Body>),
+        sink,
+    )
 }
 
 fn is_loop_header(req: &Request<Body>) -> bool {
@@ -1234,6 +1476,12 @@ fn cors_response(status: StatusCode) -> Response<Body> {
     builder.body(Body::empty()).unwrap()
 }
 
+fn upstream_failure(message: &str) -> Response<Body> {
+    let mut response = text_response(StatusCode::BAD_GATEWAY, message);
+    response.extensions_mut().insert(UpstreamFailure);
+    response
+}
+
 fn text_response(status: StatusCode, body: &str) -> Response<Body> {
     Response::builder()
         .status(status)
@@ -1382,39 +1630,89 @@ fn service_worker_response() -> Response<Body> {
 
 #[cfg(test)]
 mod tests {
-    use super::decode_body_with_encoding;
+    use super::{BodyDecoder, BodyEncoder, negotiate_encoding};
     use flate2::{Compression, write::GzEncoder};
     use std::io::Write;
 
+    fn decode_all(chunks: &[&[u8]], encoding: Option<&str>) -> std::io::Result<Vec<u8>> {
+        let mut decoder = BodyDecoder::new(encoding)?;
+        let mut out = Vec::new();
+        for chunk in chunks {
+            out.extend(decoder.decode(chunk)?);
+        }
+        out.extend(decoder.finish()?);
+        Ok(out)
+    }
+
     #[test]
     fn decodes_identity_and_none_encodings() {
         let payload = b"hello world";
-        assert_eq!(decode_body_with_encoding(payload, None).unwrap(), payload);
-        assert_eq!(
-            decode_body_with_encoding(payload, Some("identity")).unwrap(),
-            payload
-        );
-        assert_eq!(
-            decode_body_with_encoding(payload, Some("")).unwrap(),
-            payload
-        );
+        assert_eq!(decode_all(&[payload], None).unwrap(), payload);
+        assert_eq!(decode_all(&[payload], Some("identity")).unwrap(), payload);
+        assert_eq!(decode_all(&[payload], Some("")).unwrap(), payload);
     }
 
     #[test]
     fn decodes_gzip_payloads() {
         let payload = b"compressed content";
         let compressed = gzip(payload);
-        let decoded = decode_body_with_encoding(&compressed, Some("gzip")).unwrap();
+        let (head, tail) = compressed.split_at(compressed.len() / 2);
+        let decoded = decode_all(&[head, tail], Some("gzip")).unwrap();
         assert_eq!(decoded, payload);
     }
 
+    #[test]
+    fn truncated_payloads_fail_to_decode() {
+        let compressed = gzip(b"compressed content");
+        assert!(decode_all(&[&compressed[..compressed.len() - 4]], Some("gzip")).is_err());
+    }
+
     #[test]
     fn errors_on_unsupported_encoding() {
-        let payload = b"noop";
-        let err = decode_body_with_encoding(payload, Some("unknown-enc")).unwrap_err();
+        let err = BodyDecoder::new(Some("unknown-enc")).err().unwrap();
         assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
     }
 
+    #[test]
+    fn encoded_chunks_decode_back() {
+        for encoding in ["br", "zstd", "gzip", "deflate"] {
+            let mut encoder = BodyEncoder::new(Some(encoding));
+            let mut decoder = BodyDecoder::new(Some(encoding)).unwrap();
+            let mut out = decoder
+                .decode(&encoder.encode(b"<html>".to_vec()).unwrap())
+                .unwrap();
+            // Each chunk is flushed, so it decodes before the stream ends.
+            assert_eq!(out, b"<html>", "{encoding}");
+            out.extend(
+                decoder
+                    .decode(&encoder.encode(b"</html>".to_vec()).unwrap())
+                    .unwrap(),
+            );
+            out.extend(decoder.decode(&encoder.finish().unwrap()).unwrap());
+            out.extend(decoder.finish().unwrap());
+            assert_eq!(out, b"<html></html>", "{encoding}");
+        }
+    }
+
+    #[test]
+    fn negotiates_response_encoding() {
+        assert_eq!(negotiate_encoding(None, Some("gzip")), None);
+        assert_eq!(
+            negotiate_encoding(Some("gzip, br"), Some("gzip")),
+            Some("gzip")
+        );
+        assert_eq!(negotiate_encoding(Some("gzip, br"), None), Some("br"));
+        assert_eq!(
+            negotiate_encoding(Some("br;q=0.5, gzip;q=0.8"), Some("zstd")),
+            Some("gzip")
+        );
+        assert_eq!(
+            negotiate_encoding(Some("gzip;q=0, identity"), Some("gzip")),
+            None
+        );
+        assert_eq!(negotiate_encoding(Some("*"), None), Some("br"));
+    }
+
     fn gzip(payload: &[u8]) -> Vec<u8> {
         let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
         encoder.write_all(payload).unwrap();
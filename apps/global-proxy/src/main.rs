@@ -1,8 +1,12 @@
-use std::{net::SocketAddr, str::FromStr};
+use std::{net::SocketAddr, path::PathBuf, str::FromStr, sync::Arc, time::Duration};
 
-use global_proxy::{ProxyConfig, spawn_proxy};
-use http::uri::Scheme;
-use tracing::info;
+use global_proxy::{
+    ProxyConfig,
+    auth::{Access, AuthConfig, JwksVerifier, StaticKeyVerifier, TokenVerifier},
+    spawn_proxy,
+};
+use http::{Uri, uri::Scheme};
+use tracing::{info, warn};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -40,12 +44,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .ok()
         .and_then(normalize_suffix);
 
+    let auth = auth_config().await?;
+    if auth.is_none() {
+        warn!("no GLOBAL_PROXY_AUTH_* key configured; proxied routes are unauthenticated");
+    }
+
+    let routes_file = std::env::var_os("GLOBAL_PROXY_ROUTES_FILE").map(PathBuf::from);
+    let access_log = std::env::var("GLOBAL_PROXY_ACCESS_LOG")
+        .map(|value| matches!(value.trim(), "1" | "true" | "json"))
+        .unwrap_or(false);
+    let metrics_token = std::env::var("GLOBAL_PROXY_METRICS_TOKEN")
+        .ok()
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty());
+
     let handle = spawn_proxy(ProxyConfig {
         bind_addr,
         backend_host,
         backend_scheme,
         morph_domain_suffix,
         workspace_domain_suffix,
+        auth,
+        routes_file,
+        access_log,
+        metrics_token,
+        ..ProxyConfig::default()
     })
     .await?;
 
@@ -68,3 +91,55 @@ fn normalize_suffix(value: String) -> Option<String> {
         Some(format!(".{}", trimmed))
     }
 }
+
+/// Builds the auth layer from `GLOBAL_PROXY_AUTH_HS256_SECRET` or
+/// `GLOBAL_PROXY_AUTH_JWKS_URL`; `None` when neither is set.
+async fn auth_config() -> Result<Option<AuthConfig>, Box<dyn std::error::Error>> {
+    let verifier: Arc<dyn TokenVerifier> =
+        if let Ok(url) = std::env::var("GLOBAL_PROXY_AUTH_JWKS_URL") {
+            let url: Uri = url
+                .parse()
+                .map_err(|_| format!("GLOBAL_PROXY_AUTH_JWKS_URL '{}' is invalid", url))?;
+            let verifier = Arc::new(JwksVerifier::fetch(url).await?);
+            verifier.clone().spawn_refresh(Duration::from_secs(300));
+            verifier
+        } else if let Ok(secret) = std::env::var("GLOBAL_PROXY_AUTH_HS256_SECRET") {
+            Arc::new(StaticKeyVerifier::hs256(secret.as_bytes()))
+        } else {
+            return Ok(None);
+        };
+
+    let mut config = AuthConfig::new(verifier);
+    match std::env::var("GLOBAL_PROXY_SESSION_SECRET") {
+        Ok(secret) => config.session_key = secret.into_bytes(),
+        Err(_) => warn!("GLOBAL_PROXY_SESSION_SECRET not set; sessions are tied to this instance"),
+    }
+    if let Ok(value) = std::env::var("GLOBAL_PROXY_AUTH_DEFAULT") {
+        config.default_access = parse_access(&value)
+            .ok_or_else(|| format!("GLOBAL_PROXY_AUTH_DEFAULT '{}' is invalid", value))?;
+    }
+    for (var, access) in [
+        ("GLOBAL_PROXY_PUBLIC_PORTS", Access::Public),
+        ("GLOBAL_PROXY_PRIVATE_PORTS", Access::Private),
+    ] {
+        for port in std::env::var(var).unwrap_or_default().split(',') {
+            let port = port.trim();
+            if port.is_empty() {
+                continue;
+            }
+            let port: u16 = port
+                .parse()
+                .map_err(|_| format!("{} has an invalid port '{}'", var, port))?;
+            config.port_access.insert(port, access);
+        }
+    }
+    Ok(Some(config))
+}
+
+fn parse_access(value: &str) -> Option<Access> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "public" => Some(Access::Public),
+        "private" => Some(Access::Private),
+        _ => None,
+    }
+}
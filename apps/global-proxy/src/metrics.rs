@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+/// Label used for requests that never matched a route (health checks, the
+/// apex host, unknown subdomains).
+pub const NO_ROUTE: &str = "none";
+
+/// Port label for routed requests that got no upstream response (rejected,
+/// or the upstream was unreachable), so made-up ports don't add series.
+pub const OTHER_PORT: &str = "other";
+
+/// Prometheus metrics for one proxy instance, served on `/metrics` to holders
+/// of the metrics token.
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    duration: HistogramVec,
+    upstream_errors: IntCounterVec,
+    websocket_tunnels: IntGaugeVec,
+    bytes: IntCounterVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let requests = IntCounterVec::new(
+            Opts::new("global_proxy_requests_total", "Requests handled."),
+            &["route", "port", "status"],
+        )
+        .unwrap();
+        let duration = HistogramVec::new(
+            HistogramOpts::new(
+                "global_proxy_request_duration_seconds",
+                "Time until response headers were sent.",
+            ),
+            &["route", "port"],
+        )
+        .unwrap();
+        let upstream_errors = IntCounterVec::new(
+            Opts::new(
+                "global_proxy_upstream_errors_total",
+                "Requests that failed to reach the upstream.",
+            ),
+            &["route"],
+        )
+        .unwrap();
+        let websocket_tunnels = IntGaugeVec::new(
+            Opts::new(
+                "global_proxy_websocket_tunnels_active",
+                "Open WebSocket tunnels.",
+            ),
+            &["route"],
+        )
+        .unwrap();
+        let bytes = IntCounterVec::new(
+            Opts::new(
+                "global_proxy_bytes_total",
+                "Body and tunnel bytes sent upstream or downstream (to the client).",
+            ),
+            &["route", "direction"],
+        )
+        .unwrap();
+
+        for collector in [
+            Box::new(requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(duration.clone()),
+            Box::new(upstream_errors.clone()),
+            Box::new(websocket_tunnels.clone()),
+            Box::new(bytes.clone()),
+        ] {
+            registry.register(collector).unwrap();
+        }
+
+        Self {
+            registry,
+            requests,
+            duration,
+            upstream_errors,
+            websocket_tunnels,
+            bytes,
+        }
+    }
+
+    pub fn observe_request(&self, route: &str, port: &str, status: u16, elapsed: Duration) {
+        self.requests
+            .with_label_values(&[route, port, &status.to_string()])
+            .inc();
+        self.duration
+            .with_label_values(&[route, port])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn upstream_error(&self, route: &str) {
+        self.upstream_errors.with_label_values(&[route]).inc();
+    }
+
+    pub fn bytes_upstream(&self, route: &str, n: u64) {
+        self.bytes.with_label_values(&[route, "upstream"]).inc_by(n);
+    }
+
+    pub fn bytes_downstream(&self, route: &str, n: u64) {
+        self.bytes
+            .with_label_values(&[route, "downstream"])
+            .inc_by(n);
+    }
+
+    /// Counts a WebSocket tunnel as open until the guard is dropped.
+    pub fn tunnel_opened(&self, route: &str) -> TunnelGuard {
+        let gauge = self.websocket_tunnels.with_label_values(&[route]);
+        gauge.inc();
+        TunnelGuard(gauge)
+    }
+
+    /// Renders all metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut out = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut out)
+            .expect("text encoding never fails");
+        String::from_utf8(out).expect("prometheus text is utf-8")
+    }
+}
+
+pub struct TunnelGuard(prometheus::IntGauge);
+
+impl Drop for TunnelGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_labelled_series() {
+        let metrics = Metrics::new();
+        metrics.observe_request("port", "3000", 200, Duration::from_millis(20));
+        metrics.upstream_error("cmux");
+        metrics.bytes_downstream("port", 42);
+        let guard = metrics.tunnel_opened("cmux");
+
+        let text = metrics.render();
+        assert!(
+            text.contains(
+                r#"global_proxy_requests_total{port="3000",route="port",status="200"} 1"#
+            )
+        );
+        assert!(text.contains(
+            r#"global_proxy_request_duration_seconds_count{port="3000",route="port"} 1"#
+        ));
+        assert!(text.contains(r#"global_proxy_upstream_errors_total{route="cmux"} 1"#));
+        assert!(
+            text.contains(r#"global_proxy_bytes_total{direction="downstream",route="port"} 42"#)
+        );
+        assert!(text.contains(r#"global_proxy_websocket_tunnels_active{route="cmux"} 1"#));
+
+        drop(guard);
+        assert!(
+            metrics
+                .render()
+                .contains(r#"global_proxy_websocket_tunnels_active{route="cmux"} 0"#)
+        );
+    }
+}
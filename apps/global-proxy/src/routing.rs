@@ -0,0 +1,631 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use http::{Uri, uri::Scheme};
+use serde::Deserialize;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::{ProxyBehavior, Target};
+
+/// Built-in routes; also a starting point for custom route files.
+pub const DEFAULT_ROUTES: &str = include_str!("default_routes.json");
+
+/// How often a route file is checked for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Template variables besides the pattern captures.
+const VARIABLES: &[&str] = &["morph_domain_suffix", "workspace_domain_suffix"];
+
+#[derive(thiserror::Error, Debug)]
+pub enum RoutingError {
+    #[error("failed to read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid routes: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("route {route}: {message}")]
+    Invalid { route: String, message: String },
+}
+
+/// Routing rules for cmux subdomains, tried in order. The first route whose
+/// pattern prefix matches a subdomain handles it.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoutingConfig {
+    pub routes: Vec<RouteRule>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteRule {
+    pub name: String,
+    /// `-`-separated segments: literals, `{name}` (one segment), `{name+}`
+    /// (one or more) or `{name*}` (zero or more). At most one repeated
+    /// capture; `{port}` is required and must be a port number.
+    pub pattern: Pattern,
+    /// Error messages for subdomains this route claims but can't parse, keyed
+    /// by capture name, with `invalid` as the fallback.
+    #[serde(default)]
+    pub errors: HashMap<String, String>,
+    /// Capture values treated as empty (case-insensitive).
+    #[serde(default)]
+    pub empty_values: HashMap<String, Vec<String>>,
+    /// Sandbox the route belongs to, for authentication.
+    pub sandbox: String,
+    /// Upstream URL template. When unset, or when it uses a domain suffix
+    /// that isn't configured, requests go to the backend host on `{port}`.
+    #[serde(default)]
+    pub upstream: Option<String>,
+    /// `X-Cmux-Workspace-Internal` value; omitted when it renders empty.
+    #[serde(default)]
+    pub workspace_header: Option<String>,
+    /// `X-Cmux-Port-Internal` value; omitted when it renders empty.
+    #[serde(default)]
+    pub port_header: Option<String>,
+    #[serde(flatten)]
+    pub behavior: BehaviorRule,
+    /// Per-port overrides of `behavior`.
+    #[serde(default)]
+    pub ports: BTreeMap<u16, BehaviorRule>,
+}
+
+/// Per-route (or per-port) response handling. Unset fields fall back to the
+/// route's value, then to the defaults noted below.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BehaviorRule {
+    /// Inject the cmux scripts into HTML responses (default true).
+    #[serde(default)]
+    pub inject_html: Option<bool>,
+    /// Include the service worker registration in injected HTML (default true).
+    #[serde(default)]
+    pub service_worker: Option<bool>,
+    /// What to do with upstream CORS headers (default keep).
+    #[serde(default)]
+    pub cors: Option<CorsPolicy>,
+    /// Sources for a `content-security-policy: frame-ancestors` header.
+    #[serde(default)]
+    pub frame_ancestors: Option<String>,
+    /// How `OPTIONS` requests are answered (default forward).
+    #[serde(default)]
+    pub options: Option<OptionsPolicy>,
+    /// Retry `HEAD` as `GET` when the upstream rejects it (default true).
+    #[serde(default)]
+    pub head_fallback: Option<bool>,
+}
+
+impl BehaviorRule {
+    fn or(&self, fallback: &BehaviorRule) -> BehaviorRule {
+        BehaviorRule {
+            inject_html: self.inject_html.or(fallback.inject_html),
+            service_worker: self.service_worker.or(fallback.service_worker),
+            cors: self.cors.or(fallback.cors),
+            frame_ancestors: self
+                .frame_ancestors
+                .clone()
+                .or_else(|| fallback.frame_ancestors.clone()),
+            options: self.options.or(fallback.options),
+            head_fallback: self.head_fallback.or(fallback.head_fallback),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorsPolicy {
+    Keep,
+    Add,
+    Strip,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OptionsPolicy {
+    /// Send the request upstream.
+    Forward,
+    /// Answer `204` without headers.
+    NoContent,
+    /// Answer `204` with permissive CORS headers.
+    Cors,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Pattern {
+    /// Leading literal segments including the trailing `-`, e.g. `port-`.
+    prefix: String,
+    tokens: Vec<Token>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Literal(String),
+    Capture {
+        name: String,
+        min: usize,
+        repeat: bool,
+    },
+}
+
+impl TryFrom<String> for Pattern {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        let mut tokens = Vec::new();
+        for segment in source.split('-') {
+            let token = match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(inner) => {
+                    let (name, min, repeat) = match inner.as_bytes().last() {
+                        Some(b'+') => (&inner[..inner.len() - 1], 1, true),
+                        Some(b'*') => (&inner[..inner.len() - 1], 0, true),
+                        _ => (inner, 1, false),
+                    };
+                    if name.is_empty()
+                        || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                    {
+                        return Err(format!("invalid capture {{{}}} in {:?}", inner, source));
+                    }
+                    Token::Capture {
+                        name: name.to_string(),
+                        min,
+                        repeat,
+                    }
+                }
+                None if segment.is_empty() || segment.contains(['{', '}']) => {
+                    return Err(format!("invalid segment {:?} in {:?}", segment, source));
+                }
+                None => Token::Literal(segment.to_string()),
+            };
+            tokens.push(token);
+        }
+
+        let repeats = tokens
+            .iter()
+            .filter(|t| matches!(t, Token::Capture { repeat: true, .. }))
+            .count();
+        if repeats > 1 {
+            return Err(format!("{:?} has more than one repeated capture", source));
+        }
+        let mut names: Vec<&str> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Capture { name, .. } => Some(name.as_str()),
+                Token::Literal(_) => None,
+            })
+            .collect();
+        if !names.contains(&"port") {
+            return Err(format!("{:?} has no {{port}} capture", source));
+        }
+        names.sort_unstable();
+        if names.windows(2).any(|w| w[0] == w[1]) {
+            return Err(format!("{:?} captures a name twice", source));
+        }
+
+        let prefix: String = tokens
+            .iter()
+            .map_while(|t| match t {
+                Token::Literal(literal) => Some(format!("{}-", literal)),
+                Token::Capture { .. } => None,
+            })
+            .collect();
+        Ok(Pattern { prefix, tokens })
+    }
+}
+
+impl Pattern {
+    fn captures(&self) -> impl Iterator<Item = &str> {
+        self.tokens.iter().filter_map(|t| match t {
+            Token::Capture { name, .. } => Some(name.as_str()),
+            Token::Literal(_) => None,
+        })
+    }
+
+    /// `None` if the subdomain isn't claimed by this pattern; otherwise the
+    /// captures with their minimum segment count, or `Err` if the segments
+    /// don't line up with the pattern.
+    fn match_subdomain(&self, subdomain: &str) -> Option<Result<Vec<Captured<'_>>, ()>> {
+        if !subdomain.starts_with(&self.prefix) {
+            return None;
+        }
+        Some(self.split(subdomain))
+    }
+
+    fn split(&self, subdomain: &str) -> Result<Vec<Captured<'_>>, ()> {
+        let segments: Vec<&str> = subdomain.split('-').collect();
+        let fixed = self
+            .tokens
+            .iter()
+            .filter(|t| !matches!(t, Token::Capture { repeat: true, .. }))
+            .count();
+        let repeat_min = self.tokens.iter().find_map(|t| match t {
+            Token::Capture {
+                repeat: true, min, ..
+            } => Some(*min),
+            _ => None,
+        });
+        let spare = match repeat_min {
+            Some(min) if segments.len() >= fixed + min => segments.len() - fixed,
+            None if segments.len() == fixed => 0,
+            _ => return Err(()),
+        };
+
+        let mut captures = Vec::new();
+        let mut rest = segments.as_slice();
+        for token in &self.tokens {
+            match token {
+                Token::Literal(literal) => {
+                    if rest[0] != literal {
+                        return Err(());
+                    }
+                    rest = &rest[1..];
+                }
+                Token::Capture { name, min, repeat } => {
+                    let take = if *repeat { spare } else { 1 };
+                    captures.push(Captured {
+                        name,
+                        min: *min,
+                        value: rest[..take].join("-"),
+                    });
+                    rest = &rest[take..];
+                }
+            }
+        }
+        Ok(captures)
+    }
+}
+
+struct Captured<'a> {
+    name: &'a str,
+    min: usize,
+    value: String,
+}
+
+impl RoutingConfig {
+    /// The rules embedded from `default_routes.json`
+    pub fn builtin() -> Result<Self, RoutingError> {
+        RoutingConfig::from_json(DEFAULT_ROUTES)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, RoutingError> {
+        let config: RoutingConfig = serde_json::from_str(json)?;
+        for route in &config.routes {
+            route.validate()?;
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, RoutingError> {
+        let json = fs::read_to_string(path).map_err(|source| RoutingError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// Matches `subdomain` against the routes. `vars` holds the configured
+    /// domain suffixes; `None` means no route claims the subdomain.
+    pub(crate) fn resolve(
+        &self,
+        subdomain: &str,
+        vars: &HashMap<&str, String>,
+    ) -> Option<Resolution> {
+        self.routes
+            .iter()
+            .find_map(|route| Some(route.resolve(route.pattern.match_subdomain(subdomain)?, vars)))
+    }
+}
+
+pub(crate) enum Resolution {
+    Route(ResolvedRoute),
+    /// The subdomain is malformed; the message is sent as a 400.
+    Invalid(String),
+    /// The upstream template rendered to something that isn't a URL.
+    BadUpstream,
+}
+
+pub(crate) struct ResolvedRoute {
+    pub name: String,
+    pub port: u16,
+    pub sandbox: String,
+    pub target: Target,
+    pub options: OptionsPolicy,
+    pub behavior: ProxyBehavior,
+}
+
+impl RouteRule {
+    fn validate(&self) -> Result<(), RoutingError> {
+        let invalid = |message: String| RoutingError::Invalid {
+            route: self.name.clone(),
+            message,
+        };
+        let captures: Vec<&str> = self.pattern.captures().collect();
+        let templates = [
+            Some(&self.sandbox),
+            self.upstream.as_ref(),
+            self.workspace_header.as_ref(),
+            self.port_header.as_ref(),
+        ];
+        for template in templates.into_iter().flatten() {
+            for name in template_names(template).map_err(&invalid)? {
+                if !captures.contains(&name) && !VARIABLES.contains(&name) {
+                    return Err(invalid(format!(
+                        "unknown variable {{{}}} in {:?}",
+                        name, template
+                    )));
+                }
+            }
+        }
+        for name in self.empty_values.keys() {
+            if !captures.contains(&name.as_str()) {
+                return Err(invalid(format!(
+                    "empty_values names unknown capture {:?}",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve(
+        &self,
+        matched: Result<Vec<Captured<'_>>, ()>,
+        vars: &HashMap<&str, String>,
+    ) -> Resolution {
+        let error = |name: &str| {
+            let message = self
+                .errors
+                .get(name)
+                .or_else(|| self.errors.get("invalid"))
+                .cloned()
+                .unwrap_or_else(|| "Invalid cmux subdomain".to_string());
+            Resolution::Invalid(message)
+        };
+        let Ok(captures) = matched else {
+            return error("invalid");
+        };
+
+        let mut values: HashMap<&str, String> = HashMap::new();
+        let mut port = None;
+        for Captured {
+            name,
+            min,
+            mut value,
+        } in captures
+        {
+            if self
+                .empty_values
+                .get(name)
+                .is_some_and(|empty| empty.iter().any(|e| e.eq_ignore_ascii_case(&value)))
+            {
+                value.clear();
+            }
+            if name == "port" {
+                match value.parse::<u16>() {
+                    Ok(parsed) => port = Some(parsed),
+                    Err(_) => return error("port"),
+                }
+            } else if min > 0 && value.is_empty() {
+                return error(name);
+            }
+            values.insert(name, value);
+        }
+        let Some(port) = port else {
+            return error("port");
+        };
+        for (name, value) in vars {
+            values.insert(name, value.clone());
+        }
+
+        let render = |template: &Option<String>| {
+            template
+                .as_deref()
+                .and_then(|t| render(t, &values))
+                .filter(|v| !v.is_empty())
+        };
+        let target = match render(&self.upstream) {
+            Some(url) => match absolute_target(&url) {
+                Some(target) => target,
+                None => return Resolution::BadUpstream,
+            },
+            None => Target::BackendPort(port),
+        };
+
+        let behavior = match self.ports.get(&port) {
+            Some(overrides) => overrides.or(&self.behavior),
+            None => self.behavior.clone(),
+        };
+        let cors = behavior.cors.unwrap_or(CorsPolicy::Keep);
+        Resolution::Route(ResolvedRoute {
+            name: self.name.clone(),
+            port,
+            sandbox: render(&Some(self.sandbox.clone())).unwrap_or_default(),
+            target,
+            options: behavior.options.unwrap_or(OptionsPolicy::Forward),
+            behavior: ProxyBehavior {
+                inject_html: behavior.inject_html.unwrap_or(true),
+                head_fallback: behavior.head_fallback.unwrap_or(true),
+                skip_service_worker: !behavior.service_worker.unwrap_or(true),
+                add_cors: cors == CorsPolicy::Add,
+                strip_cors_headers: cors == CorsPolicy::Strip,
+                workspace_header: render(&self.workspace_header),
+                port_header: render(&self.port_header),
+                frame_ancestors: behavior
+                    .frame_ancestors
+                    .map(|sources| format!("frame-ancestors {};", sources)),
+            },
+        })
+    }
+}
+
+fn template_names(template: &str) -> Result<Vec<&str>, String> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            return Err(format!("unclosed '{{' in {:?}", template));
+        };
+        names.push(&rest[start + 1..start + len]);
+        rest = &rest[start + len + 1..];
+    }
+    Ok(names)
+}
+
+/// `None` when a variable isn't set.
+fn render(template: &str, values: &HashMap<&str, String>) -> Option<String> {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}')?;
+        out.push_str(&rest[..start]);
+        out.push_str(values.get(&rest[start + 1..end])?);
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+fn absolute_target(url: &str) -> Option<Target> {
+    let uri: Uri = url.parse().ok()?;
+    let scheme = uri.scheme().cloned().unwrap_or(Scheme::HTTPS);
+    let authority = uri.authority()?;
+    Some(Target::Absolute {
+        scheme,
+        host: authority.host().to_string(),
+        port: authority.port_u16(),
+    })
+}
+
+/// Reloads `path` into `routes` whenever the file changes, keeping the
+/// current routes if the new file is invalid.
+pub(crate) fn spawn_reloader(
+    path: PathBuf,
+    routes: Arc<RwLock<Arc<RoutingConfig>>>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let stamp = |path: &Path| -> Option<(SystemTime, u64)> {
+            let meta = fs::metadata(path).ok()?;
+            Some((meta.modified().ok()?, meta.len()))
+        };
+        let mut last = stamp(&path);
+        loop {
+            tokio::time::sleep(RELOAD_INTERVAL).await;
+            let current = stamp(&path);
+            if current == last {
+                continue;
+            }
+            last = current;
+            match RoutingConfig::load(&path) {
+                Ok(config) => {
+                    info!(path = %path.display(), routes = config.routes.len(), "reloaded routes");
+                    // The config is only ever swapped whole, so a poisoned
+                    // lock still guards a consistent value
+                    *routes.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(config);
+                }
+                Err(err) => warn!(%err, "keeping previous routes"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(subdomain: &str) -> Resolution {
+        RoutingConfig::builtin()
+            .expect("builtin routes")
+            .resolve(subdomain, &HashMap::new())
+            .expect("claimed")
+    }
+
+    fn route(subdomain: &str) -> ResolvedRoute {
+        match resolve(subdomain) {
+            Resolution::Route(route) => route,
+            Resolution::Invalid(message) => panic!("{subdomain}: {message}"),
+            Resolution::BadUpstream => panic!("{subdomain}: bad upstream"),
+        }
+    }
+
+    fn invalid(subdomain: &str) -> String {
+        match resolve(subdomain) {
+            Resolution::Invalid(message) => message,
+            _ => panic!("{subdomain} should be invalid"),
+        }
+    }
+
+    #[test]
+    fn default_routes_parse_subdomains() {
+        let port = route("port-39378-abc-def");
+        assert_eq!((port.port, port.sandbox.as_str()), (39378, "abc-def"));
+        assert!(port.behavior.skip_service_worker && port.behavior.strip_cors_headers);
+        assert_eq!(port.options, OptionsPolicy::NoContent);
+        assert!(!route("port-3000-abc").behavior.skip_service_worker);
+
+        let cmux = route("cmux-abc-my-scope-8080");
+        assert_eq!(cmux.behavior.workspace_header.as_deref(), Some("my-scope"));
+        assert_eq!(cmux.behavior.port_header.as_deref(), Some("8080"));
+        assert_eq!(route("cmux-abc-BASE-8080").behavior.workspace_header, None);
+        assert_eq!(route("cmux-abc-8080").options, OptionsPolicy::Cors);
+
+        let workspace = route("my-ws-5173-vm1");
+        assert_eq!(workspace.sandbox, "vm1");
+        assert_eq!(
+            workspace.behavior.workspace_header.as_deref(),
+            Some("my-ws")
+        );
+    }
+
+    #[test]
+    fn default_routes_report_invalid_subdomains() {
+        assert_eq!(invalid("port-abc-x"), "Invalid cmux proxy subdomain");
+        assert_eq!(invalid("port-8080"), "Invalid cmux proxy subdomain");
+        assert_eq!(invalid("cmux-test"), "Invalid cmux proxy subdomain");
+        assert_eq!(
+            invalid("cmux--8080"),
+            "Missing morph id in cmux proxy subdomain"
+        );
+        assert_eq!(
+            invalid("cmux-test-abc"),
+            "Invalid port in cmux proxy subdomain"
+        );
+        assert_eq!(invalid("test-8080"), "Invalid cmux subdomain");
+        assert_eq!(invalid("ws-abc-vm"), "Invalid port in subdomain");
+    }
+
+    #[test]
+    fn upstream_needs_its_suffix() {
+        let vars = HashMap::from([("morph_domain_suffix", ".morph.example".to_string())]);
+        let config = RoutingConfig::builtin().expect("builtin routes");
+        let Some(Resolution::Route(route)) = config.resolve("port-3000-abc", &vars) else {
+            panic!("port route");
+        };
+        assert!(matches!(
+            route.target,
+            Target::Absolute { ref host, port: None, .. } if host == "port-3000-morphvm-abc.morph.example"
+        ));
+        let Some(Resolution::Route(route)) = config.resolve("ws-3000-vm", &vars) else {
+            panic!("workspace route");
+        };
+        assert!(matches!(route.target, Target::BackendPort(3000)));
+    }
+
+    #[test]
+    fn invalid_configs_are_rejected() {
+        for json in [
+            r#"{"routes":[{"name":"a","pattern":"app-{id}","sandbox":"{id}"}]}"#,
+            r#"{"routes":[{"name":"a","pattern":"{a*}-{b+}-{port}","sandbox":""}]}"#,
+            r#"{"routes":[{"name":"a","pattern":"app-{port}","sandbox":"{id}"}]}"#,
+            r#"{"routes":[{"name":"a","pattern":"app-{port}","sandbox":"","cors":"maybe"}]}"#,
+        ] {
+            assert!(RoutingConfig::from_json(json).is_err(), "{json}");
+        }
+    }
+}
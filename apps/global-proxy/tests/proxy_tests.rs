@@ -1,11 +1,18 @@
 use std::{
+    io::{Read, Write},
     net::{Ipv4Addr, SocketAddr},
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+use flate2::{Compression, write::GzEncoder};
 use futures_util::{SinkExt, StreamExt};
-use global_proxy::{ProxyConfig, spawn_proxy};
+use global_proxy::{
+    ProxyConfig,
+    auth::{Access, AuthConfig, Claims, StaticKeyVerifier, sign_hs256},
+    routing::{DEFAULT_ROUTES, RoutingConfig},
+    spawn_proxy,
+};
 use hyper::{
     Body, Method as HyperMethod, Request, Response, Server, StatusCode,
     header::HeaderValue,
@@ -27,10 +34,22 @@ struct TestProxy {
 
 impl TestProxy {
     async fn spawn() -> Self {
+        Self::spawn_with_auth(None).await
+    }
+
+    async fn spawn_with_auth(auth: Option<AuthConfig>) -> Self {
+        Self::spawn_with_config(ProxyConfig {
+            auth,
+            ..Default::default()
+        })
+        .await
+    }
+
+    async fn spawn_with_config(config: ProxyConfig) -> Self {
         let config = ProxyConfig {
             bind_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
             backend_host: "127.0.0.1".to_string(),
-            ..Default::default()
+            ..config
         };
 
         let handle = spawn_proxy(config).await.expect("failed to start proxy");
@@ -790,6 +809,112 @@ async fn html_with_unsupported_encoding_skips_rewrite() {
     backend.shutdown().await;
 }
 
+#[tokio::test]
+async fn html_responses_stream_before_upstream_finishes() {
+    let (release_tx, release_rx) = oneshot::channel::<()>();
+    let release_rx = Arc::new(Mutex::new(Some(release_rx)));
+    let backend = TestHttpBackend::serve(Arc::new(move |_req| {
+        let (mut sender, body) = Body::channel();
+        let release = release_rx.lock().unwrap().take();
+        tokio::spawn(async move {
+            let _ = sender
+                .send_data("<html><head><title>Demo</title></head><body>Start".into())
+                .await;
+            if let Some(release) = release {
+                let _ = release.await;
+            }
+            let _ = sender.send_data("End</body></html>".into()).await;
+        });
+        Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "text/html")
+            .body(body)
+            .unwrap()
+    }))
+    .await;
+
+    let proxy = TestProxy::spawn().await;
+    let host = format!("port-{}-test.cmux.sh", backend.port());
+
+    let mut response = proxy.request(Method::GET, &host, "/", &[]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut body = String::new();
+    while !body.contains("Start") {
+        let chunk = response
+            .chunk()
+            .await
+            .expect("chunk")
+            .expect("body ended before upstream finished");
+        body.push_str(std::str::from_utf8(&chunk).expect("utf8"));
+    }
+    assert!(body.contains("window.__cmuxLocation"));
+    assert!(!body.contains("End"));
+
+    release_tx.send(()).unwrap();
+    while let Some(chunk) = response.chunk().await.expect("chunk") {
+        body.push_str(std::str::from_utf8(&chunk).expect("utf8"));
+    }
+    assert!(body.ends_with("End</body></html>"));
+
+    proxy.shutdown().await;
+    backend.shutdown().await;
+}
+
+#[tokio::test]
+async fn html_responses_are_reencoded_for_the_client() {
+    let backend = TestHttpBackend::serve(Arc::new(|_req| {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(b"<html><head><title>Demo</title></head><body>Hello</body></html>")
+            .unwrap();
+        Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "text/html")
+            .header("content-encoding", "gzip")
+            .header("vary", "origin")
+            .body(Body::from(encoder.finish().unwrap()))
+            .unwrap()
+    }))
+    .await;
+
+    let proxy = TestProxy::spawn().await;
+    let host = format!("port-{}-test.cmux.sh", backend.port());
+
+    let response = proxy
+        .request(Method::GET, &host, "/", &[("accept-encoding", "br")])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(
+        headers
+            .get("content-encoding")
+            .and_then(|v| v.to_str().ok()),
+        Some("br")
+    );
+    // The upstream's Vary is kept alongside the one for the re-encoding
+    assert_eq!(
+        headers.get("vary").and_then(|v| v.to_str().ok()),
+        Some("origin, accept-encoding")
+    );
+    assert!(headers.get("content-length").is_none());
+    let compressed = response.bytes().await.expect("body");
+    let mut body = String::new();
+    brotli::Decompressor::new(compressed.as_ref(), 4096)
+        .read_to_string(&mut body)
+        .expect("brotli body");
+    assert!(body.contains("window.__cmuxLocation"));
+    assert!(body.ends_with("<body>Hello</body></html>"));
+
+    // Clients that accept nothing get identity.
+    let response = proxy.request(Method::GET, &host, "/", &[]).await;
+    assert!(response.headers().get("content-encoding").is_none());
+    let body = response.text().await.expect("body");
+    assert!(body.contains("window.__cmuxLocation"));
+
+    proxy.shutdown().await;
+    backend.shutdown().await;
+}
+
 #[tokio::test]
 async fn html_responses_skip_service_worker_for_cmux_route() {
     let backend = TestHttpBackend::serve(Arc::new(|_req| {
@@ -1091,6 +1216,418 @@ async fn head_method_not_allowed_falls_back_to_get_with_length() {
     backend.shutdown().await;
 }
 
+const TEST_SIGNING_KEY: &[u8] = b"test-signing-key";
+
+fn test_token(sandbox: &str, ttl_secs: i64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    sign_hs256(
+        TEST_SIGNING_KEY,
+        &Claims {
+            sub: Some("user".to_string()),
+            sandbox: sandbox.to_string(),
+            exp: (now + ttl_secs) as u64,
+            nbf: None,
+        },
+    )
+}
+
+async fn spawn_auth_proxy(public_port: Option<u16>) -> TestProxy {
+    let mut auth = AuthConfig::new(Arc::new(StaticKeyVerifier::hs256(TEST_SIGNING_KEY)));
+    if let Some(port) = public_port {
+        auth.port_access.insert(port, Access::Public);
+    }
+    TestProxy::spawn_with_auth(Some(auth)).await
+}
+
+fn cookie_echo_backend() -> Arc<dyn Fn(Request<Body>) -> Response<Body> + Send + Sync> {
+    Arc::new(|req: Request<Body>| {
+        let cookie = req
+            .headers()
+            .get("cookie")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+        Response::builder()
+            .status(StatusCode::OK)
+            .body(Body::from(format!("{} cookie={}", req.uri(), cookie)))
+            .unwrap()
+    })
+}
+
+#[tokio::test]
+async fn auth_requires_token_or_session() {
+    let backend = TestHttpBackend::serve(cookie_echo_backend()).await;
+    let proxy = spawn_auth_proxy(None).await;
+    let host = format!("port-{}-abc.cmux.sh", backend.port());
+
+    let response = proxy.request(Method::GET, &host, "/page", &[]).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // A valid token is exchanged for a session cookie and stripped from the URL.
+    let path = format!("/page?x=1&cmux_token={}", test_token("abc", 60));
+    let response = proxy.request(Method::GET, &host, &path, &[]).await;
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(
+        response
+            .headers()
+            .get("location")
+            .and_then(|v| v.to_str().ok()),
+        Some("/page?x=1")
+    );
+    let set_cookie = response
+        .headers()
+        .get("set-cookie")
+        .and_then(|v| v.to_str().ok())
+        .expect("session cookie")
+        .to_string();
+    assert!(set_cookie.contains("HttpOnly"));
+    assert!(
+        !set_cookie.contains("Domain="),
+        "cookie must stay on the subdomain"
+    );
+    let session = set_cookie.split(';').next().unwrap().to_string();
+
+    let cookie = format!("theme=dark; {}", session);
+    let response = proxy
+        .request(Method::GET, &host, "/page?x=1", &[("cookie", &cookie)])
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.text().await.expect("body"),
+        "/page?x=1 cookie=theme=dark",
+        "the session cookie is not forwarded"
+    );
+
+    // Sessions are bound to the sandbox they were issued for.
+    let other = TestHttpBackend::serve(cookie_echo_backend()).await;
+    let other_host = format!("port-{}-xyz.cmux.sh", other.port());
+    let response = proxy
+        .request(Method::GET, &other_host, "/", &[("cookie", &session)])
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    proxy.shutdown().await;
+    backend.shutdown().await;
+    other.shutdown().await;
+}
+
+#[tokio::test]
+async fn auth_rejects_bad_tokens_and_allows_public_ports() {
+    let backend = TestHttpBackend::serve(cookie_echo_backend()).await;
+    let proxy = spawn_auth_proxy(None).await;
+    let host = format!("port-{}-abc.cmux.sh", backend.port());
+
+    for token in [
+        test_token("abc", -120),
+        test_token("xyz", 60),
+        test_token("abc", 60).replace('.', "x"),
+    ] {
+        let path = format!("/?cmux_token={}", token);
+        let response = proxy.request(Method::GET, &host, &path, &[]).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{token}");
+    }
+
+    // Non-browser requests with a token go straight through.
+    let path = format!("/api?cmux_token={}", test_token("abc", 60));
+    let response = proxy.request(Method::POST, &host, &path, &[]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.expect("body"), "/api cookie=");
+    proxy.shutdown().await;
+
+    let proxy = spawn_auth_proxy(Some(backend.port())).await;
+    let response = proxy.request(Method::GET, &host, "/", &[]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    proxy.shutdown().await;
+    backend.shutdown().await;
+}
+
+fn html_backend() -> Arc<dyn Fn(Request<Body>) -> Response<Body> + Send + Sync> {
+    Arc::new(|_req| {
+        Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "text/html")
+            .body(Body::from("<html><head></head><body>app</body></html>"))
+            .unwrap()
+    })
+}
+
+fn write_routes_file(name: &str, routes: &str) -> std::path::PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("global-proxy-{}-{}.json", name, std::process::id()));
+    std::fs::write(&path, routes).expect("write routes");
+    path
+}
+
+const APP_ROUTES: &str = r#"{
+  "routes": [
+    {
+      "name": "app",
+      "pattern": "app-{port}-{id}",
+      "errors": { "invalid": "Bad app subdomain" },
+      "sandbox": "{id}",
+      "port_header": "{port}",
+      "inject_html": false,
+      "cors": "add",
+      "frame_ancestors": "'self'",
+      "options": "cors"
+    }
+  ]
+}"#;
+
+#[tokio::test]
+async fn custom_routes_replace_the_defaults() {
+    let backend = TestHttpBackend::serve(html_backend()).await;
+    let proxy = TestProxy::spawn_with_config(ProxyConfig {
+        routes: Some(RoutingConfig::from_json(APP_ROUTES).expect("routes")),
+        ..Default::default()
+    })
+    .await;
+
+    let host = format!("app-{}-abc.cmux.sh", backend.port());
+    let response = proxy.request(Method::GET, &host, "/", &[]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers().clone();
+    assert_eq!(
+        headers
+            .get("access-control-allow-origin")
+            .and_then(|v| v.to_str().ok()),
+        Some("*")
+    );
+    assert_eq!(
+        headers
+            .get("content-security-policy")
+            .and_then(|v| v.to_str().ok()),
+        Some("frame-ancestors 'self';")
+    );
+    assert_eq!(
+        response.text().await.expect("body"),
+        "<html><head></head><body>app</body></html>"
+    );
+
+    let response = proxy.request(Method::OPTIONS, &host, "/", &[]).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = proxy
+        .request(Method::GET, "app-x-abc.cmux.sh", "/", &[])
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.text().await.expect("body"), "Bad app subdomain");
+
+    let response = proxy
+        .request(
+            Method::GET,
+            &format!("port-{}-abc.cmux.sh", backend.port()),
+            "/",
+            &[],
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    proxy.shutdown().await;
+    backend.shutdown().await;
+}
+
+#[tokio::test]
+async fn routes_file_is_reloaded_on_change() {
+    let backend = TestHttpBackend::serve(html_backend()).await;
+    let path = write_routes_file("reload", DEFAULT_ROUTES);
+    let proxy = TestProxy::spawn_with_config(ProxyConfig {
+        routes_file: Some(path.clone()),
+        ..Default::default()
+    })
+    .await;
+
+    // Until the reload, the default workspace route handles this subdomain.
+    let host = format!("app-{}-abc.cmux.sh", backend.port());
+    let allow_origin = |response: &reqwest::Response| {
+        response
+            .headers()
+            .contains_key("access-control-allow-origin")
+    };
+    let response = proxy.request(Method::GET, &host, "/", &[]).await;
+    assert!(!allow_origin(&response));
+
+    std::fs::write(&path, APP_ROUTES).expect("rewrite routes");
+    let mut reloaded = false;
+    for _ in 0..50 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let response = proxy.request(Method::GET, &host, "/", &[]).await;
+        if allow_origin(&response) {
+            reloaded = true;
+            break;
+        }
+    }
+    assert!(reloaded, "routes were not reloaded");
+
+    // An invalid file keeps the last good routes.
+    std::fs::write(&path, "{ not json").expect("break routes");
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let response = proxy.request(Method::GET, &host, "/", &[]).await;
+    assert!(allow_origin(&response));
+
+    proxy.shutdown().await;
+    backend.shutdown().await;
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn request_ids_are_propagated() {
+    let backend = TestHttpBackend::serve(Arc::new(|req: Request<Body>| {
+        let id = req
+            .headers()
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+        Response::builder()
+            .status(StatusCode::OK)
+            .body(Body::from(id))
+            .unwrap()
+    }))
+    .await;
+    let proxy = TestProxy::spawn().await;
+    let host = format!("port-{}-abc.cmux.sh", backend.port());
+
+    let response = proxy
+        .request(Method::GET, &host, "/", &[("x-request-id", "req-123")])
+        .await;
+    assert_eq!(
+        response
+            .headers()
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok()),
+        Some("req-123")
+    );
+    assert_eq!(response.text().await.expect("body"), "req-123");
+
+    let response = proxy.request(Method::GET, &host, "/", &[]).await;
+    let generated = response
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .expect("generated id")
+        .to_string();
+    assert_eq!(response.text().await.expect("body"), generated);
+    assert_eq!(generated.len(), 36);
+
+    proxy.shutdown().await;
+    backend.shutdown().await;
+}
+
+#[tokio::test]
+async fn metrics_count_proxied_requests() {
+    let backend = TestHttpBackend::serve(Arc::new(|_req| {
+        Response::builder()
+            .status(StatusCode::OK)
+            .body(Body::from("hello"))
+            .unwrap()
+    }))
+    .await;
+    let proxy = TestProxy::spawn_with_config(ProxyConfig {
+        metrics_token: Some("scrape".to_string()),
+        ..Default::default()
+    })
+    .await;
+    let port = backend.port();
+    let host = format!("port-{}-abc.cmux.sh", port);
+
+    let response = proxy.request(Method::GET, &host, "/", &[]).await;
+    assert_eq!(response.text().await.expect("body"), "hello");
+    let response = proxy
+        .request(Method::GET, "port-1-abc.cmux.sh", "/", &[])
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+
+    // Metrics need the token.
+    let response = proxy.request(Method::GET, "cmux.sh", "/metrics", &[]).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = proxy
+        .request(
+            Method::GET,
+            "cmux.sh",
+            "/metrics",
+            &[("authorization", "Bearer wrong")],
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Subdomain requests are proxied, so metrics are only served on the apex.
+    let response = proxy
+        .request(
+            Method::GET,
+            "cmux.sh",
+            "/metrics",
+            &[("authorization", "Bearer scrape")],
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let text = response.text().await.expect("body");
+    for series in [
+        format!(r#"global_proxy_requests_total{{port="{port}",route="port",status="200"}} 1"#),
+        format!(r#"global_proxy_request_duration_seconds_count{{port="{port}",route="port"}} 1"#),
+        // Ports that got no upstream response don't get their own series.
+        r#"global_proxy_requests_total{port="other",route="port",status="502"} 1"#.to_string(),
+        r#"global_proxy_upstream_errors_total{route="port"} 1"#.to_string(),
+        // "hello" plus the proxy's own "Upstream fetch failed" body.
+        r#"global_proxy_bytes_total{direction="downstream",route="port"} 26"#.to_string(),
+    ] {
+        assert!(text.contains(&series), "missing {series} in:\n{text}");
+    }
+    assert!(!text.contains(r#"port="1""#), "{text}");
+
+    proxy.shutdown().await;
+    backend.shutdown().await;
+}
+
+#[tokio::test]
+async fn metrics_do_not_label_rejected_ports() {
+    let proxy = TestProxy::spawn_with_config(ProxyConfig {
+        auth: Some(AuthConfig::new(Arc::new(StaticKeyVerifier::hs256(
+            TEST_SIGNING_KEY,
+        )))),
+        metrics_token: Some("scrape".to_string()),
+        ..Default::default()
+    })
+    .await;
+
+    for port in [4101, 4102] {
+        let host = format!("port-{}-abc.cmux.sh", port);
+        let response = proxy.request(Method::GET, &host, "/", &[]).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    let text = proxy
+        .request(
+            Method::GET,
+            "cmux.sh",
+            "/metrics",
+            &[("authorization", "Bearer scrape")],
+        )
+        .await
+        .text()
+        .await
+        .expect("body");
+    assert!(
+        text.contains(r#"global_proxy_requests_total{port="other",route="port",status="401"} 2"#),
+        "{text}"
+    );
+    assert!(!text.contains("4101"), "{text}");
+
+    proxy.shutdown().await;
+}
+
+#[tokio::test]
+async fn metrics_are_disabled_without_a_token() {
+    let proxy = TestProxy::spawn().await;
+    let response = proxy.request(Method::GET, "cmux.sh", "/metrics", &[]).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    proxy.shutdown().await;
+}
+
 #[tokio::test]
 async fn websocket_proxy_for_cmux_route_forwards_workspace_header() {
     let (backend, header_rx) = TestWsBackend::spawn_capture_workspace_header().await;
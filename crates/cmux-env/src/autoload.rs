@@ -0,0 +1,316 @@
+// Automatic loading of `.env` / `.envrc.json` files, direnv style.
+//
+// Each `Export` carries the shell's pwd. Before computing the diff the daemon
+// looks for env files in the pwd and its ancestors and loads the ones the user
+// has allowed into that directory's scope. A file is allowed by content: the
+// trust list (`allowed.json`, next to the state snapshot) maps each file to the
+// SHA-256 of the contents `envctl allow` saw; each check reads the file once and
+// loads exactly the bytes it hashed. When a loaded file changes it is
+// reloaded if its new contents are allowed too; otherwise (or when the file is
+// removed) the values it set are taken back out until it is allowed again.
+// Files that aren't allowed are reported once per version through the export
+// script, so the shell shows why nothing was loaded.
+//
+// The values themselves persist with the rest of the state, so what was loaded
+// from which file is persisted too (`autoloaded.json`, holding digests rather
+// than values since some of them are secrets). A key is only taken back out if
+// it still holds the value the file gave it; one changed since, e.g. with
+// `envctl set`, is left alone.
+//
+// `.env` uses the dotenv grammar (see `dotenv.rs`); `.envrc.json` is a flat
+// JSON object of string, number or boolean values. When a directory has both,
+// `.envrc.json` wins.
+
+use anyhow::{anyhow, Context, Result};
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::{canon, dotenv, Scope, State};
+
+/// Files loaded from a directory, in load order
+pub const ENV_FILES: &[&str] = &[".env", ".envrc.json"];
+
+const ALLOW_FILE: &str = "allowed.json";
+const LOADED_FILE: &str = "autoloaded.json";
+
+/// A file's SHA-256 (hex) and the bytes it was computed over
+type Contents = (String, Vec<u8>);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AllowList {
+    /// File path -> allowed SHA-256 (hex)
+    files: BTreeMap<PathBuf, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Loaded {
+    hash: String,
+    /// Key -> SHA-256 (hex) of the value loaded
+    values: BTreeMap<String, String>,
+}
+
+#[derive(Debug)]
+pub struct Autoload {
+    path: PathBuf,
+    loaded_path: PathBuf,
+    allowed: AllowList,
+    loaded: BTreeMap<PathBuf, Loaded>,
+    /// (file, hash) pairs already reported as not allowed
+    warned: HashSet<(PathBuf, String)>,
+}
+
+impl Autoload {
+    /// Load the trust list and the record of loaded files kept in `dir`.
+    /// Records of keys missing from `st` (e.g. the daemon stopped before the
+    /// state was saved) are dropped, so those files load again.
+    pub fn open(dir: &Path, st: &State) -> Result<Self> {
+        let path = dir.join(ALLOW_FILE);
+        let allowed = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .with_context(|| format!("parse {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => AllowList::default(),
+            Err(e) => return Err(e).with_context(|| format!("read {}", path.display())),
+        };
+        let loaded_path = dir.join(LOADED_FILE);
+        let mut loaded: BTreeMap<PathBuf, Loaded> = match fs::read(&loaded_path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                eprintln!("envd: ignoring {}: {}", loaded_path.display(), e);
+                BTreeMap::new()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("read {}", loaded_path.display())),
+        };
+        loaded.retain(|file, record| {
+            let values = file.parent().and_then(|dir| st.scoped.get(dir));
+            record
+                .values
+                .keys()
+                .all(|key| values.is_some_and(|values| values.contains_key(key)))
+        });
+        Ok(Autoload {
+            path,
+            loaded_path,
+            allowed,
+            loaded,
+            warned: HashSet::new(),
+        })
+    }
+
+    /// Trust the current contents of the env files in `dir` and load them
+    pub fn allow(&mut self, st: &mut State, dir: &Path) -> Result<Vec<PathBuf>> {
+        let dir = canon(dir);
+        let mut files = Vec::new();
+        for file in env_files(&dir) {
+            let data = fs::read(&file).with_context(|| format!("read {}", file.display()))?;
+            self.allowed.files.insert(file.clone(), sha256_hex(&data));
+            files.push(file);
+        }
+        if files.is_empty() {
+            return Err(anyhow!(
+                "no {} in {}",
+                ENV_FILES.join(" or "),
+                dir.display()
+            ));
+        }
+        self.save()?;
+        let mut notices = Vec::new();
+        self.sync_dir(st, &dir, &mut notices);
+        if let Some(notice) = notices.into_iter().next() {
+            return Err(anyhow!(notice));
+        }
+        Ok(files)
+    }
+
+    /// Stop trusting the env files in `dir` and unload what they set
+    pub fn deny(&mut self, st: &mut State, dir: &Path) -> Result<Vec<PathBuf>> {
+        let dir = canon(dir);
+        let files: Vec<PathBuf> = ENV_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .filter(|file| self.allowed.files.contains_key(file))
+            .collect();
+        if files.is_empty() {
+            return Err(anyhow!("nothing allowed in {}", dir.display()));
+        }
+        for file in &files {
+            self.allowed.files.remove(file);
+        }
+        self.save()?;
+        let mut notices = Vec::new();
+        self.sync_dir(st, &dir, &mut notices);
+        Ok(files)
+    }
+
+    /// Bring the env files of `pwd` and its ancestors up to date, returning
+    /// messages to show in the shell
+    pub fn refresh(&mut self, st: &mut State, pwd: &Path) -> Vec<String> {
+        let pwd = canon(pwd);
+        let mut notices = Vec::new();
+        let mut dirs: Vec<&Path> = pwd.ancestors().collect();
+        // Outermost first, so nested directories load after their parents
+        dirs.reverse();
+        for dir in dirs {
+            self.sync_dir(st, dir, &mut notices);
+        }
+        notices
+    }
+
+    fn sync_dir(&mut self, st: &mut State, dir: &Path, notices: &mut Vec<String>) {
+        // The version of each file that should be loaded, if any, with the
+        // contents that were hashed so a later edit can't slip past the check
+        let mut wanted: Vec<(PathBuf, Option<Contents>)> = Vec::new();
+        for name in ENV_FILES {
+            let file = dir.join(name);
+            if !file.is_file() {
+                wanted.push((file, None));
+                continue;
+            }
+            let data = match fs::read(&file) {
+                Ok(data) => data,
+                Err(e) => {
+                    notices.push(format!("envctl: read {}: {}", file.display(), e));
+                    wanted.push((file, None));
+                    continue;
+                }
+            };
+            let hash = sha256_hex(&data);
+            if self.allowed.files.get(&file) == Some(&hash) {
+                wanted.push((file, Some((hash, data))));
+            } else {
+                if self.warned.insert((file.clone(), hash)) {
+                    notices.push(format!(
+                        "envctl: {} is not allowed; review it and run `envctl allow {}`",
+                        file.display(),
+                        dir.display()
+                    ));
+                }
+                wanted.push((file, None));
+            }
+        }
+        let up_to_date = wanted.iter().all(|(file, version)| {
+            self.loaded.get(file).map(|l| &l.hash) == version.as_ref().map(|(hash, _)| hash)
+        });
+        if up_to_date {
+            return;
+        }
+
+        // Reload the whole directory so later files keep overriding earlier
+        // ones. Unloading first also keeps `${KEY}` in a reloaded file from
+        // picking up the value it set itself last time.
+        for (file, _) in wanted.iter().rev() {
+            if let Some(loaded) = self.loaded.remove(file) {
+                for (key, digest) in loaded.values {
+                    if holds(st, dir, &key, &digest) {
+                        st.unset(Scope::Dir(dir.to_path_buf()), key);
+                    }
+                }
+            }
+        }
+        for (file, version) in wanted {
+            let Some((hash, data)) = version else {
+                continue;
+            };
+            let entries = std::str::from_utf8(&data)
+                .context("not UTF-8")
+                .and_then(|content| {
+                    parse_env_file(&file, content, |name| st.get_effective(name, dir))
+                });
+            let values = match entries {
+                Ok(entries) => {
+                    let values = entries
+                        .iter()
+                        .map(|(k, v)| (k.clone(), sha256_hex(v.as_bytes())))
+                        .collect();
+                    st.load(Scope::Dir(dir.to_path_buf()), entries, None);
+                    values
+                }
+                Err(e) => {
+                    // Remembered as loaded (with nothing in it) so the error
+                    // is reported once per version of the file
+                    notices.push(format!("envctl: {}: {:#}", file.display(), e));
+                    BTreeMap::new()
+                }
+            };
+            self.loaded.insert(file, Loaded { hash, values });
+        }
+        if let Err(e) = write_json(&self.loaded_path, &self.loaded) {
+            notices.push(format!("envctl: {:#}", e));
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        write_json(&self.path, &self.allowed)
+    }
+}
+
+/// Whether `key` in `dir`'s scope still holds the value with this digest
+fn holds(st: &State, dir: &Path, key: &str, digest: &str) -> bool {
+    st.scoped
+        .get(dir)
+        .and_then(|values| values.get(key))
+        .is_some_and(|value| sha256_hex(value.as_bytes()) == digest)
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    digest(&SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Replace `path` atomically with `value` as JSON, readable only by us
+fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    {
+        let mut file = File::options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp)
+            .with_context(|| format!("create {}", tmp.display()))?;
+        file.write_all(&serde_json::to_vec_pretty(value)?)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path).with_context(|| format!("rename to {}", path.display()))
+}
+
+fn env_files(dir: &Path) -> Vec<PathBuf> {
+    ENV_FILES
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|file| file.is_file())
+        .collect()
+}
+
+fn parse_env_file(
+    file: &Path,
+    content: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<(String, String)>> {
+    if file.extension().is_some_and(|ext| ext == "json") {
+        let map: BTreeMap<String, serde_json::Value> = serde_json::from_str(content)?;
+        map.into_iter()
+            .map(|(key, value)| {
+                if !crate::is_valid_key(&key) {
+                    return Err(anyhow!("invalid variable name {:?}", key));
+                }
+                let value = match value {
+                    serde_json::Value::String(s) => s,
+                    serde_json::Value::Number(n) => n.to_string(),
+                    serde_json::Value::Bool(b) => b.to_string(),
+                    other => return Err(anyhow!("{} must be a string, not {}", key, other)),
+                };
+                Ok((key, value))
+            })
+            .collect()
+    } else {
+        Ok(dotenv::parse(content, true, &lookup)?)
+    }
+}
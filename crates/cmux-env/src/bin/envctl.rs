@@ -1,12 +1,18 @@
-use std::fs::{self, File};
-use std::io::{self, Read};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Result};
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use cmux_env::{
-    client_send, client_send_autostart, parse_dotenv, parse_dotenv_base64, Request, Response,
-    Scope, ShellKind,
+    client_send, client_send_autostart, decode_dotenv_base64, Request, Response, Scope, ShellKind,
+    Subscription,
 };
 
 #[derive(Parser, Debug)]
@@ -23,6 +29,8 @@ enum Commands {
         kv: String,
         #[arg(long)]
         dir: Option<PathBuf>,
+        #[command(flatten)]
+        secret: SecretArgs,
     },
     /// Unset KEY. Optional --dir to scope to directory.
     Unset {
@@ -35,16 +43,20 @@ enum Commands {
         #[arg(long)]
         dir: Option<PathBuf>,
     },
-    /// Get effective value for KEY at PWD
+    /// Get effective value for KEY at PWD (secrets are masked unless --reveal)
     Get {
         key: String,
         #[arg(long)]
         pwd: Option<PathBuf>,
+        #[arg(long, help = "Print secret values instead of masking them")]
+        reveal: bool,
     },
-    /// List effective variables at PWD
+    /// List effective variables at PWD (secrets are masked unless --reveal)
     List {
         #[arg(long)]
         pwd: Option<PathBuf>,
+        #[arg(long, help = "Print secret values instead of masking them")]
+        reveal: bool,
     },
     /// Load .env from file or stdin (-). Optional --dir to scope to directory.
     Load {
@@ -54,15 +66,49 @@ enum Commands {
         dir: Option<PathBuf>,
         #[arg(long, help = "Treat INPUT (or stdin) as base64-encoded content")]
         base64: bool,
+        #[arg(long, help = "Keep ${VAR} references in values as written")]
+        no_interpolate: bool,
+        #[command(flatten)]
+        secret: SecretArgs,
     },
-    /// Print export/unset script diff since GEN and bump gen
+    /// Trust the .env/.envrc.json in DIR (default: current dir) and load it
+    /// whenever a shell enters DIR
+    Allow { dir: Option<PathBuf> },
+    /// Stop trusting the .env/.envrc.json in DIR (default: current dir)
+    Deny { dir: Option<PathBuf> },
+    /// Print export/unset script diff since GEN and bump gen. The docker,
+    /// systemd and json formats print the whole effective environment.
     Export {
-        shell: ShellType,
+        #[arg(value_name = "FORMAT")]
+        shell: ExportFormat,
         #[arg(long, default_value_t = 0)]
         since: u64,
         #[arg(long)]
         pwd: Option<PathBuf>,
     },
+    /// Stream change events (one JSON object per line) relevant to PWD,
+    /// optionally only for KEYS
+    Watch {
+        keys: Vec<String>,
+        #[arg(long)]
+        pwd: Option<PathBuf>,
+    },
+    /// Run a command with the effective environment of PWD (default: current dir)
+    Exec {
+        #[arg(long)]
+        pwd: Option<PathBuf>,
+        #[arg(long, help = "Restart the command whenever the environment changes")]
+        restart_on_change: bool,
+        #[arg(
+            long = "key",
+            value_name = "KEY",
+            requires = "restart_on_change",
+            help = "Only restart when KEY changes (repeatable)"
+        )]
+        keys: Vec<String>,
+        #[arg(last = true, required = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
     /// Print hook for bash/zsh/fish
     Hook { shell: ShellType },
     /// Install hook into the user's shell rc file
@@ -77,6 +123,29 @@ enum Commands {
     Ping,
 }
 
+/// Secret flag; without either option secrecy is guessed from the key name
+#[derive(Args, Debug)]
+struct SecretArgs {
+    #[arg(long, help = "Mark values as secret")]
+    secret: bool,
+    #[arg(
+        long,
+        conflicts_with = "secret",
+        help = "Mark values as not secret, even if the key looks like one"
+    )]
+    no_secret: bool,
+}
+
+impl SecretArgs {
+    fn flag(&self) -> Option<bool> {
+        match (self.secret, self.no_secret) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, ValueEnum)]
 enum ShellType {
     Bash,
@@ -84,12 +153,33 @@ enum ShellType {
     Fish,
 }
 
-impl From<ShellType> for ShellKind {
-    fn from(s: ShellType) -> Self {
-        match s {
-            ShellType::Bash => ShellKind::Bash,
-            ShellType::Zsh => ShellKind::Zsh,
-            ShellType::Fish => ShellKind::Fish,
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum ExportFormat {
+    Bash,
+    Zsh,
+    Fish,
+    #[value(alias = "nushell")]
+    Nu,
+    #[value(alias = "pwsh")]
+    Powershell,
+    /// For `docker run --env-file`
+    Docker,
+    /// For systemd's `EnvironmentFile=`
+    Systemd,
+    Json,
+}
+
+impl From<ExportFormat> for ShellKind {
+    fn from(f: ExportFormat) -> Self {
+        match f {
+            ExportFormat::Bash => ShellKind::Bash,
+            ExportFormat::Zsh => ShellKind::Zsh,
+            ExportFormat::Fish => ShellKind::Fish,
+            ExportFormat::Nu => ShellKind::Nu,
+            ExportFormat::Powershell => ShellKind::Powershell,
+            ExportFormat::Docker => ShellKind::Docker,
+            ExportFormat::Systemd => ShellKind::Systemd,
+            ExportFormat::Json => ShellKind::Json,
         }
     }
 }
@@ -134,22 +224,25 @@ fn main() -> Result<()> {
                     generation,
                     globals,
                     scopes,
+                    secrets,
                 } => {
                     println!("generation: {}", generation);
                     println!("globals: {}", globals);
                     println!("scopes: {}", scopes);
+                    println!("secrets: {}", secrets);
                     Ok(())
                 }
                 _ => Err(anyhow!("unexpected response")),
             }
         }
-        Commands::Set { kv, dir } => {
+        Commands::Set { kv, dir, secret } => {
             let (key, val) = parse_kv(&kv)?;
             let scope = dir.map(Scope::Dir).unwrap_or(Scope::Global);
             let _ = client_send_autostart(&Request::Set {
                 key,
                 value: val,
                 scope,
+                secret: secret.flag(),
             })?;
             Ok(())
         }
@@ -166,7 +259,7 @@ fn main() -> Result<()> {
                 _ => Err(anyhow!("unexpected response")),
             }
         }
-        Commands::Get { key, pwd } => {
+        Commands::Get { key, pwd, reveal } => {
             let pwd = match pwd {
                 Some(pwd) => pwd,
                 None => std::env::current_dir()?,
@@ -174,25 +267,30 @@ fn main() -> Result<()> {
             let resp = client_send_autostart(&Request::Get {
                 key,
                 pwd: Some(pwd),
+                reveal,
             })?;
             match resp {
-                Response::Value { value } => {
+                Response::Value { value, secret: _ } => {
                     if let Some(v) = value {
                         println!("{}", v);
                     }
                     Ok(())
                 }
+                Response::Error { message } => Err(anyhow!(message)),
                 _ => Err(anyhow!("unexpected response")),
             }
         }
-        Commands::List { pwd } => {
+        Commands::List { pwd, reveal } => {
             let pwd = match pwd {
                 Some(pwd) => pwd,
                 None => std::env::current_dir()?,
             };
-            let resp = client_send_autostart(&Request::List { pwd: Some(pwd) })?;
+            let resp = client_send_autostart(&Request::List {
+                pwd: Some(pwd),
+                reveal,
+            })?;
             match resp {
-                Response::Map { entries } => {
+                Response::Map { entries, secrets } => {
                     let mut pairs: Vec<_> = entries.into_iter().collect();
                     pairs.sort_by(|a, b| a.0.cmp(&b.0));
 
@@ -201,17 +299,31 @@ fn main() -> Result<()> {
                     } else {
                         println!("Active environment variables ({}):", pairs.len());
                         for (key, value) in pairs {
-                            println!("  - {}={}", key, obfuscate_value(&value));
+                            // Secrets arrive masked by the daemon unless revealed
+                            if secrets.contains(&key) {
+                                println!("  - {}={} (secret)", key, value);
+                            } else if reveal {
+                                println!("  - {}={}", key, value);
+                            } else {
+                                println!("  - {}={}", key, obfuscate_value(&value));
+                            }
                         }
                     }
                     Ok(())
                 }
+                Response::Error { message } => Err(anyhow!(message)),
                 _ => Err(anyhow!("unexpected response")),
             }
         }
-        Commands::Load { input, dir, base64 } => {
+        Commands::Load {
+            input,
+            dir,
+            base64,
+            no_interpolate,
+            secret,
+        } => {
             let scope = dir.map(Scope::Dir).unwrap_or(Scope::Global);
-            let entries = if base64 {
+            let content = if base64 {
                 let payload = if input == "-" {
                     let mut buf = String::new();
                     io::stdin().read_to_string(&mut buf)?;
@@ -219,17 +331,56 @@ fn main() -> Result<()> {
                 } else {
                     input.clone()
                 };
-                parse_dotenv_base64(payload)?
+                decode_dotenv_base64(payload)?
             } else if input == "-" {
                 let mut buf = String::new();
                 io::stdin().read_to_string(&mut buf)?;
-                parse_dotenv(buf.as_bytes())?
+                buf
             } else {
-                let f = File::open(&input).with_context(|| format!("open {}", input))?;
-                parse_dotenv(f)?
+                fs::read_to_string(&input).with_context(|| format!("open {}", input))?
             };
-            let _ = client_send_autostart(&Request::Load { entries, scope })?;
-            Ok(())
+            // Parsed by the daemon, which knows the values ${VAR} may refer to
+            let resp = client_send_autostart(&Request::LoadDotenv {
+                content,
+                scope,
+                secret: secret.flag(),
+                interpolate: !no_interpolate,
+            })?;
+            match resp {
+                Response::Ok => Ok(()),
+                Response::Error { message } => Err(anyhow!(message)),
+                _ => Err(anyhow!("unexpected response")),
+            }
+        }
+        Commands::Allow { dir } => {
+            // The daemon runs elsewhere, so relative paths are resolved here
+            let dir = std::env::current_dir()?.join(dir.unwrap_or_default());
+            let resp = client_send_autostart(&Request::Allow { dir })?;
+            match resp {
+                Response::Files { files } => {
+                    for file in files {
+                        println!("allowed {}", file.display());
+                    }
+                    Ok(())
+                }
+                Response::Error { message } => Err(anyhow!(message)),
+                _ => Err(anyhow!("unexpected response")),
+            }
+        }
+        Commands::Deny { dir } => {
+            // The daemon runs elsewhere, so relative paths are resolved here
+            let dir = std::env::current_dir()?.join(dir.unwrap_or_default());
+            let resp = client_send_autostart(&Request::Deny { dir })?;
+            match resp {
+                Response::Files { files } => {
+                    for file in files {
+                        println!("denied {}", file.display());
+                    }
+                    Ok(())
+                }
+                Response::Error { message } => Err(anyhow!(message)),
+                _ => Err(anyhow!("unexpected response")),
+            }
         }
         Commands::Export { shell, since, pwd } => {
             let shell: ShellKind = shell.into();
@@ -255,6 +406,39 @@ fn main() -> Result<()> {
                 _ => Err(anyhow!("unexpected response")),
             }
         }
+        Commands::Watch { keys, pwd } => {
+            let pwd = match pwd {
+                Some(pwd) => pwd,
+                None => std::env::current_dir()?,
+            };
+            let subscription = Subscription::new(Some(pwd), keys, true)?;
+            let mut stdout = io::stdout().lock();
+            for event in subscription {
+                serde_json::to_writer(&mut stdout, &event?)?;
+                stdout.write_all(b"\n")?;
+                stdout.flush()?;
+            }
+            Err(anyhow!("envd closed the connection"))
+        }
+        Commands::Exec {
+            pwd,
+            restart_on_change,
+            keys,
+            command,
+        } => {
+            // The daemon runs elsewhere, so relative paths are resolved here
+            let pwd = std::env::current_dir()?.join(pwd.unwrap_or_default());
+            if restart_on_change {
+                exec_restarting(&command, &pwd, keys)
+            } else {
+                let env = effective_env(&pwd)?;
+                let err = Command::new(&command[0])
+                    .args(&command[1..])
+                    .envs(env)
+                    .exec();
+                Err(err).with_context(|| format!("exec {}", command[0]))
+            }
+        }
         Commands::Hook { shell } => {
             match shell {
                 ShellType::Bash => print!("{}", hook_bash()),
@@ -270,6 +454,104 @@ fn main() -> Result<()> {
     }
 }
 
+fn effective_env(pwd: &Path) -> Result<HashMap<String, String>> {
+    let resp = client_send_autostart(&Request::Env {
+        pwd: pwd.to_path_buf(),
+    })?;
+    match resp {
+        Response::Map { entries, .. } => Ok(entries),
+        Response::Error { message } => Err(anyhow!(message)),
+        _ => Err(anyhow!("unexpected response")),
+    }
+}
+
+fn spawn_command(command: &[String], env: &HashMap<String, String>) -> Result<Child> {
+    Command::new(&command[0])
+        .args(&command[1..])
+        .envs(env)
+        .spawn()
+        .with_context(|| format!("spawn {}", command[0]))
+}
+
+// Ask the command to stop, killing it if it takes too long
+fn stop_command(mut child: Child) -> Result<()> {
+    // SAFETY: kill only takes plain integers. The child hasn't been waited on,
+    // so even if it already exited its pid can't have been reused yet.
+    if unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) } == -1 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("signal pid {}", child.id()));
+    }
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if child.try_wait()?.is_some() {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    child.kill()?;
+    child.wait()?;
+    Ok(())
+}
+
+fn exit_code(status: ExitStatus) -> i32 {
+    status
+        .code()
+        .or_else(|| status.signal().map(|sig| 128 + sig))
+        .unwrap_or(1)
+}
+
+/// `exec --restart-on-change`: rerun `command` with the new environment
+/// whenever a relevant key changes. Keeps watching after the command exits,
+/// until interrupted or the daemon goes away.
+fn exec_restarting(command: &[String], pwd: &Path, keys: Vec<String>) -> Result<()> {
+    // Subscribe before reading the environment so no change slips in between
+    let subscription = Subscription::new(Some(pwd.to_path_buf()), keys, true)?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for event in subscription {
+            if event.is_err() || tx.send(()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut env = effective_env(pwd)?;
+    let mut child = Some(spawn_command(command, &env)?);
+    loop {
+        if let Some(status) = child.as_mut().map(Child::try_wait).transpose()?.flatten() {
+            eprintln!(
+                "envctl: {} exited ({}); waiting for changes",
+                command[0], status
+            );
+            child = None;
+        }
+        match rx.recv_timeout(Duration::from_millis(200)) {
+            Ok(()) => {
+                // Let a burst of changes (e.g. a load) settle first
+                while rx.recv_timeout(Duration::from_millis(100)).is_ok() {}
+                let new_env = effective_env(pwd)?;
+                if new_env == env && child.is_some() {
+                    continue;
+                }
+                env = new_env;
+                if let Some(running) = child.take() {
+                    stop_command(running)?;
+                }
+                eprintln!("envctl: environment changed, restarting {}", command[0]);
+                child = Some(spawn_command(command, &env)?);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                eprintln!("envctl: lost connection to envd, no longer watching");
+                let Some(mut running) = child else {
+                    return Err(anyhow!("envd closed the connection"));
+                };
+                std::process::exit(exit_code(running.wait()?));
+            }
+        }
+    }
+}
+
 fn install_hook(shell: ShellType, rcfile: Option<PathBuf>) -> Result<()> {
     const START_MARKER: &str = "# >>> envctl hook >>>";
     const END_MARKER: &str = "# <<< envctl hook <<<";
@@ -0,0 +1,415 @@
+// Dotenv parsing, following the common dotenv / dotenv-expand conventions:
+//
+// - `KEY=value`, optionally prefixed by `export` and any amount of spaces,
+//   with optional spaces around `=`
+// - `# comments` on their own line, or after a value when preceded by
+//   whitespace (`KEY=a#b` keeps the `#`)
+// - unquoted values run to the end of the line and are trimmed
+// - 'single' and `backtick` quoted values are literal and may span lines
+// - "double" quoted values may span lines and understand `\n`, `\r`, `\t`,
+//   `\"`, `\\` and `\$`
+// - `$VAR`, `${VAR}`, `${VAR:-default}` (unset or empty) and `${VAR-default}`
+//   (unset) are expanded in unquoted and double quoted values, first from
+//   entries earlier in the same input, then from a caller-supplied lookup.
+//   Unknown variables expand to nothing; `\$` keeps a literal dollar.
+//
+// Errors carry the 1-based line and column where parsing failed.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("line {line}, column {column}: {message}")]
+pub struct DotenvError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Var {
+        name: String,
+        /// (`:-` rather than `-`, default pieces)
+        default: Option<(bool, Vec<Piece>)>,
+        /// Source text, used when not interpolating
+        raw: String,
+    },
+}
+
+struct Entry {
+    key: String,
+    pieces: Vec<Piece>,
+}
+
+/// Parse `content` and resolve each value. With `interpolate` off, `$`
+/// references are kept as written.
+pub fn parse(
+    content: &str,
+    interpolate: bool,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<Vec<(String, String)>, DotenvError> {
+    let entries = Parser::new(content).entries()?;
+    let mut seen: HashMap<String, String> = HashMap::new();
+    let mut out = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let value = render(&entry.pieces, interpolate, &seen, lookup);
+        seen.insert(entry.key.clone(), value.clone());
+        out.push((entry.key, value));
+    }
+    Ok(out)
+}
+
+fn render(
+    pieces: &[Piece],
+    interpolate: bool,
+    seen: &HashMap<String, String>,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> String {
+    let mut out = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) => out.push_str(text),
+            Piece::Var { raw, .. } if !interpolate => out.push_str(raw),
+            Piece::Var { name, default, .. } => {
+                let value = seen.get(name).cloned().or_else(|| lookup(name));
+                let value = match (value, default) {
+                    (Some(v), Some((true, _))) if v.is_empty() => None,
+                    (value, _) => value,
+                };
+                match (value, default) {
+                    (Some(v), _) => out.push_str(&v),
+                    (None, Some((_, d))) => out.push_str(&render(d, interpolate, seen, lookup)),
+                    (None, None) => {}
+                }
+            }
+        }
+    }
+    out
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn new(content: &str) -> Self {
+        Parser {
+            chars: content.chars().collect(),
+            pos: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: impl Into<String>) -> DotenvError {
+        self.error_at((self.line, self.column), message)
+    }
+
+    fn error_at(&self, (line, column): (usize, usize), message: impl Into<String>) -> DotenvError {
+        DotenvError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    fn here(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+
+    fn skip_blanks(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.bump() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn at_line_end(&self) -> bool {
+        match self.peek() {
+            None | Some('\n') => true,
+            Some('\r') => matches!(self.peek_at(1), None | Some('\n')),
+            _ => false,
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let mut word = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+                word.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        word
+    }
+
+    fn entries(mut self) -> Result<Vec<Entry>, DotenvError> {
+        let mut entries = Vec::new();
+        loop {
+            while matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+                self.bump();
+            }
+            match self.peek() {
+                None => break,
+                Some('#') => {
+                    self.skip_line();
+                    continue;
+                }
+                Some(_) => {}
+            }
+
+            let mut key_at = self.here();
+            let mut key = self.word();
+            if key == "export" && matches!(self.peek(), Some(' ' | '\t')) {
+                self.skip_blanks();
+                key_at = self.here();
+                key = self.word();
+            }
+            if !crate::is_valid_key(&key) {
+                return Err(match key.is_empty() {
+                    true => self.error_at(key_at, "expected a variable name"),
+                    false => self.error_at(key_at, format!("invalid variable name {:?}", key)),
+                });
+            }
+            self.skip_blanks();
+            if self.peek() != Some('=') {
+                return Err(self.error(format!("expected '=' after {}", key)));
+            }
+            self.bump();
+            self.skip_blanks();
+
+            let pieces = match self.peek() {
+                Some(quote @ ('\'' | '`')) => self.literal(quote)?,
+                Some('"') => self.double_quoted()?,
+                _ => self.unquoted()?,
+            };
+            self.skip_blanks();
+            if self.peek() != Some('#') && !self.at_line_end() {
+                return Err(self.error("unexpected text after closing quote"));
+            }
+            self.skip_line();
+            entries.push(Entry { key, pieces });
+        }
+        Ok(entries)
+    }
+
+    fn literal(&mut self, quote: char) -> Result<Vec<Piece>, DotenvError> {
+        let open = self.here();
+        self.bump();
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                Some(c) if c == quote => return Ok(vec![Piece::Text(text)]),
+                Some(c) => text.push(c),
+                None => return Err(self.error_at(open, "unterminated quoted value")),
+            }
+        }
+    }
+
+    fn double_quoted(&mut self) -> Result<Vec<Piece>, DotenvError> {
+        let open = self.here();
+        self.bump();
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error_at(open, "unterminated quoted value")),
+                Some('"') => {
+                    self.bump();
+                    break;
+                }
+                Some('\\') => {
+                    self.bump();
+                    match self.bump() {
+                        Some('n') => text.push('\n'),
+                        Some('r') => text.push('\r'),
+                        Some('t') => text.push('\t'),
+                        Some(c @ ('"' | '\\' | '$')) => text.push(c),
+                        Some(c) => {
+                            text.push('\\');
+                            text.push(c);
+                        }
+                        None => return Err(self.error_at(open, "unterminated quoted value")),
+                    }
+                }
+                Some('$') => self.dollar(&mut pieces, &mut text, Some('"'))?,
+                Some(c) => {
+                    text.push(c);
+                    self.bump();
+                }
+            }
+        }
+        flush(&mut pieces, &mut text);
+        Ok(pieces)
+    }
+
+    fn unquoted(&mut self) -> Result<Vec<Piece>, DotenvError> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut after_blank = true;
+        while let Some(c) = self.peek() {
+            match c {
+                '\n' => break,
+                '#' if after_blank => break,
+                '\\' if self.peek_at(1) == Some('$') => {
+                    self.bump();
+                    self.bump();
+                    text.push('$');
+                }
+                '$' => self.dollar(&mut pieces, &mut text, Some('\n'))?,
+                _ => {
+                    text.push(c);
+                    self.bump();
+                }
+            }
+            after_blank = matches!(c, ' ' | '\t');
+        }
+        // Trailing blanks (and a CR from CRLF endings) aren't part of the value
+        let trimmed = text.trim_end_matches([' ', '\t', '\r']).len();
+        text.truncate(trimmed);
+        if text.is_empty() {
+            if let Some(Piece::Text(last)) = pieces.last_mut() {
+                let trimmed = last.trim_end_matches([' ', '\t', '\r']).len();
+                last.truncate(trimmed);
+            }
+        }
+        flush(&mut pieces, &mut text);
+        Ok(pieces)
+    }
+
+    /// At a `$`: push a variable reference, or a literal `$` if none follows.
+    /// `end` is the character that closes the surrounding value.
+    fn dollar(
+        &mut self,
+        pieces: &mut Vec<Piece>,
+        text: &mut String,
+        end: Option<char>,
+    ) -> Result<(), DotenvError> {
+        let start = self.pos;
+        let at = self.here();
+        self.bump();
+        match self.peek() {
+            Some('{') => {
+                self.bump();
+                let name = self.name();
+                if name.is_empty() {
+                    return Err(self.error("expected a variable name after '${'"));
+                }
+                let default = match self.peek() {
+                    Some('}') => None,
+                    Some(':') if self.peek_at(1) == Some('-') => {
+                        self.bump();
+                        self.bump();
+                        Some((true, self.default_value(at, end)?))
+                    }
+                    Some('-') => {
+                        self.bump();
+                        Some((false, self.default_value(at, end)?))
+                    }
+                    _ => return Err(self.error(format!("expected '}}' to close ${{{}", name))),
+                };
+                if self.bump() != Some('}') {
+                    return Err(self.error_at(at, "unterminated '${'"));
+                }
+                flush(pieces, text);
+                pieces.push(Piece::Var {
+                    name,
+                    default,
+                    raw: self.chars[start..self.pos].iter().collect(),
+                });
+            }
+            Some(c) if c == '_' || c.is_ascii_alphabetic() => {
+                let name = self.name();
+                flush(pieces, text);
+                pieces.push(Piece::Var {
+                    name,
+                    default: None,
+                    raw: self.chars[start..self.pos].iter().collect(),
+                });
+            }
+            _ => text.push('$'),
+        }
+        Ok(())
+    }
+
+    fn name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c == '_' || c.is_ascii_alphanumeric() {
+                name.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        name
+    }
+
+    /// Default of `${VAR:-...}`, up to (not including) the closing brace
+    fn default_value(
+        &mut self,
+        open: (usize, usize),
+        end: Option<char>,
+    ) -> Result<Vec<Piece>, DotenvError> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                Some('}') => break,
+                None => return Err(self.error_at(open, "unterminated '${'")),
+                Some(c) if Some(c) == end => return Err(self.error_at(open, "unterminated '${'")),
+                Some('\\') if self.peek_at(1) == Some('$') => {
+                    self.bump();
+                    self.bump();
+                    text.push('$');
+                }
+                Some('$') => self.dollar(&mut pieces, &mut text, end)?,
+                Some(c) => {
+                    text.push(c);
+                    self.bump();
+                }
+            }
+        }
+        flush(&mut pieces, &mut text);
+        Ok(pieces)
+    }
+}
+
+fn flush(pieces: &mut Vec<Piece>, text: &mut String) {
+    if !text.is_empty() {
+        pieces.push(Piece::Text(std::mem::take(text)));
+    }
+}
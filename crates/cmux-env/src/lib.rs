@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{BufRead, BufReader, Cursor, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
 use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
@@ -13,6 +14,18 @@ use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
+mod autoload;
+mod dotenv;
+mod secrets;
+mod store;
+mod watch;
+
+pub use autoload::{Autoload, ENV_FILES};
+pub use dotenv::DotenvError;
+pub use secrets::{looks_secret, secret_key_path, Sealer, SECRET_MASK};
+pub use store::{FsyncPolicy, Store};
+pub use watch::{Subscribers, Subscription};
+
 // ---------------- Path helpers ----------------
 
 pub fn runtime_dir() -> PathBuf {
@@ -29,9 +42,28 @@ pub fn socket_path() -> PathBuf {
     base.join("envd.sock")
 }
 
+// The socket and the persisted state live here; without XDG_RUNTIME_DIR that's
+// under the shared /tmp, so the directory must be ours and closed to others.
 fn ensure_socket_dir() -> Result<PathBuf> {
     let dir = runtime_dir().join("cmux-envd");
-    fs::create_dir_all(&dir).with_context(|| format!("creating dir {}", dir.display()))?;
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .with_context(|| format!("creating dir {}", dir.display()))?;
+    let meta = fs::symlink_metadata(&dir).with_context(|| format!("stat {}", dir.display()))?;
+    // SAFETY: geteuid has no preconditions and cannot fail
+    let euid = unsafe { libc::geteuid() };
+    if !meta.is_dir() || meta.uid() != euid {
+        return Err(anyhow!(
+            "{} is not a directory owned by the current user",
+            dir.display()
+        ));
+    }
+    if meta.mode() & 0o077 != 0 {
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))
+            .with_context(|| format!("chmod {}", dir.display()))?;
+    }
     Ok(dir)
 }
 
@@ -50,9 +82,25 @@ pub enum ShellKind {
     Bash,
     Zsh,
     Fish,
+    Nu,
+    Powershell,
+    /// `docker run --env-file`
+    Docker,
+    /// systemd `EnvironmentFile=`
+    Systemd,
+    Json,
 }
 
-impl ShellKind {}
+impl ShellKind {
+    /// Formats describing the whole environment rather than a diff to apply
+    /// to a running shell
+    pub fn is_snapshot(&self) -> bool {
+        matches!(
+            self,
+            ShellKind::Docker | ShellKind::Systemd | ShellKind::Json
+        )
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(tag = "type", content = "path")]
@@ -70,6 +118,9 @@ pub enum Request {
         key: String,
         value: String,
         scope: Scope,
+        /// `None` guesses from the key name
+        #[serde(default)]
+        secret: Option<bool>,
     },
     Unset {
         key: String,
@@ -78,22 +129,64 @@ pub enum Request {
     Get {
         key: String,
         pwd: Option<PathBuf>,
+        /// Return secret values instead of masking them
+        #[serde(default)]
+        reveal: bool,
     },
     List {
         pwd: Option<PathBuf>,
+        #[serde(default)]
+        reveal: bool,
     },
     Load {
         entries: Vec<(String, String)>,
         scope: Scope,
+        /// Applies to every entry; `None` guesses from each key name
+        #[serde(default)]
+        secret: Option<bool>,
+    },
+    /// Parse a dotenv file in the daemon, so `${VAR}` references can resolve
+    /// against the values already effective in `scope`
+    LoadDotenv {
+        content: String,
+        scope: Scope,
+        #[serde(default)]
+        secret: Option<bool>,
+        #[serde(default = "default_true")]
+        interpolate: bool,
     },
     Reset {
         scope: Option<Scope>,
     },
+    /// Trust the current contents of the env files in `dir` and load them
+    Allow {
+        dir: PathBuf,
+    },
+    /// Stop trusting the env files in `dir`
+    Deny {
+        dir: PathBuf,
+    },
     Export {
         shell: ShellKind,
         since: u64,
         pwd: PathBuf,
     },
+    /// Effective values at `pwd` as a process started there gets them
+    /// (secrets included, like shell exports)
+    Env {
+        pwd: PathBuf,
+    },
+    /// Keep the connection open and stream `ChangeEvent`s, see `watch.rs`
+    Subscribe {
+        pwd: Option<PathBuf>,
+        /// Only these keys; all keys when empty
+        #[serde(default)]
+        keys: Vec<String>,
+    },
+}
+
+fn default_true() -> bool {
+    true
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -104,18 +197,33 @@ pub enum Response {
         generation: u64,
         globals: usize,
         scopes: usize,
+        #[serde(default)]
+        secrets: usize,
     },
     Ok,
     Value {
         value: Option<String>,
+        #[serde(default)]
+        secret: bool,
     },
     Map {
         entries: HashMap<String, String>,
+        /// Keys whose values are secret (masked unless revealed)
+        #[serde(default)]
+        secrets: Vec<String>,
     },
     Export {
         script: String,
         new_generation: u64,
     },
+    /// Start of a subscription; `ChangeEvent` lines follow
+    Subscribed {
+        generation: u64,
+    },
+    /// Env files affected by `Allow`/`Deny`
+    Files {
+        files: Vec<PathBuf>,
+    },
     Error {
         message: String,
     },
@@ -154,30 +262,38 @@ pub struct State {
     pub globals: HashMap<String, String>,
     pub scoped: HashMap<PathBuf, HashMap<String, String>>, // Dir -> (key -> value)
     pub history: Vec<ChangeEvent>,
+    /// (scope, key) pairs holding secret values
+    pub secrets: HashSet<(Scope, String)>,
 }
 
 impl State {
-    pub fn set(&mut self, scope: Scope, key: String, value: String) -> bool {
-        match scope {
-            Scope::Global => {
-                let changed = self.globals.get(&key) != Some(&value);
-                if changed {
-                    self.globals.insert(key.clone(), value);
-                    self.bump(key, Scope::Global);
-                }
-                changed
-            }
-            Scope::Dir(path) => {
-                let path_c = canon(path);
-                let entry = self.scoped.entry(path_c.clone()).or_default();
-                let changed = entry.get(&key) != Some(&value);
-                if changed {
-                    entry.insert(key.clone(), value);
-                    self.bump(key, Scope::Dir(path_c));
-                }
-                changed
+    pub fn set(&mut self, scope: Scope, key: String, value: String, secret: Option<bool>) -> bool {
+        let secret = secret.unwrap_or_else(|| looks_secret(&key));
+        let scope = match scope {
+            Scope::Dir(path) => Scope::Dir(canon(path)),
+            x => x,
+        };
+        let map = match &scope {
+            Scope::Global => &mut self.globals,
+            Scope::Dir(path) => self.scoped.entry(path.clone()).or_default(),
+        };
+        let mut changed = map.get(&key) != Some(&value);
+        if changed {
+            map.insert(key.clone(), value);
+        }
+        let entry = (scope.clone(), key.clone());
+        if secret != self.secrets.contains(&entry) {
+            if secret {
+                self.secrets.insert(entry);
+            } else {
+                self.secrets.remove(&entry);
             }
+            changed = true;
+        }
+        if changed {
+            self.bump(key, scope);
         }
+        changed
     }
 
     pub fn unset(&mut self, scope: Scope, key: String) -> bool {
@@ -185,6 +301,7 @@ impl State {
             Scope::Global => {
                 let existed = self.globals.remove(&key).is_some();
                 if existed {
+                    self.secrets.remove(&(Scope::Global, key.clone()));
                     self.bump(key, Scope::Global);
                 }
                 existed
@@ -194,6 +311,8 @@ impl State {
                 if let Some(map) = self.scoped.get_mut(&path) {
                     let existed = map.remove(&key).is_some();
                     if existed {
+                        self.secrets
+                            .remove(&(Scope::Dir(path.clone()), key.clone()));
                         self.bump(key, Scope::Dir(path));
                     }
                     existed
@@ -211,16 +330,32 @@ impl State {
             Scope::Dir(p) => Scope::Dir(canon(p)),
             x => x,
         };
-        self.history.push(ChangeEvent {
+        self.push_event(ChangeEvent {
             generation: self.generation,
             key,
             scope,
         });
     }
 
-    pub fn load(&mut self, scope: Scope, entries: Vec<(String, String)>) {
+    // Exports only need the latest change of each key in each scope, so
+    // older ones are dropped to keep history bounded by the number of keys
+    pub(crate) fn push_event(&mut self, event: ChangeEvent) {
+        self.history
+            .retain(|e| e.key != event.key || e.scope != event.scope);
+        self.history.push(event);
+    }
+
+    // Raw value of a key in one scope (no overlaying)
+    pub(crate) fn value_in(&self, scope: &Scope, key: &str) -> Option<&String> {
+        match scope {
+            Scope::Global => self.globals.get(key),
+            Scope::Dir(dir) => self.scoped.get(dir).and_then(|m| m.get(key)),
+        }
+    }
+
+    pub fn load(&mut self, scope: Scope, entries: Vec<(String, String)>, secret: Option<bool>) {
         for (k, v) in entries {
-            self.set(scope.clone(), k, v);
+            self.set(scope.clone(), k, v, secret);
         }
     }
 
@@ -232,6 +367,7 @@ impl State {
         let mut changed = false;
         for key in keys {
             if self.globals.remove(&key).is_some() {
+                self.secrets.remove(&(Scope::Global, key.clone()));
                 self.bump(key, Scope::Global);
                 changed = true;
             }
@@ -246,6 +382,7 @@ impl State {
                 let scope = Scope::Dir(dir_c);
                 let mut changed = false;
                 for key in map.into_keys() {
+                    self.secrets.remove(&(scope.clone(), key.clone()));
                     self.bump(key, scope.clone());
                     changed = true;
                 }
@@ -285,6 +422,15 @@ impl State {
         self.globals.get(key).cloned()
     }
 
+    /// Whether the value `get_effective` returns for `key` is secret
+    pub fn is_effective_secret(&self, key: &str, pwd: &Path) -> bool {
+        let scope = match self.best_scope_for_pwd(pwd) {
+            Some((dir, overlay)) if overlay.contains_key(key) => Scope::Dir(dir),
+            _ => Scope::Global,
+        };
+        self.secrets.contains(&(scope, key.to_string()))
+    }
+
     // Returns best matching directory scope (deepest ancestor) and its map
     fn best_scope_for_pwd(&self, pwd: &Path) -> Option<(PathBuf, &HashMap<String, String>)> {
         let pwd = canon(pwd);
@@ -306,6 +452,12 @@ impl State {
 
     pub fn export_since(&self, shell: ShellKind, since: u64, pwd: &Path) -> (String, u64) {
         let new_gen = self.generation;
+        if shell.is_snapshot() {
+            let script = render_snapshot(shell, &self.effective_for_pwd(pwd));
+            return (script, new_gen);
+        }
+        // A shell ahead of us saw state that was lost; resend everything
+        let since = if since > new_gen { 0 } else { since };
         let mut changed_keys: HashSet<String> = HashSet::new();
         let pwd_c = canon(pwd);
         for ev in self.history.iter().filter(|e| e.generation > since) {
@@ -395,10 +547,119 @@ fn render_script(shell: ShellKind, actions: &[(String, Option<String>)], new_gen
             }
             out.push_str(&format!("set -x ENVCTL_GEN {}\n", new_gen));
         }
+        ShellKind::Nu => {
+            for (k, v) in actions {
+                if is_valid_key(k) {
+                    match v {
+                        Some(val) => {
+                            out.push_str(&format!("$env.{} = {}\n", k, nu_raw_string(val)))
+                        }
+                        None => out.push_str(&format!("hide-env -i {}\n", k)),
+                    }
+                }
+            }
+            out.push_str(&format!("$env.ENVCTL_GEN = '{}'\n", new_gen));
+        }
+        ShellKind::Powershell => {
+            for (k, v) in actions {
+                if is_valid_key(k) {
+                    match v {
+                        Some(val) => {
+                            out.push_str(&format!("$env:{} = {}\n", k, ps_single_quote(val)))
+                        }
+                        None => out.push_str(&format!(
+                            "Remove-Item -ErrorAction SilentlyContinue Env:{}\n",
+                            k
+                        )),
+                    }
+                }
+            }
+            out.push_str(&format!("$env:ENVCTL_GEN = '{}'\n", new_gen));
+        }
+        ShellKind::Docker | ShellKind::Systemd | ShellKind::Json => {
+            let values = actions
+                .iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.clone()?)))
+                .collect();
+            out.push_str(&render_snapshot(shell, &values));
+        }
     }
     out
 }
 
+/// The whole environment as a file for tools that don't run a shell
+fn render_snapshot(format: ShellKind, values: &HashMap<String, String>) -> String {
+    let mut pairs: Vec<(&String, &String)> =
+        values.iter().filter(|(k, _)| is_valid_key(k)).collect();
+    pairs.sort();
+    let mut out = String::new();
+    match format {
+        ShellKind::Json => {
+            let map: std::collections::BTreeMap<_, _> = pairs.into_iter().collect();
+            out = serde_json::to_string_pretty(&map).unwrap_or_default();
+            out.push('\n');
+        }
+        ShellKind::Systemd => {
+            for (k, v) in pairs {
+                out.push_str(&format!("{}={}\n", k, systemd_quote(v)));
+            }
+        }
+        _ => {
+            // Docker reads everything after `=` literally, up to the newline
+            for (k, v) in pairs {
+                if v.contains('\n') {
+                    out.push_str(&format!("# {} skipped: multi-line value\n", k));
+                } else {
+                    out.push_str(&format!("{}={}\n", k, v));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// A line showing `message` on stderr when the script is evaluated
+fn render_notice(shell: &ShellKind, message: &str) -> Option<String> {
+    match shell {
+        ShellKind::Bash | ShellKind::Zsh | ShellKind::Fish => {
+            Some(format!("echo {} >&2\n", sh_single_quote(message)))
+        }
+        ShellKind::Nu => Some(format!("print -e {}\n", nu_raw_string(message))),
+        ShellKind::Powershell => Some(format!(
+            "[Console]::Error.WriteLine({})\n",
+            ps_single_quote(message)
+        )),
+        ShellKind::Docker | ShellKind::Systemd | ShellKind::Json => None,
+    }
+}
+
+// r#'...'#, with enough hashes that the value can't close it early
+fn nu_raw_string(val: &str) -> String {
+    let mut hashes = 1;
+    while val.contains(&format!("'{}", "#".repeat(hashes))) {
+        hashes += 1;
+    }
+    let hashes = "#".repeat(hashes);
+    format!("r{}'{}'{}", hashes, val, hashes)
+}
+
+fn ps_single_quote(val: &str) -> String {
+    format!("'{}'", val.replace('\'', "''"))
+}
+
+fn systemd_quote(val: &str) -> String {
+    let mut out = String::with_capacity(val.len() + 2);
+    out.push('"');
+    for ch in val.chars() {
+        if matches!(ch, '"' | '\\' | '$' | '`') {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+    out.push('"');
+    out
+}
+
 fn is_valid_key(k: &str) -> bool {
     let first = k.chars().next();
     if !first
@@ -420,14 +681,32 @@ pub fn run_server() -> Result<()> {
     }
     let listener = UnixListener::bind(&sock).with_context(|| format!("bind {}", sock.display()))?;
     write_pid_file(&dir)?;
-    let state = Arc::new(Mutex::new(State::default()));
+    let sealer = Sealer::new(secret_key_path()?);
+    let (store, state) = Store::open(&dir, FsyncPolicy::from_env(), sealer)?;
+    let autoload = Autoload::open(&dir, &state)?;
+    let state = Arc::new(Mutex::new(state));
+    let store = Arc::new(Mutex::new(store));
+    let autoload = Arc::new(Mutex::new(autoload));
+    let subscribers = Arc::new(Subscribers::default());
 
     loop {
         let (mut stream, _addr) = listener.accept()?;
         let state = state.clone();
+        let store = store.clone();
+        let autoload = autoload.clone();
+        let subscribers = subscribers.clone();
         std::thread::spawn(move || {
             let resp = match read_json(&mut stream) {
-                Ok(req) => handle_request(req, &state),
+                Ok(Request::Subscribe { pwd, keys }) => {
+                    // Registered under the state lock so no change is missed
+                    let (generation, rx) = {
+                        let st = state.lock();
+                        (st.generation, subscribers.add(pwd, keys))
+                    };
+                    watch::serve(stream, generation, rx);
+                    return;
+                }
+                Ok(req) => handle_request(req, &state, &store, &autoload, &subscribers),
                 Err(e) => Response::Error {
                     message: format!("read error: {}", e),
                 },
@@ -441,37 +720,113 @@ fn resolve_pwd(pwd: Option<PathBuf>) -> PathBuf {
     pwd.unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
 }
 
-fn handle_request(req: Request, state: &Arc<Mutex<State>>) -> Response {
+fn handle_request(
+    req: Request,
+    state: &Arc<Mutex<State>>,
+    store: &Mutex<Store>,
+    autoload: &Mutex<Autoload>,
+    subscribers: &Subscribers,
+) -> Response {
     let mut st = state.lock();
+    let before = st.generation;
+    let resp = handle_request_locked(req, &mut st, &mut autoload.lock());
+    // Journal under the state lock so entries are written in generation order
+    if st.generation > before {
+        subscribers.publish(st.history.iter().filter(|e| e.generation > before));
+        if let Err(e) = store.lock().record(&st, before) {
+            return Response::Error {
+                message: format!("change applied but not persisted: {:#}", e),
+            };
+        }
+    }
+    resp
+}
+
+fn handle_request_locked(req: Request, st: &mut State, autoload: &mut Autoload) -> Response {
     match req {
         Request::Ping => Response::Pong,
         Request::Status => Response::Status {
             generation: st.generation,
             globals: st.globals.len(),
             scopes: st.scoped.len(),
+            secrets: st.secrets.len(),
         },
-        Request::Set { key, value, scope } => {
-            st.set(scope, key, value);
+        Request::Set {
+            key,
+            value,
+            scope,
+            secret,
+        } => {
+            st.set(scope, key, value, secret);
             Response::Ok
         }
         Request::Unset { key, scope } => {
             st.unset(scope, key);
             Response::Ok
         }
-        Request::Get { key, pwd } => {
+        Request::Get { key, pwd, reveal } => {
+            if reveal && !secrets::reveal_allowed() {
+                return reveal_denied();
+            }
             let pwd = resolve_pwd(pwd);
-            let v = st.get_effective(&key, &pwd);
-            Response::Value { value: v }
+            let secret = st.is_effective_secret(&key, &pwd);
+            let mut value = st.get_effective(&key, &pwd);
+            if secret && !reveal {
+                value = value.map(|_| SECRET_MASK.to_string());
+            }
+            Response::Value { value, secret }
         }
-        Request::List { pwd } => {
+        Request::List { pwd, reveal } => {
+            if reveal && !secrets::reveal_allowed() {
+                return reveal_denied();
+            }
             let pwd = resolve_pwd(pwd);
-            let entries = st.effective_for_pwd(&pwd);
-            Response::Map { entries }
+            let mut entries = st.effective_for_pwd(&pwd);
+            let mut secrets: Vec<String> = entries
+                .keys()
+                .filter(|key| st.is_effective_secret(key, &pwd))
+                .cloned()
+                .collect();
+            secrets.sort();
+            if !reveal {
+                for key in &secrets {
+                    entries.insert(key.clone(), SECRET_MASK.to_string());
+                }
+            }
+            Response::Map { entries, secrets }
         }
-        Request::Load { entries, scope } => {
-            st.load(scope, entries);
+        Request::Load {
+            entries,
+            scope,
+            secret,
+        } => {
+            st.load(scope, entries, secret);
             Response::Ok
         }
+        Request::LoadDotenv {
+            content,
+            scope,
+            secret,
+            interpolate,
+        } => {
+            let parsed = {
+                let st = &*st;
+                let lookup = |name: &str| match &scope {
+                    Scope::Global => st.globals.get(name).cloned(),
+                    Scope::Dir(dir) => st.get_effective(name, dir),
+                };
+                dotenv::parse(&content, interpolate, &lookup)
+            };
+            match parsed {
+                Ok(entries) => {
+                    st.load(scope, entries, secret);
+                    Response::Ok
+                }
+                Err(e) => Response::Error {
+                    message: format!("invalid dotenv: {}", e),
+                },
+            }
+        }
         Request::Reset { scope } => {
             match scope {
                 Some(Scope::Global) => {
@@ -486,16 +841,55 @@ fn handle_request(req: Request, state: &Arc<Mutex<State>>) -> Response {
             }
             Response::Ok
         }
+        Request::Env { pwd } => {
+            autoload.refresh(st, &pwd);
+            let entries = st.effective_for_pwd(&pwd);
+            let mut secrets: Vec<String> = entries
+                .keys()
+                .filter(|key| st.is_effective_secret(key, &pwd))
+                .cloned()
+                .collect();
+            secrets.sort();
+            Response::Map { entries, secrets }
+        }
+        Request::Subscribe { .. } => Response::Error {
+            message: "subscriptions are served by the connection loop".to_string(),
+        },
+        Request::Allow { dir } => match autoload.allow(st, &dir) {
+            Ok(files) => Response::Files { files },
+            Err(e) => Response::Error {
+                message: format!("{:#}", e),
+            },
+        },
+        Request::Deny { dir } => match autoload.deny(st, &dir) {
+            Ok(files) => Response::Files { files },
+            Err(e) => Response::Error {
+                message: format!("{:#}", e),
+            },
+        },
         Request::Export { shell, since, pwd } => {
+            let notices = autoload.refresh(st, &pwd);
+            // Shown by the shell as it evaluates the script
+            let mut out = String::new();
+            for notice in notices {
+                out.extend(render_notice(&shell, &notice));
+            }
             let (script, new_generation) = st.export_since(shell, since, &pwd);
+            out.push_str(&script);
             Response::Export {
-                script,
+                script: out,
                 new_generation,
             }
         }
     }
 }
 
+fn reveal_denied() -> Response {
+    Response::Error {
+        message: "revealing secrets is disabled on this daemon".to_string(),
+    }
+}
+
 // --------------- Client plumbing ---------------
 
 pub fn client_send(req: &Request) -> Result<Response> {
@@ -610,33 +1004,35 @@ fn envd_binary_name() -> &'static str {
     "envd"
 }
 
-pub fn parse_dotenv<R: Read>(mut r: R) -> Result<Vec<(String, String)>> {
+/// Parse dotenv content, expanding `${VAR}` references against earlier
+/// entries only. Errors carry the line and column.
+pub fn parse_dotenv<R: Read>(r: R) -> Result<Vec<(String, String)>> {
+    parse_dotenv_with(r, true, |_| None)
+}
+
+/// Parse dotenv content, resolving references that aren't defined earlier in
+/// the file with `lookup`. With `interpolate` off, `$` references are kept as
+/// written.
+pub fn parse_dotenv_with<R, F>(
+    mut r: R,
+    interpolate: bool,
+    lookup: F,
+) -> Result<Vec<(String, String)>>
+where
+    R: Read,
+    F: Fn(&str) -> Option<String>,
+{
     let mut s = String::new();
     r.read_to_string(&mut s)?;
-    let mut out = Vec::new();
-    for (idx, line) in s.lines().enumerate() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
-        let line = line.strip_prefix("export ").unwrap_or(line);
-        if let Some(eq) = line.find('=') {
-            let (k, v) = line.split_at(eq);
-            let k = k.trim().to_string();
-            let v = v[1..].trim().to_string();
-            let v = strip_quotes(&v);
-            if !is_valid_key(&k) {
-                return Err(anyhow!("invalid key at line {}: {}", idx + 1, k));
-            }
-            out.push((k, v));
-        } else {
-            return Err(anyhow!("invalid line {}: {}", idx + 1, line));
-        }
-    }
-    Ok(out)
+    Ok(dotenv::parse(&s, interpolate, &lookup)?)
 }
 
 pub fn parse_dotenv_base64<S: AsRef<str>>(data: S) -> Result<Vec<(String, String)>> {
+    parse_dotenv(Cursor::new(decode_dotenv_base64(data)?))
+}
+
+/// Decode a base64 dotenv payload (whitespace is ignored)
+pub fn decode_dotenv_base64<S: AsRef<str>>(data: S) -> Result<String> {
     let raw = data.as_ref();
     let sanitized: String = raw.chars().filter(|c| !c.is_whitespace()).collect();
     if sanitized.is_empty() {
@@ -645,13 +1041,5 @@ pub fn parse_dotenv_base64<S: AsRef<str>>(data: S) -> Result<Vec<(String, String
     let decoded = BASE64_STANDARD
         .decode(sanitized.as_bytes())
         .map_err(|e| anyhow!("invalid base64 payload: {}", e))?;
-    parse_dotenv(Cursor::new(decoded))
-}
-
-fn strip_quotes(s: &str) -> String {
-    if (s.starts_with('\"') && s.ends_with('\"')) || (s.starts_with('\'') && s.ends_with('\'')) {
-        s[1..s.len() - 1].to_string()
-    } else {
-        s.to_string()
-    }
+    String::from_utf8(decoded).map_err(|_| anyhow!("dotenv payload is not UTF-8"))
 }
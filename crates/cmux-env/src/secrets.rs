@@ -0,0 +1,186 @@
+// Secret values: detection, masking and encryption at rest.
+//
+// A value is secret when it was set with the secret flag, or when the flag
+// was left out and its key looks like a credential (`*_TOKEN`, `*_KEY`, ...).
+// Get/List mask secrets unless the client asks to reveal them and the daemon
+// allows it (only with `ENVD_ALLOW_REVEAL=1`). Shell exports always carry
+// the real values.
+//
+// When the daemon persists its state, secret values are sealed with
+// ChaCha20-Poly1305 under a key kept outside the workspace and runtime dir:
+// `$ENVD_SECRET_KEY_FILE`, else `$XDG_CONFIG_HOME/cmux-envd/secret.key`, else
+// `~/.config/cmux-envd/secret.key`; with none of those set the daemon refuses
+// to start. The key is created (mode 0600) the first time a secret is written.
+
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64_STANDARD;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+
+use crate::Scope;
+
+/// Shown in place of a secret value
+pub const SECRET_MASK: &str = "********";
+
+const KEY_LEN: usize = 32;
+
+const SECRET_SUFFIXES: &[&str] = &[
+    "_TOKEN",
+    "_KEY",
+    "_SECRET",
+    "_PASSWORD",
+    "_PASSWD",
+    "_PASS",
+    "_PAT",
+    "_CREDENTIALS",
+    "_PRIVATE_KEY",
+];
+
+const SECRET_NAMES: &[&str] = &["TOKEN", "SECRET", "PASSWORD", "PASSWD", "API_KEY"];
+
+/// Whether a key name looks like it holds a credential
+pub fn looks_secret(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    SECRET_NAMES.contains(&key.as_str()) || SECRET_SUFFIXES.iter().any(|s| key.ends_with(s))
+}
+
+/// Whether the daemon lets clients reveal secrets; off unless
+/// `ENVD_ALLOW_REVEAL` is set to 1/true/yes
+pub fn reveal_allowed() -> bool {
+    matches!(
+        std::env::var("ENVD_ALLOW_REVEAL").as_deref(),
+        Ok("1") | Ok("true") | Ok("yes")
+    )
+}
+
+pub fn secret_key_path() -> Result<PathBuf> {
+    if let Some(path) = std::env::var_os("ENVD_SECRET_KEY_FILE").filter(|p| !p.is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+    // Never fall back to the runtime dir: the key must not sit next to the
+    // state it protects
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .filter(|p| !p.is_empty())
+                .map(|home| PathBuf::from(home).join(".config"))
+        })
+        .ok_or_else(|| {
+            anyhow!(
+                "no place for the secret key: set ENVD_SECRET_KEY_FILE, XDG_CONFIG_HOME or HOME"
+            )
+        })?;
+    Ok(config.join("cmux-envd").join("secret.key"))
+}
+
+/// Seals secret values for storage; the key is loaded (or created) on first use
+pub struct Sealer {
+    path: PathBuf,
+    key: Option<LessSafeKey>,
+    rng: SystemRandom,
+}
+
+impl std::fmt::Debug for Sealer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sealer").field("path", &self.path).finish()
+    }
+}
+
+impl Sealer {
+    pub fn new(path: PathBuf) -> Self {
+        Sealer {
+            path,
+            key: None,
+            rng: SystemRandom::new(),
+        }
+    }
+
+    /// Encrypt `value`, binding it to its key and scope
+    pub fn seal(&mut self, scope: &Scope, key: &str, value: &str) -> Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("generate nonce"))?;
+        let aad = associated_data(scope, key)?;
+        let mut data = value.as_bytes().to_vec();
+        self.key(true)?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(&aad),
+                &mut data,
+            )
+            .map_err(|_| anyhow!("encrypt secret"))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(data);
+        Ok(BASE64_STANDARD.encode(sealed))
+    }
+
+    pub fn open(&mut self, scope: &Scope, key: &str, sealed: &str) -> Result<String> {
+        let raw = BASE64_STANDARD
+            .decode(sealed)
+            .context("decode sealed secret")?;
+        if raw.len() < NONCE_LEN {
+            return Err(anyhow!("sealed secret too short"));
+        }
+        let (nonce, data) = raw.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("bad nonce"))?;
+        let aad = associated_data(scope, key)?;
+        let mut data = data.to_vec();
+        let plain = self
+            .key(false)?
+            .open_in_place(nonce, Aad::from(&aad), &mut data)
+            .map_err(|_| anyhow!("decrypt secret (wrong key?)"))?;
+        String::from_utf8(plain.to_vec()).context("secret is not UTF-8")
+    }
+
+    fn key(&mut self, create: bool) -> Result<&LessSafeKey> {
+        if let Some(ref key) = self.key {
+            return Ok(key);
+        }
+        let bytes = match fs::read_to_string(&self.path) {
+            Ok(encoded) => BASE64_STANDARD
+                .decode(encoded.trim())
+                .with_context(|| format!("decode {}", self.path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && create => self.create_key()?,
+            Err(e) => return Err(e).with_context(|| format!("read {}", self.path.display())),
+        };
+        let unbound = UnboundKey::new(&CHACHA20_POLY1305, &bytes)
+            .map_err(|_| anyhow!("{} does not hold a valid key", self.path.display()))?;
+        Ok(self.key.insert(LessSafeKey::new(unbound)))
+    }
+
+    fn create_key(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![0u8; KEY_LEN];
+        self.rng
+            .fill(&mut bytes)
+            .map_err(|_| anyhow!("generate key"))?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("creating dir {}", parent.display()))?;
+        }
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&self.path)
+            .with_context(|| format!("create {}", self.path.display()))?;
+        writeln!(file, "{}", BASE64_STANDARD.encode(&bytes))?;
+        file.sync_all()?;
+        Ok(bytes)
+    }
+}
+
+// Ties a sealed value to where it belongs, so it can't be moved to another key
+fn associated_data(scope: &Scope, key: &str) -> Result<Vec<u8>> {
+    let mut aad = serde_json::to_vec(scope)?;
+    aad.push(0);
+    aad.extend(key.as_bytes());
+    Ok(aad)
+}
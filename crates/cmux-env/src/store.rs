@@ -0,0 +1,335 @@
+// On-disk persistence for the daemon's state.
+//
+// Two files live next to the socket:
+//
+// - `state.json`: a snapshot (generation, values, compacted history), replaced
+//   atomically (write to a temp file, fsync, rename, fsync the directory).
+// - `journal.jsonl`: one line per change made since the snapshot, carrying the
+//   change's generation and the key's new value in its scope.
+//
+// On startup the snapshot is loaded and journal entries newer than it are
+// replayed; a torn last line from a crash is ignored. The result is written
+// back as a fresh snapshot and the journal starts empty, which also happens
+// whenever the journal grows past `COMPACT_AFTER` entries. Generations carry
+// on from where they were, so shells exporting `since` an old generation keep
+// getting correct diffs.
+//
+// Both files are created 0600, inside the daemon's 0700 directory.
+//
+// Secret values never reach either file in plain text: they're sealed (see
+// `secrets.rs`) and kept apart from the plain values. A secret that can't be
+// opened on restore (e.g. the key file is gone) is dropped with a warning.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::{ChangeEvent, Scope, Sealer, State};
+
+const SNAPSHOT_FILE: &str = "state.json";
+const JOURNAL_FILE: &str = "journal.jsonl";
+const COMPACT_AFTER: usize = 1000;
+
+/// When journal appends are flushed to disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FsyncPolicy {
+    /// fsync after every change (survives power loss)
+    #[default]
+    Always,
+    /// Leave it to the OS (survives daemon crashes, not machine crashes)
+    Never,
+}
+
+impl FsyncPolicy {
+    /// From `ENVD_FSYNC` (`always` or `never`)
+    pub fn from_env() -> Self {
+        match std::env::var("ENVD_FSYNC").as_deref() {
+            Ok("never") => FsyncPolicy::Never,
+            _ => FsyncPolicy::Always,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournalEntry {
+    generation: u64,
+    key: String,
+    scope: Scope,
+    /// Value after the change (sealed when secret), `None` when the key was removed
+    value: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    secret: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SealedValue {
+    scope: Scope,
+    key: String,
+    sealed: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Snapshot {
+    generation: u64,
+    globals: HashMap<String, String>,
+    scoped: HashMap<PathBuf, HashMap<String, String>>,
+    history: Vec<ChangeEvent>,
+    /// Secret values, left out of `globals`/`scoped`
+    #[serde(default)]
+    secrets: Vec<SealedValue>,
+}
+
+#[derive(Debug)]
+pub struct Store {
+    dir: PathBuf,
+    journal: File,
+    entries: usize,
+    fsync: FsyncPolicy,
+    sealer: Sealer,
+}
+
+impl Store {
+    /// Restore state from `dir`, then start a fresh journal there
+    pub fn open(dir: &Path, fsync: FsyncPolicy, mut sealer: Sealer) -> Result<(Store, State)> {
+        let snapshot_path = dir.join(SNAPSHOT_FILE);
+        let mut state = match load_snapshot(&snapshot_path, &mut sealer) {
+            Ok(state) => state,
+            Err(e) => {
+                // Keep the daemon usable; set the bad file aside for inspection
+                eprintln!("envd: starting empty, {:#}", e);
+                let _ = fs::rename(
+                    &snapshot_path,
+                    dir.join(format!("{}.corrupt", SNAPSHOT_FILE)),
+                );
+                State::default()
+            }
+        };
+        let replayed = replay_journal(&dir.join(JOURNAL_FILE), &mut state, &mut sealer)?;
+        if replayed > 0 {
+            eprintln!("envd: replayed {} journal entries", replayed);
+        }
+
+        write_snapshot(dir, &state, &mut sealer)?;
+        let journal = open_journal(dir)?;
+        let store = Store {
+            dir: dir.to_path_buf(),
+            journal,
+            entries: 0,
+            fsync,
+            sealer,
+        };
+        Ok((store, state))
+    }
+
+    /// Journal the changes newer than `since`, compacting when the journal
+    /// has grown large
+    pub fn record(&mut self, state: &State, since: u64) -> Result<()> {
+        let mut lines = String::new();
+        for ev in state.history.iter().filter(|e| e.generation > since) {
+            let secret = state.secrets.contains(&(ev.scope.clone(), ev.key.clone()));
+            let value = match state.value_in(&ev.scope, &ev.key) {
+                Some(value) if secret => Some(self.sealer.seal(&ev.scope, &ev.key, value)?),
+                value => value.cloned(),
+            };
+            let entry = JournalEntry {
+                generation: ev.generation,
+                key: ev.key.clone(),
+                scope: ev.scope.clone(),
+                value,
+                secret,
+            };
+            lines.push_str(&serde_json::to_string(&entry)?);
+            lines.push('\n');
+            self.entries += 1;
+        }
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        self.journal
+            .write_all(lines.as_bytes())
+            .context("append to journal")?;
+        if self.fsync == FsyncPolicy::Always {
+            self.journal.sync_data().context("fsync journal")?;
+        }
+        if self.entries >= COMPACT_AFTER {
+            self.compact(state)?;
+        }
+        Ok(())
+    }
+
+    fn compact(&mut self, state: &State) -> Result<()> {
+        // A crash between these steps is harmless: entries already in the
+        // snapshot are skipped when the journal is replayed
+        write_snapshot(&self.dir, state, &mut self.sealer)?;
+        self.journal = open_journal(&self.dir)?;
+        self.entries = 0;
+        Ok(())
+    }
+}
+
+/// Start an empty journal
+fn open_journal(dir: &Path) -> Result<File> {
+    let path = dir.join(JOURNAL_FILE);
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)
+        .with_context(|| format!("open journal {}", path.display()))
+}
+
+fn load_snapshot(path: &Path, sealer: &mut Sealer) -> Result<State> {
+    let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(State::default()),
+        Err(e) => return Err(e).with_context(|| format!("read {}", path.display())),
+    };
+    let snapshot: Snapshot =
+        serde_json::from_slice(&data).with_context(|| format!("parse {}", path.display()))?;
+    let mut state = State {
+        generation: snapshot.generation,
+        globals: snapshot.globals,
+        scoped: snapshot.scoped,
+        history: Vec::new(),
+        secrets: HashSet::new(),
+    };
+    for ev in snapshot.history {
+        state.push_event(ev);
+    }
+    for SealedValue { scope, key, sealed } in snapshot.secrets {
+        match sealer.open(&scope, &key, &sealed) {
+            Ok(value) => insert_value(&mut state, &scope, &key, Some(value), true),
+            Err(e) => eprintln!("envd: dropping secret {}: {:#}", key, e),
+        }
+    }
+    Ok(state)
+}
+
+/// Apply journal entries newer than the state, returning how many were applied
+fn replay_journal(path: &Path, state: &mut State, sealer: &mut Sealer) -> Result<usize> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e).with_context(|| format!("open {}", path.display())),
+    };
+    let mut applied = 0;
+    for line in BufReader::new(file).lines() {
+        let line = line.with_context(|| format!("read {}", path.display()))?;
+        let entry: JournalEntry = match serde_json::from_str(&line) {
+            Ok(entry) => entry,
+            Err(e) => {
+                // Only the last write can be torn; nothing valid follows it
+                eprintln!("envd: ignoring rest of journal after bad entry: {}", e);
+                break;
+            }
+        };
+        if entry.generation <= state.generation {
+            continue;
+        }
+        apply_entry(state, entry, sealer);
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+fn apply_entry(state: &mut State, entry: JournalEntry, sealer: &mut Sealer) {
+    let value = match entry.value {
+        Some(sealed) if entry.secret => match sealer.open(&entry.scope, &entry.key, &sealed) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                eprintln!("envd: dropping secret {}: {:#}", entry.key, e);
+                None
+            }
+        },
+        value => value,
+    };
+    insert_value(state, &entry.scope, &entry.key, value, entry.secret);
+    state.generation = entry.generation;
+    state.push_event(ChangeEvent {
+        generation: entry.generation,
+        key: entry.key,
+        scope: entry.scope,
+    });
+}
+
+/// Put a restored value in place, `None` removing it
+fn insert_value(state: &mut State, scope: &Scope, key: &str, value: Option<String>, secret: bool) {
+    let map = match scope {
+        Scope::Global => &mut state.globals,
+        Scope::Dir(dir) => state.scoped.entry(dir.clone()).or_default(),
+    };
+    let entry = (scope.clone(), key.to_string());
+    match value {
+        Some(value) => {
+            map.insert(key.to_string(), value);
+            if secret {
+                state.secrets.insert(entry);
+            } else {
+                state.secrets.remove(&entry);
+            }
+        }
+        None => {
+            map.remove(key);
+            state.secrets.remove(&entry);
+        }
+    }
+    if let Scope::Dir(dir) = scope {
+        if state.scoped.get(dir).is_some_and(|m| m.is_empty()) {
+            state.scoped.remove(dir);
+        }
+    }
+}
+
+fn write_snapshot(dir: &Path, state: &State, sealer: &mut Sealer) -> Result<()> {
+    let mut snapshot = Snapshot {
+        generation: state.generation,
+        globals: state.globals.clone(),
+        scoped: state.scoped.clone(),
+        history: state.history.clone(),
+        secrets: Vec::new(),
+    };
+    for (scope, key) in &state.secrets {
+        let map = match scope {
+            Scope::Global => Some(&mut snapshot.globals),
+            Scope::Dir(dir) => snapshot.scoped.get_mut(dir),
+        };
+        if let Some(value) = map.and_then(|m| m.remove(key)) {
+            let sealed = sealer.seal(scope, key, &value)?;
+            snapshot.secrets.push(SealedValue {
+                scope: scope.clone(),
+                key: key.clone(),
+                sealed,
+            });
+        }
+    }
+    let path = dir.join(SNAPSHOT_FILE);
+    let tmp = dir.join(format!("{}.tmp", SNAPSHOT_FILE));
+    {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp)
+            .with_context(|| format!("create {}", tmp.display()))?;
+        file.write_all(&serde_json::to_vec(&snapshot)?)?;
+        file.sync_all()
+            .with_context(|| format!("fsync {}", tmp.display()))?;
+    }
+    fs::rename(&tmp, &path).with_context(|| format!("rename to {}", path.display()))?;
+    // Make the rename itself durable
+    File::open(dir)
+        .and_then(|d| d.sync_all())
+        .with_context(|| format!("fsync {}", dir.display()))?;
+    Ok(())
+}
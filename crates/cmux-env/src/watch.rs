@@ -0,0 +1,156 @@
+// Change subscriptions.
+//
+// A `Subscribe` request keeps its connection open. The daemon first answers
+// with `Response::Subscribed { generation }` and from then on writes one
+// `ChangeEvent` per line for every change that can affect the subscriber:
+// global changes and changes scoped to an ancestor of its `pwd` (all scopes
+// when no pwd was given), limited to `keys` when that isn't empty. Events
+// carry no values; subscribers read the new values with `Get`/`Env`.
+//
+// Subscribers are registered and notified under the state lock, so every
+// change after the announced generation is delivered exactly once.
+
+use anyhow::{anyhow, Context, Result};
+use parking_lot::Mutex;
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+use crate::{
+    canon, connect_daemon, is_ancestor, write_json, ChangeEvent, Request, Response, Scope,
+};
+
+/// How often an idle subscription checks whether its client went away
+const HANGUP_CHECK: Duration = Duration::from_secs(5);
+
+struct Subscriber {
+    pwd: Option<PathBuf>,
+    keys: HashSet<String>,
+    tx: Sender<ChangeEvent>,
+}
+
+impl Subscriber {
+    fn wants(&self, ev: &ChangeEvent) -> bool {
+        if !self.keys.is_empty() && !self.keys.contains(&ev.key) {
+            return false;
+        }
+        match (&ev.scope, &self.pwd) {
+            (Scope::Dir(dir), Some(pwd)) => is_ancestor(dir, pwd),
+            _ => true,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Subscribers {
+    list: Mutex<Vec<Subscriber>>,
+}
+
+impl Subscribers {
+    pub fn add(&self, pwd: Option<PathBuf>, keys: Vec<String>) -> Receiver<ChangeEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.list.lock().push(Subscriber {
+            pwd: pwd.map(canon),
+            keys: keys.into_iter().collect(),
+            tx,
+        });
+        rx
+    }
+
+    /// Hand events to interested subscribers, dropping the ones that left
+    pub fn publish<'a>(&self, events: impl Iterator<Item = &'a ChangeEvent>) {
+        let mut list = self.list.lock();
+        if list.is_empty() {
+            return;
+        }
+        let events: Vec<&ChangeEvent> = events.collect();
+        list.retain(|sub| {
+            events
+                .iter()
+                .filter(|ev| sub.wants(ev))
+                .all(|ev| sub.tx.send((*ev).clone()).is_ok())
+        });
+    }
+}
+
+/// Stream events to a subscribed client until it disconnects
+pub fn serve(mut stream: UnixStream, generation: u64, rx: Receiver<ChangeEvent>) {
+    if write_json(&mut stream, &Response::Subscribed { generation }).is_err() {
+        return;
+    }
+    loop {
+        match rx.recv_timeout(HANGUP_CHECK) {
+            Ok(ev) => {
+                let mut line = match serde_json::to_string(&ev) {
+                    Ok(line) => line,
+                    Err(_) => continue,
+                };
+                line.push('\n');
+                if stream.write_all(line.as_bytes()).is_err() {
+                    return;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if hung_up(&mut stream) {
+                    return;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+// Clients never write after subscribing, so readable means EOF (or junk)
+fn hung_up(stream: &mut UnixStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return true;
+    }
+    let gone = !matches!(stream.read(&mut [0u8; 1]), Err(e) if e.kind() == ErrorKind::WouldBlock);
+    gone || stream.set_nonblocking(false).is_err()
+}
+
+/// Client side of a subscription: an iterator over change events
+pub struct Subscription {
+    /// Generation at the time of subscribing; later changes are streamed
+    pub generation: u64,
+    reader: BufReader<UnixStream>,
+}
+
+impl Subscription {
+    /// Subscribe to changes relevant to `pwd` (all scopes when `None`),
+    /// restricted to `keys` unless empty
+    pub fn new(pwd: Option<PathBuf>, keys: Vec<String>, autostart: bool) -> Result<Self> {
+        let mut stream = connect_daemon(autostart)?;
+        let req = serde_json::to_string(&Request::Subscribe { pwd, keys })?;
+        stream.write_all(req.as_bytes())?;
+        stream.write_all(b"\n")?;
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if line.is_empty() {
+            return Err(anyhow!("empty response"));
+        }
+        match serde_json::from_str(&line).context("parse response")? {
+            Response::Subscribed { generation } => Ok(Subscription { generation, reader }),
+            Response::Error { message } => Err(anyhow!(message)),
+            _ => Err(anyhow!("unexpected response")),
+        }
+    }
+}
+
+impl Iterator for Subscription {
+    type Item = Result<ChangeEvent>;
+
+    /// `None` once the daemon closes the connection
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(serde_json::from_str(&line).context("parse change event")),
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
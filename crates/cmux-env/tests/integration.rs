@@ -29,8 +29,14 @@ fn kill_envd_by_pid(tmp: &TempDir) {
 }
 
 fn start_envd_with_runtime(tmp: &TempDir) -> std::process::Child {
+    start_envd_with_env(tmp, &[])
+}
+
+fn start_envd_with_env(tmp: &TempDir, envs: &[(&str, &str)]) -> std::process::Child {
     let mut cmd = Command::cargo_bin("envd").expect("binary envd");
     cmd.env("XDG_RUNTIME_DIR", tmp.path());
+    cmd.env("ENVD_SECRET_KEY_FILE", tmp.path().join("secret.key"));
+    cmd.envs(envs.iter().copied());
     cmd.stdout(Stdio::null());
     cmd.stderr(Stdio::null());
     let mut child = cmd.spawn().expect("start envd");
@@ -50,6 +56,8 @@ fn start_envd_with_runtime(tmp: &TempDir) -> std::process::Child {
 fn run_envctl(tmp: &TempDir, args: &[&str]) -> assert_cmd::assert::Assert {
     let mut cmd = Command::cargo_bin("envctl").unwrap();
     cmd.env("XDG_RUNTIME_DIR", tmp.path());
+    // Picked up by envd when envctl autostarts it
+    cmd.env("ENVD_SECRET_KEY_FILE", tmp.path().join("secret.key"));
     for a in args {
         cmd.arg(a);
     }
@@ -227,6 +235,94 @@ fn list_obfuscates_values() {
     let _ = child.wait();
 }
 
+#[test]
+fn secrets_are_masked_but_exported_and_encrypted_at_rest() {
+    let tmp = TempDir::new().unwrap();
+    let reveal = [("ENVD_ALLOW_REVEAL", "1")];
+    let mut child = start_envd_with_env(&tmp, &reveal);
+
+    run_envctl(&tmp, &["set", "GITHUB_TOKEN=ghp_live"]).success();
+    run_envctl(&tmp, &["set", "PLAIN=visible"]).success();
+    run_envctl(&tmp, &["set", "DB_URL=postgres://pw@db", "--secret"]).success();
+    run_envctl(&tmp, &["set", "MONKEY=banana", "--no-secret"]).success();
+
+    run_envctl(&tmp, &["list"])
+        .success()
+        .stdout(predicate::str::contains("GITHUB_TOKEN=******** (secret)"))
+        .stdout(predicate::str::contains("DB_URL=******** (secret)"))
+        .stdout(predicate::str::contains("PLAIN=*******\n"))
+        .stdout(predicate::str::contains("MONKEY=******\n"))
+        .stdout(predicate::str::contains("ghp_live").not());
+    run_envctl(&tmp, &["list", "--reveal"])
+        .success()
+        .stdout(predicate::str::contains("GITHUB_TOKEN=ghp_live (secret)"))
+        .stdout(predicate::str::contains("PLAIN=visible\n"));
+    run_envctl(&tmp, &["get", "GITHUB_TOKEN"])
+        .success()
+        .stdout("********\n");
+    run_envctl(&tmp, &["get", "GITHUB_TOKEN", "--reveal"])
+        .success()
+        .stdout("ghp_live\n");
+    run_envctl(&tmp, &["status"])
+        .success()
+        .stdout(predicate::str::contains("secrets: 2"));
+    run_envctl(&tmp, &["export", "bash", "--since", "0"])
+        .success()
+        .stdout(predicate::str::contains("export GITHUB_TOKEN='ghp_live'"));
+
+    // Neither the journal nor the snapshot written on restart hold the value
+    let state_dir = tmp.path().join("cmux-envd");
+    let journal = fs::read_to_string(state_dir.join("journal.jsonl")).unwrap();
+    assert!(journal.contains("GITHUB_TOKEN") && !journal.contains("ghp_live"));
+    child.kill().unwrap();
+    let _ = child.wait();
+    fs::remove_file(state_dir.join("envd.sock")).unwrap();
+    let mut child = start_envd_with_env(&tmp, &reveal);
+    let snapshot = fs::read_to_string(state_dir.join("state.json")).unwrap();
+    assert!(snapshot.contains("visible") && !snapshot.contains("ghp_live"));
+
+    run_envctl(&tmp, &["get", "GITHUB_TOKEN", "--reveal"])
+        .success()
+        .stdout("ghp_live\n");
+    run_envctl(&tmp, &["get", "DB_URL"])
+        .success()
+        .stdout("********\n");
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[test]
+fn envd_refuses_to_start_without_a_secret_key_location() {
+    let tmp = TempDir::new().unwrap();
+    Command::cargo_bin("envd")
+        .unwrap()
+        .env("XDG_RUNTIME_DIR", tmp.path())
+        .env_remove("ENVD_SECRET_KEY_FILE")
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("HOME")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("secret key"));
+}
+
+#[test]
+fn reveal_is_opt_in() {
+    let tmp = TempDir::new().unwrap();
+    let mut child = start_envd_with_runtime(&tmp);
+
+    run_envctl(&tmp, &["set", "API_KEY=abc"]).success();
+    run_envctl(&tmp, &["get", "API_KEY", "--reveal"])
+        .failure()
+        .stderr(predicate::str::contains("disabled"));
+    run_envctl(&tmp, &["get", "API_KEY"])
+        .success()
+        .stdout("********\n");
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
 #[test]
 fn reset_all_clears_globals_and_scopes() {
     let tmp = TempDir::new().unwrap();
@@ -429,6 +525,81 @@ fn minimal_diff_with_generation() {
     let _ = child.wait();
 }
 
+#[test]
+fn state_survives_envd_restart() {
+    let tmp = TempDir::new().unwrap();
+    let mut child = start_envd_with_runtime(&tmp);
+
+    let proj = tmp.path().join("proj");
+    fs::create_dir_all(&proj).unwrap();
+    run_envctl(&tmp, &["set", "KEEP=1"]).success();
+    run_envctl(&tmp, &["set", "GONE=1"]).success();
+    run_envctl(&tmp, &["set", "LOCAL=yes", "--dir", proj.to_str().unwrap()]).success();
+    let out = run_envctl(&tmp, &["export", "bash", "--since", "0"])
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let gen: u64 = String::from_utf8_lossy(&out)
+        .lines()
+        .last()
+        .unwrap()
+        .split('=')
+        .next_back()
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    run_envctl(&tmp, &["unset", "GONE"]).success();
+
+    // Crash without any chance to flush
+    child.kill().unwrap();
+    let _ = child.wait();
+    fs::remove_file(tmp.path().join("cmux-envd/envd.sock")).unwrap();
+    let mut child = start_envd_with_runtime(&tmp);
+
+    run_envctl(&tmp, &["get", "KEEP"])
+        .success()
+        .stdout(predicate::str::contains("1"));
+    run_envctl(&tmp, &["get", "LOCAL", "--pwd", proj.to_str().unwrap()])
+        .success()
+        .stdout(predicate::str::contains("yes"));
+
+    // Generations carry on, so a shell at `gen` only gets what changed after it
+    run_envctl(&tmp, &["set", "NEW=2"]).success();
+    run_envctl(&tmp, &["export", "bash", "--since", &gen.to_string()])
+        .success()
+        .stdout(predicate::str::contains("unset -v GONE"))
+        .stdout(predicate::str::contains("export NEW='2'"))
+        .stdout(predicate::str::contains("KEEP").not())
+        .stdout(predicate::str::contains(format!("ENVCTL_GEN={}", gen + 2)));
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[test]
+fn state_files_are_private() {
+    let tmp = TempDir::new().unwrap();
+    let dir = tmp.path().join("cmux-envd");
+    // A directory left open to others is tightened on start
+    fs::create_dir_all(&dir).unwrap();
+    fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+    let mut child = start_envd_with_runtime(&tmp);
+    run_envctl(&tmp, &["set", "TOKEN=abc"]).success();
+
+    let mode = |name: &str| fs::metadata(dir.join(name)).unwrap().permissions().mode() & 0o777;
+    assert_eq!(
+        fs::metadata(&dir).unwrap().permissions().mode() & 0o777,
+        0o700
+    );
+    assert_eq!(mode("state.json"), 0o600);
+    assert_eq!(mode("journal.jsonl"), 0o600);
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
 #[test]
 fn interactive_shell_next_command_reflects_set() {
     let tmp = TempDir::new().unwrap();
@@ -815,3 +986,430 @@ fn load_from_base64_invalid_payload_fails() {
     let _ = child.kill();
     let _ = child.wait();
 }
+
+#[test]
+fn load_parses_full_dotenv_grammar() {
+    let tmp = TempDir::new().unwrap();
+    let mut child = start_envd_with_runtime(&tmp);
+
+    run_envctl(&tmp, &["set", "HOST=db.internal"]).success();
+
+    let env_file = tmp.path().join(".env");
+    fs::write(
+        &env_file,
+        concat!(
+            "# database\n",
+            "export   USER_NAME=app   # inline comment\n",
+            "PORT = 5432\n",
+            "HASH=a#b\n",
+            "URL=postgres://${USER_NAME}@${HOST}:${PORT}/${DB:-main}\n",
+            "SINGLE='literal ${HOST} # kept'\n",
+            "DOUBLE=\"tab\\there \\\"quoted\\\" \\$HOST\"\n",
+            "CERT=\"-----BEGIN-----\n",
+            "abc\n",
+            "-----END-----\"\n",
+        ),
+    )
+    .unwrap();
+    run_envctl(&tmp, &["load", env_file.to_str().unwrap()]).success();
+
+    let get = |key: &str, expected: &str| {
+        run_envctl(&tmp, &["get", key])
+            .success()
+            .stdout(format!("{}\n", expected));
+    };
+    get("USER_NAME", "app");
+    get("PORT", "5432");
+    get("HASH", "a#b");
+    get("URL", "postgres://app@db.internal:5432/main");
+    get("SINGLE", "literal ${HOST} # kept");
+    get("DOUBLE", "tab\there \"quoted\" $HOST");
+    get("CERT", "-----BEGIN-----\nabc\n-----END-----");
+
+    // Without interpolation references are kept as written
+    run_envctl(
+        &tmp,
+        &["load", "--no-interpolate", env_file.to_str().unwrap()],
+    )
+    .success();
+    get("URL", "postgres://${USER_NAME}@${HOST}:${PORT}/${DB:-main}");
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[test]
+fn load_reports_line_and_column_of_errors() {
+    let tmp = TempDir::new().unwrap();
+    let mut child = start_envd_with_runtime(&tmp);
+
+    let env_file = tmp.path().join(".env");
+    fs::write(&env_file, "GOOD=1\nBAD=\"never closed\nOTHER=2\n").unwrap();
+    run_envctl(&tmp, &["load", env_file.to_str().unwrap()])
+        .failure()
+        .stderr(predicate::str::contains(
+            "line 2, column 5: unterminated quoted value",
+        ));
+
+    fs::write(&env_file, "GOOD=1\nexport 9LIVES=x\n").unwrap();
+    run_envctl(&tmp, &["load", env_file.to_str().unwrap()])
+        .failure()
+        .stderr(predicate::str::contains("line 2, column 8"));
+
+    // Nothing from a bad file is applied
+    run_envctl(&tmp, &["list"])
+        .success()
+        .stdout(predicate::str::contains("No environment variables found."));
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[test]
+fn allowed_env_files_load_on_directory_entry() {
+    let tmp = TempDir::new().unwrap();
+    let mut child = start_envd_with_runtime(&tmp);
+
+    let proj = tmp.path().join("proj");
+    let sub = proj.join("sub");
+    fs::create_dir_all(&sub).unwrap();
+    fs::write(proj.join(".env"), "FROM_DOTENV=one\nSHARED=dotenv\n").unwrap();
+    fs::write(
+        proj.join(".envrc.json"),
+        r#"{"FROM_JSON": "two", "SHARED": "json", "PORT": 8080}"#,
+    )
+    .unwrap();
+    let sub_s = sub.to_str().unwrap();
+    let proj_s = proj.to_str().unwrap();
+
+    // Not trusted yet: nothing loads and the shell is told why, once
+    run_envctl(&tmp, &["export", "bash", "--pwd", sub_s])
+        .success()
+        .stdout(
+            predicate::str::contains("is not allowed")
+                .and(predicate::str::contains("FROM_DOTENV").not()),
+        );
+    run_envctl(&tmp, &["export", "bash", "--pwd", sub_s])
+        .success()
+        .stdout(predicate::str::contains("is not allowed").not());
+
+    run_envctl(&tmp, &["allow", proj_s])
+        .success()
+        .stdout(predicate::str::contains(".env").and(predicate::str::contains(".envrc.json")));
+    run_envctl(&tmp, &["export", "bash", "--since", "0", "--pwd", sub_s])
+        .success()
+        .stdout(
+            predicate::str::contains("export FROM_DOTENV='one'")
+                .and(predicate::str::contains("export FROM_JSON='two'"))
+                .and(predicate::str::contains("export SHARED='json'"))
+                .and(predicate::str::contains("export PORT='8080'")),
+        );
+    // Only inside the directory
+    run_envctl(
+        &tmp,
+        &["get", "FROM_DOTENV", "--pwd", tmp.path().to_str().unwrap()],
+    )
+    .success()
+    .stdout("");
+
+    // An edited file is unloaded until allowed again
+    fs::write(proj.join(".env"), "FROM_DOTENV=changed\n").unwrap();
+    run_envctl(&tmp, &["export", "bash", "--pwd", sub_s])
+        .success()
+        .stdout(predicate::str::contains("is not allowed"));
+    run_envctl(&tmp, &["get", "FROM_DOTENV", "--pwd", sub_s])
+        .success()
+        .stdout("");
+    run_envctl(&tmp, &["get", "FROM_JSON", "--pwd", sub_s])
+        .success()
+        .stdout("two\n");
+
+    run_envctl(&tmp, &["allow", proj_s]).success();
+    run_envctl(&tmp, &["get", "FROM_DOTENV", "--pwd", sub_s])
+        .success()
+        .stdout("changed\n");
+
+    // An edit that keeps the size and mtime is caught too
+    let dotenv = proj.join(".env");
+    let mtime = fs::metadata(&dotenv).unwrap().modified().unwrap();
+    fs::write(&dotenv, "FROM_DOTENV=CHANGED\n").unwrap();
+    fs::File::options()
+        .write(true)
+        .open(&dotenv)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+    run_envctl(&tmp, &["export", "bash", "--pwd", sub_s])
+        .success()
+        .stdout(predicate::str::contains("is not allowed"));
+    run_envctl(&tmp, &["get", "FROM_DOTENV", "--pwd", sub_s])
+        .success()
+        .stdout("");
+    run_envctl(&tmp, &["allow", proj_s]).success();
+
+    // The trust list survives a daemon restart
+    kill_envd_by_pid(&tmp);
+    let _ = child.wait();
+    let _ = fs::remove_file(tmp.path().join("cmux-envd/envd.sock"));
+    let mut child = start_envd_with_runtime(&tmp);
+    run_envctl(&tmp, &["export", "bash", "--pwd", sub_s])
+        .success()
+        .stdout(predicate::str::contains("is not allowed").not());
+    run_envctl(&tmp, &["get", "FROM_DOTENV", "--pwd", sub_s])
+        .success()
+        .stdout("CHANGED\n");
+
+    run_envctl(&tmp, &["deny", proj_s]).success();
+    run_envctl(&tmp, &["get", "FROM_JSON", "--pwd", sub_s])
+        .success()
+        .stdout("");
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[test]
+fn autoloaded_values_unload_after_restart_but_manual_sets_stay() {
+    let tmp = TempDir::new().unwrap();
+    let mut child = start_envd_with_runtime(&tmp);
+
+    let proj = tmp.path().join("proj");
+    fs::create_dir_all(&proj).unwrap();
+    fs::write(proj.join(".env"), "LOADED=1\nEDITED=1\n").unwrap();
+    let proj_s = proj.to_str().unwrap();
+    run_envctl(&tmp, &["allow", proj_s]).success();
+    run_envctl(&tmp, &["set", "EDITED=mine", "--dir", proj_s]).success();
+
+    // What was loaded is remembered across a restart
+    kill_envd_by_pid(&tmp);
+    let _ = child.wait();
+    let _ = fs::remove_file(tmp.path().join("cmux-envd/envd.sock"));
+    let mut child = start_envd_with_runtime(&tmp);
+
+    fs::remove_file(proj.join(".env")).unwrap();
+    run_envctl(&tmp, &["export", "bash", "--pwd", proj_s]).success();
+    run_envctl(&tmp, &["get", "LOADED", "--pwd", proj_s])
+        .success()
+        .stdout("");
+    run_envctl(&tmp, &["get", "EDITED", "--pwd", proj_s])
+        .success()
+        .stdout("mine\n");
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[test]
+fn allow_without_env_files_fails() {
+    let tmp = TempDir::new().unwrap();
+    let mut child = start_envd_with_runtime(&tmp);
+
+    run_envctl(&tmp, &["allow", tmp.path().to_str().unwrap()])
+        .failure()
+        .stderr(predicate::str::contains("no .env or .envrc.json"));
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+// Read stdout lines of a spawned command on a background thread
+fn line_reader(child: &mut std::process::Child) -> std::sync::mpsc::Receiver<String> {
+    use std::io::BufRead;
+    let stdout = child.stdout.take().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    thread::spawn(move || {
+        for line in std::io::BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break };
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+#[test]
+fn watch_streams_relevant_changes() {
+    let tmp = TempDir::new().unwrap();
+    let mut child = start_envd_with_runtime(&tmp);
+
+    let proj = tmp.path().join("proj");
+    let other = tmp.path().join("other");
+    fs::create_dir_all(&proj).unwrap();
+    fs::create_dir_all(&other).unwrap();
+
+    let mut watch = Command::cargo_bin("envctl").unwrap();
+    watch.env("XDG_RUNTIME_DIR", tmp.path());
+    watch.args(["watch", "FOO", "--pwd", proj.to_str().unwrap()]);
+    watch.stdout(Stdio::piped());
+    let mut watch = watch.spawn().unwrap();
+    let lines = line_reader(&mut watch);
+    // Give the subscription time to register
+    thread::sleep(Duration::from_millis(300));
+
+    run_envctl(&tmp, &["set", "BAR=ignored"]).success();
+    run_envctl(
+        &tmp,
+        &["set", "FOO=elsewhere", "--dir", other.to_str().unwrap()],
+    )
+    .success();
+    run_envctl(&tmp, &["set", "FOO=here", "--dir", proj.to_str().unwrap()]).success();
+    run_envctl(&tmp, &["unset", "FOO", "--dir", proj.to_str().unwrap()]).success();
+
+    let first = lines.recv_timeout(Duration::from_secs(5)).unwrap();
+    let second = lines.recv_timeout(Duration::from_secs(5)).unwrap();
+    let first: serde_json::Value = serde_json::from_str(&first).unwrap();
+    let second: serde_json::Value = serde_json::from_str(&second).unwrap();
+    assert_eq!(first["key"], "FOO");
+    assert_eq!(first["generation"], 3);
+    assert_eq!(second["generation"], 4);
+    assert!(lines.recv_timeout(Duration::from_millis(300)).is_err());
+
+    let _ = watch.kill();
+    let _ = watch.wait();
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[test]
+fn exec_runs_with_effective_env_and_restarts_on_change() {
+    let tmp = TempDir::new().unwrap();
+    let mut child = start_envd_with_runtime(&tmp);
+
+    run_envctl(&tmp, &["set", "FOO=one"]).success();
+    run_envctl(&tmp, &["exec", "--", "sh", "-c", "echo \"FOO=$FOO\""])
+        .success()
+        .stdout("FOO=one\n");
+    run_envctl(&tmp, &["exec", "--", "sh", "-c", "exit 3"]).code(3);
+
+    let mut exec = Command::cargo_bin("envctl").unwrap();
+    exec.env("XDG_RUNTIME_DIR", tmp.path());
+    exec.args([
+        "exec",
+        "--restart-on-change",
+        "--key",
+        "FOO",
+        "--",
+        "sh",
+        "-c",
+        "echo \"FOO=$FOO\"; exec sleep 10",
+    ]);
+    exec.stdout(Stdio::piped());
+    exec.stderr(Stdio::null());
+    let mut exec = exec.spawn().unwrap();
+    let lines = line_reader(&mut exec);
+    assert_eq!(
+        lines.recv_timeout(Duration::from_secs(5)).unwrap(),
+        "FOO=one"
+    );
+
+    // Other keys don't restart it
+    run_envctl(&tmp, &["set", "BAR=x"]).success();
+    assert!(lines.recv_timeout(Duration::from_millis(700)).is_err());
+
+    run_envctl(&tmp, &["set", "FOO=two"]).success();
+    assert_eq!(
+        lines.recv_timeout(Duration::from_secs(5)).unwrap(),
+        "FOO=two"
+    );
+
+    let _ = exec.kill();
+    let _ = exec.wait();
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[test]
+fn export_formats_for_other_shells_and_tools() {
+    let tmp = TempDir::new().unwrap();
+    let mut child = start_envd_with_runtime(&tmp);
+
+    let proj = tmp.path().join("proj");
+    fs::create_dir_all(&proj).unwrap();
+    let proj_s = proj.to_str().unwrap();
+    run_envctl(&tmp, &["set", "GREETING=it's \"fine\" $HOME"]).success();
+    run_envctl(&tmp, &["set", "CERT=line1\nline2"]).success();
+    run_envctl(&tmp, &["set", "SCOPED=yes", "--dir", proj_s]).success();
+
+    run_envctl(&tmp, &["export", "nu", "--since", "0", "--pwd", proj_s])
+        .success()
+        .stdout(
+            predicate::str::contains("$env.GREETING = r#'it's \"fine\" $HOME'#\n")
+                .and(predicate::str::contains("$env.SCOPED = r#'yes'#\n"))
+                .and(predicate::str::contains("$env.ENVCTL_GEN = '3'\n")),
+        );
+    run_envctl(
+        &tmp,
+        &["export", "powershell", "--since", "0", "--pwd", proj_s],
+    )
+    .success()
+    .stdout(
+        predicate::str::contains("$env:GREETING = 'it''s \"fine\" $HOME'\n")
+            .and(predicate::str::contains("$env:ENVCTL_GEN = '3'\n")),
+    );
+    run_envctl(&tmp, &["unset", "SCOPED", "--dir", proj_s]).success();
+    run_envctl(&tmp, &["export", "pwsh", "--since", "3", "--pwd", proj_s])
+        .success()
+        .stdout("Remove-Item -ErrorAction SilentlyContinue Env:SCOPED\n$env:ENVCTL_GEN = '4'\n");
+    run_envctl(&tmp, &["set", "SCOPED=yes", "--dir", proj_s]).success();
+
+    // File formats describe the whole environment, whatever --since says
+    run_envctl(&tmp, &["export", "docker", "--since", "5", "--pwd", proj_s])
+        .success()
+        .stdout("# CERT skipped: multi-line value\nGREETING=it's \"fine\" $HOME\nSCOPED=yes\n");
+    run_envctl(&tmp, &["export", "systemd", "--pwd", proj_s])
+        .success()
+        .stdout("CERT=\"line1\nline2\"\nGREETING=\"it's \\\"fine\\\" \\$HOME\"\nSCOPED=\"yes\"\n");
+    let out = run_envctl(
+        &tmp,
+        &["export", "json", "--pwd", tmp.path().to_str().unwrap()],
+    )
+    .success()
+    .get_output()
+    .stdout
+    .clone();
+    let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({"CERT": "line1\nline2", "GREETING": "it's \"fine\" $HOME"})
+    );
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[test]
+fn exec_uses_env_of_given_pwd() {
+    let tmp = TempDir::new().unwrap();
+    let mut child = start_envd_with_runtime(&tmp);
+
+    let proj = tmp.path().join("proj");
+    fs::create_dir_all(&proj).unwrap();
+    run_envctl(&tmp, &["set", "WHERE=global"]).success();
+    run_envctl(
+        &tmp,
+        &["set", "WHERE=proj", "--dir", proj.to_str().unwrap()],
+    )
+    .success();
+
+    run_envctl(&tmp, &["exec", "--", "sh", "-c", "echo $WHERE"])
+        .success()
+        .stdout("global\n");
+    run_envctl(
+        &tmp,
+        &[
+            "exec",
+            "--pwd",
+            proj.to_str().unwrap(),
+            "--",
+            "sh",
+            "-c",
+            "echo $WHERE",
+        ],
+    )
+    .success()
+    .stdout("proj\n");
+
+    let _ = child.kill();
+    let _ = child.wait();
+}
@@ -7,7 +7,7 @@ use std::{
     pin::Pin,
     str::FromStr,
     task::{Context, Poll},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use bytes::Bytes;
@@ -28,13 +28,21 @@ use tracing::{error, info, warn};
 
 use http::header::{CONNECTION, HOST, UPGRADE};
 
+mod metrics;
+mod upstream;
+
+use metrics::{Metrics, OTHER_PORT};
+use upstream::UpstreamConnector;
+
 type BoxBody =
     http_body_util::combinators::BoxBody<Bytes, Box<dyn std::error::Error + Send + Sync>>;
 type BoxError = Box<dyn std::error::Error + Send + Sync>;
+type ProxyClient = Client<UpstreamConnector, BoxBody>;
 const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
 const HOST_OVERRIDE_HEADER: &str = "X-Cmux-Host-Override";
 const HTTP2_KEEP_ALIVE_INTERVAL_SECS: u64 = 30;
 const HTTP2_KEEP_ALIVE_TIMEOUT_SECS: u64 = 10;
+const REQUEST_ID_HEADER: &str = "x-request-id";
 
 trait ClientKeepAliveConfig {
     fn set_pool_max_idle_per_host(&mut self, max: usize);
@@ -298,6 +306,25 @@ pub struct ProxyConfig {
     pub listen: SocketAddr,
     pub upstream_host: String,
     pub allow_default_upstream: bool,
+    /// Write one JSON line per request to stdout.
+    pub access_log: bool,
+    /// How long to keep retrying an upstream port that refuses connections.
+    pub upstream_wait: Duration,
+}
+
+/// Marks responses generated because the upstream could not be reached.
+#[derive(Clone, Copy)]
+struct UpstreamFailure;
+
+/// Reports the size of each data frame as it streams through.
+fn count_body(body: BoxBody, count: impl Fn(u64) + Send + Sync + 'static) -> BoxBody {
+    body.map_frame(move |frame| {
+        if let Some(data) = frame.data_ref() {
+            count(data.len() as u64);
+        }
+        frame
+    })
+    .boxed()
 }
 
 pub fn spawn_proxy<S>(cfg: ProxyConfig, mut shutdown: S) -> (SocketAddr, JoinHandle<()>)
@@ -309,7 +336,9 @@ where
     connector.set_connect_timeout(Some(Duration::from_secs(5)));
     let mut client_builder = Client::builder(TokioExecutor::new());
     configure_http_client_builder(&mut client_builder);
-    let client: Client<HttpConnector, BoxBody> = client_builder.build(connector);
+    let client: ProxyClient =
+        client_builder.build(UpstreamConnector::new(connector, cfg.upstream_wait));
+    let metrics = Arc::new(Metrics::new());
 
     let listen = cfg.listen;
     let std_listener = StdTcpListener::bind(listen).expect("bind");
@@ -327,8 +356,9 @@ where
                         Ok((stream, remote_addr)) => {
                             let client = client.clone();
                             let cfg = cfg.clone();
+                            let metrics = metrics.clone();
                             tokio::spawn(async move {
-                                if let Err(err) = serve_client_stream(stream, remote_addr, client, cfg).await {
+                                if let Err(err) = serve_client_stream(stream, remote_addr, client, cfg, metrics).await {
                                     error!(%err, "connection error");
                                 }
                             });
@@ -355,6 +385,8 @@ pub fn spawn_proxy_multi<S>(
     listens: Vec<SocketAddr>,
     upstream_host: String,
     allow_default_upstream: bool,
+    access_log: bool,
+    upstream_wait: Duration,
     shutdown: S,
 ) -> (Vec<SocketAddr>, JoinHandle<()>)
 where
@@ -365,7 +397,9 @@ where
     connector.set_connect_timeout(Some(Duration::from_secs(5)));
     let mut client_builder = Client::builder(TokioExecutor::new());
     configure_http_client_builder(&mut client_builder);
-    let client: Client<HttpConnector, BoxBody> = client_builder.build(connector);
+    let client: ProxyClient =
+        client_builder.build(UpstreamConnector::new(connector, upstream_wait));
+    let metrics = Arc::new(Metrics::new());
 
     let notify = Arc::new(Notify::new());
     let notify_clone = notify.clone();
@@ -379,6 +413,7 @@ where
 
     for addr in listens {
         let client = client.clone();
+        let metrics = metrics.clone();
         let upstream = upstream_host.clone();
         let notify = notify.clone();
         let allow_default = allow_default_upstream;
@@ -420,6 +455,7 @@ where
                         match result {
                             Ok((stream, remote_addr)) => {
                                 let client = client.clone();
+                                let metrics = metrics.clone();
                                 let upstream = upstream.clone();
 
                                 tokio::spawn(async move {
@@ -427,9 +463,11 @@ where
                                         listen: actual_addr,
                                         upstream_host: upstream.clone(),
                                         allow_default_upstream: allow_default,
+                                        access_log,
+                                        upstream_wait,
                                     };
                                     if let Err(err) =
-                                        serve_client_stream(stream, remote_addr, client, cfg).await
+                                        serve_client_stream(stream, remote_addr, client, cfg, metrics).await
                                     {
                                         error!(%err, "connection error");
                                     }
@@ -457,15 +495,23 @@ where
 async fn serve_client_stream(
     stream: TcpStream,
     remote_addr: SocketAddr,
-    client: Client<HttpConnector, BoxBody>,
+    client: ProxyClient,
     cfg: ProxyConfig,
+    metrics: Arc<Metrics>,
 ) -> Result<(), BoxError> {
     let (buffered_stream, client_prefers_http2) = sniff_http2_preface(stream).await?;
     let io = TokioIo::new(buffered_stream);
     let svc_client = client.clone();
     let svc_cfg = cfg.clone();
-    let service =
-        service_fn(move |req| handle(svc_client.clone(), svc_cfg.clone(), remote_addr, req));
+    let service = service_fn(move |req| {
+        handle(
+            svc_client.clone(),
+            svc_cfg.clone(),
+            metrics.clone(),
+            remote_addr,
+            req,
+        )
+    });
 
     if client_prefers_http2 {
         let mut builder = http2::Builder::new(TokioExecutor::new());
@@ -634,6 +680,16 @@ fn upstream_host_from_headers(
     Ok(default_host.to_string())
 }
 
+/// Workspace named by the request, for messages.
+fn workspace_name(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("X-Cmux-Workspace-Internal")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .or_else(|| parse_workspace_port_from_host(headers).map(|(ws, _)| ws))
+}
+
 fn is_upgrade_request(req: &Request<Incoming>) -> bool {
     if req.method() == Method::CONNECT {
         return true;
@@ -754,6 +810,12 @@ fn enforce_local_host_header(
     Ok(())
 }
 
+fn upstream_failure(msg: String) -> Response<BoxBody> {
+    let mut resp = response_with(StatusCode::BAD_GATEWAY, msg);
+    resp.extensions_mut().insert(UpstreamFailure);
+    resp
+}
+
 fn response_with(status: StatusCode, msg: String) -> Response<BoxBody> {
     Response::builder()
         .status(status)
@@ -762,39 +824,129 @@ fn response_with(status: StatusCode, msg: String) -> Response<BoxBody> {
         .unwrap()
 }
 
+fn is_metrics_request(req: &Request<Incoming>) -> bool {
+    req.method() == Method::GET
+        && req.uri().path() == "/metrics"
+        && !req.headers().contains_key("X-Cmux-Port-Internal")
+        && parse_workspace_port_from_host(req.headers()).is_none()
+}
+
+/// Keeps a client-supplied request id if it is reasonable, otherwise mints one.
+fn request_id(headers: &HeaderMap) -> String {
+    headers
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= 200)
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
 async fn handle(
-    client: Client<HttpConnector, BoxBody>,
+    client: ProxyClient,
     cfg: ProxyConfig,
+    metrics: Arc<Metrics>,
     remote_addr: SocketAddr,
-    req: Request<Incoming>,
+    mut req: Request<Incoming>,
 ) -> Result<Response<BoxBody>, Infallible> {
+    if is_metrics_request(&req) {
+        return Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "text/plain; version=0.0.4")
+            .body(full_body(metrics.render()))
+            .unwrap());
+    }
+
+    let started = Instant::now();
+    let request_id = request_id(req.headers());
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        req.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
     let method = req.method().clone();
     let is_upgrade = is_upgrade_request(&req);
+    let kind = match (&method, is_upgrade) {
+        (&Method::CONNECT, _) => "connect",
+        (_, true) => "websocket",
+        _ => "http",
+    };
+    let port = get_port_from_header(req.headers()).ok();
+    let upstream = upstream_host_from_headers(
+        req.headers(),
+        &cfg.upstream_host,
+        cfg.allow_default_upstream,
+    )
+    .ok()
+    .zip(port)
+    .map(|(host, port)| format!("{}:{}", host, port));
+    let host = req
+        .headers()
+        .get(HOST)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let path = req.uri().path().to_string();
 
-    match method {
-        Method::CONNECT => match handle_connect(req, &cfg, remote_addr).await {
-            Ok(resp) => Ok(resp),
-            Err(resp) => Ok(resp),
-        },
+    let result = match method {
+        Method::CONNECT => handle_connect(req, &cfg, metrics.clone(), remote_addr).await,
         _ => {
             if is_upgrade {
-                match handle_upgrade(client, cfg, remote_addr, req).await {
-                    Ok(resp) => Ok(resp),
-                    Err(resp) => Ok(resp),
-                }
+                handle_upgrade(client, cfg.clone(), metrics.clone(), remote_addr, req).await
             } else {
-                match handle_http(client, &cfg, remote_addr, req).await {
-                    Ok(resp) => Ok(resp),
-                    Err(resp) => Ok(resp),
-                }
+                handle_http(client, &cfg, &metrics, remote_addr, req).await
             }
         }
+    };
+    // HTTP and upgrade handlers only succeed with the upstream's response; a
+    // CONNECT is answered before the upstream is reached
+    let from_upstream = result.is_ok() && kind != "connect";
+    let mut resp = result.unwrap_or_else(|resp| resp);
+
+    let elapsed = started.elapsed();
+    let status = resp.status();
+    // The port comes from the client, so only ports that answered get a series
+    let port_label = match port {
+        Some(port) if from_upstream => port.to_string(),
+        Some(_) => OTHER_PORT.to_string(),
+        None => String::new(),
+    };
+    metrics.observe_request(kind, &port_label, status.as_u16(), elapsed);
+    if resp.extensions().get::<UpstreamFailure>().is_some() {
+        metrics.upstream_error(kind);
+    }
+    if kind == "http" {
+        let counter = metrics.clone();
+        resp = resp.map(|body| count_body(body, move |n| counter.bytes_downstream("http", n)));
     }
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        resp.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    if cfg.access_log {
+        let line = serde_json::json!({
+            "timestamp_ms": std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            "request_id": request_id,
+            "client": remote_addr.to_string(),
+            "method": method.as_str(),
+            "host": host,
+            "path": path,
+            "kind": kind,
+            "port": port,
+            "upstream": upstream,
+            "status": status.as_u16(),
+            "duration_ms": elapsed.as_secs_f64() * 1000.0,
+        });
+        println!("{}", line);
+    }
+
+    Ok(resp)
 }
 
 async fn handle_http(
-    client: Client<HttpConnector, BoxBody>,
+    client: ProxyClient,
     cfg: &ProxyConfig,
+    metrics: &Arc<Metrics>,
     remote_addr: SocketAddr,
     req: Request<Incoming>,
 ) -> Result<Response<BoxBody>, Response<BoxBody>> {
@@ -806,6 +958,8 @@ async fn handle_http(
         &cfg.upstream_host,
         cfg.allow_default_upstream,
     )?;
+    let navigation = upstream::is_navigation(&parts.method, &parts.headers);
+    let workspace = workspace_name(&parts.headers);
     let host_override = parts
         .headers
         .get(HOST_OVERRIDE_HEADER)
@@ -818,7 +972,10 @@ async fn handle_http(
     parts.version = Version::HTTP_11;
 
     // Convert incoming body to BoxBody
-    let proxied_body: BoxBody = incoming_to_box(incoming);
+    let counter = metrics.clone();
+    let proxied_body: BoxBody = count_body(incoming_to_box(incoming), move |n| {
+        counter.bytes_upstream("http", n)
+    });
     let mut new_req = Request::from_parts(parts, proxied_body);
 
     // Strip internal headers
@@ -843,11 +1000,19 @@ async fn handle_http(
         "proxy http"
     );
 
+    let started = Instant::now();
     let upstream_resp = client.request(new_req).await.map_err(|e| {
-        response_with(
-            StatusCode::BAD_GATEWAY,
-            format!("upstream request error: {}", e),
-        )
+        if upstream::is_connection_refused(&e) {
+            upstream::unavailable_response(
+                navigation,
+                port,
+                workspace.as_deref(),
+                &format!("{}:{}", upstream_host, port),
+                started.elapsed(),
+            )
+        } else {
+            upstream_failure(format!("upstream request error: {}", e))
+        }
     })?;
 
     // Map upstream response back to client, stripping hop-by-hop headers
@@ -872,8 +1037,9 @@ async fn handle_http(
 }
 
 async fn handle_upgrade(
-    client: Client<HttpConnector, BoxBody>,
+    client: ProxyClient,
     cfg: ProxyConfig,
+    metrics: Arc<Metrics>,
     remote_addr: SocketAddr,
     req: Request<Incoming>,
 ) -> Result<Response<BoxBody>, Response<BoxBody>> {
@@ -887,6 +1053,7 @@ async fn handle_upgrade(
         cfg.allow_default_upstream,
     )?;
     let upstream_uri = build_upstream_uri(&upstream_host, port, req.uri())?;
+    let workspace = workspace_name(req.headers());
     let host_override = req
         .headers()
         .get(HOST_OVERRIDE_HEADER)
@@ -937,11 +1104,19 @@ async fn handle_upgrade(
     info!(client = %remote_addr, port = port, upstream = %upstream_host, "proxy upgrade (e.g. websocket)");
 
     // Send to upstream and get its response (should be 101)
+    let started = Instant::now();
     let upstream_resp = client.request(proxied_req).await.map_err(|e| {
-        response_with(
-            StatusCode::BAD_GATEWAY,
-            format!("upstream upgrade error: {}", e),
-        )
+        if upstream::is_connection_refused(&e) {
+            upstream::unavailable_response(
+                false,
+                port,
+                workspace.as_deref(),
+                &format!("{}:{}", upstream_host, port),
+                started.elapsed(),
+            )
+        } else {
+            upstream_failure(format!("upstream upgrade error: {}", e))
+        }
     })?;
 
     if upstream_resp.status() != StatusCode::SWITCHING_PROTOCOLS {
@@ -992,10 +1167,15 @@ async fn handle_upgrade(
         .await
         {
             Ok((client_upgraded, upstream_upgraded)) => {
+                let _open = metrics.tunnel_opened("websocket");
                 let mut client_io = TokioIo::new(client_upgraded);
                 let mut upstream_io = TokioIo::new(upstream_upgraded);
-                if let Err(e) = copy_bidirectional(&mut client_io, &mut upstream_io).await {
-                    warn!(%e, "upgrade tunnel error");
+                match copy_bidirectional(&mut client_io, &mut upstream_io).await {
+                    Ok((up, down)) => {
+                        metrics.bytes_upstream("websocket", up);
+                        metrics.bytes_downstream("websocket", down);
+                    }
+                    Err(e) => warn!(%e, "upgrade tunnel error"),
                 }
                 // Try to shutdown both sides
                 let _ = client_io.shutdown().await;
@@ -1013,6 +1193,7 @@ async fn handle_upgrade(
 async fn handle_connect(
     req: Request<Incoming>,
     cfg: &ProxyConfig,
+    metrics: Arc<Metrics>,
     remote_addr: SocketAddr,
 ) -> Result<Response<BoxBody>, Response<BoxBody>> {
     let port = get_port_from_header(req.headers())?;
@@ -1022,6 +1203,7 @@ async fn handle_connect(
         cfg.allow_default_upstream,
     )?;
     let target = format!("{}:{}", upstream_host, port);
+    let upstream_wait = cfg.upstream_wait;
     info!(client = %remote_addr, %target, "tcp tunnel via CONNECT");
 
     // Consume request to get parts for upgrade later
@@ -1044,15 +1226,21 @@ async fn handle_connect(
         match hyper::upgrade::on(original_req).await {
             Ok(upgraded) => {
                 let mut client_io = TokioIo::new(upgraded);
-                match TcpStream::connect(&target).await {
+                match upstream::connect_tcp(&target, upstream_wait).await {
                     Ok(mut upstream) => {
-                        if let Err(e) = copy_bidirectional(&mut client_io, &mut upstream).await {
-                            warn!(%e, "tcp tunnel error");
+                        let _open = metrics.tunnel_opened("connect");
+                        match copy_bidirectional(&mut client_io, &mut upstream).await {
+                            Ok((up, down)) => {
+                                metrics.bytes_upstream("connect", up);
+                                metrics.bytes_downstream("connect", down);
+                            }
+                            Err(e) => warn!(%e, "tcp tunnel error"),
                         }
                         let _ = client_io.shutdown().await;
                         let _ = upstream.shutdown().await;
                     }
                     Err(e) => {
+                        metrics.upstream_error("connect");
                         warn!(%e, "failed to connect to upstream for CONNECT");
                         let _ = client_io
                             .write_all(b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\n\r\n")
@@ -1,4 +1,5 @@
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
 
 use clap::Parser;
 use tracing::info;
@@ -23,6 +24,15 @@ struct Args {
     /// Allow requests without workspace headers to route to the default upstream host.
     #[arg(long, env = "CMUX_ALLOW_DEFAULT_UPSTREAM", default_value_t = true)]
     allow_default_upstream: bool,
+
+    /// Print one JSON access log line per request to stdout.
+    #[arg(long, env = "CMUX_ACCESS_LOG", default_value_t = false)]
+    access_log: bool,
+
+    /// Seconds to keep retrying an upstream port that refuses connections
+    /// (e.g. a dev server that is still starting). 0 disables waiting.
+    #[arg(long, env = "CMUX_UPSTREAM_WAIT_SECS", default_value_t = 15)]
+    upstream_wait_secs: u64,
 }
 
 #[tokio::main]
@@ -42,6 +52,7 @@ async fn main() {
         "listen" = ?args.listen,
         "upstream_host" = %args.upstream_host,
         allow_default_upstream = args.allow_default_upstream,
+        upstream_wait_secs = args.upstream_wait_secs,
         "Starting cmux-proxy"
     );
 
@@ -58,10 +69,16 @@ async fn main() {
     let upstream_host = args.upstream_host;
     let allow_default_upstream = args.allow_default_upstream;
 
-    let (bound, handle) =
-        cmux_proxy::spawn_proxy_multi(listens, upstream_host, allow_default_upstream, async {
+    let (bound, handle) = cmux_proxy::spawn_proxy_multi(
+        listens,
+        upstream_host,
+        allow_default_upstream,
+        args.access_log,
+        Duration::from_secs(args.upstream_wait_secs),
+        async {
             let _ = tokio::signal::ctrl_c().await;
-        });
+        },
+    );
     info!("bound_addrs" = ?bound, "proxy started");
     let _ = handle.await;
 }
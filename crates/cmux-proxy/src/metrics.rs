@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use prometheus::{
+    core::Collector, Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec,
+    Opts, Registry, TextEncoder,
+};
+
+/// Port label for requests that got no upstream response (rejected, failed, or
+/// CONNECT tunnels), so made-up ports don't add series.
+pub(crate) const OTHER_PORT: &str = "other";
+
+/// Prometheus metrics shared by all listeners of one proxy, served on `/metrics`.
+///
+/// The `kind` label is `http`, `websocket` or `connect`.
+pub(crate) struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    duration: HistogramVec,
+    upstream_errors: IntCounterVec,
+    tunnels: IntGaugeVec,
+    bytes: IntCounterVec,
+}
+
+impl Metrics {
+    pub(crate) fn new() -> Self {
+        let registry = Registry::new();
+        let requests = IntCounterVec::new(
+            Opts::new("cmux_proxy_requests_total", "Requests handled."),
+            &["kind", "port", "status"],
+        )
+        .unwrap();
+        let duration = HistogramVec::new(
+            HistogramOpts::new(
+                "cmux_proxy_request_duration_seconds",
+                "Time until response headers were sent.",
+            ),
+            &["kind", "port"],
+        )
+        .unwrap();
+        let upstream_errors = IntCounterVec::new(
+            Opts::new(
+                "cmux_proxy_upstream_errors_total",
+                "Requests that failed to reach the upstream.",
+            ),
+            &["kind"],
+        )
+        .unwrap();
+        let tunnels = IntGaugeVec::new(
+            Opts::new(
+                "cmux_proxy_tunnels_active",
+                "Open WebSocket and CONNECT tunnels.",
+            ),
+            &["kind"],
+        )
+        .unwrap();
+        let bytes = IntCounterVec::new(
+            Opts::new(
+                "cmux_proxy_bytes_total",
+                "Body and tunnel bytes sent upstream or downstream (to the client).",
+            ),
+            &["kind", "direction"],
+        )
+        .unwrap();
+
+        let collectors: [Box<dyn Collector>; 5] = [
+            Box::new(requests.clone()),
+            Box::new(duration.clone()),
+            Box::new(upstream_errors.clone()),
+            Box::new(tunnels.clone()),
+            Box::new(bytes.clone()),
+        ];
+        for collector in collectors {
+            registry.register(collector).unwrap();
+        }
+
+        Self {
+            registry,
+            requests,
+            duration,
+            upstream_errors,
+            tunnels,
+            bytes,
+        }
+    }
+
+    pub(crate) fn observe_request(&self, kind: &str, port: &str, status: u16, elapsed: Duration) {
+        self.requests
+            .with_label_values(&[kind, port, &status.to_string()])
+            .inc();
+        self.duration
+            .with_label_values(&[kind, port])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub(crate) fn upstream_error(&self, kind: &str) {
+        self.upstream_errors.with_label_values(&[kind]).inc();
+    }
+
+    pub(crate) fn bytes_upstream(&self, kind: &str, n: u64) {
+        self.bytes.with_label_values(&[kind, "upstream"]).inc_by(n);
+    }
+
+    pub(crate) fn bytes_downstream(&self, kind: &str, n: u64) {
+        self.bytes
+            .with_label_values(&[kind, "downstream"])
+            .inc_by(n);
+    }
+
+    /// Counts a tunnel as open until the guard is dropped.
+    pub(crate) fn tunnel_opened(&self, kind: &str) -> TunnelGuard {
+        let gauge = self.tunnels.with_label_values(&[kind]);
+        gauge.inc();
+        TunnelGuard(gauge)
+    }
+
+    pub(crate) fn render(&self) -> String {
+        let mut out = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut out)
+            .expect("text encoding never fails");
+        String::from_utf8(out).expect("prometheus text is utf-8")
+    }
+}
+
+pub(crate) struct TunnelGuard(IntGauge);
+
+impl Drop for TunnelGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
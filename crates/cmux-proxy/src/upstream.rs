@@ -0,0 +1,195 @@
+use std::{
+    cmp::min,
+    error::Error as StdError,
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use http::{HeaderMap, Method, Response, StatusCode, Uri};
+use hyper_util::client::legacy::connect::HttpConnector;
+use tokio::net::TcpStream;
+use tokio::time::sleep;
+use tower_service::Service;
+
+use crate::{full_body, BoxBody, BoxError, UpstreamFailure};
+
+const FIRST_RETRY_DELAY: Duration = Duration::from_millis(100);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// How often the "waiting for port" page reloads itself.
+const REFRESH_SECS: u64 = 2;
+
+/// Retries `connect` while it fails with connection refused, for up to `wait`.
+async fn retry_refused<T, E, F, Fut>(wait: Duration, mut connect: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: StdError + 'static,
+{
+    let deadline = Instant::now() + wait;
+    let mut delay = FIRST_RETRY_DELAY;
+    loop {
+        match connect().await {
+            Err(err) if is_connection_refused(&err) && Instant::now() + delay <= deadline => {
+                sleep(delay).await;
+                delay = min(delay * 2, MAX_RETRY_DELAY);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Connects to a TCP upstream, waiting up to `wait` for the port to open.
+pub(crate) async fn connect_tcp(target: &str, wait: Duration) -> io::Result<TcpStream> {
+    retry_refused(wait, || TcpStream::connect(target)).await
+}
+
+/// HTTP connector that waits up to `wait` for a refused port to open, so
+/// requests to dev servers that are still starting succeed instead of failing.
+/// Retrying at connect time means no request body has been consumed yet.
+#[derive(Clone)]
+pub(crate) struct UpstreamConnector {
+    inner: HttpConnector,
+    wait: Duration,
+}
+
+impl UpstreamConnector {
+    pub(crate) fn new(inner: HttpConnector, wait: Duration) -> Self {
+        Self { inner, wait }
+    }
+}
+
+impl Service<Uri> for UpstreamConnector {
+    type Response = <HttpConnector as Service<Uri>>::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let inner = self.inner.clone();
+        let wait = self.wait;
+        Box::pin(async move {
+            retry_refused(wait, || inner.clone().call(uri.clone()))
+                .await
+                .map_err(Into::into)
+        })
+    }
+}
+
+/// Whether any error in the chain is an io error with `ConnectionRefused`.
+pub(crate) fn is_connection_refused(err: &(dyn StdError + 'static)) -> bool {
+    let mut current = Some(err);
+    while let Some(err) = current {
+        if let Some(io_err) = err.downcast_ref::<io::Error>() {
+            if io_err.kind() == io::ErrorKind::ConnectionRefused {
+                return true;
+            }
+        }
+        current = err.source();
+    }
+    false
+}
+
+/// Browsers loading a page get HTML; everything else gets JSON.
+pub(crate) fn is_navigation(method: &Method, headers: &HeaderMap) -> bool {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    if let Some(mode) = header("sec-fetch-mode") {
+        return mode.eq_ignore_ascii_case("navigate");
+    }
+    method == Method::GET && header("accept").is_some_and(|accept| accept.contains("text/html"))
+}
+
+/// Response for an upstream port that stayed closed for the whole retry window.
+pub(crate) fn unavailable_response(
+    navigation: bool,
+    port: u16,
+    workspace: Option<&str>,
+    upstream: &str,
+    waited: Duration,
+) -> Response<BoxBody> {
+    let target = match workspace {
+        Some(ws) => format!("port {} in {}", port, ws),
+        None => format!("port {}", port),
+    };
+    let builder = Response::builder()
+        .status(StatusCode::BAD_GATEWAY)
+        .header("retry-after", REFRESH_SECS.to_string())
+        .header("cache-control", "no-store");
+
+    let mut resp = if navigation {
+        let target = html_escape(&target);
+        let page = format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+             <meta http-equiv=\"refresh\" content=\"{refresh}\">\
+             <title>Waiting for {target}</title>\
+             <style>body{{font-family:system-ui,sans-serif;display:flex;align-items:center;\
+             justify-content:center;height:100vh;margin:0;color:#444}}</style></head>\
+             <body><p>Waiting for {target}\u{2026}</p></body></html>\n",
+            refresh = REFRESH_SECS,
+        );
+        builder
+            .header("content-type", "text/html; charset=utf-8")
+            .body(full_body(page))
+            .unwrap()
+    } else {
+        let body = serde_json::json!({
+            "error": "upstream_unavailable",
+            "message": format!("{} is not accepting connections", target),
+            "port": port,
+            "workspace": workspace,
+            "upstream": upstream,
+            "waited_ms": waited.as_millis() as u64,
+        });
+        builder
+            .header("content-type", "application/json")
+            .body(full_body(body.to_string()))
+            .unwrap()
+    };
+    resp.extensions_mut().insert(UpstreamFailure);
+    resp
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn waits_for_a_port_to_open() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let err = connect_tcp(&addr, Duration::ZERO).await.unwrap_err();
+        assert!(is_connection_refused(&err));
+
+        let reopen = addr.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(300)).await;
+            let listener = tokio::net::TcpListener::bind(&reopen).await.unwrap();
+            let _ = listener.accept().await;
+        });
+        assert!(connect_tcp(&addr, Duration::from_secs(5)).await.is_ok());
+    }
+
+    #[test]
+    fn navigations_get_html() {
+        let mut headers = HeaderMap::new();
+        headers.insert("accept", "text/html,*/*".parse().unwrap());
+        assert!(is_navigation(&Method::GET, &headers));
+        headers.insert("sec-fetch-mode", "cors".parse().unwrap());
+        assert!(!is_navigation(&Method::GET, &headers));
+        assert!(!is_navigation(&Method::GET, &HeaderMap::new()));
+    }
+}
@@ -103,9 +103,11 @@ async fn start_upstream_real_ws_echo_multi() -> (SocketAddr, tokio::task::JoinHa
 }
 
 async fn start_upstream_http() -> SocketAddr {
-    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
-        .await
-        .unwrap();
+    start_upstream_http_at(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).await
+}
+
+async fn start_upstream_http_at(addr: SocketAddr) -> SocketAddr {
+    let listener = TcpListener::bind(addr).await.unwrap();
     let local = listener.local_addr().unwrap();
     tokio::spawn(async move {
         loop {
@@ -162,11 +164,13 @@ async fn start_upstream_host_echo() -> SocketAddr {
 }
 
 async fn start_upstream_ws_like_upgrade_echo() -> SocketAddr {
+    start_upstream_ws_like_upgrade_echo_at(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).await
+}
+
+async fn start_upstream_ws_like_upgrade_echo_at(addr: SocketAddr) -> SocketAddr {
     use hyper::header::{CONNECTION, UPGRADE};
 
-    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
-        .await
-        .unwrap();
+    let listener = TcpListener::bind(addr).await.unwrap();
     let local = listener.local_addr().unwrap();
     tokio::spawn(async move {
         loop {
@@ -267,11 +271,28 @@ async fn start_proxy(
     listen: SocketAddr,
     upstream_host: &str,
     allow_default_upstream: bool,
+) -> (SocketAddr, oneshot::Sender<()>, tokio::task::JoinHandle<()>) {
+    start_proxy_with_wait(
+        listen,
+        upstream_host,
+        allow_default_upstream,
+        Duration::ZERO,
+    )
+    .await
+}
+
+async fn start_proxy_with_wait(
+    listen: SocketAddr,
+    upstream_host: &str,
+    allow_default_upstream: bool,
+    upstream_wait: Duration,
 ) -> (SocketAddr, oneshot::Sender<()>, tokio::task::JoinHandle<()>) {
     let cfg = ProxyConfig {
         listen,
         upstream_host: upstream_host.to_string(),
         allow_default_upstream,
+        access_log: false,
+        upstream_wait,
     };
     let (tx, rx) = oneshot::channel::<()>();
     let (bound, handle) = cmux_proxy::spawn_proxy(
@@ -772,3 +793,253 @@ async fn test_missing_host_override_keeps_host_header() {
     let _ = shutdown.send(());
     let _ = handle.await;
 }
+
+async fn start_upstream_request_id_echo() -> SocketAddr {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .await
+        .unwrap();
+    let local = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = match listener.accept().await {
+                Ok(s) => s,
+                Err(_) => break,
+            };
+            tokio::spawn(async move {
+                let service = service_fn(|req: Request<Incoming>| async move {
+                    let id = req
+                        .headers()
+                        .get("x-request-id")
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or("")
+                        .to_string();
+                    Ok::<_, Infallible>(Response::new(Full::new(Bytes::from(id))))
+                });
+                let _ = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    });
+    local
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_request_ids_and_metrics() {
+    let upstream_addr = start_upstream_request_id_echo().await;
+    let (proxy_addr, shutdown, handle) = start_proxy(
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+        "127.0.0.1",
+        false,
+    )
+    .await;
+    let client: Client<HttpConnector, TestRequestBody> = new_test_client();
+    let url = format!("http://{}/page", proxy_addr);
+    let port = upstream_addr.port();
+
+    let req = Request::builder()
+        .uri(&url)
+        .header("X-Cmux-Port-Internal", port.to_string())
+        .header("X-Request-Id", "req-42")
+        .body(Empty::new())
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(
+        resp.headers()
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok()),
+        Some("req-42")
+    );
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"req-42");
+
+    // Without an id the proxy mints one and sends the same id both ways.
+    let req = Request::builder()
+        .uri(&url)
+        .header("X-Cmux-Port-Internal", port.to_string())
+        .body(Empty::new())
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    let id = resp
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .expect("generated id")
+        .to_string();
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body, id.as_bytes());
+
+    // A port nothing answers on doesn't get its own series
+    let closed_port = closed_local_addr().port();
+    let req = Request::builder()
+        .uri(&url)
+        .header("X-Cmux-Port-Internal", closed_port.to_string())
+        .body(Empty::new())
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+
+    let req = Request::builder()
+        .uri(format!("http://{}/metrics", proxy_addr))
+        .body(Empty::new())
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let text = String::from_utf8(body.to_vec()).unwrap();
+    for series in [
+        format!(r#"cmux_proxy_requests_total{{kind="http",port="{port}",status="200"}} 2"#),
+        format!(r#"cmux_proxy_request_duration_seconds_count{{kind="http",port="{port}"}} 2"#),
+    ] {
+        assert!(text.contains(&series), "missing {series} in:\n{text}");
+    }
+    assert!(text.contains(r#"cmux_proxy_bytes_total{direction="downstream",kind="http"}"#));
+    assert!(text.contains(r#"cmux_proxy_requests_total{kind="http",port="other",status="502"} 1"#));
+    assert!(
+        !text.contains(&format!(r#"port="{closed_port}""#)),
+        "{text}"
+    );
+
+    let _ = shutdown.send(());
+    let _ = handle.await;
+}
+
+/// An address on which nothing is listening (yet).
+fn closed_local_addr() -> SocketAddr {
+    let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    listener.local_addr().unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_http_waits_for_upstream_port() {
+    let upstream_addr = closed_local_addr();
+    let (proxy_addr, shutdown, handle) = start_proxy_with_wait(
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+        "127.0.0.1",
+        false,
+        Duration::from_secs(5),
+    )
+    .await;
+
+    tokio::spawn(async move {
+        sleep(Duration::from_millis(400)).await;
+        start_upstream_http_at(upstream_addr).await;
+    });
+
+    let client: Client<HttpConnector, TestRequestBody> = new_test_client();
+    let req = Request::builder()
+        .uri(format!("http://{}/late", proxy_addr))
+        .header("X-Cmux-Port-Internal", upstream_addr.port().to_string())
+        .body(Empty::new())
+        .unwrap();
+    let resp = timeout(Duration::from_secs(10), client.request(req))
+        .await
+        .expect("resp timeout")
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"ok:GET:/late");
+
+    let _ = shutdown.send(());
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_upstream_down_responses() {
+    let port = closed_local_addr().port();
+    let (proxy_addr, shutdown, handle) = start_proxy_with_wait(
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+        "127.0.0.1",
+        false,
+        Duration::from_millis(300),
+    )
+    .await;
+    let client: Client<HttpConnector, TestRequestBody> = new_test_client();
+    let url = format!("http://{}/", proxy_addr);
+
+    // Browser navigations get a page that reloads until the port opens.
+    let req = Request::builder()
+        .uri(&url)
+        .header("X-Cmux-Port-Internal", port.to_string())
+        .header("X-Cmux-Workspace-Internal", "workspace-1")
+        .header("Sec-Fetch-Mode", "navigate")
+        .header("Accept", "text/html")
+        .body(Empty::new())
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+    assert_eq!(
+        resp.headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok()),
+        Some("text/html; charset=utf-8")
+    );
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let page = String::from_utf8(body.to_vec()).unwrap();
+    assert!(page.contains("http-equiv=\"refresh\""), "page: {}", page);
+    assert!(
+        page.contains(&format!("Waiting for port {} in workspace-1", port)),
+        "page: {}",
+        page
+    );
+
+    // API clients get JSON describing the failure.
+    let req = Request::builder()
+        .uri(&url)
+        .header("X-Cmux-Port-Internal", port.to_string())
+        .header("Accept", "application/json")
+        .body(Empty::new())
+        .unwrap();
+    let resp = client.request(req).await.unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+    let body = resp.into_body().collect().await.unwrap().to_bytes();
+    let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(json["error"], "upstream_unavailable");
+    assert_eq!(json["port"], port);
+    assert_eq!(json["upstream"], format!("127.0.0.1:{}", port));
+    assert!(json["waited_ms"].as_u64().unwrap() >= 100, "json: {}", json);
+
+    let _ = shutdown.send(());
+    let _ = handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_websocket_upgrade_waits_for_upstream_port() {
+    let ws_addr = closed_local_addr();
+    let (proxy_addr, shutdown, handle) = start_proxy_with_wait(
+        SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+        "127.0.0.1",
+        false,
+        Duration::from_secs(5),
+    )
+    .await;
+
+    tokio::spawn(async move {
+        sleep(Duration::from_millis(400)).await;
+        start_upstream_ws_like_upgrade_echo_at(ws_addr).await;
+    });
+
+    let mut stream = TcpStream::connect(proxy_addr).await.unwrap();
+    let req = format!(
+        "GET /ws HTTP/1.1\r\nHost: 127.0.0.1:{}\r\nConnection: upgrade\r\nUpgrade: websocket\r\nX-Cmux-Port-Internal: {}\r\n\r\n",
+        proxy_addr.port(),
+        ws_addr.port()
+    );
+    stream.write_all(req.as_bytes()).await.unwrap();
+
+    let mut resp_buf = Vec::new();
+    let mut tmp = [0u8; 1024];
+    while !resp_buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = timeout(Duration::from_secs(10), stream.read(&mut tmp))
+            .await
+            .expect("read timeout")
+            .unwrap();
+        assert!(n > 0);
+        resp_buf.extend_from_slice(&tmp[..n]);
+    }
+    let resp_text = String::from_utf8_lossy(&resp_buf);
+    assert!(resp_text.starts_with("HTTP/1.1 101"), "resp: {}", resp_text);
+
+    let _ = shutdown.send(());
+    let _ = handle.await;
+}
@@ -85,6 +85,8 @@ async fn start_proxy(
         listen,
         upstream_host: upstream_host.to_string(),
         allow_default_upstream,
+        access_log: false,
+        upstream_wait: Duration::ZERO,
     };
     let (tx, rx) = oneshot::channel::<()>();
     let (bound, handle) = cmux_proxy::spawn_proxy(
@@ -0,0 +1,322 @@
+//! Bookkeeping for clients attached to a session's terminal WebSocket
+//!
+//! Clients attach as a `driver` (may hold the input lock) or a read-only
+//! `viewer`. At most one driver holds the input lock at a time; it is handed
+//! over explicitly, or to the longest-attached driver when the holder leaves.
+//! A lock taken explicitly (`take_input` or a handoff) also shuts out input
+//! over HTTP; one a driver merely got by attaching first doesn't.
+//! The session's [`SizePolicy`] decides whose resize requests take effect.
+//!
+//! Client IDs are visible to every attached client, so an ID that is already
+//! attached is refused rather than replaced; otherwise a viewer could evict
+//! the driver and take over its input lock.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachMode {
+    #[default]
+    Driver,
+    Viewer,
+}
+
+/// How the PTY size is chosen when several clients are attached
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizePolicy {
+    /// Smallest columns and rows reported by any attached client
+    Smallest,
+    /// The input holder's size (any client's when nobody holds input)
+    #[default]
+    Driver,
+    /// Only the HTTP resize endpoint changes the size
+    Fixed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClientInfo {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub mode: AttachMode,
+    /// Holds the input lock
+    pub has_input: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cols: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rows: Option<u16>,
+    pub attached_at: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct AttachedClients {
+    /// In attach order
+    clients: Vec<ClientInfo>,
+    input_holder: Option<String>,
+    /// The holder took the lock explicitly rather than by attaching first
+    input_claimed: bool,
+}
+
+impl AttachedClients {
+    /// Register a client. A driver takes the input lock if it's free.
+    pub fn attach(
+        &mut self,
+        id: String,
+        name: Option<String>,
+        mode: AttachMode,
+        now: f64,
+    ) -> Result<(), &'static str> {
+        if self.contains(&id) {
+            return Err("client_id is already attached");
+        }
+        match mode {
+            AttachMode::Driver if self.input_holder.is_none() => {
+                self.input_holder = Some(id.clone());
+                self.input_claimed = false;
+            }
+            // Never let a viewer inherit a lock recorded under its ID
+            AttachMode::Viewer if self.input_holder.as_deref() == Some(id.as_str()) => {
+                self.input_holder = None;
+                self.input_claimed = false;
+            }
+            _ => {}
+        }
+        self.clients.push(ClientInfo {
+            id,
+            name,
+            mode,
+            has_input: false,
+            cols: None,
+            rows: None,
+            attached_at: now,
+        });
+        Ok(())
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Remove a client, passing the input lock on to the longest-attached driver
+    pub fn detach(&mut self, id: &str) {
+        self.clients.retain(|c| c.id != id);
+        if self.input_holder.as_deref() == Some(id) {
+            self.input_holder = self
+                .clients
+                .iter()
+                .find(|c| c.mode == AttachMode::Driver)
+                .map(|c| c.id.clone());
+            self.input_claimed = false;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    pub fn can_input(&self, id: &str) -> bool {
+        self.input_holder.as_deref() == Some(id)
+    }
+
+    /// Whether a driver explicitly took the input lock, shutting out other
+    /// input (e.g. HTTP `send-keys`)
+    pub fn input_claimed(&self) -> bool {
+        self.input_holder.is_some() && self.input_claimed
+    }
+
+    /// Take the input lock if nobody holds it.
+    pub fn take_input(&mut self, id: &str) -> Result<(), &'static str> {
+        let client = self.get(id).ok_or("client not attached")?;
+        if client.mode == AttachMode::Viewer {
+            return Err("viewers are read-only");
+        }
+        match &self.input_holder {
+            Some(holder) if holder != id => Err("input is held by another client"),
+            _ => {
+                self.input_holder = Some(id.to_string());
+                self.input_claimed = true;
+                Ok(())
+            }
+        }
+    }
+
+    /// Give up the input lock. Returns true if `id` held it.
+    pub fn release_input(&mut self, id: &str) -> bool {
+        if self.can_input(id) {
+            self.input_holder = None;
+            self.input_claimed = false;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Hand the input lock from its holder to another driver.
+    pub fn handoff(&mut self, from: &str, to: &str) -> Result<(), &'static str> {
+        if !self.can_input(from) {
+            return Err("only the input holder can hand off");
+        }
+        let target = self.get(to).ok_or("target client not attached")?;
+        if target.mode == AttachMode::Viewer {
+            return Err("viewers are read-only");
+        }
+        self.input_holder = Some(to.to_string());
+        self.input_claimed = true;
+        Ok(())
+    }
+
+    pub fn record_size(&mut self, id: &str, cols: u16, rows: u16) {
+        if let Some(client) = self.clients.iter_mut().find(|c| c.id == id) {
+            client.cols = Some(cols);
+            client.rows = Some(rows);
+        }
+    }
+
+    /// Size the PTY should have under `policy`, if it should follow clients at all.
+    /// `resizing` is the client whose resize triggered the recalculation.
+    pub fn effective_size(&self, policy: SizePolicy, resizing: Option<&str>) -> Option<(u16, u16)> {
+        let size_of = |id: &str| self.get(id).and_then(|c| Some((c.cols?, c.rows?)));
+        match policy {
+            SizePolicy::Fixed => None,
+            SizePolicy::Smallest => {
+                let sizes = self.clients.iter().filter_map(|c| Some((c.cols?, c.rows?)));
+                sizes.reduce(|(cols, rows), (c, r)| (cols.min(c), rows.min(r)))
+            }
+            SizePolicy::Driver => match &self.input_holder {
+                Some(holder) => size_of(holder),
+                None => resizing.and_then(size_of),
+            },
+        }
+    }
+
+    pub fn infos(&self) -> Vec<ClientInfo> {
+        self.clients
+            .iter()
+            .map(|c| ClientInfo {
+                has_input: self.can_input(&c.id),
+                ..c.clone()
+            })
+            .collect()
+    }
+
+    fn get(&self, id: &str) -> Option<&ClientInfo> {
+        self.clients.iter().find(|c| c.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_driver_gets_input_and_lock_passes_on_detach() {
+        let mut clients = AttachedClients::default();
+        clients
+            .attach("viewer".into(), None, AttachMode::Viewer, 0.0)
+            .unwrap();
+        clients
+            .attach("a".into(), Some("alice".into()), AttachMode::Driver, 1.0)
+            .unwrap();
+        clients
+            .attach("b".into(), None, AttachMode::Driver, 2.0)
+            .unwrap();
+
+        assert!(clients.can_input("a"));
+        assert!(!clients.can_input("b"));
+        assert!(!clients.can_input("viewer"));
+        assert!(clients.take_input("b").is_err());
+        assert!(clients.take_input("viewer").is_err());
+
+        clients.detach("a");
+        assert!(clients.can_input("b"));
+        let infos = clients.infos();
+        assert_eq!(infos.len(), 2);
+        assert!(infos.iter().any(|c| c.id == "b" && c.has_input));
+    }
+
+    #[test]
+    fn attached_ids_cannot_be_reused() {
+        let mut clients = AttachedClients::default();
+        clients
+            .attach("a".into(), None, AttachMode::Driver, 0.0)
+            .unwrap();
+        assert!(clients
+            .attach("a".into(), None, AttachMode::Viewer, 1.0)
+            .is_err());
+        assert!(clients.can_input("a"));
+        assert_eq!(clients.infos().len(), 1);
+        assert!(!clients.input_claimed());
+
+        clients.detach("a");
+        assert!(!clients.can_input("a"));
+        clients
+            .attach("a".into(), None, AttachMode::Viewer, 2.0)
+            .unwrap();
+        assert!(!clients.can_input("a"));
+    }
+
+    #[test]
+    fn handoff_and_release() {
+        let mut clients = AttachedClients::default();
+        clients
+            .attach("a".into(), None, AttachMode::Driver, 0.0)
+            .unwrap();
+        clients
+            .attach("b".into(), None, AttachMode::Driver, 0.0)
+            .unwrap();
+        clients
+            .attach("v".into(), None, AttachMode::Viewer, 0.0)
+            .unwrap();
+
+        // Attaching first grants input without claiming the lock
+        assert!(clients.can_input("a"));
+        assert!(!clients.input_claimed());
+
+        assert!(clients.handoff("b", "a").is_err());
+        assert!(clients.handoff("a", "v").is_err());
+        clients.handoff("a", "b").unwrap();
+        assert!(clients.can_input("b"));
+        assert!(clients.input_claimed());
+
+        assert!(clients.release_input("b"));
+        assert!(!clients.can_input("b"));
+        assert!(!clients.input_claimed());
+        clients.take_input("a").unwrap();
+        assert!(clients.can_input("a"));
+        assert!(clients.input_claimed());
+
+        clients.detach("a");
+        assert!(clients.can_input("b"));
+        assert!(!clients.input_claimed());
+    }
+
+    #[test]
+    fn effective_size_follows_policy() {
+        let mut clients = AttachedClients::default();
+        clients
+            .attach("a".into(), None, AttachMode::Driver, 0.0)
+            .unwrap();
+        clients
+            .attach("v".into(), None, AttachMode::Viewer, 0.0)
+            .unwrap();
+        clients.record_size("a", 120, 40);
+        clients.record_size("v", 100, 50);
+
+        assert_eq!(
+            clients.effective_size(SizePolicy::Smallest, Some("a")),
+            Some((100, 40))
+        );
+        assert_eq!(
+            clients.effective_size(SizePolicy::Driver, Some("v")),
+            Some((120, 40))
+        );
+        assert_eq!(clients.effective_size(SizePolicy::Fixed, Some("a")), None);
+
+        clients.release_input("a");
+        assert_eq!(
+            clients.effective_size(SizePolicy::Driver, Some("v")),
+            Some((100, 50))
+        );
+    }
+}
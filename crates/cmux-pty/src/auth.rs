@@ -0,0 +1,279 @@
+//! Access control for the HTTP and WebSocket API
+//!
+//! With a token file configured, every request except `/health` must present
+//! the token as `Authorization: Bearer <token>`, a `token` query parameter
+//! (for WebSocket clients that can't set headers) or the `cmux_pty_token`
+//! cookie. A request authenticated by query parameter gets the cookie set, so
+//! opening `/?token=...` once is enough for the bundled frontend.
+//!
+//! Loopback connections need the token too: proxies in front of the server
+//! (cmux-proxy, the sandbox daemon) forward remote requests from loopback.
+//! Where nothing forwards to the server, `--trust-loopback`
+//! (`CMUX_PTY_TRUST_LOOPBACK=1`) lets non-browser loopback clients in without it.
+//!
+//! Independently of the token, requests carrying an `Origin` header (i.e. from
+//! a browser) are only accepted from the server's own origin or the
+//! configured allowlist, so a page on another site can't drive a WebSocket.
+
+use std::{net::SocketAddr, path::Path, sync::Arc};
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::warn;
+
+use super::AppState;
+
+pub const TOKEN_COOKIE: &str = "cmux_pty_token";
+const TOKEN_QUERY_PARAM: &str = "token";
+
+/// Allowlist entry that accepts any origin
+const ANY_ORIGIN: &str = "*";
+
+/// Paths reachable without a token (liveness checks)
+const PUBLIC_PATHS: &[&str] = &["/health"];
+
+#[derive(Debug, Clone, Default)]
+pub struct AccessConfig {
+    /// Required token; `None` disables token checks
+    pub token: Option<String>,
+    /// Browser origins allowed besides the server's own (e.g. `https://cmux.app`)
+    pub allowed_origins: Vec<String>,
+    /// Let loopback clients without an `Origin` header skip the token
+    pub trust_loopback: bool,
+}
+
+impl AccessConfig {
+    /// Read the token from `token_file` (surrounding whitespace is ignored).
+    pub fn load(
+        token_file: Option<&Path>,
+        allowed_origins: Vec<String>,
+        trust_loopback: bool,
+    ) -> Result<Self> {
+        let token = match token_file {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read token file {}", path.display()))?;
+                let token = contents.trim().to_string();
+                if token.is_empty() {
+                    anyhow::bail!("Token file {} is empty", path.display());
+                }
+                Some(token)
+            }
+            None => None,
+        };
+        Ok(Self {
+            token,
+            allowed_origins: allowed_origins
+                .into_iter()
+                .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect(),
+            trust_loopback,
+        })
+    }
+
+    /// CORS for allowlisted origins. Without a token or allowlist the server
+    /// keeps the historical permissive CORS.
+    pub fn cors_layer(&self) -> CorsLayer {
+        if self.token.is_none() && self.allowed_origins.is_empty() {
+            return CorsLayer::permissive();
+        }
+        let origins = if self.allowed_origins.iter().any(|o| o == ANY_ORIGIN) {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::list(
+                self.allowed_origins
+                    .iter()
+                    .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+            )
+        };
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(Any)
+            .allow_headers(Any)
+    }
+
+    /// Whether a browser at `origin` may talk to the server reached as `host`
+    fn origin_allowed(&self, origin: &str, host: Option<&str>) -> bool {
+        let origin = origin.trim_end_matches('/');
+        if self
+            .allowed_origins
+            .iter()
+            .any(|allowed| allowed == ANY_ORIGIN || allowed == origin)
+        {
+            return true;
+        }
+        // Same origin: the page was served by this server (e.g. the bundled frontend)
+        let authority = origin
+            .strip_prefix("http://")
+            .or_else(|| origin.strip_prefix("https://"));
+        matches!((authority, host), (Some(authority), Some(host)) if authority.eq_ignore_ascii_case(host))
+    }
+
+    fn token_matches(&self, candidate: &str) -> bool {
+        self.token
+            .as_deref()
+            .is_some_and(|token| constant_time_eq(token.as_bytes(), candidate.as_bytes()))
+    }
+}
+
+/// Where a request presented its token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenSource {
+    Header,
+    Query,
+    Cookie,
+}
+
+fn presented_tokens(headers: &HeaderMap, query: Option<&str>) -> Vec<(TokenSource, String)> {
+    let mut tokens = Vec::new();
+    if let Some(bearer) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    {
+        tokens.push((TokenSource::Header, bearer.trim().to_string()));
+    }
+    if let Some(query) = query {
+        tokens.extend(
+            url::form_urlencoded::parse(query.as_bytes())
+                .filter(|(key, _)| key == TOKEN_QUERY_PARAM)
+                .map(|(_, value)| (TokenSource::Query, value.into_owned())),
+        );
+    }
+    for cookies in headers.get_all(header::COOKIE) {
+        let Ok(cookies) = cookies.to_str() else {
+            continue;
+        };
+        tokens.extend(cookies.split(';').filter_map(|pair| {
+            let (name, value) = pair.trim().split_once('=')?;
+            (name == TOKEN_COOKIE).then(|| (TokenSource::Cookie, value.to_string()))
+        }));
+    }
+    tokens
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn reject(status: StatusCode, message: &'static str) -> Response {
+    (status, axum::Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// Middleware enforcing the origin allowlist and token
+pub async fn require_access(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let access = &state.access;
+    let headers = request.headers();
+    let path = request.uri().path();
+
+    let origin = headers.get(header::ORIGIN).and_then(|v| v.to_str().ok());
+    if let Some(origin) = origin {
+        let host = headers.get(header::HOST).and_then(|v| v.to_str().ok());
+        if !access.origin_allowed(origin, host) {
+            warn!(
+                "[security] Rejected request to {} from origin {}",
+                path, origin
+            );
+            return reject(StatusCode::FORBIDDEN, "Origin not allowed");
+        }
+    }
+
+    if access.token.is_none() || PUBLIC_PATHS.contains(&path) {
+        return next.run(request).await;
+    }
+
+    let presented = presented_tokens(headers, request.uri().query());
+    let authenticated = presented
+        .iter()
+        .find(|(_, token)| access.token_matches(token))
+        .map(|(source, _)| *source);
+
+    let trusted_loopback = access.trust_loopback
+        && origin.is_none()
+        && request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .is_some_and(|ConnectInfo(addr)| addr.ip().is_loopback());
+
+    match authenticated {
+        Some(TokenSource::Query) => {
+            let mut response = next.run(request).await;
+            let cookie = format!(
+                "{}={}; Path=/; HttpOnly; SameSite=Strict",
+                TOKEN_COOKIE,
+                access.token.as_deref().unwrap_or_default()
+            );
+            if let Ok(value) = HeaderValue::from_str(&cookie) {
+                response.headers_mut().append(header::SET_COOKIE, value);
+            }
+            response
+        }
+        Some(_) => next.run(request).await,
+        None if trusted_loopback => next.run(request).await,
+        None => {
+            if !presented.is_empty() {
+                warn!("[security] Invalid token presented for {}", path);
+            }
+            reject(StatusCode::UNAUTHORIZED, "Missing or invalid token")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(token: Option<&str>, origins: &[&str]) -> AccessConfig {
+        AccessConfig {
+            token: token.map(str::to_string),
+            allowed_origins: origins.iter().map(|s| s.to_string()).collect(),
+            trust_loopback: false,
+        }
+    }
+
+    #[test]
+    fn origin_allowed_for_same_host_and_allowlist() {
+        let access = config(None, &["https://cmux.app"]);
+        assert!(access.origin_allowed("https://cmux.app", Some("other:1")));
+        assert!(access.origin_allowed("https://cmux.app/", None));
+        assert!(access.origin_allowed("http://localhost:39383", Some("localhost:39383")));
+        assert!(!access.origin_allowed("https://evil.example", Some("localhost:39383")));
+        assert!(!access.origin_allowed("null", Some("localhost:39383")));
+        assert!(config(None, &["*"]).origin_allowed("https://evil.example", None));
+    }
+
+    #[test]
+    fn tokens_are_read_from_header_query_and_cookie() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer abc".parse().unwrap());
+        headers.insert(header::COOKIE, "a=1; cmux_pty_token=ghi".parse().unwrap());
+        let tokens = presented_tokens(&headers, Some("mode=viewer&token=d%2Bf"));
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenSource::Header, "abc".to_string()),
+                (TokenSource::Query, "d+f".to_string()),
+                (TokenSource::Cookie, "ghi".to_string()),
+            ]
+        );
+
+        let access = config(Some("ghi"), &[]);
+        assert!(access.token_matches("ghi"));
+        assert!(!access.token_matches("gh"));
+        assert!(!config(None, &[]).token_matches(""));
+    }
+}
@@ -3,6 +3,7 @@
 //! Provides tmux-like commands for managing PTY sessions.
 
 use std::io::Write;
+use std::path::Path;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
@@ -13,7 +14,10 @@ use crossterm::{
 };
 use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, Message},
+};
 
 // =============================================================================
 // Types (shared with server)
@@ -31,6 +35,65 @@ pub struct SessionInfo {
     pub created_at: f64,
     pub alive: bool,
     pub pid: u32,
+    #[serde(default)]
+    pub command: Option<Vec<String>>,
+    #[serde(default)]
+    pub restart_count: u32,
+    #[serde(default)]
+    pub clients: Vec<AttachedClient>,
+    #[serde(default)]
+    pub foreground: Option<ProcessInfo>,
+    #[serde(default)]
+    pub processes: Vec<ProcessInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub ppid: u32,
+    pub name: String,
+    #[serde(default)]
+    pub cmdline: Vec<String>,
+    #[serde(default)]
+    pub cwd: Option<String>,
+    pub cpu_time_ms: u64,
+    pub rss_bytes: u64,
+    pub foreground: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachedClient {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// "driver" or "viewer"
+    pub mode: String,
+    pub has_input: bool,
+}
+
+impl AttachedClient {
+    /// Short label for listings: name (or ID prefix), `*` for the input holder, `(ro)` for viewers
+    fn label(&self) -> String {
+        let mut label = self
+            .name
+            .clone()
+            .unwrap_or_else(|| self.id.chars().take(8).collect());
+        if self.has_input {
+            label.push('*');
+        }
+        if self.mode == "viewer" {
+            label.push_str("(ro)");
+        }
+        label
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    Never,
+    OnFailure,
+    Always,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,10 +101,14 @@ pub struct SessionListResponse {
     pub sessions: Vec<SessionInfo>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CreateSessionRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub shell: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restart: Option<RestartPolicy>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cwd: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -50,6 +117,52 @@ pub struct CreateSessionRequest {
     pub rows: Option<u16>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    #[serde(default)]
+    pub reap: ReapPolicy,
+}
+
+/// Idle/lifetime limits, in seconds (unset ones use the server defaults)
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ReapPolicy {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detached_timeout_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_lifetime_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exited_grace_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle_ms: Option<u64>,
+    pub timeout_ms: u64,
+    pub context_lines: usize,
+    pub include_existing: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitMatch {
+    pub text: String,
+    pub captures: Vec<Option<String>>,
+    pub line: usize,
+    pub context_start: usize,
+    pub context: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitResponse {
+    /// "match", "idle", "exit" or "timeout"
+    pub reason: String,
+    #[serde(default)]
+    pub matched: Option<WaitMatch>,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    pub elapsed_ms: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,20 +178,42 @@ pub struct ResizeRequest {
 pub struct PtyClient {
     base_url: String,
     client: reqwest::Client,
+    token: Option<String>,
 }
 
 impl PtyClient {
-    pub fn new(server_url: &str) -> Self {
+    pub fn new(server_url: &str, token: Option<String>) -> Self {
         let base_url = server_url.trim_end_matches('/').to_string();
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(value) = token
+            .as_deref()
+            .and_then(|t| reqwest::header::HeaderValue::from_str(&format!("Bearer {}", t)).ok())
+        {
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
         Self {
             base_url,
             client: reqwest::Client::builder()
                 .timeout(Duration::from_secs(30))
+                .default_headers(headers)
                 .build()
                 .expect("Failed to create HTTP client"),
+            token,
         }
     }
 
+    /// Create a client, reading the API token from `token_file` if given
+    pub fn connect(server_url: &str, token_file: Option<&Path>) -> Result<Self> {
+        let token = token_file
+            .map(|path| {
+                std::fs::read_to_string(path)
+                    .map(|t| t.trim().to_string())
+                    .with_context(|| format!("Failed to read token file {}", path.display()))
+            })
+            .transpose()?;
+        Ok(Self::new(server_url, token))
+    }
+
     /// List all sessions
     pub async fn list_sessions(&self) -> Result<Vec<SessionInfo>> {
         let url = format!("{}/sessions", self.base_url);
@@ -100,27 +235,13 @@ impl PtyClient {
     }
 
     /// Create a new session
-    pub async fn create_session(
-        &self,
-        name: Option<String>,
-        shell: Option<String>,
-        cwd: Option<String>,
-        cols: Option<u16>,
-        rows: Option<u16>,
-    ) -> Result<SessionInfo> {
+    pub async fn create_session(&self, request: &CreateSessionRequest) -> Result<SessionInfo> {
         let url = format!("{}/sessions", self.base_url);
-        let request = CreateSessionRequest {
-            shell,
-            cwd,
-            cols,
-            rows,
-            name,
-        };
 
         let resp = self
             .client
             .post(&url)
-            .json(&request)
+            .json(request)
             .send()
             .await
             .context("Failed to connect to server")?;
@@ -167,23 +288,25 @@ impl PtyClient {
         Ok(())
     }
 
-    /// Send keys to a session via WebSocket
+    /// Send keys to a session.
+    /// Refused by the server while an attached client has explicitly taken the input lock.
     pub async fn send_keys(&self, session_id: &str, keys: &str) -> Result<()> {
         let actual_id = self.resolve_session_id(session_id).await?;
-        let ws_url = self.get_ws_url(&format!("/sessions/{}/ws", actual_id))?;
-
-        let (mut ws_stream, _) = connect_async(&ws_url)
-            .await
-            .context("Failed to connect to WebSocket")?;
+        let url = format!("{}/sessions/{}/input", self.base_url, actual_id);
 
-        // Send keys as binary (raw terminal input)
-        ws_stream
-            .send(Message::Binary(keys.as_bytes().to_vec()))
+        let resp = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({ "data": keys }))
+            .send()
             .await
-            .context("Failed to send keys")?;
+            .context("Failed to connect to server")?;
 
-        // Close gracefully
-        ws_stream.close(None).await.ok();
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            anyhow::bail!("Server returned {}: {}", status, body);
+        }
 
         Ok(())
     }
@@ -238,15 +361,95 @@ impl PtyClient {
         Ok(())
     }
 
+    /// Block until the session's output matches, goes idle, or the process exits
+    pub async fn wait(&self, session_id: &str, request: &WaitRequest) -> Result<WaitResponse> {
+        let actual_id = self.resolve_session_id(session_id).await?;
+        let url = format!("{}/sessions/{}/wait", self.base_url, actual_id);
+
+        // The server holds the request open for up to timeout_ms
+        let resp = self
+            .client
+            .post(&url)
+            .json(request)
+            .timeout(Duration::from_millis(request.timeout_ms) + Duration::from_secs(10))
+            .send()
+            .await
+            .context("Failed to connect to server")?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            anyhow::bail!("Server returned {}: {}", status, body);
+        }
+
+        resp.json().await.context("Failed to parse response")
+    }
+
+    /// Read part of a session's log starting at `from` (negative counts back from the end)
+    pub async fn read_log(&self, session_id: &str, from: i64, text: bool) -> Result<LogChunk> {
+        let url = format!("{}/sessions/{}/log", self.base_url, session_id);
+        let format = if text { "text" } else { "raw" };
+
+        let resp = self
+            .client
+            .get(&url)
+            .query(&[("from", from.to_string()), ("format", format.to_string())])
+            .send()
+            .await
+            .context("Failed to connect to server")?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            anyhow::bail!("Server returned {}: {}", status, body);
+        }
+
+        let header = |name: &str| {
+            resp.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+                .with_context(|| format!("Missing {} header", name))
+        };
+        let end: u64 = header("x-log-end")?.parse().context("Invalid x-log-end")?;
+        let size: u64 = header("x-log-size")?
+            .parse()
+            .context("Invalid x-log-size")?;
+        let complete = header("x-log-complete")? == "true";
+        let data = resp.bytes().await.context("Failed to read log")?.to_vec();
+
+        Ok(LogChunk {
+            data,
+            end,
+            size,
+            complete,
+        })
+    }
+
     /// Attach to a session interactively
-    pub async fn attach(&self, session_id: &str) -> Result<()> {
+    pub async fn attach(&self, session_id: &str, read_only: bool) -> Result<()> {
         let actual_id = self.resolve_session_id(session_id).await?;
-        let ws_url = self.get_ws_url(&format!("/sessions/{}/ws", actual_id))?;
+        let name = std::env::var("USER").unwrap_or_else(|_| "cli".to_string());
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("mode", if read_only { "viewer" } else { "driver" })
+            .append_pair("name", &name)
+            .finish();
+        let ws_url = self.get_ws_url(&format!("/sessions/{}/ws?{}", actual_id, query))?;
 
         eprintln!("Attaching to session {}...", actual_id);
-        eprintln!("Press Ctrl+B then D to detach");
+        if read_only {
+            eprintln!("Read-only. Press Ctrl+B then D to detach");
+        } else {
+            eprintln!("Press Ctrl+B then D to detach, Ctrl+B then T to take input");
+        }
 
-        let (ws_stream, _) = connect_async(&ws_url)
+        let mut ws_request = ws_url.into_client_request()?;
+        if let Some(token) = &self.token {
+            ws_request
+                .headers_mut()
+                .insert("Authorization", format!("Bearer {}", token).parse()?);
+        }
+        let (ws_stream, _) = connect_async(ws_request)
             .await
             .context("Failed to connect to WebSocket")?;
 
@@ -287,6 +490,16 @@ impl PtyClient {
                             // Control message (e.g., exit)
                             break;
                         }
+                        if let Ok(ctrl) = serde_json::from_str::<serde_json::Value>(&text) {
+                            // A refused message (e.g. take_input while someone else
+                            // holds input): ring the bell rather than draw over the screen
+                            if ctrl.get("type").and_then(|t| t.as_str()) == Some("error") {
+                                let mut stdout = std::io::stdout();
+                                stdout.write_all(b"\x07").ok();
+                                stdout.flush().ok();
+                                continue;
+                            }
+                        }
                         let mut stdout = std::io::stdout();
                         stdout.write_all(text.as_bytes()).ok();
                         stdout.flush().ok();
@@ -312,6 +525,14 @@ impl PtyClient {
                             {
                                 break 'input Ok(true); // Detach requested
                             }
+                            if key_event.code == KeyCode::Char('t')
+                                || key_event.code == KeyCode::Char('T')
+                            {
+                                // Ask for the input lock (granted only if nobody holds it)
+                                let take = serde_json::json!({ "type": "take_input" });
+                                ws_sender.send(Message::Text(take.to_string())).await.ok();
+                                continue;
+                            }
                             // Not 'd', send both Ctrl+B and this key
                             ws_sender
                                 .send(Message::Binary(vec![0x02])) // Ctrl+B
@@ -327,6 +548,10 @@ impl PtyClient {
                             continue;
                         }
 
+                        if read_only {
+                            continue;
+                        }
+
                         // Convert key event to bytes
                         let data = key_event_to_bytes(&key_event);
                         if !data.is_empty() && ws_sender.send(Message::Binary(data)).await.is_err()
@@ -476,12 +701,23 @@ fn key_event_to_bytes(key: &event::KeyEvent) -> Vec<u8> {
     }
 }
 
+/// A range read from `GET /sessions/{id}/log`
+#[derive(Debug, Clone)]
+pub struct LogChunk {
+    pub data: Vec<u8>,
+    /// Offset after the last returned byte
+    pub end: u64,
+    /// Offset after the last byte logged so far
+    pub size: u64,
+    /// The session stopped logging, so nothing more will be appended
+    pub complete: bool,
+}
+
 // =============================================================================
 // CLI Commands
 // =============================================================================
 
-pub async fn cmd_list(server: &str, json: bool) -> Result<()> {
-    let client = PtyClient::new(server);
+pub async fn cmd_list(client: &PtyClient, json: bool, long: bool) -> Result<()> {
     let sessions = client.list_sessions().await?;
 
     if json {
@@ -496,68 +732,113 @@ pub async fn cmd_list(server: &str, json: bool) -> Result<()> {
 
     // Print header
     println!(
-        "{:<4} {:<36} {:<20} {:<8} {:<10} {:<6}",
-        "IDX", "ID", "NAME", "SIZE", "SHELL", "PID"
+        "{:<4} {:<36} {:<20} {:<8} {:<24} {:<8} {:<8} ATTACHED",
+        "IDX", "ID", "NAME", "SIZE", "COMMAND", "PID", "RESTARTS"
     );
-    println!("{}", "-".repeat(90));
+    println!("{}", "-".repeat(130));
 
     for session in sessions {
-        let shell_name = std::path::Path::new(&session.shell)
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or(&session.shell);
+        let command = match &session.command {
+            Some(argv) => argv.join(" "),
+            None => std::path::Path::new(&session.shell)
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or(&session.shell)
+                .to_string(),
+        };
+        // Name the job that owns the terminal when it isn't the session process itself
+        let command = match &session.foreground {
+            Some(fg) if fg.pid != session.pid => format!("{}: {}", command, fg.name),
+            _ => command,
+        };
 
         let status = if session.alive { "" } else { " (dead)" };
+        let attached: Vec<String> = session.clients.iter().map(AttachedClient::label).collect();
 
         println!(
-            "{:<4} {:<36} {:<20} {:>3}x{:<4} {:<10} {:<6}{}",
+            "{:<4} {:<36} {:<20} {:>3}x{:<4} {:<24} {:<8} {:<8} {}{}",
             session.index,
             &session.id[..36.min(session.id.len())],
-            &session.name[..20.min(session.name.len())],
+            truncate(&session.name, 20),
             session.cols,
             session.rows,
-            shell_name,
+            truncate(&command, 24),
             session.pid,
+            session.restart_count,
+            attached.join(", "),
             status
         );
+
+        if long {
+            print_process_tree(&session.processes);
+        }
     }
 
     Ok(())
 }
 
+/// Print a session's processes indented under its row, `*` marking the foreground group
+fn print_process_tree(processes: &[ProcessInfo]) {
+    if processes.is_empty() {
+        println!("     (no process information)");
+        return;
+    }
+    println!(
+        "     {:<9} {:>8} {:>8}  {:<24} COMMAND",
+        "PID", "CPU", "RSS", "CWD"
+    );
+    for process in processes {
+        // Depth from the parent chain within the tree
+        let mut depth = 0;
+        let mut ppid = process.ppid;
+        while let Some(parent) = processes.iter().find(|p| p.pid == ppid) {
+            depth += 1;
+            ppid = parent.ppid;
+        }
+        let command = if process.cmdline.is_empty() {
+            format!("[{}]", process.name)
+        } else {
+            process.cmdline.join(" ")
+        };
+        println!(
+            "   {} {:<9} {:>7.1}s {:>7.1}M  {:<24} {}{}",
+            if process.foreground { "*" } else { " " },
+            process.pid,
+            process.cpu_time_ms as f64 / 1000.0,
+            process.rss_bytes as f64 / (1024.0 * 1024.0),
+            truncate(process.cwd.as_deref().unwrap_or("-"), 24),
+            "  ".repeat(depth),
+            command
+        );
+    }
+}
+
 pub async fn cmd_new(
-    server: &str,
-    name: Option<String>,
-    shell: Option<String>,
-    cwd: Option<String>,
+    client: &PtyClient,
+    mut request: CreateSessionRequest,
     detached: bool,
 ) -> Result<()> {
-    let client = PtyClient::new(server);
-
     // Get terminal size
     let (cols, rows) = terminal::size().unwrap_or((80, 24));
+    request.cols = request.cols.or(Some(cols));
+    request.rows = request.rows.or(Some(rows));
 
-    let session = client
-        .create_session(name, shell, cwd, Some(cols), Some(rows))
-        .await?;
+    let session = client.create_session(&request).await?;
 
     println!("Created session: {} ({})", session.id, session.name);
 
     if !detached {
-        client.attach(&session.id).await?;
+        client.attach(&session.id, false).await?;
     }
 
     Ok(())
 }
 
-pub async fn cmd_attach(server: &str, session: &str) -> Result<()> {
-    let client = PtyClient::new(server);
-    client.attach(session).await
+pub async fn cmd_attach(client: &PtyClient, session: &str, read_only: bool) -> Result<()> {
+    client.attach(session, read_only).await
 }
 
-pub async fn cmd_kill(server: &str, sessions: &[String]) -> Result<()> {
-    let client = PtyClient::new(server);
-
+pub async fn cmd_kill(client: &PtyClient, sessions: &[String]) -> Result<()> {
     for session_id in sessions {
         match client.kill_session(session_id).await {
             Ok(()) => println!("Killed session: {}", session_id),
@@ -568,9 +849,7 @@ pub async fn cmd_kill(server: &str, sessions: &[String]) -> Result<()> {
     Ok(())
 }
 
-pub async fn cmd_send_keys(server: &str, session: &str, keys: &[String]) -> Result<()> {
-    let client = PtyClient::new(server);
-
+pub async fn cmd_send_keys(client: &PtyClient, session: &str, keys: &[String]) -> Result<()> {
     // Join keys with spaces and process escape sequences
     let text = keys.join(" ");
     let processed = process_key_string(&text);
@@ -581,8 +860,7 @@ pub async fn cmd_send_keys(server: &str, session: &str, keys: &[String]) -> Resu
     Ok(())
 }
 
-pub async fn cmd_capture_pane(server: &str, session: &str, print: bool) -> Result<()> {
-    let client = PtyClient::new(server);
+pub async fn cmd_capture_pane(client: &PtyClient, session: &str, print: bool) -> Result<()> {
     let content = client.capture_pane(session).await?;
 
     if print {
@@ -594,13 +872,104 @@ pub async fn cmd_capture_pane(server: &str, session: &str, print: bool) -> Resul
     Ok(())
 }
 
-pub async fn cmd_resize(server: &str, session: &str, cols: u16, rows: u16) -> Result<()> {
-    let client = PtyClient::new(server);
+pub async fn cmd_wait(
+    client: &PtyClient,
+    session: &str,
+    request: &WaitRequest,
+    json: bool,
+) -> Result<()> {
+    let response = client.wait(session, request).await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&response)?);
+    } else if let Some(matched) = &response.matched {
+        for line in &matched.context {
+            println!("{}", line);
+        }
+    }
+
+    // Without a pattern, going idle or exiting is what the caller waited for
+    let satisfied = match response.reason.as_str() {
+        "match" => true,
+        "idle" | "exit" => request.pattern.is_none(),
+        _ => false,
+    };
+    if !satisfied {
+        anyhow::bail!(
+            "Wait ended without a match ({} after {}ms)",
+            response.reason,
+            response.elapsed_ms
+        );
+    }
+
+    Ok(())
+}
+
+/// Parse durations like "500ms", "2s", "1m" (bare numbers are milliseconds)
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration: {:?}", s))?;
+    match unit {
+        "" | "ms" => Ok(Duration::from_millis(value)),
+        "s" => Ok(Duration::from_secs(value)),
+        "m" => Ok(Duration::from_secs(value * 60)),
+        "h" => Ok(Duration::from_secs(value * 60 * 60)),
+        _ => Err(format!(
+            "invalid duration unit in {:?} (use ms, s, m or h)",
+            s
+        )),
+    }
+}
+
+/// Print a session's log (the last `bytes` of it if given), then with `follow`
+/// keep printing new output until the session stops logging
+pub async fn cmd_logs(
+    client: &PtyClient,
+    session: &str,
+    follow: bool,
+    text: bool,
+    bytes: Option<u64>,
+) -> Result<()> {
+    const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+    let session_id = client.resolve_session_id(session).await?;
+    let mut from = bytes.map_or(0, |n| -(n.min(i64::MAX as u64) as i64));
+    let mut stdout = std::io::stdout();
+    loop {
+        let chunk = client.read_log(&session_id, from, text).await?;
+        stdout.write_all(&chunk.data)?;
+        stdout.flush()?;
+        from = chunk.end as i64;
+
+        // Large logs come back in several ranges
+        if chunk.end < chunk.size && !chunk.data.is_empty() {
+            continue;
+        }
+        if !follow || chunk.complete {
+            return Ok(());
+        }
+        tokio::time::sleep(FOLLOW_INTERVAL).await;
+    }
+}
+
+pub async fn cmd_resize(client: &PtyClient, session: &str, cols: u16, rows: u16) -> Result<()> {
     client.resize(session, cols, rows).await?;
     println!("Resized session {} to {}x{}", session, cols, rows);
     Ok(())
 }
 
+/// Truncate to at most `max` characters (not bytes, so multi-byte names don't panic)
+fn truncate(s: &str, max: usize) -> &str {
+    match s.char_indices().nth(max) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
+}
+
 // Process tmux-like key strings (e.g., "Enter", "C-c")
 fn process_key_string(s: &str) -> String {
     let mut result = String::new();
@@ -0,0 +1,835 @@
+//! Durable per-session output logs
+//!
+//! A logged session writes under `<log dir>/<session id>/`:
+//!
+//! - `raw-<offset>.log`: output bytes as sent to clients. Offsets are the
+//!   session's output stream offsets, so they line up with `?since=`.
+//! - `text-<offset>.log`: ANSI-stripped lines, taken from the session's
+//!   virtual terminal as they scroll off the screen (plus the final screen
+//!   when the process exits). Full-screen apps on the alternate screen don't
+//!   produce lines.
+//!
+//! A segment is named by the offset of its first byte. Once it reaches
+//! `max_bytes` a new one is started and the old one is gzipped in the
+//! background (`.log.gz`); only the newest `keep` rotated segments are kept.
+//! Logs outlive their session and can still be read after it's removed, until
+//! they've gone unwritten for the retention period (`--log-retention`).
+//!
+//! Logs go under `$XDG_STATE_HOME/cmux-pty/logs` by default. Directories are
+//! created 0700 and files 0600, since output can contain secrets. Writes happen
+//! on a per-session thread so the PTY reader never waits on the disk.
+//!
+//! Logging is off unless the server enables it for every session
+//! (`--log-sessions`) or a session asks for it in its metadata:
+//! `{"log": true}`, `{"log": false}` or `{"log": {"format": "both", ...}}`.
+
+use std::{
+    collections::HashSet,
+    fs::{self, DirBuilder, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt},
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc},
+    thread::JoinHandle,
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    extract::{Path as UrlPath, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::{AppState, ServerError};
+
+pub const DEFAULT_LOG_MAX_BYTES: u64 = 10 * 1024 * 1024;
+pub const DEFAULT_LOG_KEEP: usize = 5;
+pub const DEFAULT_LOG_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Most bytes returned by one `GET /sessions/{id}/log`
+const MAX_LOG_RANGE: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Raw,
+    Text,
+    Both,
+}
+
+/// One of a session's logs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogKind {
+    #[default]
+    Raw,
+    Text,
+}
+
+impl LogKind {
+    fn prefix(self) -> &'static str {
+        match self {
+            LogKind::Raw => "raw-",
+            LogKind::Text => "text-",
+        }
+    }
+}
+
+/// Log settings; unset fields fall back to the server defaults
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<LogFormat>,
+    /// Rotate once a segment reaches this size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
+    /// Rotated segments to keep
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep: Option<usize>,
+    /// Gzip rotated segments
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compress: Option<bool>,
+}
+
+impl LogSettings {
+    /// The `log` entry of a session's metadata. Setting anything other than
+    /// `enabled: false` turns logging on.
+    pub fn from_metadata(metadata: Option<&serde_json::Value>) -> Self {
+        match metadata.and_then(|m| m.get("log")) {
+            Some(serde_json::Value::Bool(enabled)) => Self {
+                enabled: Some(*enabled),
+                ..Self::default()
+            },
+            Some(value @ serde_json::Value::Object(_)) => {
+                match serde_json::from_value::<Self>(value.clone()) {
+                    Ok(settings) => Self {
+                        enabled: settings.enabled.or(Some(true)),
+                        ..settings
+                    },
+                    Err(e) => {
+                        warn!("[logs] Ignoring invalid log settings in metadata: {}", e);
+                        Self::default()
+                    }
+                }
+            }
+            _ => Self::default(),
+        }
+    }
+
+    pub fn or(self, defaults: LogSettings) -> LogSettings {
+        LogSettings {
+            enabled: self.enabled.or(defaults.enabled),
+            format: self.format.or(defaults.format),
+            max_bytes: self.max_bytes.or(defaults.max_bytes),
+            keep: self.keep.or(defaults.keep),
+            compress: self.compress.or(defaults.compress),
+        }
+    }
+}
+
+/// Server-wide log location and defaults
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    pub dir: PathBuf,
+    pub defaults: LogSettings,
+    /// Remove a gone session's logs once they've been unwritten this long
+    pub retention: Duration,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_log_dir(),
+            defaults: LogSettings::default(),
+            retention: DEFAULT_LOG_RETENTION,
+        }
+    }
+}
+
+/// `$XDG_STATE_HOME/cmux-pty/logs`, falling back to `~/.local/state` and then
+/// the temp dir
+fn default_log_dir() -> PathBuf {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")));
+    match state_home {
+        Some(dir) => dir.join("cmux-pty").join("logs"),
+        None => std::env::temp_dir().join("cmux-pty-logs"),
+    }
+}
+
+impl LogConfig {
+    /// Effective settings for a session, `None` when it isn't logged
+    pub fn resolve(&self, metadata: Option<&serde_json::Value>) -> Option<LogSettings> {
+        let settings = LogSettings::from_metadata(metadata).or(self.defaults);
+        settings.enabled.unwrap_or(false).then_some(settings)
+    }
+
+    /// Log directory for a session; `None` for IDs that aren't safe path components
+    pub fn session_dir(&self, session_id: &str) -> Option<PathBuf> {
+        let valid = !session_id.is_empty()
+            && session_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        valid.then(|| self.dir.join(session_id))
+    }
+
+    /// Remove the logs of sessions that are gone (not in `live`) and haven't
+    /// been written for the retention period
+    pub fn prune(&self, live: &HashSet<String>) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let Ok(session_id) = entry.file_name().into_string() else {
+                continue;
+            };
+            if live.contains(&session_id) || self.session_dir(&session_id).is_none() {
+                continue;
+            }
+            let path = entry.path();
+            let stale = last_written(&path)
+                .and_then(|at| at.elapsed().ok())
+                .is_some_and(|age| age >= self.retention);
+            if !stale {
+                continue;
+            }
+            match fs::remove_dir_all(&path) {
+                Ok(()) => info!("[logs] Removed logs of ended session {}", session_id),
+                Err(e) => warn!("[logs] Failed to remove {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+/// When anything in a session's log directory was last modified
+fn last_written(session_dir: &Path) -> Option<SystemTime> {
+    let modified = |path: &Path| fs::symlink_metadata(path).and_then(|m| m.modified()).ok();
+    fs::read_dir(session_dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| modified(&entry.path()))
+        .chain(modified(session_dir))
+        .max()
+}
+
+/// Create `dir` (and missing parents) readable only by us, refusing one that
+/// already exists but belongs to someone else
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    let metadata = fs::symlink_metadata(dir)?;
+    // SAFETY: geteuid has no preconditions and can't fail
+    let euid = unsafe { libc::geteuid() };
+    if !metadata.is_dir() || metadata.uid() != euid {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a directory owned by us", dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Open `path` for appending, creating it readable only by us
+fn open_private(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .mode(0o600)
+        .open(path)
+}
+
+/// A segment on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Segment {
+    start: u64,
+    path: PathBuf,
+    compressed: bool,
+}
+
+impl Segment {
+    /// Uncompressed length
+    fn len(&self) -> io::Result<u64> {
+        if !self.compressed {
+            return Ok(fs::metadata(&self.path)?.len());
+        }
+        // Gzip stores the uncompressed size mod 2^32 in its last four bytes;
+        // segments are far smaller than that
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::End(-4))?;
+        let mut size = [0u8; 4];
+        file.read_exact(&mut size)?;
+        Ok(u32::from_le_bytes(size) as u64)
+    }
+
+    /// Bytes `[from, to)` relative to the segment start
+    fn read(&self, from: u64, to: u64) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        if self.compressed {
+            let mut decoder = GzDecoder::new(File::open(&self.path)?);
+            io::copy(&mut (&mut decoder).take(from), &mut io::sink())?;
+            decoder.take(to - from).read_to_end(&mut data)?;
+        } else {
+            let mut file = File::open(&self.path)?;
+            file.seek(SeekFrom::Start(from))?;
+            file.take(to - from).read_to_end(&mut data)?;
+        }
+        Ok(data)
+    }
+}
+
+/// A kind's segments, oldest first. A segment still being compressed is
+/// listed once, as its uncompressed file.
+fn list_segments(session_dir: &Path, kind: LogKind) -> Vec<Segment> {
+    let Ok(entries) = fs::read_dir(session_dir) else {
+        return Vec::new();
+    };
+    let mut segments: Vec<Segment> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let rest = name.strip_prefix(kind.prefix())?;
+            let (start, compressed) = match rest.strip_suffix(".log.gz") {
+                Some(start) => (start, true),
+                None => (rest.strip_suffix(".log")?, false),
+            };
+            Some(Segment {
+                start: start.parse().ok()?,
+                path: entry.path(),
+                compressed,
+            })
+        })
+        .collect();
+    segments.sort_by_key(|s| (s.start, s.compressed));
+    segments.dedup_by_key(|s| s.start);
+    segments
+}
+
+fn segment_path(session_dir: &Path, kind: LogKind, start: u64, compressed: bool) -> PathBuf {
+    let suffix = if compressed { ".log.gz" } else { ".log" };
+    session_dir.join(format!("{}{}{}", kind.prefix(), start, suffix))
+}
+
+fn compress_segment(path: &Path) -> io::Result<()> {
+    let gz_path = path.with_extension("log.gz");
+    let tmp_path = path.with_extension("log.gz.tmp");
+    let tmp = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp_path)?;
+    let mut encoder = GzEncoder::new(tmp, Compression::default());
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::rename(&tmp_path, &gz_path)?;
+    fs::remove_file(path)
+}
+
+/// Appends to one kind of log, rotating by size
+struct LogWriter {
+    session_dir: PathBuf,
+    kind: LogKind,
+    file: File,
+    start: u64,
+    len: u64,
+    settings: LogSettings,
+    /// Held by the background job compressing and pruning rotated segments,
+    /// so jobs for one log don't race each other
+    maintenance: Arc<Mutex<()>>,
+}
+
+impl LogWriter {
+    /// Continue an existing log, or start a new segment at `offset` if there's
+    /// a gap (or nothing yet)
+    fn open(
+        session_dir: &Path,
+        kind: LogKind,
+        offset: Option<u64>,
+        settings: LogSettings,
+    ) -> io::Result<Self> {
+        let last = list_segments(session_dir, kind).pop();
+        let (start, len) = match last {
+            Some(last) => {
+                let len = last.len()?;
+                let end = last.start + len;
+                if !last.compressed && offset.is_none_or(|o| o == end) {
+                    (last.start, len)
+                } else {
+                    (offset.unwrap_or(end), 0)
+                }
+            }
+            None => (offset.unwrap_or(0), 0),
+        };
+        let file = open_private(&segment_path(session_dir, kind, start, false))?;
+        Ok(Self {
+            session_dir: session_dir.to_path_buf(),
+            kind,
+            file,
+            start,
+            len,
+            settings,
+            maintenance: Arc::default(),
+        })
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let max_bytes = self.settings.max_bytes.unwrap_or(DEFAULT_LOG_MAX_BYTES);
+        if self.len > 0 && self.len + data.len() as u64 > max_bytes {
+            self.rotate()?;
+        }
+        self.file.write_all(data)?;
+        self.len += data.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let rotated = segment_path(&self.session_dir, self.kind, self.start, false);
+        self.start += self.len;
+        self.len = 0;
+        self.file = open_private(&segment_path(
+            &self.session_dir,
+            self.kind,
+            self.start,
+            false,
+        ))?;
+
+        let session_dir = self.session_dir.clone();
+        let kind = self.kind;
+        let compress = self.settings.compress.unwrap_or(true);
+        let keep = self.settings.keep.unwrap_or(DEFAULT_LOG_KEEP);
+        let maintenance = self.maintenance.clone();
+        std::thread::spawn(move || {
+            let _guard = maintenance.lock();
+            // An earlier job may already have pruned it
+            if compress && rotated.exists() {
+                if let Err(e) = compress_segment(&rotated) {
+                    warn!("[logs] Failed to compress {}: {}", rotated.display(), e);
+                }
+            }
+
+            // Drop the oldest rotated segments (all but the active one count)
+            let segments = list_segments(&session_dir, kind);
+            let rotated_count = segments.len().saturating_sub(1);
+            for old in segments.iter().take(rotated_count.saturating_sub(keep)) {
+                if let Err(e) = fs::remove_file(&old.path) {
+                    warn!("[logs] Failed to remove {}: {}", old.path.display(), e);
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Queued for a session's log thread
+enum LogOp {
+    Raw(Vec<u8>),
+    Lines(Vec<String>),
+}
+
+/// A session's open logs. Writes are queued for a thread that owns the files.
+pub struct SessionLog {
+    settings: LogSettings,
+    format: LogFormat,
+    ops: mpsc::Sender<LogOp>,
+    thread: JoinHandle<()>,
+}
+
+impl SessionLog {
+    /// Open the logs `settings` ask for; the raw log continues at `raw_offset`
+    pub fn open(session_dir: &Path, settings: LogSettings, raw_offset: u64) -> io::Result<Self> {
+        create_private_dir(session_dir)?;
+        let format = settings.format.unwrap_or_default();
+        let raw = matches!(format, LogFormat::Raw | LogFormat::Both)
+            .then(|| LogWriter::open(session_dir, LogKind::Raw, Some(raw_offset), settings))
+            .transpose()?;
+        let text = matches!(format, LogFormat::Text | LogFormat::Both)
+            .then(|| LogWriter::open(session_dir, LogKind::Text, None, settings))
+            .transpose()?;
+        let (ops, ops_rx) = mpsc::channel();
+        let dir = session_dir.to_path_buf();
+        let thread = std::thread::Builder::new()
+            .name("cmux-pty-log".to_string())
+            .spawn(move || write_log_ops(&dir, raw, text, ops_rx))?;
+        info!("[logs] Logging to {} ({:?})", session_dir.display(), format);
+        Ok(Self {
+            settings,
+            format,
+            ops,
+            thread,
+        })
+    }
+
+    pub fn settings(&self) -> LogSettings {
+        self.settings
+    }
+
+    pub fn wants_text(&self) -> bool {
+        matches!(self.format, LogFormat::Text | LogFormat::Both)
+    }
+
+    fn wants_raw(&self) -> bool {
+        matches!(self.format, LogFormat::Raw | LogFormat::Both)
+    }
+
+    /// Queue output; fails once the log thread has stopped after a write error
+    pub fn write_raw(&self, data: &[u8]) -> io::Result<()> {
+        if !self.wants_raw() || data.is_empty() {
+            return Ok(());
+        }
+        self.send(LogOp::Raw(data.to_vec()))
+    }
+
+    pub fn write_lines(&self, lines: Vec<String>) -> io::Result<()> {
+        if !self.wants_text() || lines.is_empty() {
+            return Ok(());
+        }
+        self.send(LogOp::Lines(lines))
+    }
+
+    fn send(&self, op: LogOp) -> io::Result<()> {
+        self.ops
+            .send(op)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "log thread stopped"))
+    }
+
+    /// Wait for queued writes to reach the files
+    pub fn close(self) {
+        drop(self.ops);
+        if self.thread.join().is_err() {
+            warn!("[logs] Log thread panicked");
+        }
+    }
+}
+
+fn write_log_ops(
+    session_dir: &Path,
+    mut raw: Option<LogWriter>,
+    mut text: Option<LogWriter>,
+    ops: mpsc::Receiver<LogOp>,
+) {
+    for op in ops {
+        let result = match op {
+            LogOp::Raw(data) => raw.as_mut().map_or(Ok(()), |raw| raw.write(&data)),
+            LogOp::Lines(lines) => {
+                let mut data = lines.join("\n");
+                data.push('\n');
+                text.as_mut()
+                    .map_or(Ok(()), |text| text.write(data.as_bytes()))
+            }
+        };
+        if let Err(e) = result {
+            warn!(
+                "[logs] Stopped logging to {} after write error: {}",
+                session_dir.display(),
+                e
+            );
+            return;
+        }
+    }
+}
+
+/// Bytes read from a log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRange {
+    pub data: Vec<u8>,
+    /// Offset of the first returned byte (later than asked if that was rotated away)
+    pub start: u64,
+    /// Offset after the last returned byte
+    pub end: u64,
+    /// Offset after the last byte logged so far
+    pub size: u64,
+}
+
+/// Read `[from, to)` of a log. A negative `from` counts back from the end.
+pub fn read_range(
+    session_dir: &Path,
+    kind: LogKind,
+    from: Option<i64>,
+    to: Option<u64>,
+) -> io::Result<LogRange> {
+    let mut segments = Vec::new();
+    for segment in list_segments(session_dir, kind) {
+        let len = segment.len()?;
+        segments.push((segment, len));
+    }
+    let earliest = segments.first().map_or(0, |(s, _)| s.start);
+    let size = segments.last().map_or(0, |(s, len)| s.start + len);
+
+    let from = match from {
+        Some(from) if from < 0 => size.saturating_sub(from.unsigned_abs()),
+        Some(from) => from as u64,
+        None => earliest,
+    }
+    .max(earliest);
+    let to = to.unwrap_or(size).min(size).min(from + MAX_LOG_RANGE);
+
+    let mut range = LogRange {
+        data: Vec::new(),
+        start: from,
+        end: from,
+        size,
+    };
+    for (segment, len) in &segments {
+        let segment_end = segment.start + len;
+        if segment_end <= range.end || segment.start >= to {
+            continue;
+        }
+        if segment.start > range.end {
+            // Gap (e.g. logging was off for a while): skip ahead, unless
+            // there's already data to return
+            if !range.data.is_empty() {
+                break;
+            }
+            range.start = segment.start;
+            range.end = segment.start;
+        }
+        let read_to = segment_end.min(to);
+        range
+            .data
+            .extend(segment.read(range.end - segment.start, read_to - segment.start)?);
+        range.end = read_to;
+    }
+    if range.start > to {
+        range.start = to.max(from);
+        range.end = range.start;
+    }
+    Ok(range)
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LogParams {
+    /// Start offset; negative counts back from the end
+    pub from: Option<i64>,
+    /// End offset (exclusive)
+    pub to: Option<u64>,
+    pub format: LogKind,
+}
+
+/// `GET /sessions/{id}/log?from=&to=&format=raw|text`: a range of the log.
+/// `X-Log-Start`/`X-Log-End` give the returned range, `X-Log-Size` how much
+/// has been logged, and `X-Log-Complete` whether more can still arrive.
+pub async fn read_log(
+    State(state): State<Arc<AppState>>,
+    UrlPath(session_id): UrlPath<String>,
+    Query(params): Query<LogParams>,
+) -> Result<Response, ServerError> {
+    let not_found = || ServerError::SessionNotFound(session_id.clone());
+    let session_dir = state.logs.session_dir(&session_id).ok_or_else(not_found)?;
+    let live = state
+        .sessions
+        .read()
+        .get(&session_id)
+        .map(|session| session.is_logging());
+    if live.is_none() && !session_dir.is_dir() {
+        return Err(not_found());
+    }
+
+    let range = tokio::task::spawn_blocking(move || {
+        read_range(&session_dir, params.format, params.from, params.to)
+    })
+    .await
+    .map_err(|e| ServerError::LogReadError(e.to_string()))?
+    .map_err(|e| ServerError::LogReadError(e.to_string()))?;
+
+    let content_type = match params.format {
+        LogKind::Raw => "application/octet-stream",
+        LogKind::Text => "text/plain; charset=utf-8",
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::HeaderName::from_static("x-log-start"),
+                range.start.to_string(),
+            ),
+            (
+                header::HeaderName::from_static("x-log-end"),
+                range.end.to_string(),
+            ),
+            (
+                header::HeaderName::from_static("x-log-size"),
+                range.size.to_string(),
+            ),
+            (
+                header::HeaderName::from_static("x-log-complete"),
+                (live != Some(true)).to_string(),
+            ),
+        ],
+        range.data,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cmux-pty-logs-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn metadata_settings_override_defaults() {
+        let config = LogConfig {
+            dir: PathBuf::from("/logs"),
+            defaults: LogSettings {
+                format: Some(LogFormat::Text),
+                keep: Some(2),
+                ..Default::default()
+            },
+            retention: DEFAULT_LOG_RETENTION,
+        };
+        assert_eq!(config.resolve(None), None);
+        assert_eq!(
+            config.resolve(Some(&serde_json::json!({ "log": false }))),
+            None
+        );
+
+        let settings = config
+            .resolve(Some(&serde_json::json!({ "log": { "format": "both" } })))
+            .unwrap();
+        assert_eq!(settings.format, Some(LogFormat::Both));
+        assert_eq!(settings.keep, Some(2));
+
+        let server_wide = LogConfig {
+            defaults: LogSettings {
+                enabled: Some(true),
+                ..config.defaults
+            },
+            ..config
+        };
+        assert!(server_wide.resolve(Some(&serde_json::json!({}))).is_some());
+        assert_eq!(
+            server_wide.resolve(Some(&serde_json::json!({ "log": false }))),
+            None
+        );
+        assert_eq!(server_wide.session_dir("../etc"), None);
+    }
+
+    #[test]
+    fn rotated_segments_are_compressed_pruned_and_readable() {
+        let dir = temp_dir();
+        let settings = LogSettings {
+            enabled: Some(true),
+            max_bytes: Some(10),
+            keep: Some(2),
+            ..Default::default()
+        };
+        let log = SessionLog::open(&dir, settings, 100).unwrap();
+        for chunk in ["0123456789", "abcdefghij", "ABCDEFGHIJ", "klmno"] {
+            log.write_raw(chunk.as_bytes()).unwrap();
+        }
+        log.close();
+        // Let background compression and pruning finish
+        let layout = || {
+            list_segments(&dir, LogKind::Raw)
+                .into_iter()
+                .map(|s| (s.start, s.compressed))
+                .collect::<Vec<_>>()
+        };
+        let expected = vec![(110, true), (120, true), (130, false)];
+        for _ in 0..200 {
+            if layout() == expected {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(layout(), expected);
+
+        // The first segment was pruned, so reading starts at the earliest kept byte
+        let all = read_range(&dir, LogKind::Raw, Some(0), None).unwrap();
+        assert_eq!(all.data, b"abcdefghijABCDEFGHIJklmno".to_vec());
+        assert_eq!((all.start, all.end, all.size), (110, 135, 135));
+
+        let middle = read_range(&dir, LogKind::Raw, Some(115), Some(123)).unwrap();
+        assert_eq!(middle.data, b"fghijABC".to_vec());
+        let tail = read_range(&dir, LogKind::Raw, Some(-3), None).unwrap();
+        assert_eq!(tail.data, b"mno".to_vec());
+        assert_eq!(tail.start, 132);
+
+        // Reopening continues the active segment
+        let log = SessionLog::open(&dir, settings, 135).unwrap();
+        log.write_raw(b"pq").unwrap();
+        log.close();
+        assert_eq!(list_segments(&dir, LogKind::Raw).len(), 3);
+        assert_eq!(
+            read_range(&dir, LogKind::Raw, Some(-7), None).unwrap().data,
+            b"klmnopq".to_vec()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn text_log_appends_lines() {
+        let dir = temp_dir();
+        let settings = LogSettings {
+            format: Some(LogFormat::Text),
+            ..Default::default()
+        };
+        let log = SessionLog::open(&dir, settings, 0).unwrap();
+        assert!(log.wants_text());
+        log.write_raw(b"\x1b[31mignored").unwrap();
+        log.write_lines(vec!["$ make".to_string(), "ok".to_string()])
+            .unwrap();
+        log.write_lines(Vec::new()).unwrap();
+        log.close();
+
+        let text = read_range(&dir, LogKind::Text, None, None).unwrap();
+        assert_eq!(text.data, b"$ make\nok\n".to_vec());
+        assert!(list_segments(&dir, LogKind::Raw).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn logs_are_private_and_pruned_once_stale() {
+        let root = temp_dir();
+        let config = LogConfig {
+            dir: root.clone(),
+            defaults: LogSettings::default(),
+            retention: Duration::ZERO,
+        };
+        let live_id = uuid::Uuid::new_v4().to_string();
+        let gone_id = uuid::Uuid::new_v4().to_string();
+        for id in [&live_id, &gone_id] {
+            let dir = config.session_dir(id).unwrap();
+            let log = SessionLog::open(&dir, LogSettings::default(), 0).unwrap();
+            log.write_raw(b"secret").unwrap();
+            log.close();
+
+            let mode = |path: &Path| fs::metadata(path).unwrap().mode() & 0o777;
+            assert_eq!(mode(&dir), 0o700);
+            assert_eq!(mode(&segment_path(&dir, LogKind::Raw, 0, false)), 0o600);
+        }
+
+        config.prune(&HashSet::from([live_id.clone()]));
+        assert!(config.session_dir(&live_id).unwrap().is_dir());
+        assert!(!config.session_dir(&gone_id).unwrap().exists());
+
+        // Within the retention period a gone session's logs stay readable
+        let kept = LogConfig {
+            retention: DEFAULT_LOG_RETENTION,
+            ..config
+        };
+        kept.prune(&HashSet::new());
+        assert!(kept.session_dir(&live_id).unwrap().is_dir());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
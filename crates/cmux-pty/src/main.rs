@@ -5,17 +5,30 @@
 //!
 //! Also provides a CLI client for managing PTY sessions (tmux-like interface).
 
+mod attach;
+mod auth;
 mod cli;
+mod logs;
+mod output;
+mod policy;
+mod procinfo;
+mod reaper;
+mod wait;
 
 // Re-export terminal emulation library
 use cmux_terminal::{DaFilter, VirtualTerminal};
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     env,
     io::{Read, Write as IoWrite},
-    sync::Arc,
-    time::{SystemTime, UNIX_EPOCH},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{Context, Result};
@@ -25,20 +38,28 @@ use axum::{
         Path, Query, State, WebSocketUpgrade,
     },
     http::StatusCode,
+    middleware,
     response::{Html, IntoResponse, Json},
     routing::{delete, get, patch, post},
     Router,
 };
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use futures::{SinkExt, StreamExt};
 use parking_lot::{Mutex, RwLock};
 use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
 use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
-use tower_http::cors::CorsLayer;
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
+use attach::{AttachMode, AttachedClients, ClientInfo, SizePolicy};
+use auth::AccessConfig;
+use logs::{LogConfig, LogFormat, LogSettings, SessionLog};
+use output::{Delivery, OutputBatch, OutputBuffer, OutputChunk, OutputFollower};
+use policy::AllowPolicy;
+use procinfo::{ProcessInfo, ProcessSnapshot, ProcessTable};
+use reaper::{DeleteReason, ReapPolicy, SessionActivity};
+
 // =============================================================================
 // CLI Argument Parsing
 // =============================================================================
@@ -57,6 +78,10 @@ struct Cli {
     )]
     server: String,
 
+    /// File holding the API token: required by the server, sent by client commands
+    #[arg(long, env = "PTY_TOKEN_FILE", global = true)]
+    token_file: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -72,6 +97,30 @@ enum Commands {
         /// Port to listen on
         #[arg(short, long, env = "PTY_SERVER_PORT", default_value = "39383")]
         port: u16,
+
+        /// JSON allow policy for shells, commands and working directories
+        #[arg(long, env = "PTY_POLICY_FILE")]
+        policy_file: Option<PathBuf>,
+
+        /// Browser origin allowed to connect besides the server's own (repeatable, "*" for any)
+        #[arg(
+            long = "allowed-origin",
+            env = "PTY_ALLOWED_ORIGINS",
+            value_delimiter = ','
+        )]
+        allowed_origins: Vec<String>,
+
+        /// Let non-browser clients on loopback in without the token. Leave off
+        /// when a proxy on the same host forwards remote requests to the server.
+        #[arg(long, env = "CMUX_PTY_TRUST_LOOPBACK")]
+        trust_loopback: bool,
+
+        /// Defaults for sessions that don't set their own limits
+        #[command(flatten)]
+        reap: ReapArgs,
+
+        #[command(flatten)]
+        logs: LogArgs,
     },
 
     /// List all sessions
@@ -80,6 +129,10 @@ enum Commands {
         /// Output as JSON
         #[arg(long)]
         json: bool,
+
+        /// Show each session's process tree (foreground group marked with *)
+        #[arg(short, long)]
+        long: bool,
     },
 
     /// Create a new session
@@ -99,12 +152,33 @@ enum Commands {
         /// Create session but don't attach
         #[arg(short, long)]
         detached: bool,
+
+        /// Restart policy for the command
+        #[arg(long, value_enum, requires = "cmd")]
+        restart: Option<cli::RestartPolicy>,
+
+        /// Run a command instead of a shell (must be the last option)
+        #[arg(
+            long,
+            num_args = 1..,
+            allow_hyphen_values = true,
+            value_name = "ARGV",
+            conflicts_with = "shell"
+        )]
+        cmd: Vec<String>,
+
+        #[command(flatten)]
+        reap: ReapArgs,
     },
 
     /// Attach to a session
     Attach {
         /// Session ID, name, or index
         session: String,
+
+        /// Watch without sending input
+        #[arg(short, long)]
+        read_only: bool,
     },
 
     /// Kill one or more sessions
@@ -132,6 +206,54 @@ enum Commands {
         print: bool,
     },
 
+    /// Wait for output to match a pattern, go idle, or the process to exit
+    Wait {
+        /// Session ID, name, or index
+        session: String,
+
+        /// Regex to wait for in the processed terminal content
+        #[arg(short, long)]
+        pattern: Option<String>,
+
+        /// Finish once output has been quiet this long (e.g. 500ms, 2s)
+        #[arg(long, value_parser = cli::parse_duration)]
+        idle: Option<std::time::Duration>,
+
+        /// Give up after this long
+        #[arg(short, long, value_parser = cli::parse_duration, default_value = "30s")]
+        timeout: std::time::Duration,
+
+        /// Lines of context to print around a match
+        #[arg(short = 'C', long, default_value = "2")]
+        context: usize,
+
+        /// Also match content already on screen when the wait starts
+        #[arg(short, long)]
+        all: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print a session's output log
+    Logs {
+        /// Session ID, name, or index (a full ID also works after the session is gone)
+        session: String,
+
+        /// Keep printing new output until the session exits
+        #[arg(short, long)]
+        follow: bool,
+
+        /// Print the ANSI-stripped text log instead of raw output
+        #[arg(long)]
+        text: bool,
+
+        /// Start this many bytes before the end
+        #[arg(short = 'c', long)]
+        bytes: Option<u64>,
+    },
+
     /// Resize a session
     Resize {
         /// Session ID, name, or index
@@ -145,6 +267,95 @@ enum Commands {
     },
 }
 
+/// Idle/lifetime limits (durations like "90s", "30m")
+#[derive(Args, Debug, Clone, Default)]
+struct ReapArgs {
+    /// Kill the session after this long with no attached clients
+    #[arg(long, value_parser = cli::parse_duration)]
+    detached_timeout: Option<Duration>,
+
+    /// Kill the session after this long without output
+    #[arg(long, value_parser = cli::parse_duration)]
+    idle_timeout: Option<Duration>,
+
+    /// Kill the session this long after it was created
+    #[arg(long, value_parser = cli::parse_duration)]
+    max_lifetime: Option<Duration>,
+
+    /// Keep an exited session's scrollback this long before removing it [server default: 5m]
+    #[arg(long, value_parser = cli::parse_duration)]
+    exited_grace: Option<Duration>,
+}
+
+impl ReapArgs {
+    fn to_policy(&self) -> ReapPolicy {
+        let secs = |d: Option<Duration>| d.map(|d| d.as_secs());
+        ReapPolicy {
+            detached_timeout_secs: secs(self.detached_timeout),
+            idle_timeout_secs: secs(self.idle_timeout),
+            max_lifetime_secs: secs(self.max_lifetime),
+            exited_grace_secs: secs(self.exited_grace),
+        }
+    }
+
+    /// Server defaults: an unset exited grace falls back to `DEFAULT_EXITED_GRACE`
+    fn to_server_defaults(&self) -> ReapPolicy {
+        ReapPolicy {
+            exited_grace_secs: Some(self.exited_grace.unwrap_or(DEFAULT_EXITED_GRACE).as_secs()),
+            ..self.to_policy()
+        }
+    }
+}
+
+/// Session log location and defaults (sessions override them with `metadata.log`)
+#[derive(Args, Debug, Clone, Default)]
+struct LogArgs {
+    /// Directory for session logs [default: $XDG_STATE_HOME/cmux-pty/logs]
+    #[arg(long, env = "PTY_LOG_DIR")]
+    log_dir: Option<PathBuf>,
+
+    /// Log every session, not just those that ask for it in their metadata
+    #[arg(long)]
+    log_sessions: bool,
+
+    /// What to log [default: raw]
+    #[arg(long, value_enum)]
+    log_format: Option<LogFormat>,
+
+    /// Rotate a log once it reaches this many bytes [default: 10485760]
+    #[arg(long)]
+    log_max_bytes: Option<u64>,
+
+    /// Rotated log segments to keep per session [default: 5]
+    #[arg(long)]
+    log_keep: Option<usize>,
+
+    /// Keep rotated log segments uncompressed
+    #[arg(long)]
+    no_log_compress: bool,
+
+    /// Remove an ended session's logs once they've gone unwritten this long [default: 24h]
+    #[arg(long, value_parser = cli::parse_duration)]
+    log_retention: Option<Duration>,
+}
+
+impl LogArgs {
+    fn to_config(&self) -> LogConfig {
+        let default = LogConfig::default();
+        LogConfig {
+            dir: self.log_dir.clone().unwrap_or(default.dir),
+            retention: self.log_retention.unwrap_or(default.retention),
+            defaults: LogSettings {
+                enabled: self.log_sessions.then_some(true),
+                format: self.log_format,
+                max_bytes: self.log_max_bytes,
+                keep: self.log_keep,
+                compress: self.no_log_compress.then_some(false),
+            },
+        }
+    }
+}
+
 // =============================================================================
 // Constants
 // =============================================================================
@@ -154,6 +365,17 @@ const MAX_SCROLLBACK: usize = 100_000;
 const PTY_READ_BUFFER_SIZE: usize = 4096;
 const PTY_WRITE_CHUNK_SIZE: usize = 512; // Small chunks for smooth writes
 const PTY_INPUT_CHANNEL_SIZE: usize = 1024; // Bounded channel for backpressure
+const MAX_EXIT_HISTORY: usize = 20;
+const RESTART_BACKOFF_INITIAL: Duration = Duration::from_millis(500);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(30);
+/// A run lasting this long resets the restart backoff
+const RESTART_RESET_AFTER: Duration = Duration::from_secs(30);
+const EXIT_STATUS_POLL_ATTEMPTS: usize = 20;
+const EXIT_STATUS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const PROCESS_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const REAP_INTERVAL: Duration = Duration::from_secs(5);
+/// How long exited sessions stay capturable unless configured otherwise
+const DEFAULT_EXITED_GRACE: Duration = Duration::from_secs(300);
 
 // =============================================================================
 // Error Types
@@ -166,6 +388,21 @@ enum ServerError {
 
     #[error("Failed to spawn PTY: {0}")]
     PtySpawnError(String),
+
+    #[error("Not allowed by policy: {0}")]
+    PolicyViolation(String),
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Failed to read log: {0}")]
+    LogReadError(String),
+
+    #[error("Client ID already attached: {0}")]
+    ClientIdInUse(String),
+
+    #[error("Input is locked: {0}")]
+    InputLocked(String),
 }
 
 impl IntoResponse for ServerError {
@@ -173,6 +410,11 @@ impl IntoResponse for ServerError {
         let (status, message) = match &self {
             ServerError::SessionNotFound(_) => (StatusCode::NOT_FOUND, self.to_string()),
             ServerError::PtySpawnError(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
+            ServerError::PolicyViolation(_) => (StatusCode::FORBIDDEN, self.to_string()),
+            ServerError::InvalidRequest(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            ServerError::LogReadError(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
+            ServerError::ClientIdInUse(_) => (StatusCode::CONFLICT, self.to_string()),
+            ServerError::InputLocked(_) => (StatusCode::CONFLICT, self.to_string()),
         };
 
         let body = serde_json::json!({ "error": message });
@@ -189,6 +431,14 @@ impl IntoResponse for ServerError {
 struct CreateSessionRequest {
     #[serde(default = "default_shell")]
     shell: String,
+    /// Explicit argv to run instead of `shell` (checked against the allow policy)
+    command: Vec<String>,
+    /// When to restart `command` after it exits
+    restart: RestartPolicy,
+    /// Whose size wins when several clients are attached
+    size_policy: SizePolicy,
+    /// Idle/lifetime limits (unset ones fall back to the server defaults)
+    reap: ReapPolicy,
     #[serde(default = "default_cwd")]
     cwd: String,
     #[serde(default = "default_cols")]
@@ -216,119 +466,46 @@ fn default_rows() -> u16 {
     24
 }
 
-// =============================================================================
-// Security: Shell and CWD Validation
-// =============================================================================
-
-/// Allowed shells for PTY sessions (whitelist to prevent command injection)
-const ALLOWED_SHELLS: &[&str] = &[
-    "/bin/sh",
-    "/bin/bash",
-    "/bin/zsh",
-    "/bin/fish",
-    "/bin/dash",
-    "/bin/ash",
-    "/usr/bin/sh",
-    "/usr/bin/bash",
-    "/usr/bin/zsh",
-    "/usr/bin/fish",
-];
-
-/// Mapping from common shell names to their allowed absolute paths
-/// This allows clients to pass bare shell names like "bash" or "zsh"
-const SHELL_NAME_MAPPINGS: &[(&str, &str)] = &[
-    ("sh", "/bin/sh"),
-    ("bash", "/bin/bash"),
-    ("zsh", "/bin/zsh"),
-    ("fish", "/bin/fish"),
-    ("dash", "/bin/dash"),
-    ("ash", "/bin/ash"),
-];
-
-/// Allowed base directories for cwd (prevent path traversal)
-/// Note: Both with and without trailing slashes are checked
-const ALLOWED_CWD_PREFIXES: &[&str] = &[
-    "/home",
-    "/root",
-    "/tmp",
-    "/var/tmp",
-    "/workspace",
-    "/workspaces",
-    "/Users", // macOS home directories
-];
-
-/// Validate the shell path against whitelist
-fn validate_shell(shell: &str) -> Result<&'static str, &'static str> {
-    // First, check if the input is already an allowed absolute path
-    if ALLOWED_SHELLS.contains(&shell) {
-        // Return the matching static str from the whitelist
-        return Ok(ALLOWED_SHELLS.iter().find(|&&s| s == shell).unwrap());
-    }
-
-    // If not an absolute path, try to map common shell names to their paths
-    // This supports inputs like "bash", "zsh", "sh" without full paths
-    let shell_name = std::path::Path::new(shell)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or(shell);
-
-    for (name, path) in SHELL_NAME_MAPPINGS {
-        if shell_name == *name {
-            return Ok(path);
-        }
-    }
-
-    warn!(
-        "[security] Rejected shell: {} (not in whitelist or known mappings)",
-        shell
-    );
-    Err("Shell not in allowed list")
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
 }
 
-/// Validate and canonicalize the cwd path
-fn validate_cwd(cwd: &str) -> Result<String, &'static str> {
-    use std::path::Path;
-
-    let path = Path::new(cwd);
-
-    // Check for path traversal attempts in the raw input
-    let cwd_normalized = cwd.replace("\\", "/");
-    if cwd_normalized.contains("..") {
-        warn!("[security] Path traversal attempt in cwd: {}", cwd);
-        return Err("Path traversal not allowed");
-    }
-
-    // Path must be absolute
-    if !path.is_absolute() {
-        warn!("[security] Relative cwd path rejected: {}", cwd);
-        return Err("cwd must be an absolute path");
-    }
-
-    // Check against allowed prefixes
-    // This handles both exact matches (e.g., "/root") and paths with children (e.g., "/root/workspace")
-    let path_str = path.to_str().ok_or("Invalid cwd path encoding")?;
-    let is_allowed = ALLOWED_CWD_PREFIXES.iter().any(|prefix| {
-        // Exact match (e.g., cwd="/root" matches prefix="/root")
-        path_str == *prefix
-            // Path is under the prefix (e.g., cwd="/root/foo" starts with "/root/")
-            || path_str.starts_with(&format!("{}/", prefix))
-    });
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum RestartPolicy {
+    #[default]
+    Never,
+    OnFailure,
+    Always,
+}
 
-    if !is_allowed {
-        warn!(
-            "[security] cwd outside allowed directories: {} (allowed: {:?})",
-            cwd, ALLOWED_CWD_PREFIXES
-        );
-        return Err("cwd must be within allowed directories");
+impl RestartPolicy {
+    fn should_restart(self, exit_code: Option<i32>) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => exit_code != Some(0),
+            RestartPolicy::Always => true,
+        }
     }
+}
 
-    Ok(path_str.to_string())
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ExitRecord {
+    exit_code: Option<i32>,
+    exited_at: f64,
 }
 
 impl Default for CreateSessionRequest {
     fn default() -> Self {
         Self {
             shell: default_shell(),
+            command: Vec::new(),
+            restart: RestartPolicy::default(),
+            size_policy: SizePolicy::default(),
+            reap: ReapPolicy::default(),
             cwd: default_cwd(),
             cols: default_cols(),
             rows: default_rows(),
@@ -346,6 +523,9 @@ struct UpdateSessionRequest {
     index: Option<usize>,
     /// Update metadata - merges with existing metadata (use null to remove keys)
     metadata: Option<serde_json::Value>,
+    size_policy: Option<SizePolicy>,
+    /// Replaces the session's idle/lifetime limits
+    reap: Option<ReapPolicy>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -360,6 +540,28 @@ struct SessionInfo {
     created_at: f64,
     alive: bool,
     pid: u32,
+    /// Argv for command sessions (absent for shells)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command: Option<Vec<String>>,
+    restart_policy: RestartPolicy,
+    restart_count: u32,
+    /// Oldest first, capped at `MAX_EXIT_HISTORY`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    exit_history: Vec<ExitRecord>,
+    size_policy: SizePolicy,
+    /// Clients attached to the terminal WebSocket, in attach order
+    clients: Vec<ClientInfo>,
+    /// Process owning the terminal (the shell itself when at a prompt)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    foreground: Option<ProcessInfo>,
+    /// Session process tree, sampled every `PROCESS_POLL_INTERVAL`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    processes: Vec<ProcessInfo>,
+    /// Limits set on this session (server defaults apply to unset ones)
+    reap: ReapPolicy,
+    /// When the process exited, for sessions kept around after exit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exited_at: Option<f64>,
     /// Flexible metadata for client use (location, type, managed flag, etc.)
     #[serde(skip_serializing_if = "Option::is_none")]
     metadata: Option<serde_json::Value>,
@@ -384,7 +586,17 @@ enum ServerEvent {
     },
 
     #[serde(rename = "pty_deleted")]
-    PtyDeleted { pty_id: String },
+    PtyDeleted {
+        pty_id: String,
+        #[serde(default)]
+        reason: DeleteReason,
+    },
+
+    #[serde(rename = "clients_changed")]
+    ClientsChanged {
+        pty_id: String,
+        clients: Vec<ClientInfo>,
+    },
 
     #[serde(rename = "output")]
     Output { data: String },
@@ -405,6 +617,10 @@ enum ClientMessage {
     #[serde(rename = "create_pty")]
     CreatePty {
         shell: Option<String>,
+        command: Option<Vec<String>>,
+        restart: Option<RestartPolicy>,
+        size_policy: Option<SizePolicy>,
+        reap: Option<ReapPolicy>,
         cwd: Option<String>,
         cols: Option<u16>,
         rows: Option<u16>,
@@ -430,11 +646,104 @@ enum ClientMessage {
 struct PtySessionInner {
     master: Box<dyn MasterPty + Send>,
     child: Box<dyn portable_pty::Child + Send>,
+    started_at: Instant,
+}
+
+/// What to run for a session, kept so the process can be respawned
+#[derive(Debug, Clone)]
+struct LaunchSpec {
+    program: String,
+    args: Vec<String>,
+    cwd: String,
+    env: Option<HashMap<String, String>>,
+    /// Explicit argv session (as opposed to an interactive shell)
+    is_command: bool,
+}
+
+impl LaunchSpec {
+    fn command_builder(&self) -> CommandBuilder {
+        let mut cmd = CommandBuilder::new(&self.program);
+        cmd.args(&self.args);
+        cmd.cwd(&self.cwd);
+        cmd.env("TERM", "xterm-256color");
+        cmd.env("COLORTERM", "truecolor");
+        if !self.is_command {
+            cmd.env("SHELL", &self.program);
+        }
+
+        if let Some(env) = &self.env {
+            for (key, value) in env {
+                cmd.env(key, value);
+            }
+        }
+        cmd
+    }
+
+    fn argv(&self) -> Vec<String> {
+        std::iter::once(self.program.clone())
+            .chain(self.args.iter().cloned())
+            .collect()
+    }
+}
+
+/// A freshly spawned PTY process and its I/O handles
+struct SpawnedPty {
+    master: Box<dyn MasterPty + Send>,
+    child: Box<dyn portable_pty::Child + Send>,
+    reader: Box<dyn Read + Send>,
+    writer: Box<dyn IoWrite + Send>,
+    pid: u32,
+}
+
+fn spawn_pty_process(launch: &LaunchSpec, cols: u16, rows: u16) -> Result<SpawnedPty, ServerError> {
+    let pty_system = native_pty_system();
+
+    let pair = pty_system
+        .openpty(PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(|e| ServerError::PtySpawnError(e.to_string()))?;
+
+    let child = pair
+        .slave
+        .spawn_command(launch.command_builder())
+        .map_err(|e| ServerError::PtySpawnError(e.to_string()))?;
+
+    let pid = child.process_id().unwrap_or(0);
+
+    let reader = pair
+        .master
+        .try_clone_reader()
+        .map_err(|e| ServerError::PtySpawnError(e.to_string()))?;
+
+    let writer = pair
+        .master
+        .take_writer()
+        .map_err(|e| ServerError::PtySpawnError(e.to_string()))?;
+
+    Ok(SpawnedPty {
+        master: pair.master,
+        child,
+        reader,
+        writer,
+        pid,
+    })
 }
 
 struct PtySession {
     id: String,
     inner: Mutex<PtySessionInner>,
+    launch: LaunchSpec,
+    restart_policy: RestartPolicy,
+    restart_count: RwLock<u32>,
+    /// Restarts since the process last ran for `RESTART_RESET_AFTER`, drives backoff
+    backoff_attempts: RwLock<u32>,
+    exit_history: RwLock<Vec<ExitRecord>>,
+    /// Waiting out the restart backoff (process is dead but the session stays listed)
+    restarting: AtomicBool,
     shell: String,
     cwd: String,
     name: RwLock<String>,
@@ -442,11 +751,19 @@ struct PtySession {
     created_at: f64,
     cols: RwLock<u16>,
     rows: RwLock<u16>,
-    scrollback: RwLock<String>,
-    output_tx: broadcast::Sender<String>,
-    input_tx: std::sync::mpsc::SyncSender<Vec<u8>>, // Bounded channel for backpressure
-    pid: u32,
+    scrollback: RwLock<OutputBuffer>,
+    output_tx: broadcast::Sender<OutputChunk>,
+    input_tx: RwLock<std::sync::mpsc::SyncSender<Vec<u8>>>, // Bounded channel for backpressure
+    pid: RwLock<u32>,
     metadata: RwLock<Option<serde_json::Value>>,
+    size_policy: RwLock<SizePolicy>,
+    clients: Mutex<AttachedClients>,
+    processes: RwLock<ProcessSnapshot>,
+    reap: RwLock<ReapPolicy>,
+    last_output: RwLock<Instant>,
+    /// When the last client detached (or the session was created)
+    last_detach: RwLock<Instant>,
+    exited_at: RwLock<Option<Instant>>,
     /// DA (Device Attributes) filter to prevent feedback loops with nested terminals.
     /// Filters DA1/DA2 queries and responses that can cause infinite loops when
     /// running terminal emulators inside terminal emulators.
@@ -454,6 +771,8 @@ struct PtySession {
     /// Virtual terminal emulator for tracking terminal state.
     /// Provides server-side ANSI sequence parsing and grid-based storage.
     terminal: Mutex<VirtualTerminal>,
+    /// Output log on disk, when logging is enabled (lock after `terminal`)
+    log: Mutex<Option<SessionLog>>,
 }
 
 impl PtySession {
@@ -462,6 +781,7 @@ impl PtySession {
             let mut inner = self.inner.lock();
             inner.child.try_wait().ok().flatten().is_none()
         };
+        let processes = self.processes.read();
 
         SessionInfo {
             id: self.id.clone(),
@@ -473,7 +793,20 @@ impl PtySession {
             rows: *self.rows.read(),
             created_at: self.created_at,
             alive,
-            pid: self.pid,
+            pid: self.pid(),
+            command: self.launch.is_command.then(|| self.launch.argv()),
+            restart_policy: self.restart_policy,
+            restart_count: *self.restart_count.read(),
+            exit_history: self.exit_history.read().clone(),
+            size_policy: *self.size_policy.read(),
+            clients: self.clients.lock().infos(),
+            foreground: processes.foreground().cloned(),
+            processes: processes.processes.clone(),
+            reap: *self.reap.read(),
+            exited_at: self
+                .exited_at
+                .read()
+                .map(|at| unix_now() - at.elapsed().as_secs_f64()),
             metadata: self.metadata.read().clone(),
         }
     }
@@ -483,6 +816,108 @@ impl PtySession {
         inner.child.try_wait().ok().flatten().is_none()
     }
 
+    /// Whether the session belongs in listings (alive, or about to be restarted)
+    fn is_listed(&self) -> bool {
+        self.restarting.load(Ordering::SeqCst) || self.is_alive()
+    }
+
+    fn pid(&self) -> u32 {
+        *self.pid.read()
+    }
+
+    fn detach_client(&self, client_id: &str) {
+        let mut clients = self.clients.lock();
+        clients.detach(client_id);
+        if clients.is_empty() {
+            *self.last_detach.write() = Instant::now();
+        }
+    }
+
+    fn activity(&self) -> SessionActivity {
+        SessionActivity {
+            age: Duration::from_secs_f64((unix_now() - self.created_at).max(0.0)),
+            since_output: self.last_output.read().elapsed(),
+            detached_for: self
+                .clients
+                .lock()
+                .is_empty()
+                .then(|| self.last_detach.read().elapsed()),
+            exited_for: self.exited_at.read().map(|at| at.elapsed()),
+        }
+    }
+
+    /// Foreground process group of the terminal (`tcgetpgrp` on the master)
+    fn foreground_pgid(&self) -> Option<u32> {
+        let inner = self.inner.lock();
+        inner
+            .master
+            .process_group_leader()
+            .and_then(|pgid| u32::try_from(pgid).ok())
+    }
+
+    /// Wait briefly for the exited child to be reaped and return its exit code.
+    async fn wait_exit_code(&self) -> Option<i32> {
+        for _ in 0..EXIT_STATUS_POLL_ATTEMPTS {
+            let status = self.inner.lock().child.try_wait().ok().flatten();
+            if let Some(status) = status {
+                return Some(status.exit_code().try_into().unwrap_or(1));
+            }
+            tokio::time::sleep(EXIT_STATUS_POLL_INTERVAL).await;
+        }
+        None
+    }
+
+    /// Record an exit and return the backoff before restarting, if the
+    /// restart policy asks for one.
+    fn record_exit(&self, exit_code: Option<i32>) -> Option<Duration> {
+        {
+            let mut history = self.exit_history.write();
+            history.push(ExitRecord {
+                exit_code,
+                exited_at: unix_now(),
+            });
+            if history.len() > MAX_EXIT_HISTORY {
+                let excess = history.len() - MAX_EXIT_HISTORY;
+                history.drain(..excess);
+            }
+        }
+
+        if !self.restart_policy.should_restart(exit_code) {
+            return None;
+        }
+
+        let ran_for = self.inner.lock().started_at.elapsed();
+        let mut attempts = self.backoff_attempts.write();
+        if ran_for >= RESTART_RESET_AFTER {
+            *attempts = 0;
+        }
+        let delay = RESTART_BACKOFF_INITIAL
+            .saturating_mul(1 << (*attempts).min(16))
+            .min(RESTART_BACKOFF_MAX);
+        *attempts += 1;
+        Some(delay)
+    }
+
+    /// Start the process again with the original launch spec and current size.
+    /// Returns the new output reader.
+    fn respawn(&self) -> Result<Box<dyn Read + Send>, ServerError> {
+        let spawned = spawn_pty_process(&self.launch, *self.cols.read(), *self.rows.read())?;
+
+        let (input_tx, input_rx) = std::sync::mpsc::sync_channel(PTY_INPUT_CHANNEL_SIZE);
+        spawn_pty_writer_thread(self.id.clone(), spawned.writer, input_rx);
+
+        *self.inner.lock() = PtySessionInner {
+            master: spawned.master,
+            child: spawned.child,
+            started_at: Instant::now(),
+        };
+        *self.input_tx.write() = input_tx;
+        *self.pid.write() = spawned.pid;
+        *self.restart_count.write() += 1;
+
+        Ok(spawned.reader)
+    }
+
     /// Send input to the PTY via the channel.
     /// Uses a bounded channel for backpressure - if the PTY can't keep up,
     /// this will block (which is correct behavior for flow control).
@@ -498,7 +933,8 @@ impl PtySession {
         if len > 100 {
             info!("[session:{}] Queueing large input: {} bytes", self.id, len);
         }
-        self.input_tx.send(data).map_err(|e| {
+        let input_tx = self.input_tx.read().clone();
+        input_tx.send(data).map_err(|e| {
             error!("[session:{}] Input channel send failed: {}", self.id, e);
             anyhow::anyhow!("PTY input channel closed")
         })?;
@@ -535,21 +971,133 @@ impl PtySession {
         }
     }
 
-    fn append_scrollback(&self, data: &str) {
-        let mut scrollback = self.scrollback.write();
-        scrollback.push_str(data);
-        if scrollback.len() > MAX_SCROLLBACK {
-            let mut start = scrollback.len() - MAX_SCROLLBACK;
-            // Find a valid UTF-8 char boundary to avoid panic on multi-byte chars
-            while start < scrollback.len() && !scrollback.is_char_boundary(start) {
-                start += 1;
+    /// Record output, returning its stream offset
+    fn append_scrollback(&self, data: &str) -> u64 {
+        *self.last_output.write() = Instant::now();
+        let offset = self.scrollback.write().push(data.as_bytes());
+        let mut log = self.log.lock();
+        if let Some(session_log) = log.as_ref() {
+            if let Err(e) = session_log.write_raw(data.as_bytes()) {
+                warn!("[logs:{}] Disabling log after write error: {}", self.id, e);
+                *log = None;
+            }
+        }
+        offset
+    }
+
+    fn is_logging(&self) -> bool {
+        self.log.lock().is_some()
+    }
+
+    /// Open, reopen or close the log to match the session's metadata and the
+    /// server defaults
+    fn configure_log(&self, config: &LogConfig) {
+        let settings = config.resolve(self.metadata.read().as_ref());
+        let mut terminal = self.terminal.lock();
+        let mut log = self.log.lock();
+        if log.as_ref().map(SessionLog::settings) == settings {
+            return;
+        }
+
+        // Let queued writes land before a new log appends to the same files
+        if let Some(old) = log.take() {
+            old.close();
+        }
+        if let (Some(settings), Some(dir)) = (settings, config.session_dir(&self.id)) {
+            let offset = self.scrollback.read().end_offset();
+            match SessionLog::open(&dir, settings, offset) {
+                Ok(session_log) => *log = Some(session_log),
+                Err(e) => warn!("[logs:{}] Failed to open log: {}", self.id, e),
+            }
+        }
+        terminal.set_line_capture(log.as_ref().is_some_and(SessionLog::wants_text));
+    }
+
+    /// Write lines that scrolled off the screen to the text log
+    fn log_scrolled_lines(&self, terminal: &mut VirtualTerminal) {
+        let mut log = self.log.lock();
+        let Some(session_log) = log.as_ref().filter(|l| l.wants_text()) else {
+            // The log was closed after a write error
+            terminal.set_line_capture(false);
+            return;
+        };
+        let lines = terminal.drain_scrolled_lines();
+        if let Err(e) = session_log.write_lines(lines) {
+            warn!("[logs:{}] Disabling log after write error: {}", self.id, e);
+            *log = None;
+            terminal.set_line_capture(false);
+        }
+    }
+
+    /// Flush what's left on screen to the text log and detach the log; the
+    /// caller waits for its writes with `SessionLog::close`
+    fn take_log(&self) -> Option<SessionLog> {
+        let mut terminal = self.terminal.lock();
+        let mut log = self.log.lock();
+        if let Some(session_log) = log.as_ref().filter(|l| l.wants_text()) {
+            let mut lines = terminal.drain_scrolled_lines();
+            let mut screen = terminal.viewport_lines();
+            while screen.last().is_some_and(|line| line.trim().is_empty()) {
+                screen.pop();
+            }
+            lines.extend(screen.into_iter().map(|line| line.trim_end().to_string()));
+            if let Err(e) = session_log.write_lines(lines) {
+                warn!("[logs:{}] Failed to flush text log: {}", self.id, e);
             }
-            *scrollback = scrollback[start..].to_string();
         }
+        terminal.set_line_capture(false);
+        log.take()
+    }
+
+    /// Record output and send it to subscribers
+    fn publish_output(
+        &self,
+        data: String,
+    ) -> Result<usize, broadcast::error::SendError<OutputChunk>> {
+        let offset = self.append_scrollback(&data);
+        self.output_tx.send(OutputChunk { offset, data })
+    }
+
+    /// Send a `\x00`-prefixed control message to subscribers (not recorded)
+    fn send_control(&self, message: String) {
+        let offset = self.scrollback.read().end_offset();
+        let _ = self.output_tx.send(OutputChunk {
+            offset,
+            data: message,
+        });
     }
 
     fn get_scrollback(&self) -> String {
-        self.scrollback.read().clone()
+        String::from_utf8_lossy(&self.scrollback.read().replay()).into_owned()
+    }
+
+    /// Output after `since` (the retained history when `None`), or a redraw of
+    /// the screen when that output has been evicted.
+    fn catch_up(&self, since: Option<u64>) -> OutputBatch {
+        // The reader records output under the terminal lock, so the screen
+        // matches the buffer's end offset
+        let terminal = self.terminal.lock();
+        let scrollback = self.scrollback.read();
+        let next_offset = scrollback.end_offset();
+        let (data, snapshot) = match since {
+            None => (scrollback.replay(), false),
+            Some(since) => match scrollback.since(since) {
+                Some(data) => (data, false),
+                None => (terminal.render_screen().into_bytes(), true),
+            },
+        };
+        OutputBatch {
+            data,
+            next_offset,
+            snapshot,
+        }
+    }
+
+    /// Exit code of the final exit, once the process has exited for good
+    fn final_exit_code(&self) -> Option<Option<i32>> {
+        self.exited_at
+            .read()
+            .map(|_| self.exit_history.read().last().and_then(|r| r.exit_code))
     }
 
     fn set_name(&self, name: String) {
@@ -568,13 +1116,6 @@ impl PtySession {
         *self.metadata.write() = metadata;
     }
 
-    /// Process bytes through the virtual terminal emulator and collect responses.
-    fn process_terminal(&self, data: &[u8]) -> Vec<Vec<u8>> {
-        let mut terminal = self.terminal.lock();
-        terminal.process(data);
-        terminal.drain_responses()
-    }
-
     /// Resize the virtual terminal emulator.
     fn resize_terminal(&self, rows: usize, cols: usize) {
         let mut terminal = self.terminal.lock();
@@ -592,6 +1133,28 @@ impl PtySession {
         let terminal = self.terminal.lock();
         terminal.viewport_lines()
     }
+
+    /// Resize to what the size policy picks from the attached clients' sizes.
+    /// `resizing` is the client whose resize triggered this, if any.
+    fn apply_client_sizes(&self, resizing: Option<&str>) -> Result<()> {
+        let size = self
+            .clients
+            .lock()
+            .effective_size(*self.size_policy.read(), resizing);
+        match size {
+            Some((cols, rows)) if (cols, rows) != (*self.cols.read(), *self.rows.read()) => {
+                self.resize(cols, rows)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Absolute number of the cursor's line: its index within
+    /// `get_terminal_content()` plus the lines evicted from the scrollback.
+    fn cursor_line(&self) -> u64 {
+        let terminal = self.terminal.lock();
+        terminal.lines_evicted() + (terminal.scrollback_len() + terminal.cursor_row()) as u64
+    }
 }
 
 // =============================================================================
@@ -602,18 +1165,46 @@ struct AppState {
     sessions: RwLock<HashMap<String, Arc<PtySession>>>,
     terminal_counter: RwLock<u32>,
     event_tx: broadcast::Sender<ServerEvent>,
+    policy: AllowPolicy,
+    access: AccessConfig,
+    /// Limits for sessions that don't set their own
+    reap_defaults: ReapPolicy,
+    logs: LogConfig,
 }
 
 impl AppState {
     fn new() -> Self {
+        Self::with_policy(AllowPolicy::default())
+    }
+
+    fn with_policy(policy: AllowPolicy) -> Self {
         let (event_tx, _) = broadcast::channel(1024);
         Self {
             sessions: RwLock::new(HashMap::new()),
             terminal_counter: RwLock::new(0),
             event_tx,
+            policy,
+            access: AccessConfig::default(),
+            reap_defaults: ReapPolicy::default(),
+            logs: LogConfig::default(),
+        }
+    }
+
+    fn with_access(self, access: AccessConfig) -> Self {
+        Self { access, ..self }
+    }
+
+    fn with_reap_defaults(self, reap_defaults: ReapPolicy) -> Self {
+        Self {
+            reap_defaults,
+            ..self
         }
     }
 
+    fn with_logs(self, logs: LogConfig) -> Self {
+        Self { logs, ..self }
+    }
+
     fn get_next_terminal_name(&self, shell: &str) -> String {
         let mut counter = self.terminal_counter.write();
         *counter += 1;
@@ -629,7 +1220,7 @@ impl AppState {
         let sessions = self.sessions.read();
         let mut infos: Vec<_> = sessions
             .values()
-            .filter(|s| s.is_alive())
+            .filter(|s| s.is_listed())
             .map(|s| s.to_info())
             .collect();
         infos.sort_by_key(|s| s.index);
@@ -646,7 +1237,7 @@ impl AppState {
         let sessions = self.sessions.read();
         let mut infos: Vec<_> = sessions
             .values()
-            .filter(|s| s.is_alive())
+            .filter(|s| s.is_listed())
             .map(|s| (s.id.clone(), s.get_index()))
             .collect();
         infos.sort_by_key(|(_, idx)| *idx);
@@ -666,6 +1257,25 @@ impl AppState {
     fn broadcast_state_sync(&self) {
         self.broadcast_event(self.get_full_state());
     }
+
+    /// Remove and kill a session, announcing why it went away
+    fn remove_session(&self, session_id: &str, reason: DeleteReason) -> Option<Arc<PtySession>> {
+        let session = self.sessions.write().remove(session_id)?;
+        session.kill();
+        self.reindex_sessions();
+        self.broadcast_event(ServerEvent::PtyDeleted {
+            pty_id: session_id.to_string(),
+            reason,
+        });
+        Some(session)
+    }
+
+    fn broadcast_clients_changed(&self, session: &PtySession) {
+        self.broadcast_event(ServerEvent::ClientsChanged {
+            pty_id: session.id.clone(),
+            clients: session.clients.lock().infos(),
+        });
+    }
 }
 
 // =============================================================================
@@ -790,11 +1400,8 @@ fn find_utf8_boundary(bytes: &[u8]) -> usize {
 // PTY Output Reader Task
 // =============================================================================
 
-async fn spawn_pty_reader(
-    session: Arc<PtySession>,
-    mut reader: Box<dyn Read + Send>,
-    state: Arc<AppState>,
-) {
+/// Forward output until the PTY reaches EOF or fails.
+async fn pump_pty_output(session: &PtySession, mut reader: Box<dyn Read + Send>) {
     let session_id = session.id.clone();
     let mut buf = [0u8; PTY_READ_BUFFER_SIZE];
     let mut utf8_buffer: Vec<u8> = Vec::new(); // Buffer for incomplete UTF-8 sequences
@@ -834,8 +1441,7 @@ async fn spawn_pty_reader(
                 }
                 if !utf8_buffer.is_empty() {
                     let data = String::from_utf8_lossy(&utf8_buffer).to_string();
-                    session.append_scrollback(&data);
-                    let _ = session.output_tx.send(data);
+                    let _ = session.publish_output(data);
                 }
                 info!(
                     "[reader:{}] EOF received. Total: {} reads, {} bytes",
@@ -847,18 +1453,13 @@ async fn spawn_pty_reader(
                 read_count += 1;
                 total_bytes_read += n;
 
-                // Process through virtual terminal emulator for state tracking
-                let responses = session.process_terminal(&buf[..n]);
-                if !responses.is_empty() {
-                    for response in responses {
-                        if let Err(e) = session.write_input_bytes(response) {
-                            error!(
-                                "[reader:{}] Failed to send terminal response: {}",
-                                session_id, e
-                            );
-                        }
-                    }
-                }
+                // Process through virtual terminal emulator for state tracking.
+                // The output is recorded before releasing the terminal so that
+                // screen snapshots line up with output offsets.
+                let mut terminal = session.terminal.lock();
+                terminal.process(&buf[..n]);
+                let responses = terminal.drain_responses();
+                session.log_scrolled_lines(&mut terminal);
 
                 // Apply DaFilter to raw bytes to remove DA query/response sequences
                 let filtered_bytes = {
@@ -872,24 +1473,36 @@ async fn spawn_pty_reader(
                 // Find the last valid UTF-8 boundary
                 let valid_up_to = find_utf8_boundary(&utf8_buffer);
 
-                if valid_up_to > 0 {
+                let chunk = (valid_up_to > 0).then(|| {
                     // Convert valid portion to string
                     let data = String::from_utf8_lossy(&utf8_buffer[..valid_up_to]).to_string();
 
                     // Update scrollback
-                    session.append_scrollback(&data);
+                    let offset = session.append_scrollback(&data);
+
+                    // Keep any incomplete bytes for the next read
+                    utf8_buffer = utf8_buffer[valid_up_to..].to_vec();
+                    OutputChunk { offset, data }
+                });
+                drop(terminal);
+
+                for response in responses {
+                    if let Err(e) = session.write_input_bytes(response) {
+                        error!(
+                            "[reader:{}] Failed to send terminal response: {}",
+                            session_id, e
+                        );
+                    }
+                }
 
-                    // Send to session-specific subscribers
-                    let send_result = session.output_tx.send(data);
-                    if send_result.is_err() {
+                // Send to session-specific subscribers
+                if let Some(chunk) = chunk {
+                    if session.output_tx.send(chunk).is_err() {
                         warn!(
                             "[reader:{}] No subscribers for output ({} bytes)",
                             session_id, valid_up_to
                         );
                     }
-
-                    // Keep any incomplete bytes for the next read
-                    utf8_buffer = utf8_buffer[valid_up_to..].to_vec();
                 }
                 // If valid_up_to is 0, we're still accumulating an incomplete char
             }
@@ -909,45 +1522,102 @@ async fn spawn_pty_reader(
             }
         }
     }
+}
 
-    // Get exit code
-    let exit_code = {
-        let mut inner = session.inner.lock();
-        inner
-            .child
-            .try_wait()
-            .ok()
-            .flatten()
-            .map(|s| s.exit_code().try_into().unwrap_or(1))
-    };
+async fn spawn_pty_reader(
+    session: Arc<PtySession>,
+    mut reader: Box<dyn Read + Send>,
+    state: Arc<AppState>,
+) {
+    let session_id = session.id.clone();
 
-    info!(
-        "[reader:{}] Process exited with code: {:?}",
-        session_id, exit_code
-    );
+    let exit_code = loop {
+        pump_pty_output(&session, reader).await;
+
+        let exit_code = session.wait_exit_code().await;
+        info!(
+            "[reader:{}] Process exited with code: {:?}",
+            session_id, exit_code
+        );
+
+        let Some(delay) = session.record_exit(exit_code) else {
+            break exit_code;
+        };
+        // Deleted sessions are removed from state before being killed
+        if !state.sessions.read().contains_key(&session_id) {
+            break exit_code;
+        }
+
+        session.restarting.store(true, Ordering::SeqCst);
+        let code = exit_code.map_or_else(|| "unknown".to_string(), |c| c.to_string());
+        let notice = format!(
+            "\r\n[process exited with code {}, restarting in {:.1}s]\r\n",
+            code,
+            delay.as_secs_f64()
+        );
+        let _ = session.publish_output(notice);
+        info!(
+            "[reader:{}] Restarting in {:?} (policy: {:?})",
+            session_id, delay, session.restart_policy
+        );
+        tokio::time::sleep(delay).await;
+
+        let respawned = if state.sessions.read().contains_key(&session_id) {
+            session.respawn()
+        } else {
+            Err(ServerError::SessionNotFound(session_id.clone()))
+        };
+        session.restarting.store(false, Ordering::SeqCst);
+        match respawned {
+            Ok(new_reader) => {
+                reader = new_reader;
+                let mut changes = HashMap::new();
+                changes.insert(
+                    "restart_count".to_string(),
+                    serde_json::json!(*session.restart_count.read()),
+                );
+                changes.insert("pid".to_string(), serde_json::json!(session.pid()));
+                state.broadcast_event(ServerEvent::PtyUpdated {
+                    terminal: session.to_info(),
+                    changes,
+                });
+            }
+            Err(e) => {
+                warn!("[reader:{}] Not restarting: {}", session_id, e);
+                break exit_code;
+            }
+        }
+    };
+
+    let removed = !state.sessions.read().contains_key(&session_id);
+    // Mark the exit before announcing it, so output followers that subscribe
+    // from now on see it without waiting for the message
+    *session.exited_at.write() = Some(Instant::now());
+    if let Some(session_log) = session.take_log() {
+        // Reads of the log after the exit see all of it
+        let _ = tokio::task::spawn_blocking(move || session_log.close()).await;
+    }
 
     // Send exit event to terminal-specific subscribers
     // Prefix with \x00 to distinguish control messages from regular PTY output
     let exit_json = serde_json::to_string(&ServerEvent::Exit { exit_code }).unwrap_or_default();
     let exit_msg = format!("\x00{}", exit_json);
-    let _ = session.output_tx.send(exit_msg);
+    session.send_control(exit_msg);
 
-    // Clean up: remove session from state and broadcast deletion
-    let session_count = {
-        let mut sessions = state.sessions.write();
-        sessions.remove(&session_id);
-        sessions.len()
-    };
-    state.reindex_sessions();
-
-    info!(
-        "[reader:{}] Session removed from state. Remaining sessions: {}",
-        session_id, session_count
-    );
+    // Sessions that were deleted or reaped are already gone and announced
+    if removed {
+        info!("[reader:{}] Session was removed before exit", session_id);
+        return;
+    }
 
+    // Drop it from listings now; the reaper removes it once the exited grace
+    // period is over, until then its scrollback can still be captured
+    state.reindex_sessions();
     state.broadcast_event(ServerEvent::PtyDeleted {
         pty_id: session_id.clone(),
+        reason: DeleteReason::Exited,
     });
+    reap_sessions(&state);
 
     info!(
         "[reader:{}] Cleanup complete. Broadcast pty_deleted event.",
@@ -956,71 +1626,159 @@ async fn spawn_pty_reader(
 }
 
 // =============================================================================
-// Session Creation Helper
+// Session Reaper Task
 // =============================================================================
 
-fn create_pty_session_inner(
-    state: &AppState,
-    request: &CreateSessionRequest,
-) -> Result<(Arc<PtySession>, Box<dyn Read + Send>), ServerError> {
-    // Security: Validate shell against whitelist
-    let validated_shell = validate_shell(&request.shell)
-        .map_err(|e| ServerError::PtySpawnError(format!("Invalid shell: {}", e)))?;
-
-    // Security: Validate and sanitize cwd path
-    let validated_cwd = validate_cwd(&request.cwd)
-        .map_err(|e| ServerError::PtySpawnError(format!("Invalid cwd: {}", e)))?;
+async fn poll_reap_sessions(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(REAP_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        reap_sessions(&state);
 
-    let pty_system = native_pty_system();
+        let live: HashSet<String> = state.sessions.read().keys().cloned().collect();
+        let logs = state.logs.clone();
+        let _ = tokio::task::spawn_blocking(move || logs.prune(&live)).await;
+    }
+}
 
-    let pair = pty_system
-        .openpty(PtySize {
-            rows: request.rows,
-            cols: request.cols,
-            pixel_width: 0,
-            pixel_height: 0,
+/// Kill sessions over their idle/lifetime limits and drop exited sessions
+/// whose grace period is over.
+fn reap_sessions(state: &AppState) {
+    let verdicts: Vec<(String, DeleteReason)> = state
+        .sessions
+        .read()
+        .values()
+        .filter_map(|session| {
+            let policy = session.reap.read().or(state.reap_defaults);
+            let reason = policy.verdict(&session.activity())?;
+            Some((session.id.clone(), reason))
         })
-        .map_err(|e| ServerError::PtySpawnError(e.to_string()))?;
+        .collect();
+
+    for (session_id, reason) in verdicts {
+        if reason == DeleteReason::Exited {
+            // Already announced when the process exited
+            state.sessions.write().remove(&session_id);
+            info!("[reaper:{}] Removed exited session", session_id);
+        } else if state.remove_session(&session_id, reason).is_some() {
+            info!("[reaper:{}] Killed session: {:?}", session_id, reason);
+        }
+    }
+}
 
-    let mut cmd = CommandBuilder::new(validated_shell);
-    cmd.cwd(&validated_cwd);
-    cmd.env("TERM", "xterm-256color");
-    cmd.env("COLORTERM", "truecolor");
-    cmd.env("SHELL", validated_shell);
+// =============================================================================
+// Process Sampler Task
+// =============================================================================
 
-    if let Some(env) = &request.env {
-        for (key, value) in env {
-            cmd.env(key, value);
-        }
+/// Periodically sample every session's process tree
+async fn poll_session_processes(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(PROCESS_POLL_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        interval.tick().await;
+        sample_session_processes(&state).await;
     }
+}
 
-    let child = pair
-        .slave
-        .spawn_command(cmd)
-        .map_err(|e| ServerError::PtySpawnError(e.to_string()))?;
+/// Refresh every session's process snapshot, broadcasting `PtyUpdated` when
+/// the foreground job, command lines or cwds change.
+async fn sample_session_processes(state: &AppState) {
+    let targets: Vec<(Arc<PtySession>, u32, Option<u32>)> = state
+        .sessions
+        .read()
+        .values()
+        .filter(|s| s.is_listed())
+        .map(|s| (s.clone(), s.pid(), s.foreground_pgid()))
+        .collect();
+    if targets.is_empty() {
+        return;
+    }
 
-    let pid = child.process_id().unwrap_or(0);
+    let roots: Vec<(u32, Option<u32>)> =
+        targets.iter().map(|(_, pid, pgid)| (*pid, *pgid)).collect();
+    let Ok(snapshots) = tokio::task::spawn_blocking(move || {
+        let table = ProcessTable::read();
+        roots
+            .into_iter()
+            .map(|(pid, pgid)| table.snapshot(pid, pgid))
+            .collect::<Vec<_>>()
+    })
+    .await
+    else {
+        return;
+    };
 
-    let reader = pair
-        .master
-        .try_clone_reader()
-        .map_err(|e| ServerError::PtySpawnError(e.to_string()))?;
+    for ((session, _, _), snapshot) in targets.into_iter().zip(snapshots) {
+        let changed = !session.processes.read().same_shape(&snapshot);
+        *session.processes.write() = snapshot;
+        if changed {
+            let info = session.to_info();
+            let mut changes = HashMap::new();
+            changes.insert("foreground".to_string(), serde_json::json!(info.foreground));
+            changes.insert("processes".to_string(), serde_json::json!(info.processes));
+            state.broadcast_event(ServerEvent::PtyUpdated {
+                terminal: info,
+                changes,
+            });
+        }
+    }
+}
 
-    let writer = pair
-        .master
-        .take_writer()
-        .map_err(|e| ServerError::PtySpawnError(e.to_string()))?;
+// =============================================================================
+// Session Creation Helper
+// =============================================================================
+
+fn create_pty_session_inner(
+    state: &AppState,
+    request: &CreateSessionRequest,
+) -> Result<(Arc<PtySession>, Box<dyn Read + Send>), ServerError> {
+    // Security: Validate and sanitize cwd path
+    let validated_cwd = state
+        .policy
+        .validate_cwd(&request.cwd)
+        .map_err(|e| ServerError::PolicyViolation(format!("Invalid cwd: {}", e)))?;
+
+    // Security: Validate the shell or command against the allow policy
+    let launch = if request.command.is_empty() {
+        let validated_shell = state
+            .policy
+            .validate_shell(&request.shell)
+            .map_err(|e| ServerError::PolicyViolation(format!("Invalid shell: {}", e)))?;
+        LaunchSpec {
+            program: validated_shell,
+            args: Vec::new(),
+            cwd: validated_cwd,
+            env: request.env.clone(),
+            is_command: false,
+        }
+    } else {
+        state
+            .policy
+            .validate_command(&request.command)
+            .map_err(|e| ServerError::PolicyViolation(format!("Invalid command: {}", e)))?;
+        if let Some(env) = &request.env {
+            state
+                .policy
+                .validate_command_env(env.keys())
+                .map_err(|e| ServerError::PolicyViolation(format!("Invalid env: {}", e)))?;
+        }
+        LaunchSpec {
+            program: request.command[0].clone(),
+            args: request.command[1..].to_vec(),
+            cwd: validated_cwd,
+            env: request.env.clone(),
+            is_command: true,
+        }
+    };
+
+    let spawned = spawn_pty_process(&launch, request.cols, request.rows)?;
 
     let session_id = Uuid::new_v4().to_string();
     let name = request
         .name
         .clone()
-        .unwrap_or_else(|| state.get_next_terminal_name(validated_shell));
-
-    let created_at = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs_f64())
-        .unwrap_or(0.0);
+        .unwrap_or_else(|| state.get_next_terminal_name(&launch.program));
 
     let (output_tx, _) = broadcast::channel(1024);
 
@@ -1028,36 +1786,56 @@ fn create_pty_session_inner(
     let (input_tx, input_rx) = std::sync::mpsc::sync_channel(PTY_INPUT_CHANNEL_SIZE);
 
     // Spawn dedicated writer thread
-    spawn_pty_writer_thread(session_id.clone(), writer, input_rx);
+    spawn_pty_writer_thread(session_id.clone(), spawned.writer, input_rx);
 
     let index = state.sessions.read().len();
 
     let session = Arc::new(PtySession {
         id: session_id,
         inner: Mutex::new(PtySessionInner {
-            master: pair.master,
-            child,
+            master: spawned.master,
+            child: spawned.child,
+            started_at: Instant::now(),
         }),
-        shell: validated_shell.to_string(),
-        cwd: validated_cwd,
+        shell: launch.program.clone(),
+        cwd: launch.cwd.clone(),
+        launch,
+        restart_policy: if request.command.is_empty() {
+            RestartPolicy::Never
+        } else {
+            request.restart
+        },
+        restart_count: RwLock::new(0),
+        backoff_attempts: RwLock::new(0),
+        exit_history: RwLock::new(Vec::new()),
+        restarting: AtomicBool::new(false),
+        size_policy: RwLock::new(request.size_policy),
+        clients: Mutex::new(AttachedClients::default()),
+        processes: RwLock::new(ProcessSnapshot::default()),
+        reap: RwLock::new(request.reap),
+        last_output: RwLock::new(Instant::now()),
+        last_detach: RwLock::new(Instant::now()),
+        exited_at: RwLock::new(None),
         name: RwLock::new(name),
         index: RwLock::new(index),
-        created_at,
+        created_at: unix_now(),
         cols: RwLock::new(request.cols),
         rows: RwLock::new(request.rows),
-        scrollback: RwLock::new(String::new()),
+        scrollback: RwLock::new(OutputBuffer::new(MAX_SCROLLBACK)),
         output_tx,
-        input_tx,
-        pid,
+        input_tx: RwLock::new(input_tx),
+        pid: RwLock::new(spawned.pid),
         metadata: RwLock::new(request.metadata.clone()),
         da_filter: Mutex::new(DaFilter::new()),
         terminal: Mutex::new(VirtualTerminal::new(
             request.rows as usize,
             request.cols as usize,
         )),
+        log: Mutex::new(None),
     });
+    session.configure_log(&state.logs);
 
-    Ok((session, reader))
+    Ok((session, spawned.reader))
 }
 
 // =============================================================================
@@ -1089,8 +1867,8 @@ async fn create_session(
     let client_id = request.client_id.clone();
 
     info!(
-        "[http] POST /sessions - shell={}, cwd={}, client={:?}",
-        request.shell, request.cwd, client_id
+        "[http] POST /sessions - shell={}, command={:?}, cwd={}, client={:?}",
+        request.shell, request.command, request.cwd, client_id
     );
 
     let (session, reader) = create_pty_session_inner(&state, &request)?;
@@ -1120,6 +1898,17 @@ async fn create_session(
     Ok(Json(info))
 }
 
+async fn get_session(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+) -> Result<impl IntoResponse, ServerError> {
+    let sessions = state.sessions.read();
+    let session = sessions
+        .get(&session_id)
+        .ok_or_else(|| ServerError::SessionNotFound(session_id.clone()))?;
+    Ok(Json(session.to_info()))
+}
+
 async fn update_session(
     State(state): State<Arc<AppState>>,
     Path(session_id): Path<String>,
@@ -1174,9 +1963,23 @@ async fn update_session(
 
     if let Some(new_metadata) = request.metadata {
         session.set_metadata(Some(new_metadata.clone()));
+        session.configure_log(&state.logs);
         changes.insert("metadata".to_string(), new_metadata);
     }
 
+    if let Some(size_policy) = request.size_policy {
+        *session.size_policy.write() = size_policy;
+        changes.insert("size_policy".to_string(), serde_json::json!(size_policy));
+        if let Err(e) = session.apply_client_sizes(None) {
+            warn!("[http] Failed to apply size policy: {}", e);
+        }
+    }
+
+    if let Some(reap) = request.reap {
+        *session.reap.write() = reap;
+        changes.insert("reap".to_string(), serde_json::json!(reap));
+    }
+
     state.reindex_sessions();
 
     let info = session.to_info();
@@ -1197,26 +2000,12 @@ async fn delete_session(
 ) -> Result<impl IntoResponse, ServerError> {
     info!("[http] DELETE /sessions/{}", session_id);
 
-    let (session, remaining) = {
-        let mut sessions = state.sessions.write();
-        let session = sessions.remove(&session_id).ok_or_else(|| {
+    state
+        .remove_session(&session_id, DeleteReason::Deleted)
+        .ok_or_else(|| {
             warn!("[http] Session not found: {}", session_id);
             ServerError::SessionNotFound(session_id.clone())
         })?;
-        (session, sessions.len())
-    };
-
-    info!(
-        "[http] Killing session {} (remaining: {})",
-        session_id, remaining
-    );
-
-    session.kill();
-
-    state.reindex_sessions();
-    state.broadcast_event(ServerEvent::PtyDeleted {
-        pty_id: session_id.clone(),
-    });
 
     info!("[http] Session {} deleted successfully", session_id);
 
@@ -1318,6 +2107,14 @@ async fn send_input(
         .get(&session_id)
         .ok_or_else(|| ServerError::SessionNotFound(session_id.clone()))?;
 
+    // A driver that explicitly took the input lock keeps others out; merely
+    // being attached (the default driver mode) doesn't block automation
+    if session.clients.lock().input_claimed() {
+        return Err(ServerError::InputLocked(
+            "an attached client took the input lock".to_string(),
+        ));
+    }
+
     session
         .write_input(&request.data)
         .map_err(|e| ServerError::PtySpawnError(e.to_string()))?;
@@ -1368,19 +2165,24 @@ async fn send_signal(
 
     for session in sessions_to_signal {
         if session.is_alive() {
-            let pid = Pid::from_raw(session.pid as i32);
+            let pid = Pid::from_raw(session.pid() as i32);
             match kill(pid, signal) {
                 Ok(()) => {
                     info!(
                         "[signal] Sent {} to session {} (pid {})",
-                        signal, session.id, session.pid
+                        signal,
+                        session.id,
+                        session.pid()
                     );
                     sent_count += 1;
                 }
                 Err(e) => {
                     warn!(
                         "[signal] Failed to send {} to session {} (pid {}): {}",
-                        signal, session.id, session.pid, e
+                        signal,
+                        session.id,
+                        session.pid(),
+                        e
                     );
                     errors.push(format!("{}: {}", session.id, e));
                 }
@@ -1451,6 +2253,7 @@ async fn handle_event_websocket(socket: WebSocket, state: Arc<AppState>) {
                 ServerEvent::PtyCreated { .. } => "pty_created",
                 ServerEvent::PtyUpdated { .. } => "pty_updated",
                 ServerEvent::PtyDeleted { .. } => "pty_deleted",
+                ServerEvent::ClientsChanged { .. } => "clients_changed",
                 ServerEvent::Output { .. } => "output",
                 ServerEvent::Exit { .. } => "exit",
                 ServerEvent::Error { .. } => "error",
@@ -1499,6 +2302,10 @@ async fn handle_event_websocket(socket: WebSocket, state: Arc<AppState>) {
             }
             ClientMessage::CreatePty {
                 shell,
+                command,
+                restart,
+                size_policy,
+                reap,
                 cwd,
                 cols,
                 rows,
@@ -1508,6 +2315,10 @@ async fn handle_event_websocket(socket: WebSocket, state: Arc<AppState>) {
             } => {
                 let request = CreateSessionRequest {
                     shell: shell.unwrap_or_else(default_shell),
+                    command: command.unwrap_or_default(),
+                    restart: restart.unwrap_or_default(),
+                    size_policy: size_policy.unwrap_or_default(),
+                    reap: reap.unwrap_or_default(),
                     cwd: cwd.unwrap_or_else(default_cwd),
                     cols: cols.unwrap_or_else(default_cols),
                     rows: rows.unwrap_or_else(default_rows),
@@ -1594,17 +2405,16 @@ async fn handle_event_websocket(socket: WebSocket, state: Arc<AppState>) {
                 state.broadcast_state_sync();
             }
             ClientMessage::DeletePty { pty_id } => {
-                let session = {
-                    let mut sessions = state.sessions.write();
-                    sessions.remove(&pty_id)
-                };
-
-                if let Some(session) = session {
-                    session.kill();
+                if state
+                    .remove_session(&pty_id, DeleteReason::Deleted)
+                    .is_none()
+                {
+                    // Unknown ID: announce it anyway so stale views drop the terminal
+                    state.broadcast_event(ServerEvent::PtyDeleted {
+                        pty_id,
+                        reason: DeleteReason::Deleted,
+                    });
                 }
-
-                state.reindex_sessions();
-                state.broadcast_event(ServerEvent::PtyDeleted { pty_id });
             }
         }
     }
@@ -1613,80 +2423,194 @@ async fn handle_event_websocket(socket: WebSocket, state: Arc<AppState>) {
     info!("Event subscriber disconnected");
 }
 
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct AttachParams {
+    mode: AttachMode,
+    /// Stable ID so the client can recognise itself in `clients_changed`.
+    /// Refused while another client is attached with the same ID; generated
+    /// when omitted.
+    client_id: Option<String>,
+    /// Display name shown to other clients (e.g. user@host)
+    name: Option<String>,
+    /// Resume from this output offset. Also opts into `output_offset` text
+    /// frames, sent after the catch-up and after every screen redraw.
+    since: Option<u64>,
+}
+
 async fn websocket_terminal(
     ws: WebSocketUpgrade,
     Path(session_id): Path<String>,
+    Query(params): Query<AttachParams>,
     State(state): State<Arc<AppState>>,
 ) -> Result<impl IntoResponse, ServerError> {
-    // Verify session exists and get data
-    let (scrollback, output_rx) = {
-        let sessions = state.sessions.read();
-        let session = sessions
-            .get(&session_id)
-            .ok_or_else(|| ServerError::SessionNotFound(session_id.clone()))?;
-        (session.get_scrollback(), session.output_tx.subscribe())
-    };
-
     let session = {
         let sessions = state.sessions.read();
         sessions.get(&session_id).cloned()
     };
 
     let session = session.ok_or_else(|| ServerError::SessionNotFound(session_id.clone()))?;
+    if let Some(client_id) = &params.client_id {
+        if session.clients.lock().contains(client_id) {
+            return Err(ServerError::ClientIdInUse(client_id.clone()));
+        }
+    }
+    let (follower, catch_up) = OutputFollower::start(session.clone(), params.since);
 
     Ok(ws.on_upgrade(move |socket| {
-        handle_terminal_websocket(socket, session, scrollback, output_rx)
+        handle_terminal_websocket(socket, state, session, params, catch_up, follower)
     }))
 }
 
+/// Tells an offset-aware client where the output it has received ends
+fn output_offset_message(batch: &OutputBatch) -> Message {
+    Message::Text(
+        serde_json::json!({
+            "type": "output_offset",
+            "offset": batch.next_offset,
+            "snapshot": batch.snapshot,
+        })
+        .to_string(),
+    )
+}
+
+/// Tells an attached client that one of its messages was refused
+fn error_message(error: &str) -> Message {
+    Message::Text(serde_json::json!({ "type": "error", "error": error }).to_string())
+}
+
+/// Forward input from an attached client unless it lacks the input lock,
+/// in which case the client is told so.
+fn write_client_input(
+    session: &PtySession,
+    client_id: &str,
+    data: &str,
+    notices: &tokio::sync::mpsc::UnboundedSender<Message>,
+) {
+    if !session.clients.lock().can_input(client_id) {
+        let _ = notices.send(error_message(
+            "input rejected: client does not hold the input lock",
+        ));
+        return;
+    }
+    if let Err(e) = session.write_input(data) {
+        error!("[term-ws:{}] Failed to write to PTY: {}", session.id, e);
+    }
+}
+
 async fn handle_terminal_websocket(
     socket: WebSocket,
+    state: Arc<AppState>,
     session: Arc<PtySession>,
-    scrollback: String,
-    mut output_rx: broadcast::Receiver<String>,
+    params: AttachParams,
+    catch_up: OutputBatch,
+    mut follower: OutputFollower,
 ) {
     let (mut sender, mut receiver) = socket.split();
     let session_id = session.id.clone();
+    let client_id = params
+        .client_id
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
 
     info!(
-        "[term-ws:{}] Terminal WebSocket connected (scrollback: {} bytes)",
+        "[term-ws:{}] Terminal WebSocket connected (client: {}, mode: {:?}, since: {:?}, catch-up: {} bytes)",
         session_id,
-        scrollback.len()
+        client_id,
+        params.mode,
+        params.since,
+        catch_up.data.len()
     );
+    let send_offsets = params.since.is_some();
+
+    // The ID was checked before the upgrade, but another client may have taken it since
+    let attached =
+        session
+            .clients
+            .lock()
+            .attach(client_id.clone(), params.name, params.mode, unix_now());
+    if let Err(e) = attached {
+        warn!(
+            "[term-ws:{}] Refused client {}: {}",
+            session_id, client_id, e
+        );
+        let _ = sender.send(error_message(e)).await;
+        let _ = sender.close().await;
+        return;
+    }
+    state.broadcast_clients_changed(&session);
 
-    // Send scrollback as raw binary (xterm expects raw data)
-    if !scrollback.is_empty() {
+    // Send scrollback (or what the client missed) as raw binary (xterm expects raw data)
+    let mut greeting = Vec::new();
+    if !catch_up.data.is_empty() {
         info!(
-            "[term-ws:{}] Sending scrollback: {} bytes",
+            "[term-ws:{}] Sending {}: {} bytes",
             session_id,
-            scrollback.len()
+            if catch_up.snapshot {
+                "screen snapshot"
+            } else {
+                "scrollback"
+            },
+            catch_up.data.len()
         );
-        if sender
-            .send(Message::Binary(scrollback.into_bytes()))
-            .await
-            .is_err()
-        {
+        greeting.push(Message::Binary(catch_up.data.clone()));
+    }
+    if send_offsets {
+        greeting.push(output_offset_message(&catch_up));
+    }
+    for message in greeting {
+        if sender.send(message).await.is_err() {
             warn!("[term-ws:{}] Failed to send scrollback", session_id);
+            session.detach_client(&client_id);
+            state.broadcast_clients_changed(&session);
             return;
         }
     }
 
+    // Error replies to this client's messages, sent by the output task
+    let (notice_tx, mut notice_rx) = tokio::sync::mpsc::unbounded_channel::<Message>();
+
     // Spawn task to forward PTY output to WebSocket as raw binary
     let session_id_clone = session_id.clone();
     let send_task = tokio::spawn(async move {
         let mut output_count = 0usize;
         let mut total_bytes = 0usize;
 
-        while let Ok(data) = output_rx.recv().await {
+        loop {
+            let delivery = tokio::select! {
+                delivery = follower.next() => match delivery {
+                    Some(delivery) => delivery,
+                    None => break,
+                },
+                Some(notice) = notice_rx.recv() => {
+                    if sender.send(notice).await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+            };
+            let (data, offset) = match delivery {
+                Delivery::Output(batch) => {
+                    // Redraws reset the client's offset
+                    let offset =
+                        (send_offsets && batch.snapshot).then(|| output_offset_message(&batch));
+                    (batch.data, offset)
+                }
+                // Control messages keep their \x00-prefixed form
+                Delivery::Exit(exit_code) => {
+                    let exit_json =
+                        serde_json::to_string(&ServerEvent::Exit { exit_code }).unwrap_or_default();
+                    (format!("\x00{}", exit_json).into_bytes(), None)
+                }
+            };
             output_count += 1;
             total_bytes += data.len();
 
             // Send raw binary data (xterm AttachAddon expects this)
-            if sender
-                .send(Message::Binary(data.into_bytes()))
-                .await
-                .is_err()
-            {
+            let mut sent = sender.send(Message::Binary(data)).await;
+            if let (Ok(()), Some(offset)) = (&sent, offset) {
+                sent = sender.send(offset).await;
+            }
+            if sent.is_err() {
                 warn!(
                     "[term-ws:{}] Failed to send output, closing",
                     session_id_clone
@@ -1721,9 +2645,7 @@ async fn handle_terminal_websocket(
                 }
 
                 if let Ok(text) = String::from_utf8(data) {
-                    if let Err(e) = session.write_input(&text) {
-                        error!("[term-ws:{}] Failed to write to PTY: {}", session_id, e);
-                    }
+                    write_client_input(&session, &client_id, &text, &notice_tx);
                 }
             }
             Ok(Message::Text(text)) => {
@@ -1741,22 +2663,64 @@ async fn handle_terminal_websocket(
                                         ctrl.get("rows").and_then(|r| r.as_u64()).unwrap_or(24)
                                             as u16;
                                     info!("[term-ws:{}] Resize: {}x{}", session_id, cols, rows);
-                                    if let Err(e) = session.resize(cols, rows) {
+                                    session.clients.lock().record_size(&client_id, cols, rows);
+                                    if let Err(e) = session.apply_client_sizes(Some(&client_id)) {
                                         error!(
                                             "[term-ws:{}] Failed to resize PTY: {}",
                                             session_id, e
                                         );
                                     }
+                                    state.broadcast_clients_changed(&session);
                                 }
                                 "input" => {
                                     if let Some(data) = ctrl.get("data").and_then(|d| d.as_str()) {
                                         input_count += 1;
                                         input_bytes += data.len();
-                                        if let Err(e) = session.write_input(data) {
-                                            error!(
-                                                "[term-ws:{}] Failed to write to PTY: {}",
-                                                session_id, e
+                                        write_client_input(&session, &client_id, data, &notice_tx);
+                                    }
+                                }
+                                // Input lock: take it if free, give it up, or hand it to another driver
+                                "take_input" | "release_input" | "handoff_input" => {
+                                    let result = {
+                                        let mut clients = session.clients.lock();
+                                        match typ {
+                                            "take_input" => clients.take_input(&client_id),
+                                            "release_input" => {
+                                                clients.release_input(&client_id);
+                                                Ok(())
+                                            }
+                                            _ => {
+                                                match ctrl.get("client_id").and_then(|c| c.as_str())
+                                                {
+                                                    Some(to) => clients.handoff(&client_id, to),
+                                                    None => Err("handoff_input needs a client_id"),
+                                                }
+                                            }
+                                        }
+                                    };
+                                    match result {
+                                        Ok(()) => {
+                                            info!(
+                                                "[term-ws:{}] {} by client {}",
+                                                session_id, typ, client_id
+                                            );
+                                            if let Err(e) = session.apply_client_sizes(None) {
+                                                error!(
+                                                    "[term-ws:{}] Failed to resize PTY: {}",
+                                                    session_id, e
+                                                );
+                                            }
+                                            state.broadcast_clients_changed(&session);
+                                        }
+                                        Err(e) => {
+                                            warn!(
+                                                "[term-ws:{}] {} rejected for client {}: {}",
+                                                session_id, typ, client_id, e
                                             );
+                                            let _ = notice_tx.send(error_message(&format!(
+                                                "{} rejected: {}",
+                                                typ, e
+                                            )));
                                         }
                                     }
                                 }
@@ -1778,9 +2742,7 @@ async fn handle_terminal_websocket(
                     );
                 }
 
-                if let Err(e) = session.write_input(&text) {
-                    error!("[term-ws:{}] Failed to write to PTY: {}", session_id, e);
-                }
+                write_client_input(&session, &client_id, &text, &notice_tx);
             }
             Ok(Message::Close(reason)) => {
                 info!(
@@ -1798,6 +2760,13 @@ async fn handle_terminal_websocket(
     }
 
     send_task.abort();
+
+    session.detach_client(&client_id);
+    if let Err(e) = session.apply_client_sizes(None) {
+        error!("[term-ws:{}] Failed to resize PTY: {}", session_id, e);
+    }
+    state.broadcast_clients_changed(&session);
+
     info!(
         "[term-ws:{}] Disconnected. Total input: {} messages, {} bytes",
         session_id, input_count, input_bytes
@@ -1811,10 +2780,31 @@ async fn handle_terminal_websocket(
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    let connect = || cli::PtyClient::connect(&cli.server, cli.token_file.as_deref());
 
     match cli.command {
         // Server mode
-        Some(Commands::Server { host, port }) => run_server(&host, port).await,
+        Some(Commands::Server {
+            host,
+            port,
+            policy_file,
+            allowed_origins,
+            trust_loopback,
+            reap,
+            logs,
+        }) => {
+            let access =
+                AccessConfig::load(cli.token_file.as_deref(), allowed_origins, trust_loopback)?;
+            run_server(
+                &host,
+                port,
+                policy_file,
+                access,
+                reap.to_server_defaults(),
+                logs.to_config(),
+            )
+            .await
+        }
 
         // No command = server mode (for backwards compatibility)
         None => {
@@ -1823,40 +2813,121 @@ async fn main() -> Result<()> {
                 .unwrap_or_else(|_| "39383".to_string())
                 .parse()
                 .context("Invalid PTY_SERVER_PORT")?;
-            run_server(&host, port).await
+            let policy_file = env::var_os("PTY_POLICY_FILE").map(PathBuf::from);
+            let allowed_origins = env::var("PTY_ALLOWED_ORIGINS")
+                .map(|origins| origins.split(',').map(str::to_string).collect())
+                .unwrap_or_default();
+            let trust_loopback = env::var("CMUX_PTY_TRUST_LOOPBACK")
+                .is_ok_and(|value| matches!(value.as_str(), "1" | "true"));
+            let access =
+                AccessConfig::load(cli.token_file.as_deref(), allowed_origins, trust_loopback)?;
+            let reap_defaults = ReapArgs::default().to_server_defaults();
+            let logs = LogArgs {
+                log_dir: env::var_os("PTY_LOG_DIR").map(PathBuf::from),
+                ..LogArgs::default()
+            };
+            run_server(
+                &host,
+                port,
+                policy_file,
+                access,
+                reap_defaults,
+                logs.to_config(),
+            )
+            .await
         }
 
         // Client commands
-        Some(Commands::List { json }) => cli::cmd_list(&cli.server, json).await,
+        Some(Commands::List { json, long }) => cli::cmd_list(&connect()?, json, long).await,
 
         Some(Commands::New {
             name,
             shell,
             cwd,
             detached,
-        }) => cli::cmd_new(&cli.server, name, shell, cwd, detached).await,
+            restart,
+            cmd,
+            reap,
+        }) => {
+            let ReapPolicy {
+                detached_timeout_secs,
+                idle_timeout_secs,
+                max_lifetime_secs,
+                exited_grace_secs,
+            } = reap.to_policy();
+            let request = cli::CreateSessionRequest {
+                shell,
+                command: cmd,
+                restart,
+                cwd,
+                name,
+                reap: cli::ReapPolicy {
+                    detached_timeout_secs,
+                    idle_timeout_secs,
+                    max_lifetime_secs,
+                    exited_grace_secs,
+                },
+                ..Default::default()
+            };
+            cli::cmd_new(&connect()?, request, detached).await
+        }
 
-        Some(Commands::Attach { session }) => cli::cmd_attach(&cli.server, &session).await,
+        Some(Commands::Attach { session, read_only }) => {
+            cli::cmd_attach(&connect()?, &session, read_only).await
+        }
 
-        Some(Commands::Kill { sessions }) => cli::cmd_kill(&cli.server, &sessions).await,
+        Some(Commands::Kill { sessions }) => cli::cmd_kill(&connect()?, &sessions).await,
 
         Some(Commands::SendKeys { session, keys }) => {
-            cli::cmd_send_keys(&cli.server, &session, &keys).await
+            cli::cmd_send_keys(&connect()?, &session, &keys).await
         }
 
         Some(Commands::CapturePane { session, print }) => {
-            cli::cmd_capture_pane(&cli.server, &session, print).await
+            cli::cmd_capture_pane(&connect()?, &session, print).await
         }
 
+        Some(Commands::Wait {
+            session,
+            pattern,
+            idle,
+            timeout,
+            context,
+            all,
+            json,
+        }) => {
+            let request = cli::WaitRequest {
+                pattern,
+                idle_ms: idle.map(|d| d.as_millis() as u64),
+                timeout_ms: timeout.as_millis() as u64,
+                context_lines: context,
+                include_existing: all,
+            };
+            cli::cmd_wait(&connect()?, &session, &request, json).await
+        }
+
+        Some(Commands::Logs {
+            session,
+            follow,
+            text,
+            bytes,
+        }) => cli::cmd_logs(&connect()?, &session, follow, text, bytes).await,
+
         Some(Commands::Resize {
             session,
             cols,
             rows,
-        }) => cli::cmd_resize(&cli.server, &session, cols, rows).await,
+        }) => cli::cmd_resize(&connect()?, &session, cols, rows).await,
     }
 }
 
-async fn run_server(host: &str, port: u16) -> Result<()> {
+async fn run_server(
+    host: &str,
+    port: u16,
+    policy_file: Option<PathBuf>,
+    access: AccessConfig,
+    reap_defaults: ReapPolicy,
+    logs: LogConfig,
+) -> Result<()> {
     // Debug output to ensure binary is running
     eprintln!("[pty-server] Starting...");
     std::io::Write::flush(&mut std::io::stderr()).ok();
@@ -1871,7 +2942,27 @@ async fn run_server(host: &str, port: u16) -> Result<()> {
 
     eprintln!("[pty-server] Logging initialized");
 
-    let state = Arc::new(AppState::new());
+    let state = match &policy_file {
+        Some(path) => {
+            let policy = AllowPolicy::load(path)?;
+            info!("Loaded allow policy from {}", path.display());
+            AppState::with_policy(policy)
+        }
+        None => AppState::new(),
+    };
+    if access.token.is_none() {
+        warn!("No token file configured: the API is open to anyone who can reach it");
+    }
+    let cors = access.cors_layer();
+    info!("Session logs go to {}", logs.dir.display());
+    let state = Arc::new(
+        state
+            .with_access(access)
+            .with_reap_defaults(reap_defaults)
+            .with_logs(logs),
+    );
+    tokio::spawn(poll_session_processes(state.clone()));
+    tokio::spawn(poll_reap_sessions(state.clone()));
 
     let app = Router::new()
         // Static frontend
@@ -1881,16 +2972,28 @@ async fn run_server(host: &str, port: u16) -> Result<()> {
         .route("/health", get(health))
         .route("/sessions", get(list_sessions))
         .route("/sessions", post(create_session))
+        .route("/sessions/:session_id", get(get_session))
         .route("/sessions/:session_id", patch(update_session))
         .route("/sessions/:session_id", delete(delete_session))
         .route("/sessions/:session_id/capture", get(capture_session))
         .route("/sessions/:session_id/resize", post(resize_session))
         .route("/sessions/:session_id/input", post(send_input))
+        .route("/sessions/:session_id/wait", post(wait::wait_for_output))
+        .route("/sessions/:session_id/output", get(output::poll_output))
+        .route("/sessions/:session_id/log", get(logs::read_log))
+        .route(
+            "/sessions/:session_id/output/stream",
+            get(output::stream_output),
+        )
         .route("/signal", post(send_signal))
         // WebSocket endpoints
         .route("/ws", get(websocket_events))
         .route("/sessions/:session_id/ws", get(websocket_terminal))
-        .layer(CorsLayer::permissive())
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_access,
+        ))
+        .layer(cors)
         .with_state(state);
 
     let addr = format!("{}:{}", host, port);
@@ -1904,7 +3007,13 @@ async fn run_server(host: &str, port: u16) -> Result<()> {
     eprintln!("[pty-server] Server running on {}", addr);
     info!("PTY server running on {}", addr);
 
-    axum::serve(listener, app).await.context("Server error")?;
+    // Peer addresses let loopback clients skip the token check (--trust-loopback)
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .context("Server error")?;
 
     Ok(())
 }
@@ -1921,16 +3030,26 @@ mod tests {
     use tower::ServiceExt;
 
     fn create_test_app() -> Router {
-        let state = Arc::new(AppState::new());
+        create_test_app_with_access(AccessConfig::default())
+    }
+
+    fn create_test_app_with_access(access: AccessConfig) -> Router {
+        let cors = access.cors_layer();
+        let state = Arc::new(AppState::new().with_access(access));
         Router::new()
             .route("/health", get(health))
             .route("/sessions", get(list_sessions))
             .route("/sessions", post(create_session))
+            .route("/sessions/:session_id", get(get_session))
             .route("/sessions/:session_id", patch(update_session))
             .route("/sessions/:session_id", delete(delete_session))
             .route("/ws", get(websocket_events))
             .route("/sessions/:session_id/ws", get(websocket_terminal))
-            .layer(CorsLayer::permissive())
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                auth::require_access,
+            ))
+            .layer(cors)
             .with_state(state)
     }
 
@@ -1951,6 +3070,115 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_token_and_origin_checks() {
+        let app = create_test_app_with_access(AccessConfig {
+            token: Some("secret".to_string()),
+            allowed_origins: vec!["https://cmux.app".to_string()],
+            trust_loopback: false,
+        });
+        let get = |uri: &str, headers: &[(&str, &str)]| {
+            let mut request = Request::builder()
+                .uri(uri)
+                .header("host", "localhost:39383");
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+
+        let app_ref = &app;
+        let send = |request| async move { app_ref.clone().oneshot(request).await.unwrap() };
+
+        assert_eq!(send(get("/health", &[])).await.status(), StatusCode::OK);
+        assert_eq!(
+            send(get("/sessions", &[])).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            send(get("/sessions", &[("authorization", "Bearer wrong")]))
+                .await
+                .status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            send(get("/sessions", &[("authorization", "Bearer secret")]))
+                .await
+                .status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(get("/sessions", &[("cookie", "cmux_pty_token=secret")]))
+                .await
+                .status(),
+            StatusCode::OK
+        );
+
+        // A query token also sets the cookie for the browser frontend
+        let response = send(get("/sessions?token=secret", &[])).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let cookie = response
+            .headers()
+            .get("set-cookie")
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(cookie.starts_with("cmux_pty_token=secret;"));
+
+        // Browsers are held to the origin allowlist even with a valid token
+        assert_eq!(
+            send(get(
+                "/sessions?token=secret",
+                &[("origin", "https://evil.example")]
+            ))
+            .await
+            .status(),
+            StatusCode::FORBIDDEN
+        );
+        for origin in ["https://cmux.app", "http://localhost:39383"] {
+            assert_eq!(
+                send(get("/sessions?token=secret", &[("origin", origin)]))
+                    .await
+                    .status(),
+                StatusCode::OK
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_loopback_needs_token_unless_trusted() {
+        let loopback_request = || {
+            let mut request = Request::builder()
+                .uri("/sessions")
+                .body(Body::empty())
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(axum::extract::ConnectInfo(SocketAddr::from((
+                    [127, 0, 0, 1],
+                    40000,
+                ))));
+            request
+        };
+        let access = |trust_loopback| AccessConfig {
+            token: Some("secret".to_string()),
+            allowed_origins: Vec::new(),
+            trust_loopback,
+        };
+
+        let response = create_test_app_with_access(access(false))
+            .oneshot(loopback_request())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = create_test_app_with_access(access(true))
+            .oneshot(loopback_request())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
     #[tokio::test]
     async fn test_list_sessions_empty() {
         let app = create_test_app();
@@ -2005,6 +3233,243 @@ mod tests {
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 
+    #[tokio::test]
+    async fn test_command_session_rejects_loader_env() {
+        let policy = AllowPolicy {
+            commands: vec!["true".to_string()],
+            ..AllowPolicy::default()
+        };
+        let state = Arc::new(AppState::with_policy(policy));
+
+        let request = CreateSessionRequest {
+            command: vec!["true".into()],
+            cwd: "/tmp".to_string(),
+            env: Some(HashMap::from([(
+                "PATH".to_string(),
+                "/tmp/evil".to_string(),
+            )])),
+            ..Default::default()
+        };
+        assert!(matches!(
+            create_pty_session_inner(&state, &request),
+            Err(ServerError::PolicyViolation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_create_command_session_rejected_by_default_policy() {
+        let app = create_test_app();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/sessions")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        r#"{"command": ["/bin/sh", "-c", "true"], "cwd": "/tmp"}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    /// Test that a failing command is restarted and its exits recorded
+    #[tokio::test]
+    async fn test_command_session_restarts_on_failure() {
+        let policy = AllowPolicy {
+            commands: vec!["/bin/sh".to_string()],
+            ..AllowPolicy::default()
+        };
+        let state = Arc::new(AppState::with_policy(policy));
+
+        let request = CreateSessionRequest {
+            command: vec!["/bin/sh".into(), "-c".into(), "exit 3".into()],
+            restart: RestartPolicy::OnFailure,
+            cwd: "/tmp".to_string(),
+            ..Default::default()
+        };
+
+        let (session, reader) = create_pty_session_inner(&state, &request).unwrap();
+        let session_id = session.id.clone();
+        state
+            .sessions
+            .write()
+            .insert(session_id.clone(), session.clone());
+        let mut event_rx = state.event_tx.subscribe();
+        tokio::spawn(spawn_pty_reader(session.clone(), reader, state.clone()));
+
+        // Wait for the restart to be announced
+        tokio::time::timeout(tokio::time::Duration::from_secs(10), async {
+            loop {
+                match event_rx.recv().await {
+                    Ok(ServerEvent::PtyUpdated { changes, .. })
+                        if changes.contains_key("restart_count") =>
+                    {
+                        break
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(e) => panic!("event channel closed: {}", e),
+                }
+            }
+        })
+        .await
+        .expect("session was not restarted");
+
+        let info = session.to_info();
+        assert_eq!(
+            info.command.as_deref(),
+            Some(&request.command[..]),
+            "command should be reported"
+        );
+        assert!(info.restart_count >= 1, "expected a restart: {:?}", info);
+        assert_eq!(info.exit_history[0].exit_code, Some(3));
+
+        // Deleting the session stops the restart loop
+        state.sessions.write().remove(&session_id);
+        session.kill();
+    }
+
+    /// Test that sampling reports the foreground process and announces changes
+    #[tokio::test]
+    async fn test_process_sampling_reports_foreground() {
+        let state = Arc::new(AppState::new());
+        let request = CreateSessionRequest {
+            shell: "/bin/sh".to_string(),
+            cwd: "/tmp".to_string(),
+            ..Default::default()
+        };
+
+        let (session, reader) = create_pty_session_inner(&state, &request).unwrap();
+        state
+            .sessions
+            .write()
+            .insert(session.id.clone(), session.clone());
+        tokio::spawn(spawn_pty_reader(session.clone(), reader, state.clone()));
+        let mut event_rx = state.event_tx.subscribe();
+
+        sample_session_processes(&state).await;
+        let info = session.to_info();
+        assert_eq!(info.processes.first().map(|p| p.pid), Some(session.pid()));
+        assert_eq!(info.foreground.map(|p| p.pid), Some(session.pid()));
+        assert!(matches!(
+            event_rx.try_recv(),
+            Ok(ServerEvent::PtyUpdated { changes, .. }) if changes.contains_key("foreground")
+        ));
+
+        // Nothing changed, so no new event
+        sample_session_processes(&state).await;
+        assert!(event_rx.try_recv().is_err());
+
+        session.kill();
+    }
+
+    /// Test that sessions over a limit are killed and exited ones kept for the grace period
+    #[tokio::test]
+    async fn test_reaper_enforces_limits() {
+        let state = Arc::new(AppState::new().with_reap_defaults(ReapPolicy {
+            exited_grace_secs: Some(60),
+            ..Default::default()
+        }));
+        let mut event_rx = state.event_tx.subscribe();
+
+        let spawn = |reap: ReapPolicy| {
+            let request = CreateSessionRequest {
+                shell: "/bin/sh".to_string(),
+                cwd: "/tmp".to_string(),
+                reap,
+                ..Default::default()
+            };
+            let (session, reader) = create_pty_session_inner(&state, &request).unwrap();
+            state
+                .sessions
+                .write()
+                .insert(session.id.clone(), session.clone());
+            tokio::spawn(spawn_pty_reader(session.clone(), reader, state.clone()));
+            session
+        };
+
+        let expired = spawn(ReapPolicy {
+            max_lifetime_secs: Some(0),
+            ..Default::default()
+        });
+        let exiting = spawn(ReapPolicy::default());
+
+        reap_sessions(&state);
+        assert!(!state.sessions.read().contains_key(&expired.id));
+        assert!(matches!(
+            event_rx.recv().await,
+            Ok(ServerEvent::PtyDeleted { pty_id, reason: DeleteReason::MaxLifetime }) if pty_id == expired.id
+        ));
+
+        exiting.write_input("exit\n").unwrap();
+        let deleted = tokio::time::timeout(Duration::from_secs(5), event_rx.recv()).await;
+        assert!(matches!(
+            deleted,
+            Ok(Ok(ServerEvent::PtyDeleted { pty_id, reason: DeleteReason::Exited })) if pty_id == exiting.id
+        ));
+
+        // Still reachable by ID during the grace period, but no longer listed
+        reap_sessions(&state);
+        let sessions = state.sessions.read();
+        let kept = sessions.get(&exiting.id).expect("exited session kept");
+        assert!(!kept.is_listed());
+        assert!(kept.to_info().exited_at.is_some());
+    }
+
+    /// Test that wait returns once new output matches the pattern
+    #[tokio::test]
+    async fn test_wait_endpoint_matches_new_output() {
+        let state = Arc::new(AppState::new());
+
+        let request = CreateSessionRequest {
+            shell: "/bin/sh".to_string(),
+            cwd: "/tmp".to_string(),
+            ..Default::default()
+        };
+
+        let (session, reader) = create_pty_session_inner(&state, &request).unwrap();
+        let session_id = session.id.clone();
+        state
+            .sessions
+            .write()
+            .insert(session_id.clone(), session.clone());
+        tokio::spawn(spawn_pty_reader(session.clone(), reader, state.clone()));
+
+        let app = Router::new()
+            .route("/sessions/:session_id/wait", post(wait::wait_for_output))
+            .with_state(state.clone());
+
+        session.write_input("echo wait-$((40 + 2))\n").unwrap();
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/sessions/{}/wait", session_id))
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        r#"{"pattern": "^wait-(\\d+)$", "timeout_ms": 5000, "include_existing": true}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let result: wait::WaitResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(result.reason, wait::WaitReason::Match);
+        let matched = result.matched.unwrap();
+        assert_eq!(matched.captures, vec![Some("42".to_string())]);
+
+        session.kill();
+    }
+
     /// Test moderate input size (small enough to not block)
     #[tokio::test]
     async fn test_pty_moderate_input() {
@@ -2237,6 +3702,151 @@ mod tests {
         session.kill();
     }
 
+    /// Test that output can be resumed from an offset, with a redraw once it's evicted
+    #[tokio::test]
+    async fn test_output_endpoint_resumes_from_offset() {
+        let state = Arc::new(AppState::new());
+        let request = CreateSessionRequest {
+            shell: "/bin/sh".to_string(),
+            cwd: "/tmp".to_string(),
+            ..Default::default()
+        };
+        let (session, reader) = create_pty_session_inner(&state, &request).unwrap();
+        let session_id = session.id.clone();
+        state
+            .sessions
+            .write()
+            .insert(session_id.clone(), session.clone());
+        tokio::spawn(spawn_pty_reader(session.clone(), reader, state.clone()));
+
+        let app = Router::new()
+            .route("/sessions/:session_id/output", get(output::poll_output))
+            .with_state(state.clone());
+        let poll = |query: String| {
+            let app = app.clone();
+            let uri = format!("/sessions/{}/output?{}", session_id, query);
+            async move {
+                let response = app
+                    .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+
+        let start = session.scrollback.read().end_offset();
+        session.write_input("echo resume-$((6 * 7))\n").unwrap();
+        let mut seen = String::new();
+        let mut offset = start;
+        while !seen.contains("resume-42") {
+            let page = poll(format!("since={}&timeout_ms=5000", offset)).await;
+            assert_eq!(page["snapshot"], false);
+            seen.push_str(page["data"].as_str().unwrap());
+            offset = page["next_offset"].as_u64().unwrap();
+        }
+        assert_eq!(offset - start, seen.len() as u64);
+
+        // Nothing new: the same offset comes back after the timeout
+        let idle = poll(format!("since={}&timeout_ms=50", offset)).await;
+        assert_eq!(idle["data"], "");
+        assert_eq!(idle["next_offset"].as_u64(), Some(offset));
+
+        // Push the start out of the buffer: resuming from it redraws the screen
+        let _ = session.publish_output("x".repeat(MAX_SCROLLBACK));
+        let evicted = poll(format!("since={}", start)).await;
+        assert_eq!(evicted["snapshot"], true);
+        assert!(evicted["data"].as_str().unwrap().contains("resume-42"));
+        assert_eq!(
+            evicted["next_offset"].as_u64(),
+            Some(session.scrollback.read().end_offset())
+        );
+
+        session.kill();
+    }
+
+    #[tokio::test]
+    async fn test_session_log_is_served_after_exit() {
+        let dir = std::env::temp_dir().join(format!("cmux-pty-logs-{}", Uuid::new_v4()));
+        let state = Arc::new(AppState::new().with_logs(LogConfig {
+            dir: dir.clone(),
+            ..LogConfig::default()
+        }));
+        let request = CreateSessionRequest {
+            shell: "/bin/sh".to_string(),
+            cwd: "/tmp".to_string(),
+            metadata: Some(serde_json::json!({ "log": { "format": "both" } })),
+            ..Default::default()
+        };
+        let (session, reader) = create_pty_session_inner(&state, &request).unwrap();
+        let session_id = session.id.clone();
+        assert!(session.is_logging());
+        state
+            .sessions
+            .write()
+            .insert(session_id.clone(), session.clone());
+        let reader_task = tokio::spawn(spawn_pty_reader(session.clone(), reader, state.clone()));
+
+        session
+            .write_input("printf 'logged-%s\\n' $((6 * 7)); exit\n")
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(10), reader_task)
+            .await
+            .unwrap()
+            .unwrap();
+        state.sessions.write().remove(&session_id);
+
+        let app = Router::new()
+            .route("/sessions/:session_id/log", get(logs::read_log))
+            .with_state(state.clone());
+        let read = |id: &str, query: &str| {
+            let app = app.clone();
+            let uri = format!("/sessions/{}/log?{}", id, query);
+            async move {
+                let response = app
+                    .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let complete = response
+                    .headers()
+                    .get("x-log-complete")
+                    .map(|v| v.to_str().unwrap().to_string());
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    complete,
+                    String::from_utf8_lossy(&body).into_owned(),
+                )
+            }
+        };
+
+        // The raw log matches the output stream
+        let (status, complete, raw) = read(&session_id, "").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(complete.as_deref(), Some("true"));
+        assert!(raw.contains("logged-42"));
+        assert_eq!(raw.len() as u64, session.scrollback.read().end_offset());
+
+        let (_, _, tail) = read(&session_id, "from=-5").await;
+        assert_eq!(tail, raw[raw.len() - 5..]);
+
+        // The text log has the screen's lines without escape sequences
+        let (_, _, text) = read(&session_id, "format=text").await;
+        assert!(text.lines().any(|line| line == "logged-42"), "{:?}", text);
+        assert!(!text.contains('\x1b'));
+
+        let (status, _, _) = read(&Uuid::new_v4().to_string(), "").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     /// Test resize endpoint
     #[tokio::test]
     async fn test_resize_endpoint() {
@@ -2314,6 +3924,7 @@ mod tests {
             .with_state(state.clone());
 
         let response = app
+            .clone()
             .oneshot(
                 Request::builder()
                     .method("POST")
@@ -2327,6 +3938,31 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::OK);
 
+        // send-keys keeps working while a driver is merely attached
+        let send_keys = || {
+            app.clone().oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/sessions/{}/input", session_id))
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"data": "echo test\n"}"#))
+                    .unwrap(),
+            )
+        };
+        session
+            .clients
+            .lock()
+            .attach("driver".into(), None, AttachMode::Driver, 0.0)
+            .unwrap();
+        assert_eq!(send_keys().await.unwrap().status(), StatusCode::OK);
+
+        // Taking the lock explicitly makes the session read-only over HTTP
+        session.clients.lock().take_input("driver").unwrap();
+        assert_eq!(send_keys().await.unwrap().status(), StatusCode::CONFLICT);
+
+        session.clients.lock().release_input("driver");
+        assert_eq!(send_keys().await.unwrap().status(), StatusCode::OK);
+
         session.kill();
     }
 }
@@ -0,0 +1,360 @@
+//! Session output history with stream offsets
+//!
+//! Every byte a session prints gets a monotonically increasing offset that
+//! survives restarts. The last `MAX_SCROLLBACK` bytes are kept in a ring
+//! buffer, so a client that reconnects with the offset it had reached gets
+//! exactly the output it missed. When that output has already been evicted it
+//! gets a redraw of the current screen instead, followed by live output.
+//!
+//! Besides the terminal WebSocket (`?since=<offset>`), output can be followed
+//! over plain HTTP: `GET /sessions/{id}/output` long-polls and
+//! `GET /sessions/{id}/output/stream` is a server-sent event stream whose event
+//! IDs are offsets, so `EventSource` resumes on its own after a reconnect.
+
+use std::{collections::VecDeque, convert::Infallible, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json,
+    },
+};
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use super::{AppState, PtySession, ServerError, ServerEvent};
+
+const DEFAULT_POLL_TIMEOUT_MS: u64 = 30_000;
+const MAX_POLL_TIMEOUT_MS: u64 = 600_000;
+
+/// Ring buffer of the most recent output bytes
+#[derive(Debug)]
+pub struct OutputBuffer {
+    bytes: VecDeque<u8>,
+    capacity: usize,
+    /// Offset of the oldest retained byte
+    start: u64,
+}
+
+impl OutputBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            bytes: VecDeque::new(),
+            capacity,
+            start: 0,
+        }
+    }
+
+    /// Append output, returning the offset of its first byte
+    pub fn push(&mut self, data: &[u8]) -> u64 {
+        let offset = self.end_offset();
+        self.bytes.extend(data);
+        if self.bytes.len() > self.capacity {
+            let excess = self.bytes.len() - self.capacity;
+            self.bytes.drain(..excess);
+            self.start += excess as u64;
+        }
+        offset
+    }
+
+    /// Offset just past the newest byte
+    pub fn end_offset(&self) -> u64 {
+        self.start + self.bytes.len() as u64
+    }
+
+    /// Output from `offset` on, or `None` if it was evicted (or never written)
+    pub fn since(&self, offset: u64) -> Option<Vec<u8>> {
+        if offset < self.start || offset > self.end_offset() {
+            return None;
+        }
+        Some(self.copy_from((offset - self.start) as usize))
+    }
+
+    /// The retained history for a fresh client. Once output has been evicted
+    /// the cut may fall inside an escape sequence or character, so replay
+    /// starts at the first line boundary instead.
+    pub fn replay(&self) -> Vec<u8> {
+        if self.start == 0 {
+            return self.copy_from(0);
+        }
+        let skip = match self.bytes.iter().position(|&b| b == b'\n') {
+            Some(newline) => newline + 1,
+            // No newline to resync on: at least skip a partial character
+            None => self
+                .bytes
+                .iter()
+                .position(|&b| b & 0b1100_0000 != 0b1000_0000)
+                .unwrap_or(self.bytes.len()),
+        };
+        self.copy_from(skip)
+    }
+
+    fn copy_from(&self, index: usize) -> Vec<u8> {
+        self.bytes.range(index..).copied().collect()
+    }
+}
+
+/// A piece of output as broadcast to a session's subscribers
+#[derive(Debug, Clone)]
+pub struct OutputChunk {
+    /// Offset of the first byte of `data`
+    pub offset: u64,
+    /// Output text, or a `\x00`-prefixed control message (not part of the stream)
+    pub data: String,
+}
+
+impl OutputChunk {
+    pub fn is_control(&self) -> bool {
+        self.data.starts_with('\x00')
+    }
+
+    /// Exit code carried by an exit control message
+    fn exit_code(&self) -> Option<i32> {
+        match serde_json::from_str::<ServerEvent>(self.data.get(1..)?).ok()? {
+            ServerEvent::Exit { exit_code } => exit_code,
+            _ => None,
+        }
+    }
+}
+
+/// Output delivered to a follower in one go
+#[derive(Debug, Clone, Default)]
+pub struct OutputBatch {
+    pub data: Vec<u8>,
+    /// Offset to resume from after this batch
+    pub next_offset: u64,
+    /// `data` is a redraw of the screen rather than stream bytes
+    pub snapshot: bool,
+}
+
+pub enum Delivery {
+    Output(OutputBatch),
+    Exit(Option<i32>),
+}
+
+/// Follows a session's output from an offset, recovering from the buffer when
+/// it falls behind the broadcast channel.
+pub struct OutputFollower {
+    session: Arc<PtySession>,
+    output_rx: broadcast::Receiver<OutputChunk>,
+    next_offset: u64,
+}
+
+impl OutputFollower {
+    /// Subscribe, then catch up from `since` (the retained history when `None`)
+    pub fn start(session: Arc<PtySession>, since: Option<u64>) -> (Self, OutputBatch) {
+        let output_rx = session.output_tx.subscribe();
+        let batch = session.catch_up(since);
+        let follower = Self {
+            session,
+            output_rx,
+            next_offset: batch.next_offset,
+        };
+        (follower, batch)
+    }
+
+    /// Exit code if the session's process has exited for good
+    pub fn exited(&self) -> Option<Option<i32>> {
+        self.session.final_exit_code()
+    }
+
+    /// Wait for the next output or the exit; `None` if the channel closed
+    pub async fn next(&mut self) -> Option<Delivery> {
+        loop {
+            match self.output_rx.recv().await {
+                Ok(chunk) if chunk.is_control() => return Some(Delivery::Exit(chunk.exit_code())),
+                // Already covered by the catch-up
+                Ok(chunk) if chunk.offset < self.next_offset => continue,
+                Ok(chunk) if chunk.offset == self.next_offset => {
+                    self.next_offset += chunk.data.len() as u64;
+                    return Some(Delivery::Output(OutputBatch {
+                        data: chunk.data.into_bytes(),
+                        next_offset: self.next_offset,
+                        snapshot: false,
+                    }));
+                }
+                // Missed chunks: resume from the buffer
+                Ok(_) | Err(RecvError::Lagged(_)) => {
+                    let batch = self.session.catch_up(Some(self.next_offset));
+                    self.next_offset = batch.next_offset;
+                    if !batch.data.is_empty() {
+                        return Some(Delivery::Output(batch));
+                    }
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct OutputParams {
+    /// Offset to resume from; omitted means the retained history
+    pub since: Option<u64>,
+    /// Long-poll: how long to wait for new output
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputResponse {
+    pub data: String,
+    /// Pass as `since` on the next request
+    pub next_offset: u64,
+    /// `data` redraws the screen because the requested output was evicted
+    pub snapshot: bool,
+    /// The process has exited for good
+    pub exited: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+}
+
+impl OutputResponse {
+    fn new(batch: OutputBatch, exited: Option<Option<i32>>) -> Self {
+        Self {
+            data: String::from_utf8_lossy(&batch.data).into_owned(),
+            next_offset: batch.next_offset,
+            snapshot: batch.snapshot,
+            exited: exited.is_some(),
+            exit_code: exited.flatten(),
+        }
+    }
+}
+
+fn find_session(state: &AppState, session_id: &str) -> Result<Arc<PtySession>, ServerError> {
+    state
+        .sessions
+        .read()
+        .get(session_id)
+        .cloned()
+        .ok_or_else(|| ServerError::SessionNotFound(session_id.to_string()))
+}
+
+/// `GET /sessions/{id}/output?since=N`: return output after `since`, waiting
+/// up to `timeout_ms` for some if there is none yet.
+pub async fn poll_output(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Query(params): Query<OutputParams>,
+) -> Result<impl IntoResponse, ServerError> {
+    let session = find_session(&state, &session_id)?;
+    let (mut follower, batch) = OutputFollower::start(session, params.since);
+    if !batch.data.is_empty() || batch.snapshot || follower.exited().is_some() {
+        let exited = follower.exited();
+        return Ok(Json(OutputResponse::new(batch, exited)));
+    }
+
+    let timeout = Duration::from_millis(
+        params
+            .timeout_ms
+            .unwrap_or(DEFAULT_POLL_TIMEOUT_MS)
+            .min(MAX_POLL_TIMEOUT_MS),
+    );
+    let response = match tokio::time::timeout(timeout, follower.next()).await {
+        Ok(Some(Delivery::Output(batch))) => OutputResponse::new(batch, None),
+        Ok(Some(Delivery::Exit(exit_code))) => OutputResponse::new(batch, Some(exit_code)),
+        Ok(None) => OutputResponse::new(batch, Some(None)),
+        // Nothing new: same offset back
+        Err(_) => OutputResponse::new(batch, None),
+    };
+    Ok(Json(response))
+}
+
+#[derive(Serialize)]
+struct OutputEventData {
+    data: String,
+}
+
+fn output_event(batch: &OutputBatch) -> Event {
+    Event::default()
+        .event(if batch.snapshot { "snapshot" } else { "output" })
+        .id(batch.next_offset.to_string())
+        .json_data(OutputEventData {
+            data: String::from_utf8_lossy(&batch.data).into_owned(),
+        })
+        .unwrap_or_default()
+}
+
+fn exit_event(exit_code: Option<i32>) -> Event {
+    Event::default()
+        .event("exit")
+        .json_data(ServerEvent::Exit { exit_code })
+        .unwrap_or_default()
+}
+
+/// `GET /sessions/{id}/output/stream?since=N`: server-sent `output`,
+/// `snapshot` and `exit` events. A reconnecting `EventSource` sends the last
+/// event ID, which takes the place of `since`.
+pub async fn stream_output(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Query(params): Query<OutputParams>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ServerError> {
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let session = find_session(&state, &session_id)?;
+    let (follower, batch) = OutputFollower::start(session, last_event_id.or(params.since));
+
+    let mut initial = Vec::new();
+    if !batch.data.is_empty() || batch.snapshot {
+        initial.push(output_event(&batch));
+    }
+    let exited = follower.exited();
+    if let Some(exit_code) = exited {
+        initial.push(exit_event(exit_code));
+    }
+
+    // Follow until the process exits
+    let live = stream::unfold(exited.is_none().then_some(follower), |follower| async {
+        let mut follower = follower?;
+        match follower.next().await? {
+            Delivery::Output(batch) => Some((output_event(&batch), Some(follower))),
+            Delivery::Exit(exit_code) => Some((exit_event(exit_code), None)),
+        }
+    });
+
+    let events = stream::iter(initial).chain(live).map(Ok::<_, Infallible>);
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_tracks_offsets_across_eviction() {
+        let mut buffer = OutputBuffer::new(8);
+        assert_eq!(buffer.push(b"abc"), 0);
+        assert_eq!(buffer.push(b"defgh"), 3);
+        assert_eq!(buffer.since(2), Some(b"cdefgh".to_vec()));
+
+        assert_eq!(buffer.push(b"ijk"), 8);
+        assert_eq!(buffer.end_offset(), 11);
+        assert_eq!(buffer.since(3), Some(b"defghijk".to_vec()));
+        assert_eq!(buffer.since(11), Some(Vec::new()));
+        // Evicted, or from a different server
+        assert_eq!(buffer.since(2), None);
+        assert_eq!(buffer.since(12), None);
+    }
+
+    #[test]
+    fn replay_starts_at_a_line_boundary_after_eviction() {
+        let mut buffer = OutputBuffer::new(14);
+        buffer.push(b"\x1b[31mred\x1b[0m\r\n");
+        assert_eq!(buffer.replay(), b"\x1b[31mred\x1b[0m\r\n".to_vec());
+
+        buffer.push(b"next\r\nlast");
+        // The cut landed inside the first line's escape sequences
+        assert_eq!(buffer.replay(), b"next\r\nlast".to_vec());
+
+        let mut wide = OutputBuffer::new(4);
+        wide.push("日本".as_bytes());
+        assert_eq!(wide.replay(), "本".as_bytes());
+    }
+}
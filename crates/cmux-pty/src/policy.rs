@@ -0,0 +1,252 @@
+//! Server-side allow policy for PTY sessions
+//!
+//! Decides which shells, commands and working directories clients may use.
+//! Without a policy file the built-in defaults apply: the common system shells,
+//! the usual home/workspace directories, and no arbitrary commands.
+//!
+//! Example policy file (JSON, all fields optional):
+//!
+//! ```json
+//! {
+//!   "shells": ["/bin/bash", "/bin/zsh"],
+//!   "commands": ["pnpm", "node", "/usr/local/bin/claude"],
+//!   "cwd_prefixes": ["/root", "/workspace"]
+//! }
+//! ```
+//!
+//! Command entries containing a `/` must match `argv[0]` exactly. Bare names
+//! only match a bare `argv[0]` (resolved through `PATH` at spawn time), so
+//! allowing `pnpm` does not allow `/tmp/pnpm`. The entry `"*"` allows any command.
+//! Because of that, command sessions may not set `PATH` or dynamic loader
+//! variables (`LD_*`, `DYLD_*`) unless the policy allows any command.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Shells allowed when no policy file overrides them
+const DEFAULT_SHELLS: &[&str] = &[
+    "/bin/sh",
+    "/bin/bash",
+    "/bin/zsh",
+    "/bin/fish",
+    "/bin/dash",
+    "/bin/ash",
+    "/usr/bin/sh",
+    "/usr/bin/bash",
+    "/usr/bin/zsh",
+    "/usr/bin/fish",
+];
+
+/// Base directories allowed for cwd when no policy file overrides them
+const DEFAULT_CWD_PREFIXES: &[&str] = &[
+    "/home",
+    "/root",
+    "/tmp",
+    "/var/tmp",
+    "/workspace",
+    "/workspaces",
+    "/Users", // macOS home directories
+];
+
+/// Wildcard entry in `commands` that allows any program
+const ANY_COMMAND: &str = "*";
+
+/// Environment variable prefixes that change which code a program loads
+const LOADER_ENV_PREFIXES: &[&str] = &["LD_", "DYLD_"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AllowPolicy {
+    /// Absolute paths of allowed shells. Bare shell names ("bash") map to the
+    /// first entry with the same file name.
+    pub shells: Vec<String>,
+    /// Programs allowed as `argv[0]` for command sessions.
+    pub commands: Vec<String>,
+    /// Directories (and their children) allowed as cwd.
+    pub cwd_prefixes: Vec<String>,
+}
+
+impl Default for AllowPolicy {
+    fn default() -> Self {
+        Self {
+            shells: DEFAULT_SHELLS.iter().map(|s| s.to_string()).collect(),
+            commands: Vec::new(),
+            cwd_prefixes: DEFAULT_CWD_PREFIXES.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+impl AllowPolicy {
+    /// Load a policy from a JSON file. Missing fields keep their defaults.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read policy file {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid policy file {}", path.display()))
+    }
+
+    /// Validate the shell path, returning the allowed absolute path
+    pub fn validate_shell(&self, shell: &str) -> Result<String, &'static str> {
+        // First, check if the input is already an allowed absolute path
+        if self.shells.iter().any(|s| s == shell) {
+            return Ok(shell.to_string());
+        }
+
+        // Otherwise map the shell name to an allowed path with the same file name.
+        // This supports inputs like "bash", "zsh", "sh" without full paths
+        let shell_name = file_name(shell);
+        if let Some(path) = self.shells.iter().find(|s| file_name(s) == shell_name) {
+            return Ok(path.clone());
+        }
+
+        warn!("[security] Rejected shell: {} (not in allow policy)", shell);
+        Err("Shell not in allowed list")
+    }
+
+    /// Validate an explicit argv for a command session
+    pub fn validate_command(&self, argv: &[String]) -> Result<(), &'static str> {
+        let Some(program) = argv.first() else {
+            return Err("command must not be empty");
+        };
+        if program.is_empty() {
+            return Err("command must not be empty");
+        }
+
+        // Exact match only: a bare entry never matches a path with the same file name
+        let allowed = self
+            .commands
+            .iter()
+            .any(|entry| entry == ANY_COMMAND || entry == program);
+        if !allowed {
+            warn!(
+                "[security] Rejected command: {:?} (not in allow policy)",
+                argv
+            );
+            return Err("Command not in allowed list");
+        }
+        Ok(())
+    }
+
+    /// Validate the env of a command session. `PATH` and loader variables
+    /// would let a client run any binary under an allowed name.
+    pub fn validate_command_env<'a>(
+        &self,
+        keys: impl IntoIterator<Item = &'a String>,
+    ) -> Result<(), &'static str> {
+        if self.commands.iter().any(|entry| entry == ANY_COMMAND) {
+            return Ok(());
+        }
+        for key in keys {
+            if key == "PATH"
+                || LOADER_ENV_PREFIXES
+                    .iter()
+                    .any(|prefix| key.starts_with(prefix))
+            {
+                warn!(
+                    "[security] Rejected command env {} (not allowed by policy)",
+                    key
+                );
+                return Err("PATH and loader variables are not allowed for commands");
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate and canonicalize the cwd path
+    pub fn validate_cwd(&self, cwd: &str) -> Result<String, &'static str> {
+        let path = Path::new(cwd);
+
+        // Check for path traversal attempts in the raw input
+        let cwd_normalized = cwd.replace("\\", "/");
+        if cwd_normalized.contains("..") {
+            warn!("[security] Path traversal attempt in cwd: {}", cwd);
+            return Err("Path traversal not allowed");
+        }
+
+        // Path must be absolute
+        if !path.is_absolute() {
+            warn!("[security] Relative cwd path rejected: {}", cwd);
+            return Err("cwd must be an absolute path");
+        }
+
+        // Check against allowed prefixes
+        // This handles both exact matches (e.g., "/root") and paths with children (e.g., "/root/workspace")
+        let path_str = path.to_str().ok_or("Invalid cwd path encoding")?;
+        let is_allowed = self.cwd_prefixes.iter().any(|prefix| {
+            // Exact match (e.g., cwd="/root" matches prefix="/root")
+            path_str == prefix
+                // Path is under the prefix (e.g., cwd="/root/foo" starts with "/root/")
+                || path_str.starts_with(&format!("{}/", prefix))
+        });
+
+        if !is_allowed {
+            warn!(
+                "[security] cwd outside allowed directories: {} (allowed: {:?})",
+                cwd, self.cwd_prefixes
+            );
+            return Err("cwd must be within allowed directories");
+        }
+
+        Ok(path_str.to_string())
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn default_policy_maps_shell_names_and_rejects_commands() {
+        let policy = AllowPolicy::default();
+        assert_eq!(policy.validate_shell("bash").unwrap(), "/bin/bash");
+        assert_eq!(
+            policy.validate_shell("/usr/bin/zsh").unwrap(),
+            "/usr/bin/zsh"
+        );
+        assert!(policy.validate_shell("/usr/bin/python3").is_err());
+        assert!(policy.validate_command(&argv(&["pnpm", "dev"])).is_err());
+        assert!(policy.validate_cwd("/root/project").is_ok());
+        assert!(policy.validate_cwd("/etc").is_err());
+        assert!(policy.validate_cwd("/root/../etc").is_err());
+    }
+
+    #[test]
+    fn command_entries_match_exact_paths_and_bare_names() {
+        let policy: AllowPolicy =
+            serde_json::from_str(r#"{"commands": ["pnpm", "/usr/bin/node"]}"#).unwrap();
+        assert!(policy.validate_command(&argv(&["pnpm", "dev"])).is_ok());
+        assert!(policy
+            .validate_command(&argv(&["/usr/bin/node", "x.js"]))
+            .is_ok());
+        assert!(policy.validate_command(&argv(&["/tmp/pnpm"])).is_err());
+        assert!(policy.validate_command(&argv(&["node"])).is_err());
+        assert!(policy.validate_command(&[]).is_err());
+        // Unset fields keep their defaults
+        assert_eq!(policy.shells, AllowPolicy::default().shells);
+
+        let open: AllowPolicy = serde_json::from_str(r#"{"commands": ["*"]}"#).unwrap();
+        assert!(open
+            .validate_command(&argv(&["/opt/agent/bin/run"]))
+            .is_ok());
+
+        let keys = argv(&["NODE_ENV", "LD_PRELOAD"]);
+        assert!(policy.validate_command_env(&keys[..1]).is_ok());
+        assert!(policy.validate_command_env(&keys).is_err());
+        assert!(policy.validate_command_env(&argv(&["PATH"])).is_err());
+        assert!(open.validate_command_env(&keys).is_ok());
+    }
+}
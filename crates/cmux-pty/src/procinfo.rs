@@ -0,0 +1,263 @@
+//! Process tree and foreground-process introspection from `/proc`
+//!
+//! A session's tree is its shell (or command) and every descendant. The
+//! foreground process group comes from `tcgetpgrp` on the PTY master: when it
+//! is the shell's own group the shell is sitting at a prompt, otherwise it
+//! names the job that owns the terminal (`vim`, `pytest`, ...).
+//!
+//! On systems without `/proc` the snapshots are simply empty.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub ppid: u32,
+    pub pgid: u32,
+    /// Executable name (`comm`)
+    pub name: String,
+    /// Full command line (empty for zombies)
+    pub cmdline: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// User + system CPU time
+    pub cpu_time_ms: u64,
+    pub rss_bytes: u64,
+    /// Member of the terminal's foreground process group
+    pub foreground: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessSnapshot {
+    /// Session process first, then descendants breadth-first
+    pub processes: Vec<ProcessInfo>,
+}
+
+impl ProcessSnapshot {
+    /// The process owning the terminal: the foreground group's leader if it's
+    /// in the tree, else the group's first member
+    pub fn foreground(&self) -> Option<&ProcessInfo> {
+        let in_foreground = || self.processes.iter().filter(|p| p.foreground);
+        in_foreground()
+            .find(|p| p.pid == p.pgid)
+            .or_else(|| in_foreground().next())
+    }
+
+    /// Same processes, command lines, cwds and foreground group, ignoring
+    /// CPU and memory (which change on every sample)
+    pub fn same_shape(&self, other: &Self) -> bool {
+        let shape = |snapshot: &Self| {
+            snapshot
+                .processes
+                .iter()
+                .map(|p| (p.pid, p.cmdline.clone(), p.cwd.clone(), p.foreground))
+                .collect::<Vec<_>>()
+        };
+        shape(self) == shape(other)
+    }
+}
+
+/// `/proc/<pid>/stat` fields needed to build trees
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProcStat {
+    name: String,
+    ppid: u32,
+    pgid: u32,
+    cpu_ticks: u64,
+    rss_pages: u64,
+}
+
+/// Parse a `/proc/<pid>/stat` line.
+fn parse_proc_stat(stat: &str) -> Option<ProcStat> {
+    // The command name is parenthesized and may contain spaces, so split after the last ')'.
+    // Field numbering follows proc(5): state is field 3, ppid 4, pgrp 5, utime 14, stime 15, rss 24.
+    let open = stat.find('(')?;
+    let close = stat.rfind(')')?;
+    let name = stat.get(open + 1..close)?.to_string();
+    let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3).and_then(|f| f.parse::<u64>().ok());
+    Some(ProcStat {
+        name,
+        ppid: field(4)? as u32,
+        pgid: field(5)? as u32,
+        cpu_ticks: field(14)? + field(15)?,
+        rss_pages: field(24)?,
+    })
+}
+
+/// One pass over `/proc`, shared by every session's snapshot
+pub struct ProcessTable {
+    proc_root: PathBuf,
+    stats: HashMap<u32, ProcStat>,
+    children: HashMap<u32, Vec<u32>>,
+    ticks_per_sec: u64,
+    page_size: u64,
+}
+
+impl ProcessTable {
+    pub fn read() -> Self {
+        // SAFETY: sysconf has no preconditions and only reads system configuration
+        let (ticks_per_sec, page_size) = unsafe {
+            (
+                libc::sysconf(libc::_SC_CLK_TCK),
+                libc::sysconf(libc::_SC_PAGESIZE),
+            )
+        };
+        Self::read_from(
+            Path::new("/proc"),
+            u64::try_from(ticks_per_sec).unwrap_or(100).max(1),
+            u64::try_from(page_size).unwrap_or(4096),
+        )
+    }
+
+    fn read_from(proc_root: &Path, ticks_per_sec: u64, page_size: u64) -> Self {
+        let mut stats = HashMap::new();
+        if let Ok(entries) = fs::read_dir(proc_root) {
+            for entry in entries.flatten() {
+                let Some(pid) = entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| name.parse::<u32>().ok())
+                else {
+                    continue;
+                };
+                // Processes can exit between listing and reading; skip them quietly
+                if let Some(stat) = fs::read_to_string(entry.path().join("stat"))
+                    .ok()
+                    .and_then(|stat| parse_proc_stat(&stat))
+                {
+                    stats.insert(pid, stat);
+                }
+            }
+        }
+
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for (&pid, stat) in &stats {
+            children.entry(stat.ppid).or_default().push(pid);
+        }
+        for pids in children.values_mut() {
+            pids.sort_unstable();
+        }
+
+        Self {
+            proc_root: proc_root.to_path_buf(),
+            stats,
+            children,
+            ticks_per_sec,
+            page_size,
+        }
+    }
+
+    /// Snapshot the tree rooted at `root_pid`
+    pub fn snapshot(&self, root_pid: u32, foreground_pgid: Option<u32>) -> ProcessSnapshot {
+        let mut processes = Vec::new();
+        let mut queue = std::collections::VecDeque::from([root_pid]);
+        while let Some(pid) = queue.pop_front() {
+            let Some(stat) = self.stats.get(&pid) else {
+                continue;
+            };
+            processes.push(self.describe(pid, stat, foreground_pgid));
+            if let Some(children) = self.children.get(&pid) {
+                queue.extend(children);
+            }
+        }
+        ProcessSnapshot { processes }
+    }
+
+    fn describe(&self, pid: u32, stat: &ProcStat, foreground_pgid: Option<u32>) -> ProcessInfo {
+        let dir = self.proc_root.join(pid.to_string());
+        let cmdline = fs::read(dir.join("cmdline"))
+            .map(|raw| {
+                raw.split(|&b| b == 0)
+                    .filter(|arg| !arg.is_empty())
+                    .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let cwd = fs::read_link(dir.join("cwd"))
+            .ok()
+            .map(|cwd| cwd.to_string_lossy().into_owned());
+
+        ProcessInfo {
+            pid,
+            ppid: stat.ppid,
+            pgid: stat.pgid,
+            name: stat.name.clone(),
+            cmdline,
+            cwd,
+            cpu_time_ms: stat.cpu_ticks * 1000 / self.ticks_per_sec,
+            rss_bytes: stat.rss_pages * self.page_size,
+            foreground: foreground_pgid == Some(stat.pgid),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_proc(root: &Path, pid: u32, stat: &str, cmdline: &str) {
+        let dir = root.join(pid.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("stat"), stat).unwrap();
+        fs::write(dir.join("cmdline"), cmdline.replace(' ', "\0")).unwrap();
+    }
+
+    #[test]
+    fn parse_proc_stat_handles_spaces_in_comm() {
+        let stat = "4242 (tmux: server) S 1 4242 4242 0 -1 4194560 1200 0 0 0 \
+                    150 50 0 0 20 0 1 0 12345 10000000 2048 18446744073709551615";
+        assert_eq!(
+            parse_proc_stat(stat),
+            Some(ProcStat {
+                name: "tmux: server".to_string(),
+                ppid: 1,
+                pgid: 4242,
+                cpu_ticks: 200,
+                rss_pages: 2048,
+            })
+        );
+        assert_eq!(parse_proc_stat("4242 (sh) S 1"), None);
+    }
+
+    #[test]
+    fn snapshot_walks_descendants_and_marks_foreground() {
+        let root = std::env::temp_dir().join(format!("cmux-pty-proc-{}", uuid::Uuid::new_v4()));
+        let stat = |pid: u32, name: &str, ppid: u32, pgid: u32| {
+            format!(
+                "{pid} ({name}) S {ppid} {pgid} 10 0 -1 0 0 0 0 0 100 100 0 0 20 0 1 0 0 0 10 0"
+            )
+        };
+        write_proc(&root, 10, &stat(10, "bash", 1, 10), "-bash");
+        write_proc(&root, 20, &stat(20, "vim", 10, 20), "vim notes.md");
+        write_proc(&root, 21, &stat(21, "rg", 20, 20), "rg TODO");
+        write_proc(&root, 30, &stat(30, "other", 1, 30), "other");
+        fs::create_dir_all(root.join("self")).unwrap();
+
+        let table = ProcessTable::read_from(&root, 100, 4096);
+        let snapshot = table.snapshot(10, Some(20));
+        let pids: Vec<u32> = snapshot.processes.iter().map(|p| p.pid).collect();
+        assert_eq!(pids, vec![10, 20, 21]);
+
+        let foreground = snapshot.foreground().unwrap();
+        assert_eq!(foreground.name, "vim");
+        assert_eq!(foreground.cmdline, vec!["vim", "notes.md"]);
+        assert_eq!(foreground.cpu_time_ms, 2000);
+        assert_eq!(foreground.rss_bytes, 40960);
+
+        // CPU and memory churn doesn't count as a change; the foreground job does
+        let mut busier = snapshot.clone();
+        busier.processes[1].cpu_time_ms += 10;
+        assert!(snapshot.same_shape(&busier));
+        assert!(!snapshot.same_shape(&table.snapshot(10, Some(10))));
+        assert!(table.snapshot(99, None).processes.is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
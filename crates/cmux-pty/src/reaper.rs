@@ -0,0 +1,170 @@
+//! Idle/lifetime limits and automatic removal of PTY sessions
+//!
+//! Each limit can be set per session, falling back to the server defaults.
+//! A live session over a limit is killed and removed; an exited session is
+//! kept (hidden from listings, but still capturable by ID) for the exited
+//! grace period and then dropped. `pty_deleted` carries a [`DeleteReason`] so
+//! UIs can say why a terminal went away.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReapPolicy {
+    /// Kill after this long with no attached clients
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detached_timeout_secs: Option<u64>,
+    /// Kill after this long without output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_secs: Option<u64>,
+    /// Kill this long after creation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_lifetime_secs: Option<u64>,
+    /// Keep an exited session (and its scrollback) this long before removing it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exited_grace_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeleteReason {
+    /// Deleted through the API
+    #[default]
+    Deleted,
+    /// The process exited (and wasn't restarted)
+    Exited,
+    DetachedTimeout,
+    IdleTimeout,
+    MaxLifetime,
+}
+
+/// How long ago things happened to a session
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionActivity {
+    pub age: Duration,
+    pub since_output: Duration,
+    /// Time since the last client detached; `None` while clients are attached
+    pub detached_for: Option<Duration>,
+    /// Time since the process exited; `None` while it's alive
+    pub exited_for: Option<Duration>,
+}
+
+impl ReapPolicy {
+    /// Fill unset limits from `defaults`
+    pub fn or(self, defaults: ReapPolicy) -> ReapPolicy {
+        ReapPolicy {
+            detached_timeout_secs: self
+                .detached_timeout_secs
+                .or(defaults.detached_timeout_secs),
+            idle_timeout_secs: self.idle_timeout_secs.or(defaults.idle_timeout_secs),
+            max_lifetime_secs: self.max_lifetime_secs.or(defaults.max_lifetime_secs),
+            exited_grace_secs: self.exited_grace_secs.or(defaults.exited_grace_secs),
+        }
+    }
+
+    /// Why the session should be removed now, if it should
+    pub fn verdict(&self, activity: &SessionActivity) -> Option<DeleteReason> {
+        let exceeded = |limit: Option<u64>, elapsed: Duration| {
+            limit.is_some_and(|secs| elapsed >= Duration::from_secs(secs))
+        };
+
+        if let Some(exited_for) = activity.exited_for {
+            // Without a grace period exited sessions are removed right away
+            return exceeded(Some(self.exited_grace_secs.unwrap_or(0)), exited_for)
+                .then_some(DeleteReason::Exited);
+        }
+        if exceeded(self.max_lifetime_secs, activity.age) {
+            return Some(DeleteReason::MaxLifetime);
+        }
+        if let Some(detached_for) = activity.detached_for {
+            if exceeded(self.detached_timeout_secs, detached_for) {
+                return Some(DeleteReason::DetachedTimeout);
+            }
+        }
+        if exceeded(self.idle_timeout_secs, activity.since_output) {
+            return Some(DeleteReason::IdleTimeout);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(n: u64) -> Duration {
+        Duration::from_secs(n)
+    }
+
+    #[test]
+    fn session_limits_override_defaults() {
+        let defaults = ReapPolicy {
+            idle_timeout_secs: Some(600),
+            exited_grace_secs: Some(300),
+            ..Default::default()
+        };
+        let session = ReapPolicy {
+            idle_timeout_secs: Some(60),
+            ..Default::default()
+        };
+        let effective = session.or(defaults);
+        assert_eq!(effective.idle_timeout_secs, Some(60));
+        assert_eq!(effective.exited_grace_secs, Some(300));
+        assert_eq!(effective.max_lifetime_secs, None);
+    }
+
+    #[test]
+    fn verdict_checks_each_limit() {
+        let policy = ReapPolicy {
+            detached_timeout_secs: Some(30),
+            idle_timeout_secs: Some(60),
+            max_lifetime_secs: Some(3600),
+            exited_grace_secs: Some(10),
+        };
+        let active = SessionActivity {
+            age: secs(100),
+            since_output: secs(5),
+            detached_for: None,
+            exited_for: None,
+        };
+        assert_eq!(policy.verdict(&active), None);
+
+        let detached = SessionActivity {
+            detached_for: Some(secs(31)),
+            ..active
+        };
+        assert_eq!(
+            policy.verdict(&detached),
+            Some(DeleteReason::DetachedTimeout)
+        );
+
+        let idle = SessionActivity {
+            since_output: secs(60),
+            ..active
+        };
+        assert_eq!(policy.verdict(&idle), Some(DeleteReason::IdleTimeout));
+
+        let old = SessionActivity {
+            age: secs(3600),
+            ..idle
+        };
+        assert_eq!(policy.verdict(&old), Some(DeleteReason::MaxLifetime));
+
+        let exited = SessionActivity {
+            exited_for: Some(secs(5)),
+            ..old
+        };
+        assert_eq!(policy.verdict(&exited), None);
+        let expired = SessionActivity {
+            exited_for: Some(secs(10)),
+            ..active
+        };
+        assert_eq!(policy.verdict(&expired), Some(DeleteReason::Exited));
+        assert_eq!(
+            ReapPolicy::default().verdict(&exited),
+            Some(DeleteReason::Exited)
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! Expect-style waiting on session output
+//!
+//! `POST /sessions/{id}/wait` blocks until the processed terminal content
+//! matches a regex, the output goes quiet, or the process exits, so scripts
+//! can drive interactive programs without sleeping and polling `capture`.
+//!
+//! By default only lines from the cursor's line at the start of the wait
+//! onwards are searched, so a prompt that was already on screen doesn't
+//! satisfy a wait for the next prompt. Set `include_existing` to search the
+//! whole buffer.
+//!
+//! Lines are tracked by absolute number (including lines already evicted from
+//! the scrollback), so the start of a wait stays put when the scrollback is
+//! full. Scrollback lines don't change, so after each check only the lines
+//! that can still change, plus a short overlap for multi-line matches, are
+//! searched again.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Json},
+};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::broadcast::error::RecvError, time::Instant};
+use tracing::info;
+
+use super::{AppState, PtySession, ServerError, ServerEvent};
+
+const DEFAULT_WAIT_TIMEOUT_MS: u64 = 30_000;
+/// Scrollback lines searched again after a check, for matches spanning into new output
+const RESCAN_SCROLLBACK_LINES: u64 = 100;
+const MAX_WAIT_TIMEOUT_MS: u64 = 600_000;
+const DEFAULT_CONTEXT_LINES: usize = 2;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WaitRequest {
+    /// Regex to look for (multi-line mode: `^`/`$` match at line boundaries)
+    pub pattern: Option<String>,
+    /// Finish once no output has arrived for this long
+    pub idle_ms: Option<u64>,
+    #[serde(default = "default_wait_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Lines of context to return around a match
+    #[serde(default = "default_context_lines")]
+    pub context_lines: usize,
+    /// Also match content that was on screen before the wait started
+    #[serde(default)]
+    pub include_existing: bool,
+}
+
+fn default_wait_timeout_ms() -> u64 {
+    DEFAULT_WAIT_TIMEOUT_MS
+}
+fn default_context_lines() -> usize {
+    DEFAULT_CONTEXT_LINES
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitReason {
+    Match,
+    Idle,
+    Exit,
+    Timeout,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WaitMatch {
+    /// The matched text
+    pub text: String,
+    /// Capture groups (group 0 excluded); `None` for groups that didn't participate
+    pub captures: Vec<Option<String>>,
+    /// Line index of the start of the match in the processed content
+    pub line: usize,
+    /// Line index of the first context line
+    pub context_start: usize,
+    pub context: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitResponse {
+    pub reason: WaitReason,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched: Option<WaitMatch>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub elapsed_ms: u64,
+}
+
+/// Processed content from an absolute line on, see `content_since`
+struct ContentSince {
+    /// Index of `lines[0]` within the processed content
+    first: usize,
+    lines: Vec<String>,
+    /// Lines evicted from the scrollback: absolute line = index + evicted
+    evicted: u64,
+    /// Absolute number of the first line not yet in the scrollback
+    scrollback_end: u64,
+}
+
+/// Copy the processed content from absolute line `from` on, holding the
+/// terminal lock only for the copy.
+fn content_since(session: &PtySession, from: u64) -> ContentSince {
+    let terminal = session.terminal.lock();
+    let evicted = terminal.lines_evicted();
+    let first = from.saturating_sub(evicted) as usize;
+    ContentSince {
+        first,
+        lines: terminal.get_lines_from(first),
+        evicted,
+        scrollback_end: evicted + terminal.scrollback_len() as u64,
+    }
+}
+
+/// Search for `regex` from line `start_line` on, returning the first match
+/// with context. `lines[0]` is line `first_line` of the content.
+fn find_match(
+    lines: &[String],
+    first_line: usize,
+    start_line: usize,
+    regex: &Regex,
+    context_lines: usize,
+) -> Option<WaitMatch> {
+    let skip = start_line.saturating_sub(first_line).min(lines.len());
+    let haystack = lines[skip..].join("\n");
+    let captures = regex.captures(&haystack)?;
+    let whole = captures.get(0)?;
+
+    let first = skip + haystack[..whole.start()].matches('\n').count();
+    let last = first + whole.as_str().matches('\n').count();
+    let context_start = first.saturating_sub(context_lines);
+    let context_end = (last + context_lines + 1).min(lines.len());
+
+    Some(WaitMatch {
+        text: whole.as_str().to_string(),
+        captures: captures
+            .iter()
+            .skip(1)
+            .map(|group| group.map(|m| m.as_str().to_string()))
+            .collect(),
+        line: first_line + first,
+        context_start: first_line + context_start,
+        context: lines[context_start..context_end].to_vec(),
+    })
+}
+
+pub async fn wait_for_output(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Json(request): Json<WaitRequest>,
+) -> Result<impl IntoResponse, ServerError> {
+    let regex = request
+        .pattern
+        .as_deref()
+        .map(|pattern| RegexBuilder::new(pattern).multi_line(true).build())
+        .transpose()
+        .map_err(|e| ServerError::InvalidRequest(format!("Invalid pattern: {}", e)))?;
+    info!(
+        "[wait:{}] pattern={:?}, idle_ms={:?}, timeout_ms={}",
+        session_id, request.pattern, request.idle_ms, request.timeout_ms
+    );
+
+    let session = state
+        .sessions
+        .read()
+        .get(&session_id)
+        .cloned()
+        .ok_or_else(|| ServerError::SessionNotFound(session_id.clone()))?;
+
+    // Subscribe before inspecting the content so no output is missed in between
+    let mut output_rx = session.output_tx.subscribe();
+    let mut search_from = if request.include_existing {
+        0
+    } else {
+        session.cursor_line()
+    };
+
+    let started = Instant::now();
+    let deadline = started + Duration::from_millis(request.timeout_ms.min(MAX_WAIT_TIMEOUT_MS));
+    let idle = request.idle_ms.map(Duration::from_millis);
+    let mut last_output = started;
+
+    let respond = |reason, matched, exit_code| {
+        Ok(Json(WaitResponse {
+            reason,
+            matched,
+            exit_code,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        }))
+    };
+
+    if !session.is_listed() {
+        return respond(WaitReason::Exit, None, None);
+    }
+
+    loop {
+        if let Some(regex) = &regex {
+            let context = request.context_lines as u64;
+            let content = content_since(&session, search_from.saturating_sub(context));
+            let start_line = search_from.saturating_sub(content.evicted) as usize;
+            if let Some(matched) = find_match(
+                &content.lines,
+                content.first,
+                start_line,
+                regex,
+                request.context_lines,
+            ) {
+                return respond(WaitReason::Match, Some(matched), None);
+            }
+            search_from = search_from.max(
+                content
+                    .scrollback_end
+                    .saturating_sub(RESCAN_SCROLLBACK_LINES),
+            );
+        }
+
+        let idle_deadline = idle.map(|idle| last_output + idle);
+        tokio::select! {
+            received = output_rx.recv() => match received {
+                // Control messages (exit) are prefixed with \x00
+                Ok(chunk) if chunk.is_control() => {
+                    let exit_code = serde_json::from_str::<ServerEvent>(&chunk.data[1..])
+                        .ok()
+                        .and_then(|event| match event {
+                            ServerEvent::Exit { exit_code } => exit_code,
+                            _ => None,
+                        });
+                    return respond(WaitReason::Exit, None, exit_code);
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => {
+                    last_output = Instant::now();
+                }
+                Err(RecvError::Closed) => return respond(WaitReason::Exit, None, None),
+            },
+            _ = tokio::time::sleep_until(idle_deadline.unwrap_or(deadline)), if idle_deadline.is_some() => {
+                return respond(WaitReason::Idle, None, None);
+            }
+            _ = tokio::time::sleep_until(deadline) => {
+                return respond(WaitReason::Timeout, None, None);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn find_match_skips_lines_before_start_and_returns_context() {
+        let content = lines("$ make\nbuilding\nerror: old\n$ make\nbuilding\nerror: code 2\n$");
+        let regex = RegexBuilder::new(r"^error: (\w+) ?(\d+)?$")
+            .multi_line(true)
+            .build()
+            .unwrap();
+
+        let all = find_match(&content, 0, 0, &regex, 1).unwrap();
+        assert_eq!(all.line, 2);
+        assert_eq!(all.captures, vec![Some("old".to_string()), None]);
+
+        let new = find_match(&content, 0, 3, &regex, 1).unwrap();
+        assert_eq!(new.text, "error: code 2");
+        assert_eq!(new.line, 5);
+        assert_eq!(new.context_start, 4);
+        assert_eq!(new.context, lines("building\nerror: code 2\n$"));
+
+        // Only part of the content was copied: line numbers stay the same
+        let tail = find_match(&content[4..], 4, 5, &regex, 1).unwrap();
+        assert_eq!(tail, new);
+
+        assert!(find_match(&content, 0, 99, &regex, 1).is_none());
+    }
+
+    #[test]
+    fn find_match_reports_multi_line_matches() {
+        let content = lines("a\nstart\nmiddle\nend\nb");
+        let regex = Regex::new(r"start\n.*\nend").unwrap();
+        let matched = find_match(&content, 0, 0, &regex, 0).unwrap();
+        assert_eq!(matched.line, 1);
+        assert_eq!(matched.context, lines("start\nmiddle\nend"));
+    }
+}
@@ -45,6 +45,12 @@ pub struct Grid {
     pub changed_lines: HashSet<usize>,
     /// Flag to indicate full redraw is needed.
     pub needs_full_redraw: bool,
+    /// Also collect rows in `scrolled_off` as they enter the scrollback.
+    pub capture_scrolled_off: bool,
+    /// Rows that entered the scrollback since they were last taken.
+    pub scrolled_off: Vec<Row>,
+    /// Rows dropped from the front of a full scrollback so far.
+    pub lines_evicted: u64,
 }
 
 impl Grid {
@@ -67,6 +73,9 @@ impl Grid {
             right_margin: cols.saturating_sub(1),
             changed_lines: HashSet::new(),
             needs_full_redraw: true,
+            capture_scrolled_off: false,
+            scrolled_off: Vec::new(),
+            lines_evicted: 0,
         }
     }
 
@@ -177,6 +186,14 @@ impl Grid {
         (self.cursor_row, self.cursor_col)
     }
 
+    /// Mark the cursor's row as the start of a logical line, or as the
+    /// continuation of an auto-wrapped one.
+    pub fn set_cursor_row_canonical(&mut self, canonical: bool) {
+        if let Some(row) = self.viewport.get_mut(self.cursor_row) {
+            row.is_canonical = canonical;
+        }
+    }
+
     /// Move to a new line, scrolling if necessary.
     pub fn newline(&mut self) {
         let (_top, bottom) = self.scroll_region;
@@ -295,6 +312,10 @@ impl Grid {
     fn push_to_scrollback(&mut self, line: Row) {
         if self.lines_above.len() >= MAX_SCROLLBACK_LINES {
             self.lines_above.pop_front();
+            self.lines_evicted += 1;
+        }
+        if self.capture_scrolled_off {
+            self.scrolled_off.push(line.clone());
         }
         self.lines_above.push_back(line);
     }
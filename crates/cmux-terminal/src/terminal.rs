@@ -6,7 +6,7 @@
 use ratatui::style::{Color, Modifier, Style};
 use vte::{Params, Parser, Perform};
 
-use crate::character::{CharacterStyles, Row, TerminalCharacter};
+use crate::character::{CharacterStyles, Row, SharedStyles, TerminalCharacter};
 use crate::grid::Grid;
 
 /// Default foreground color for OSC 10 queries when no color is set.
@@ -1028,23 +1028,73 @@ impl VirtualTerminal {
             .collect()
     }
 
+    /// Collect lines as they scroll off the top of the main screen, for
+    /// `drain_scrolled_lines`. Lines scrolled on the alternate screen are not
+    /// collected.
+    pub fn set_line_capture(&mut self, enabled: bool) {
+        let mut grids = vec![&mut self.internal_grid];
+        if let Some(saved) = self.alternate_screen.as_mut() {
+            grids.push(&mut saved.grid);
+        }
+        for grid in grids {
+            grid.capture_scrolled_off = enabled;
+            if !enabled {
+                grid.scrolled_off.clear();
+            }
+        }
+    }
+
+    /// Take the lines that scrolled off since the last call as plain text,
+    /// joining wrapped rows into their logical line.
+    pub fn drain_scrolled_lines(&mut self) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        for row in std::mem::take(&mut self.internal_grid.scrolled_off) {
+            let text: String = row
+                .iter()
+                .filter(|c| !c.wide_spacer)
+                .map(|c| c.character)
+                .collect();
+            match lines.last_mut() {
+                Some(line) if !row.is_canonical => line.push_str(&text),
+                _ => lines.push(text),
+            }
+        }
+        for line in &mut lines {
+            line.truncate(line.trim_end().len());
+        }
+        lines
+    }
+
     /// Get all content including scrollback as plain text lines.
     /// Scrollback lines come first, then viewport lines.
     pub fn get_lines(&self) -> Vec<String> {
+        self.get_lines_from(0)
+    }
+
+    /// Like `get_lines`, but only the lines from index `start` on.
+    pub fn get_lines_from(&self, start: usize) -> Vec<String> {
         let mut lines = Vec::new();
 
         // Add scrollback
-        for row in self.scrollback_snapshot() {
-            let line: String = row.iter().map(|cell| cell.c).collect();
+        for row in self.internal_grid.lines_above.iter().skip(start) {
+            let line: String = row.columns.iter().map(|tc| Cell::from(tc).c).collect();
             lines.push(line.trim_end().to_string());
         }
 
         // Add viewport
-        lines.extend(self.viewport_lines());
+        let viewport_start = start.saturating_sub(self.scrollback_len());
+        lines.extend(self.viewport_lines().into_iter().skip(viewport_start));
 
         lines
     }
 
+    /// Lines dropped from the front of the scrollback so far. Adding this to
+    /// an index into `get_lines` gives a line number that doesn't shift once
+    /// the scrollback is full.
+    pub fn lines_evicted(&self) -> u64 {
+        self.internal_grid.lines_evicted
+    }
+
     /// Scroll the screen up by one line within the scroll region
     fn scroll_up(&mut self) {
         self.internal_grid.scroll_up_in_region(1);
@@ -1072,6 +1122,7 @@ impl VirtualTerminal {
             self.pending_wrap = false;
             self.internal_grid.cursor_col = 0;
             self.newline();
+            self.internal_grid.set_cursor_row_canonical(false);
         }
 
         // Apply line drawing character set if active
@@ -1361,7 +1412,11 @@ impl VirtualTerminal {
 
     /// Generate SGR parameter string for current attributes
     fn get_sgr_string(&self) -> String {
-        let styles = &self.internal_grid.current_styles;
+        self.sgr_params(&self.internal_grid.current_styles)
+    }
+
+    /// Generate SGR parameter string for the given attributes
+    fn sgr_params(&self, styles: &CharacterStyles) -> String {
         let mut params = vec!["0".to_string()]; // Always start with reset
 
         if styles.modifiers.contains(Modifier::BOLD) {
@@ -1443,6 +1498,59 @@ impl VirtualTerminal {
         }
     }
 
+    /// Render the visible screen as escape sequences that redraw it (contents,
+    /// colors, cursor) on a terminal of the same size. Used to resynchronize
+    /// clients that missed output.
+    pub fn render_screen(&self) -> String {
+        let mut out = String::new();
+        // Switch screens first: entering or leaving the alternate screen clears it
+        out.push_str(if self.alternate_screen.is_some() {
+            "\x1b[?1049h"
+        } else {
+            "\x1b[?1049l"
+        });
+        out.push_str("\x1b[0m\x1b[H\x1b[2J");
+
+        for (row_index, row) in self.internal_grid.viewport.iter().enumerate() {
+            if row_index > 0 {
+                out.push_str("\r\n");
+            }
+            // Trailing default blanks are already there after the clear
+            let end = row
+                .columns
+                .iter()
+                .rposition(|c| c.character != ' ' || !c.styles.is_default())
+                .map_or(0, |last| last + 1);
+            let mut current = &SharedStyles::Default;
+            for cell in row.columns.iter().take(end) {
+                if cell.wide_spacer {
+                    continue;
+                }
+                if cell.styles != *current {
+                    out.push_str(&format!("\x1b[{}m", self.sgr_params(cell.styles.get())));
+                    current = &cell.styles;
+                }
+                out.push(cell.character);
+            }
+            if !current.is_default() {
+                out.push_str("\x1b[0m");
+            }
+        }
+
+        out.push_str(&format!(
+            "\x1b[{};{}H\x1b[{}m",
+            self.cursor_row() + 1,
+            self.cursor_col() + 1,
+            self.get_sgr_string()
+        ));
+        out.push_str(if self.cursor_visible {
+            "\x1b[?25h"
+        } else {
+            "\x1b[?25l"
+        });
+        out
+    }
+
     /// Get visible lines for rendering (including scrollback)
     pub fn visible_lines(&self, height: usize, scroll_offset: usize) -> Vec<&Row> {
         self.internal_grid
@@ -1654,6 +1762,7 @@ impl Perform for VirtualTerminal {
             0x0A..=0x0C => {
                 self.newline();
                 self.carriage_return();
+                self.internal_grid.set_cursor_row_canonical(true);
             }
             // Carriage return
             0x0D => {
@@ -2872,6 +2981,54 @@ mod tests {
         assert_eq!(cell.style.fg, Some(Color::Red));
     }
 
+    #[test]
+    fn render_screen_reproduces_contents_and_cursor() {
+        let mut term = VirtualTerminal::new(6, 20);
+        term.process(
+            b"plain\r\n\x1b[1;32mgreen\x1b[0m \x1b[44mbg\x1b[0m\r\n\xe4\xb8\xad\xe6\x96\x87",
+        );
+        term.process(b"\x1b[5;3H\x1b[4m");
+
+        let mut copy = VirtualTerminal::new(6, 20);
+        copy.process(b"stale text that should be cleared");
+        copy.process(term.render_screen().as_bytes());
+
+        assert_eq!(copy.viewport_lines(), term.viewport_lines());
+        assert_eq!((copy.cursor_row(), copy.cursor_col()), (4, 2));
+        for (row, col) in [(1, 0), (1, 6), (2, 0)] {
+            assert_eq!(copy.get_cell(row, col).style, term.get_cell(row, col).style);
+        }
+        assert_eq!(copy.current_style(), term.current_style());
+    }
+
+    #[test]
+    fn drain_scrolled_lines_joins_wrapped_rows() {
+        let mut term = VirtualTerminal::new(3, 10);
+        term.process(b"\x1b[2J\x1b[H");
+        term.set_line_capture(true);
+        term.process(b"one\r\n0123456789abcdef\r\nthree\r\nfour\r\n");
+        assert_eq!(term.drain_scrolled_lines(), vec!["one", "0123456789abcdef"]);
+        assert!(term.drain_scrolled_lines().is_empty());
+
+        // Full-screen apps scrolling on the alternate screen aren't captured
+        term.process(b"\x1b[?1049hx\r\ny\r\nz\r\nw\r\n\x1b[?1049l");
+        assert!(term.drain_scrolled_lines().is_empty());
+    }
+
+    #[test]
+    fn lines_evicted_counts_rows_dropped_from_full_scrollback() {
+        let mut term = VirtualTerminal::new(2, 20);
+        for i in 0..10_005 {
+            term.process(format!("line {}\r\n", i).as_bytes());
+        }
+        assert_eq!(term.scrollback_len(), 10_000);
+        assert_eq!(term.lines_evicted(), 4);
+        let all = term.get_lines();
+        assert_eq!(all[0], "line 4");
+        assert_eq!(term.get_lines_from(9_999), all[9_999..].to_vec());
+        assert_eq!(term.get_lines_from(10_001), vec![""]);
+    }
+
     #[test]
     fn virtual_terminal_resize() {
         let mut term = VirtualTerminal::new(24, 80);
@@ -16,6 +16,8 @@ use crate::acp_client::ui::ui;
 use crate::terminal_guard;
 
 pub async fn run_demo_tui() -> Result<()> {
+    crate::theme::init(crate::settings::Settings::load().theme_name(), None);
+
     let mut stdout = std::io::stdout();
     execute!(
         stdout,
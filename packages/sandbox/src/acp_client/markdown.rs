@@ -88,7 +88,7 @@ pub(crate) fn markdown_to_lines(source: &str) -> Vec<Line<'static>> {
             }
             MdEvent::Code(code) => {
                 let code_style = ratatui::style::Style::default()
-                    .fg(ratatui::style::Color::Yellow)
+                    .fg(crate::theme::current().ui.warning)
                     .add_modifier(ratatui::style::Modifier::BOLD);
                 current_spans.push(Span::styled(format!("`{}`", code), code_style));
             }
@@ -106,7 +106,7 @@ pub(crate) fn markdown_to_lines(source: &str) -> Vec<Line<'static>> {
             MdEvent::Start(Tag::Heading { level, .. }) => {
                 let prefix = "#".repeat(level as usize);
                 let header_style = ratatui::style::Style::default()
-                    .fg(ratatui::style::Color::Cyan)
+                    .fg(crate::theme::current().ui.accent)
                     .add_modifier(ratatui::style::Modifier::BOLD);
                 current_spans.push(Span::styled(format!("{} ", prefix), header_style));
             }
@@ -84,6 +84,8 @@ pub async fn run_chat_tui_with_workspace_status(
     provider: AcpProvider,
     workspace_status_rx: Option<mpsc::UnboundedReceiver<WorkspaceSyncStatus>>,
 ) -> Result<()> {
+    crate::theme::init(crate::settings::Settings::load().theme_name(), None);
+
     let mut stdout = std::io::stdout();
     execute!(
         stdout,
@@ -370,6 +372,9 @@ async fn run_app<B: ratatui::backend::Backend>(
                                                 PaletteCommand::SwitchProviderModel => {
                                                     app.open_switch_palette();
                                                 }
+                                                PaletteCommand::CycleTheme => {
+                                                    app.cycle_theme();
+                                                }
                                             }
                                         }
                                     }
@@ -51,6 +51,7 @@ pub(crate) enum UiMode {
 pub(crate) enum PaletteCommand {
     ToggleDebugMode,
     SwitchProviderModel,
+    CycleTheme,
 }
 
 impl PaletteCommand {
@@ -58,6 +59,7 @@ impl PaletteCommand {
         &[
             PaletteCommand::ToggleDebugMode,
             PaletteCommand::SwitchProviderModel,
+            PaletteCommand::CycleTheme,
         ]
     }
 
@@ -65,6 +67,7 @@ impl PaletteCommand {
         match self {
             PaletteCommand::ToggleDebugMode => "Toggle Debug Mode",
             PaletteCommand::SwitchProviderModel => "Switch Provider / Model",
+            PaletteCommand::CycleTheme => "Next Theme",
         }
     }
 
@@ -72,6 +75,7 @@ impl PaletteCommand {
         match self {
             PaletteCommand::ToggleDebugMode => "Show/hide raw ACP protocol messages",
             PaletteCommand::SwitchProviderModel => "Change AI provider or model",
+            PaletteCommand::CycleTheme => "Switch to the next color theme",
         }
     }
 
@@ -155,7 +159,9 @@ impl<'a> App<'a> {
         textarea.set_block(
             Block::default()
                 .borders(Borders::TOP | Borders::BOTTOM)
-                .border_style(ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray)),
+                .border_style(
+                    ratatui::style::Style::default().fg(crate::theme::current().borders.inactive),
+                ),
         );
         textarea
             .set_placeholder_text("Type a message and press Enter to send. Ctrl+J for new line.");
@@ -505,6 +511,20 @@ impl<'a> App<'a> {
         }
     }
 
+    /// Switch to the next color theme and save it as the default.
+    pub(crate) fn cycle_theme(&mut self) {
+        let mut settings = crate::settings::Settings::load();
+        let next = crate::theme::next_theme_name(settings.theme_name());
+        if let Err(e) = crate::theme::apply_theme(&next) {
+            tracing::warn!("Failed to load theme '{}': {}", next, e);
+            return;
+        }
+        settings.theme = (next != crate::theme::AUTO_THEME).then_some(next);
+        if let Err(e) = settings.save() {
+            tracing::warn!("Failed to save theme setting: {}", e);
+        }
+    }
+
     pub(crate) fn scroll_up(&mut self, lines: u16) {
         self.scroll_offset_from_bottom = self.scroll_offset_from_bottom.saturating_add(lines);
     }
@@ -668,7 +688,9 @@ impl<'a> App<'a> {
         self.textarea.set_block(
             Block::default()
                 .borders(Borders::TOP | Borders::BOTTOM)
-                .border_style(ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray)),
+                .border_style(
+                    ratatui::style::Style::default().fg(crate::theme::current().borders.inactive),
+                ),
         );
         self.textarea
             .set_placeholder_text("Type a message and press Enter to send. Ctrl+J for new line.");
@@ -3,33 +3,16 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph},
 };
-use std::sync::LazyLock;
 use tui_textarea::TextArea;
 
 use crate::acp_client::markdown::markdown_to_lines;
 use crate::acp_client::state::{
     App, ChatEntry, ConnectionState, PaletteCommand, SwitchPaletteItem, UiMode, WorkspaceSyncState,
 };
-
-/// Detect if terminal is in dark mode (cached at startup)
-static IS_DARK_MODE: LazyLock<bool> = LazyLock::new(|| {
-    match terminal_light::luma() {
-        Ok(luma) if luma > 0.5 => false, // Light background
-        Ok(_) => true,                   // Dark background
-        Err(_) => true,                  // Default to dark mode if detection fails
-    }
-});
-
-/// Get background color for user messages based on terminal mode
-fn user_message_bg_color() -> ratatui::style::Color {
-    if *IS_DARK_MODE {
-        ratatui::style::Color::Rgb(60, 60, 60) // Lighter gray for dark mode
-    } else {
-        ratatui::style::Color::Rgb(230, 230, 230) // Darker gray for light mode
-    }
-}
+use crate::theme;
 
 pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
+    let theme = theme::current();
     let line_count = app.textarea.lines().len() as u16;
     let input_height = (line_count + 2).clamp(3, 12);
     let status_height = 1u16;
@@ -110,7 +93,7 @@ pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
             .map(|s| {
                 Line::styled(
                     s.clone(),
-                    ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray),
+                    ratatui::style::Style::default().fg(theme.ui.muted),
                 )
             })
             .collect();
@@ -119,11 +102,11 @@ pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
             .title(" Debug (ACP Messages) ")
             .title_style(
                 ratatui::style::Style::default()
-                    .fg(ratatui::style::Color::Yellow)
+                    .fg(theme.ui.warning)
                     .add_modifier(ratatui::style::Modifier::BOLD),
             )
             .borders(Borders::ALL)
-            .border_style(ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray));
+            .border_style(ratatui::style::Style::default().fg(theme.borders.inactive));
 
         let debug_paragraph = Paragraph::new(debug_lines).block(debug_block);
         f.render_widget(debug_paragraph, debug_area);
@@ -132,16 +115,16 @@ pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
     f.render_widget(&app.textarea, input_area);
 
     let provider_style = ratatui::style::Style::default()
-        .fg(ratatui::style::Color::Cyan)
+        .fg(theme.ui.accent)
         .add_modifier(ratatui::style::Modifier::BOLD);
-    let hint_style = ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray);
+    let hint_style = ratatui::style::Style::default().fg(theme.ui.muted);
     let connecting_style = ratatui::style::Style::default()
-        .fg(ratatui::style::Color::Yellow)
+        .fg(theme.ui.warning)
         .add_modifier(ratatui::style::Modifier::BOLD);
     let error_style = ratatui::style::Style::default()
-        .fg(ratatui::style::Color::Red)
+        .fg(theme.ui.error)
         .add_modifier(ratatui::style::Modifier::BOLD);
-    let debug_indicator_style = ratatui::style::Style::default().fg(ratatui::style::Color::Yellow);
+    let debug_indicator_style = ratatui::style::Style::default().fg(theme.ui.warning);
 
     let mut status_spans = vec![Span::styled(
         app.current_provider.display_name(),
@@ -149,7 +132,7 @@ pub(crate) fn ui(f: &mut ratatui::Frame, app: &mut App) {
     )];
 
     if let Some(model_name) = app.current_model_name() {
-        let model_style = ratatui::style::Style::default().fg(ratatui::style::Color::Magenta);
+        let model_style = ratatui::style::Style::default().fg(theme.chat.model);
         status_spans.push(Span::styled(" / ", hint_style));
         status_spans.push(Span::styled(model_name.to_string(), model_style));
     }
@@ -264,6 +247,7 @@ fn render_searchable_palette(
     selection: usize,
     items: Vec<PaletteItem>,
 ) {
+    let theme = theme::current();
     use ratatui::widgets::Clear;
 
     let area = f.area();
@@ -296,16 +280,16 @@ fn render_searchable_palette(
         .title(title)
         .title_style(
             ratatui::style::Style::default()
-                .fg(ratatui::style::Color::Cyan)
+                .fg(theme.ui.accent)
                 .add_modifier(ratatui::style::Modifier::BOLD),
         )
         .borders(Borders::ALL)
-        .border_style(ratatui::style::Style::default().fg(ratatui::style::Color::Cyan));
+        .border_style(ratatui::style::Style::default().fg(theme.borders.focused));
     f.render_widget(palette_block, palette_area);
 
     let search_prefix = Paragraph::new(Line::from(Span::styled(
         ">",
-        ratatui::style::Style::default().fg(ratatui::style::Color::Cyan),
+        ratatui::style::Style::default().fg(theme.ui.accent),
     )));
     let prefix_area = ratatui::layout::Rect::new(search_area.x, search_area.y, 2, 1);
     f.render_widget(search_prefix, prefix_area);
@@ -324,7 +308,7 @@ fn render_searchable_palette(
                 palette_lines.push(Line::styled(
                     format!("─ {} ─", text),
                     ratatui::style::Style::default()
-                        .fg(ratatui::style::Color::DarkGray)
+                        .fg(theme.ui.muted)
                         .add_modifier(ratatui::style::Modifier::BOLD),
                 ));
             }
@@ -332,7 +316,7 @@ fn render_searchable_palette(
                 palette_lines.push(Line::styled(
                     "    Loading...",
                     ratatui::style::Style::default()
-                        .fg(ratatui::style::Color::Yellow)
+                        .fg(theme.ui.warning)
                         .add_modifier(ratatui::style::Modifier::ITALIC),
                 ));
             }
@@ -351,10 +335,10 @@ fn render_searchable_palette(
 
                 let style = if is_selected {
                     ratatui::style::Style::default()
-                        .fg(ratatui::style::Color::Cyan)
+                        .fg(theme.ui.accent)
                         .add_modifier(ratatui::style::Modifier::BOLD)
                 } else if *is_current {
-                    ratatui::style::Style::default().fg(ratatui::style::Color::Green)
+                    ratatui::style::Style::default().fg(theme.ui.success)
                 } else {
                     ratatui::style::Style::default()
                 };
@@ -367,7 +351,7 @@ fn render_searchable_palette(
                 if let Some(desc) = description {
                     spans.push(Span::styled(
                         format!("  {}", desc),
-                        ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray),
+                        ratatui::style::Style::default().fg(theme.ui.muted),
                     ));
                 }
 
@@ -380,7 +364,7 @@ fn render_searchable_palette(
     if items.is_empty() {
         palette_lines.push(Line::styled(
             "  No matches",
-            ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray),
+            ratatui::style::Style::default().fg(theme.ui.muted),
         ));
     }
 
@@ -405,15 +389,15 @@ fn render_searchable_palette(
     f.render_widget(items_paragraph, items_area);
 
     if scroll_offset > 0 {
-        let up_indicator = Paragraph::new("▲")
-            .style(ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray));
+        let up_indicator =
+            Paragraph::new("▲").style(ratatui::style::Style::default().fg(theme.ui.muted));
         let up_area =
             ratatui::layout::Rect::new(items_area.x + items_area.width - 1, items_area.y, 1, 1);
         f.render_widget(up_indicator, up_area);
     }
     if needs_scroll_down {
-        let down_indicator = Paragraph::new("▼")
-            .style(ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray));
+        let down_indicator =
+            Paragraph::new("▼").style(ratatui::style::Style::default().fg(theme.ui.muted));
         let down_area = ratatui::layout::Rect::new(
             items_area.x + items_area.width - 1,
             items_area.y + items_area.height - 1,
@@ -427,7 +411,7 @@ fn render_searchable_palette(
     let help_area = ratatui::layout::Rect::new(inner_area.x, help_y, inner_area.width, 1);
     let help_text = Paragraph::new(Line::styled(
         "↑↓: navigate │ Enter: select │ Esc: cancel",
-        ratatui::style::Style::default().fg(ratatui::style::Color::DarkGray),
+        ratatui::style::Style::default().fg(theme.ui.muted),
     ));
     f.render_widget(help_text, help_area);
 }
@@ -439,9 +423,10 @@ fn render_message<'a>(
     normalized_markdown: Option<&'a str>,
     area_width: usize,
 ) {
+    let theme = theme::current();
     match role {
         "User" => {
-            let bg_style = ratatui::style::Style::default().bg(user_message_bg_color());
+            let bg_style = ratatui::style::Style::default().bg(theme.chat.user_message_bg);
             lines.push(Line::styled(" ".repeat(area_width), bg_style));
             for line in text.lines() {
                 let padded = format!("{:width$}", line, width = area_width);
@@ -467,9 +452,9 @@ fn render_message<'a>(
         "Error" => {
             lines.push(Line::raw(""));
             let prefix_style = ratatui::style::Style::default()
-                .fg(ratatui::style::Color::Red)
+                .fg(theme.ui.error)
                 .add_modifier(ratatui::style::Modifier::BOLD);
-            let text_style = ratatui::style::Style::default().fg(ratatui::style::Color::Red);
+            let text_style = ratatui::style::Style::default().fg(theme.ui.error);
             let prefix = "Error: ";
             let mut first = true;
             for text_line in text.lines() {
@@ -494,9 +479,9 @@ fn render_message<'a>(
         "System" => {
             lines.push(Line::raw(""));
             let prefix_style = ratatui::style::Style::default()
-                .fg(ratatui::style::Color::Yellow)
+                .fg(theme.ui.warning)
                 .add_modifier(ratatui::style::Modifier::BOLD);
-            let text_style = ratatui::style::Style::default().fg(ratatui::style::Color::Yellow);
+            let text_style = ratatui::style::Style::default().fg(theme.ui.warning);
             let prefix = "System: ";
             let mut first = true;
             for text_line in text.lines() {
@@ -549,6 +534,7 @@ fn render_tool_call<'a>(
     kind: &agent_client_protocol::ToolKind,
     status: &agent_client_protocol::ToolCallStatus,
 ) {
+    let theme = theme::current();
     let icon = match kind {
         agent_client_protocol::ToolKind::Read => "📖",
         agent_client_protocol::ToolKind::Edit => "✏️",
@@ -563,13 +549,13 @@ fn render_tool_call<'a>(
     };
 
     let status_indicator = match status {
-        agent_client_protocol::ToolCallStatus::Pending => ("⏳", ratatui::style::Color::Yellow),
-        agent_client_protocol::ToolCallStatus::InProgress => ("⚙️", ratatui::style::Color::Cyan),
-        agent_client_protocol::ToolCallStatus::Completed => ("✓", ratatui::style::Color::Green),
-        agent_client_protocol::ToolCallStatus::Failed => ("✗", ratatui::style::Color::Red),
+        agent_client_protocol::ToolCallStatus::Pending => ("⏳", theme.ui.warning),
+        agent_client_protocol::ToolCallStatus::InProgress => ("⚙️", theme.ui.accent),
+        agent_client_protocol::ToolCallStatus::Completed => ("✓", theme.ui.success),
+        agent_client_protocol::ToolCallStatus::Failed => ("✗", theme.ui.error),
     };
 
-    let tool_style = ratatui::style::Style::default().fg(ratatui::style::Color::Cyan);
+    let tool_style = ratatui::style::Style::default().fg(theme.ui.accent);
     let status_style = ratatui::style::Style::default().fg(status_indicator.1);
 
     lines.push(Line::from(vec![
@@ -581,22 +567,17 @@ fn render_tool_call<'a>(
 }
 
 fn render_plan<'a>(lines: &mut Vec<Line<'a>>, plan: &agent_client_protocol::Plan) {
+    let theme = theme::current();
     let header_style = ratatui::style::Style::default()
-        .fg(ratatui::style::Color::Magenta)
+        .fg(theme.ui.highlight)
         .add_modifier(ratatui::style::Modifier::BOLD);
     lines.push(Line::styled("📋 Plan", header_style));
 
     for entry in &plan.entries {
         let (status_icon, status_color) = match entry.status {
-            agent_client_protocol::PlanEntryStatus::Pending => {
-                ("○", ratatui::style::Color::DarkGray)
-            }
-            agent_client_protocol::PlanEntryStatus::InProgress => {
-                ("◐", ratatui::style::Color::Yellow)
-            }
-            agent_client_protocol::PlanEntryStatus::Completed => {
-                ("●", ratatui::style::Color::Green)
-            }
+            agent_client_protocol::PlanEntryStatus::Pending => ("○", theme.ui.muted),
+            agent_client_protocol::PlanEntryStatus::InProgress => ("◐", theme.ui.warning),
+            agent_client_protocol::PlanEntryStatus::Completed => ("●", theme.ui.success),
         };
 
         let status_style = ratatui::style::Style::default().fg(status_color);
@@ -1,8 +1,9 @@
 use crate::errors::{ErrorBody, SandboxError, SandboxResult};
 use crate::models::{
-    AwaitReadyRequest, AwaitReadyResponse, CreateSandboxRequest, ExecRequest, ExecResponse,
-    HealthResponse, HostEvent, NotificationLevel, NotificationLogEntry, NotificationRequest,
-    OpenUrlRequest, PruneRequest, PruneResponse, PrunedItem, SandboxSummary, ServiceReadiness,
+    AwaitReadyRequest, AwaitReadyResponse, CreateSandboxRequest, EnvPatchRequest, EnvVar,
+    ExecRequest, ExecResponse, HealthResponse, HostEvent, NotificationLevel, NotificationLogEntry,
+    NotificationRequest, OpenUrlRequest, PruneRequest, PruneResponse, PrunedItem, SandboxStats,
+    SandboxSummary, ServiceReadiness,
 };
 use crate::notifications::NotificationStore;
 use crate::service::{AppState, GhResponseRegistry, HostEventSender, SandboxService};
@@ -14,7 +15,7 @@ use axum::http::header::HOST;
 use axum::http::HeaderMap;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
-use axum::routing::{any, get, post};
+use axum::routing::{any, get, patch, post};
 use axum::{Json, Router};
 use serde::Deserialize;
 use std::net::SocketAddr;
@@ -47,7 +48,9 @@ fn default_tty() -> bool {
         create_sandbox,
         list_sandboxes,
         get_sandbox,
+        sandbox_stats,
         exec_sandbox,
+        patch_sandbox_env,
         delete_sandbox,
         health,
         upload_files,
@@ -61,7 +64,10 @@ fn default_tty() -> bool {
         CreateSandboxRequest,
         ExecRequest,
         ExecResponse,
+        EnvPatchRequest,
+        EnvVar,
         SandboxSummary,
+        SandboxStats,
         crate::models::SandboxNetwork,
         crate::models::SandboxStatus,
         HealthResponse,
@@ -103,7 +109,9 @@ pub fn build_router(
         .route("/healthz", get(health))
         .route("/sandboxes", get(list_sandboxes).post(create_sandbox))
         .route("/sandboxes/{id}", get(get_sandbox).delete(delete_sandbox))
+        .route("/sandboxes/{id}/stats", get(sandbox_stats))
         .route("/sandboxes/{id}/exec", post(exec_sandbox))
+        .route("/sandboxes/{id}/env", patch(patch_sandbox_env))
         .route(
             "/sandboxes/{id}/files",
             post(upload_files).layer(DefaultBodyLimit::disable()),
@@ -211,6 +219,25 @@ async fn get_sandbox(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/sandboxes/{id}/stats",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)")
+    ),
+    responses(
+        (status = 200, description = "Current resource usage", body = SandboxStats),
+        (status = 404, description = "Sandbox not found", body = ErrorBody)
+    )
+)]
+async fn sandbox_stats(
+    state: axum::extract::State<AppState>,
+    Path(id): Path<String>,
+) -> SandboxResult<Json<SandboxStats>> {
+    let stats = state.service.stats(id).await?;
+    Ok(Json(stats))
+}
+
 #[utoipa::path(
     post,
     path = "/sandboxes/{id}/exec",
@@ -232,6 +259,145 @@ async fn exec_sandbox(
     Ok(Json(response))
 }
 
+#[utoipa::path(
+    patch,
+    path = "/sandboxes/{id}/env",
+    params(
+        ("id" = String, Path, description = "Sandbox identifier (UUID or short ID)")
+    ),
+    request_body = EnvPatchRequest,
+    responses(
+        (status = 204, description = "Environment updated"),
+        (status = 400, description = "Bad request", body = ErrorBody),
+        (status = 404, description = "Sandbox not found", body = ErrorBody),
+        (status = 502, description = "envctl failed inside the sandbox", body = ErrorBody)
+    )
+)]
+async fn patch_sandbox_env(
+    state: axum::extract::State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<EnvPatchRequest>,
+) -> SandboxResult<StatusCode> {
+    let exec = env_patch_exec(&request)?;
+    let response = state.service.exec(id, exec).await?;
+    if response.exit_code != 0 {
+        return Err(SandboxError::CommandFailed {
+            command: "envctl".into(),
+            message: response.stderr.trim().to_string(),
+        });
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Where sandbox shells look for envd (the image exports it from .bashrc).
+/// Exec'd commands don't read .bashrc, so envctl would otherwise fall back to
+/// /tmp and talk to (or autostart) a different daemon.
+const SANDBOX_RUNTIME_DIR: &str = "/run/user/0";
+
+/// Applies an `EnvPatchRequest` with envctl inside the sandbox. Values are
+/// passed through the environment rather than argv so they stay out of `ps`.
+const ENV_PATCH_SCRIPT: &str = r#"set -e
+set --
+if [ -n "$CMUX_ENV_DIR" ]; then set -- --dir "$CMUX_ENV_DIR"; fi
+if [ -n "$CMUX_ENV_LOAD" ]; then
+  printf '%s' "$CMUX_ENV_LOAD" | envctl load --base64 $CMUX_ENV_SECRET "$@" -
+fi
+if [ -n "$CMUX_ENV_SET" ]; then
+  printf '%s' "$CMUX_ENV_SET" | envctl load --base64 --no-interpolate $CMUX_ENV_SECRET "$@" -
+fi
+for key in $CMUX_ENV_UNSET; do
+  envctl unset "$@" "$key"
+done
+"#;
+
+fn env_patch_exec(request: &EnvPatchRequest) -> SandboxResult<ExecRequest> {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine as _;
+
+    if request.set.is_empty() && request.unset.is_empty() && request.load.is_none() {
+        return Err(SandboxError::InvalidRequest(
+            "nothing to change: provide set, unset or load".into(),
+        ));
+    }
+    let keys = request.set.iter().map(|var| &var.key).chain(&request.unset);
+    if let Some(key) = keys.into_iter().find(|key| !is_env_key(key)) {
+        return Err(SandboxError::InvalidRequest(format!(
+            "invalid variable name {key:?}"
+        )));
+    }
+    if let Some(dir) = request.dir.as_deref().filter(|dir| !dir.starts_with('/')) {
+        return Err(SandboxError::InvalidRequest(format!(
+            "dir must be absolute, got {dir:?}"
+        )));
+    }
+
+    // Set values go through the dotenv loader too, double-quoted so any
+    // value survives as written
+    let mut set = String::new();
+    for var in &request.set {
+        set.push_str(&var.key);
+        set.push_str("=\"");
+        for ch in var.value.chars() {
+            match ch {
+                '\\' | '"' | '$' => {
+                    set.push('\\');
+                    set.push(ch);
+                }
+                '\n' => set.push_str("\\n"),
+                '\r' => set.push_str("\\r"),
+                _ => set.push(ch),
+            }
+        }
+        set.push_str("\"\n");
+    }
+    let secret = match request.secret {
+        Some(true) => "--secret",
+        Some(false) => "--no-secret",
+        None => "",
+    };
+    let env = [
+        ("CMUX_ENV_DIR", request.dir.clone().unwrap_or_default()),
+        (
+            "CMUX_ENV_LOAD",
+            request
+                .load
+                .as_ref()
+                .map(|load| STANDARD.encode(load))
+                .unwrap_or_default(),
+        ),
+        (
+            "CMUX_ENV_SET",
+            if set.is_empty() {
+                String::new()
+            } else {
+                STANDARD.encode(&set)
+            },
+        ),
+        ("CMUX_ENV_UNSET", request.unset.join(" ")),
+        ("CMUX_ENV_SECRET", secret.to_string()),
+        ("XDG_RUNTIME_DIR", SANDBOX_RUNTIME_DIR.to_string()),
+    ];
+    Ok(ExecRequest {
+        command: vec!["/bin/sh".into(), "-c".into(), ENV_PATCH_SCRIPT.into()],
+        workdir: None,
+        env: env
+            .into_iter()
+            .map(|(key, value)| EnvVar {
+                key: key.to_string(),
+                value,
+            })
+            .collect(),
+    })
+}
+
+fn is_env_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
 #[utoipa::path(
     post,
     path = "/sandboxes/{id}/files",
@@ -880,24 +1046,34 @@ async fn await_ready(
 
 const PTY_PORT: u16 = 39383;
 
-/// Get the sandbox IP address from the service.
-async fn get_sandbox_ip(state: &AppState, id: &str) -> SandboxResult<String> {
+/// Address and access token of a sandbox's cmux-pty service.
+struct PtyTarget {
+    sandbox_ip: String,
+    token: Option<String>,
+}
+
+/// Look up the sandbox's cmux-pty address from the service.
+async fn get_pty_target(state: &AppState, id: &str) -> SandboxResult<PtyTarget> {
     let sandbox = state
         .service
         .get(id.to_string())
         .await?
         .ok_or_else(|| SandboxError::NotFound(Uuid::nil()))?;
-    Ok(sandbox.network.sandbox_ip)
+    Ok(PtyTarget {
+        sandbox_ip: sandbox.network.sandbox_ip,
+        token: sandbox.display.and_then(|display| display.pty_token),
+    })
 }
 
 /// Helper to proxy HTTP requests to a sandbox's cmux-pty service.
 async fn proxy_pty_request(
-    sandbox_ip: &str,
+    target: &PtyTarget,
     method: reqwest::Method,
     path: &str,
     body: Option<Vec<u8>>,
     content_type: Option<&str>,
 ) -> Response {
+    let sandbox_ip = &target.sandbox_ip;
     let target_url = format!("http://{}:{}{}", sandbox_ip, PTY_PORT, path);
 
     tracing::debug!(
@@ -916,6 +1092,10 @@ async fn proxy_pty_request(
 
     let mut req = client.request(method, &target_url);
 
+    if let Some(token) = &target.token {
+        req = req.bearer_auth(token);
+    }
+
     if let Some(ct) = content_type {
         req = req.header("Content-Type", ct);
     }
@@ -960,12 +1140,12 @@ async fn pty_list_sessions(
     state: axum::extract::State<AppState>,
     Path(id): Path<String>,
 ) -> Response {
-    let sandbox_ip = match get_sandbox_ip(&state, &id).await {
-        Ok(ip) => ip,
+    let target = match get_pty_target(&state, &id).await {
+        Ok(target) => target,
         Err(e) => return e.into_response(),
     };
 
-    proxy_pty_request(&sandbox_ip, reqwest::Method::GET, "/sessions", None, None).await
+    proxy_pty_request(&target, reqwest::Method::GET, "/sessions", None, None).await
 }
 
 /// Create a new PTY session in a sandbox.
@@ -974,13 +1154,13 @@ async fn pty_create_session(
     Path(id): Path<String>,
     body: axum::body::Bytes,
 ) -> Response {
-    let sandbox_ip = match get_sandbox_ip(&state, &id).await {
-        Ok(ip) => ip,
+    let target = match get_pty_target(&state, &id).await {
+        Ok(target) => target,
         Err(e) => return e.into_response(),
     };
 
     proxy_pty_request(
-        &sandbox_ip,
+        &target,
         reqwest::Method::POST,
         "/sessions",
         Some(body.to_vec()),
@@ -994,13 +1174,13 @@ async fn pty_get_session(
     state: axum::extract::State<AppState>,
     Path((id, session_id)): Path<(String, String)>,
 ) -> Response {
-    let sandbox_ip = match get_sandbox_ip(&state, &id).await {
-        Ok(ip) => ip,
+    let target = match get_pty_target(&state, &id).await {
+        Ok(target) => target,
         Err(e) => return e.into_response(),
     };
 
     let path = format!("/sessions/{}", session_id);
-    proxy_pty_request(&sandbox_ip, reqwest::Method::GET, &path, None, None).await
+    proxy_pty_request(&target, reqwest::Method::GET, &path, None, None).await
 }
 
 /// Delete a PTY session.
@@ -1008,13 +1188,13 @@ async fn pty_delete_session(
     state: axum::extract::State<AppState>,
     Path((id, session_id)): Path<(String, String)>,
 ) -> Response {
-    let sandbox_ip = match get_sandbox_ip(&state, &id).await {
-        Ok(ip) => ip,
+    let target = match get_pty_target(&state, &id).await {
+        Ok(target) => target,
         Err(e) => return e.into_response(),
     };
 
     let path = format!("/sessions/{}", session_id);
-    proxy_pty_request(&sandbox_ip, reqwest::Method::DELETE, &path, None, None).await
+    proxy_pty_request(&target, reqwest::Method::DELETE, &path, None, None).await
 }
 
 /// Resize a PTY session.
@@ -1023,14 +1203,14 @@ async fn pty_resize_session(
     Path((id, session_id)): Path<(String, String)>,
     body: axum::body::Bytes,
 ) -> Response {
-    let sandbox_ip = match get_sandbox_ip(&state, &id).await {
-        Ok(ip) => ip,
+    let target = match get_pty_target(&state, &id).await {
+        Ok(target) => target,
         Err(e) => return e.into_response(),
     };
 
     let path = format!("/sessions/{}/resize", session_id);
     proxy_pty_request(
-        &sandbox_ip,
+        &target,
         reqwest::Method::POST,
         &path,
         Some(body.to_vec()),
@@ -1045,8 +1225,8 @@ async fn pty_capture_session(
     Path((id, session_id)): Path<(String, String)>,
     Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Response {
-    let sandbox_ip = match get_sandbox_ip(&state, &id).await {
-        Ok(ip) => ip,
+    let target = match get_pty_target(&state, &id).await {
+        Ok(target) => target,
         Err(e) => return e.into_response(),
     };
 
@@ -1058,7 +1238,7 @@ async fn pty_capture_session(
     };
 
     let path = format!("/sessions/{}/capture{}", session_id, query_string);
-    proxy_pty_request(&sandbox_ip, reqwest::Method::GET, &path, None, None).await
+    proxy_pty_request(&target, reqwest::Method::GET, &path, None, None).await
 }
 
 /// WebSocket attach to a PTY session.
@@ -1067,15 +1247,23 @@ async fn pty_attach_session(
     Path((id, session_id)): Path<(String, String)>,
     ws: WebSocketUpgrade,
 ) -> Response {
-    let sandbox_ip = match get_sandbox_ip(&state, &id).await {
-        Ok(ip) => ip,
+    let target = match get_pty_target(&state, &id).await {
+        Ok(target) => target,
         Err(e) => return e.into_response(),
     };
 
-    let path = format!("/sessions/{}/attach", session_id);
+    // WebSocket upgrades carry the token as a query parameter
+    let path = match &target.token {
+        Some(token) => format!(
+            "/sessions/{}/attach?token={}",
+            session_id,
+            url::form_urlencoded::byte_serialize(token.as_bytes()).collect::<String>()
+        ),
+        None => format!("/sessions/{}/attach", session_id),
+    };
 
     ws.on_upgrade(move |socket| async move {
-        if let Err(e) = proxy_websocket(socket, &sandbox_ip, PTY_PORT, &path).await {
+        if let Err(e) = proxy_websocket(socket, &target.sandbox_ip, PTY_PORT, &path).await {
             tracing::error!("PTY WebSocket proxy error: {e}");
         }
     })
@@ -1087,13 +1275,13 @@ async fn pty_signal(
     Path(id): Path<String>,
     body: axum::body::Bytes,
 ) -> Response {
-    let sandbox_ip = match get_sandbox_ip(&state, &id).await {
-        Ok(ip) => ip,
+    let target = match get_pty_target(&state, &id).await {
+        Ok(target) => target,
         Err(e) => return e.into_response(),
     };
 
     proxy_pty_request(
-        &sandbox_ip,
+        &target,
         reqwest::Method::POST,
         "/signal",
         Some(body.to_vec()),
@@ -1105,7 +1293,7 @@ async fn pty_signal(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{SandboxNetwork, SandboxStatus};
+    use crate::models::{SandboxDisplay, SandboxNetwork, SandboxStatus};
     use async_trait::async_trait;
     use axum::body::Body;
     use axum::extract::ws::WebSocket;
@@ -1133,6 +1321,13 @@ mod tests {
             Ok(vec![fake_summary("mock-list".into())])
         }
 
+        async fn stats(&self, id: String) -> SandboxResult<SandboxStats> {
+            Ok(SandboxStats {
+                id: Uuid::parse_str(&id).unwrap_or_default(),
+                ..SandboxStats::default()
+            })
+        }
+
         async fn get(&self, _id: String) -> SandboxResult<Option<SandboxSummary>> {
             Ok(Some(fake_summary("mock-one".into())))
         }
@@ -1257,6 +1452,24 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[test]
+    fn pty_token_is_not_serialized() {
+        let mut summary = fake_summary("with-display".into());
+        summary.display = Some(SandboxDisplay {
+            display_number: 10,
+            vnc_port: 5910,
+            novnc_port: 39380,
+            cdp_port: 39381,
+            vscode_port: 39378,
+            pty_port: 39383,
+            worker_port: 39377,
+            pty_token: Some("secret-token".to_string()),
+        });
+        let json = serde_json::to_string(&summary).unwrap();
+        assert!(!json.contains("secret-token"), "{}", json);
+        assert!(!json.contains("pty_token"), "{}", json);
+    }
+
     #[tokio::test]
     async fn create_endpoint_returns_summary() {
         let app = make_test_router();
@@ -1283,4 +1496,197 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::CREATED);
     }
+
+    #[tokio::test]
+    async fn env_patch_endpoint_validates_and_applies() {
+        let app = make_test_router();
+        let patch = |body: serde_json::Value| {
+            Request::builder()
+                .method("PATCH")
+                .uri("/sandboxes/mock/env")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(patch(
+                serde_json::json!({"set": [{"key": "A", "value": "1"}]}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        for bad in [
+            serde_json::json!({}),
+            serde_json::json!({"unset": ["NOT-A-KEY"]}),
+            serde_json::json!({"unset": ["A"], "dir": "relative"}),
+        ] {
+            let response = app.clone().oneshot(patch(bad)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[test]
+    fn env_patch_script_drives_envctl() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Stand-in envctl that records its arguments and stdin
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log");
+        let envctl = dir.path().join("envctl");
+        std::fs::write(
+            &envctl,
+            format!(
+                "#!/bin/sh\n{{ echo \"args: $*\"; [ \"$1\" = load ] && base64 -d; echo; }} >> {}\n",
+                log.display()
+            ),
+        )
+        .unwrap();
+        std::fs::set_permissions(&envctl, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let exec = env_patch_exec(&EnvPatchRequest {
+            set: vec![EnvVar {
+                key: "TOKEN".into(),
+                value: "a \"b\" $c\nd".into(),
+            }],
+            unset: vec!["OLD".into(), "GONE".into()],
+            load: Some("X=${TOKEN}\n".into()),
+            dir: Some("/workspace/app".into()),
+            secret: Some(true),
+        })
+        .unwrap();
+        let path = format!(
+            "{}:{}",
+            dir.path().display(),
+            std::env::var("PATH").unwrap()
+        );
+        let status = std::process::Command::new(&exec.command[0])
+            .args(&exec.command[1..])
+            .envs(exec.env.iter().map(|var| (&var.key, &var.value)))
+            .env("PATH", path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        assert_eq!(
+            std::fs::read_to_string(&log).unwrap(),
+            "args: load --base64 --secret --dir /workspace/app -\n\
+             X=${TOKEN}\n\n\
+             args: load --base64 --no-interpolate --secret --dir /workspace/app -\n\
+             TOKEN=\"a \\\"b\\\" \\$c\\nd\"\n\n\
+             args: unset --dir /workspace/app OLD\n\n\
+             args: unset --dir /workspace/app GONE\n\n"
+        );
+    }
+
+    #[test]
+    fn env_patch_reaches_the_sandbox_envd() {
+        // Build the real envd/envctl from the sibling crate
+        let manifest = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../crates/cmux-env/Cargo.toml"
+        );
+        let target = concat!(env!("CARGO_MANIFEST_DIR"), "/target/cmux-env");
+        let status = std::process::Command::new(env!("CARGO"))
+            .args(["build", "--locked", "--bins", "--manifest-path", manifest])
+            .args(["--target-dir", target])
+            .status()
+            .unwrap();
+        assert!(status.success(), "build cmux-env");
+        let bin = std::path::Path::new(target).join("debug");
+
+        // Stands in for /run/user/0, where the sandbox's envd listens
+        let runtime = tempfile::tempdir().unwrap();
+        struct KillOnDrop(std::process::Child);
+        impl Drop for KillOnDrop {
+            fn drop(&mut self) {
+                let _ = self.0.kill();
+                let _ = self.0.wait();
+            }
+        }
+        let _envd = KillOnDrop(
+            std::process::Command::new(bin.join("envd"))
+                .env("XDG_RUNTIME_DIR", runtime.path())
+                .env("ENVD_SECRET_KEY_FILE", runtime.path().join("secret.key"))
+                .env("ENVD_ALLOW_REVEAL", "1")
+                .spawn()
+                .unwrap(),
+        );
+        let sock = runtime.path().join("cmux-envd/envd.sock");
+        let start = std::time::Instant::now();
+        while !sock.exists() {
+            assert!(
+                start.elapsed() < std::time::Duration::from_secs(5),
+                "envd socket"
+            );
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+
+        // What the exec would inherit from the sandbox daemon instead
+        let decoy = tempfile::tempdir().unwrap();
+        let app = runtime.path().join("app");
+        std::fs::create_dir(&app).unwrap();
+        let app = app.to_str().unwrap().to_string();
+        let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap());
+        let patch = |request: EnvPatchRequest| {
+            let exec = env_patch_exec(&request).unwrap();
+            // The exec must not rely on the caller's environment to find envd
+            let env = exec.env.iter().map(|var| {
+                if var.key == "XDG_RUNTIME_DIR" {
+                    assert_eq!(var.value, SANDBOX_RUNTIME_DIR);
+                    (var.key.as_str(), runtime.path().as_os_str())
+                } else {
+                    (var.key.as_str(), std::ffi::OsStr::new(&var.value))
+                }
+            });
+            let status = std::process::Command::new(&exec.command[0])
+                .args(&exec.command[1..])
+                .env("XDG_RUNTIME_DIR", decoy.path())
+                // Fail rather than autostart a second envd
+                .env("ENVCTL_ENVD_PATH", decoy.path().join("envd"))
+                .envs(env)
+                .env("PATH", &path)
+                .status()
+                .unwrap();
+            assert!(status.success(), "env patch failed");
+        };
+        let get = |key: &str| {
+            let output = std::process::Command::new(bin.join("envctl"))
+                .args(["get", key, "--reveal", "--pwd", &app])
+                .env("XDG_RUNTIME_DIR", runtime.path())
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{output:?}");
+            String::from_utf8(output.stdout).unwrap()
+        };
+
+        patch(EnvPatchRequest {
+            set: vec![
+                EnvVar {
+                    key: "TOKEN".into(),
+                    value: "a \"b\" $c\nd".into(),
+                },
+                EnvVar {
+                    key: "OLD".into(),
+                    value: "1".into(),
+                },
+            ],
+            unset: vec![],
+            load: None,
+            dir: Some(app.clone()),
+            secret: Some(true),
+        });
+        patch(EnvPatchRequest {
+            set: vec![],
+            unset: vec!["OLD".into()],
+            load: Some("COPY=${TOKEN}\n".into()),
+            dir: Some(app.clone()),
+            secret: None,
+        });
+        assert_eq!(get("TOKEN"), "a \"b\" $c\nd\n");
+        assert_eq!(get("COPY"), "a \"b\" $c\nd\n");
+        assert_eq!(get("OLD"), "");
+    }
 }
@@ -1,7 +1,8 @@
 use chrono::SecondsFormat;
 use clap::{Args, Parser, Subcommand, ValueEnum};
 use cmux_sandbox::models::{
-    CreateSandboxRequest, EnvVar, ExecRequest, ExecResponse, NotificationLogEntry, SandboxSummary,
+    CreateSandboxRequest, EnvPatchRequest, EnvVar, ExecRequest, ExecResponse, NotificationLogEntry,
+    SandboxStatus, SandboxSummary,
 };
 use cmux_sandbox::{
     build_default_env_vars, cache_access_token, clear_cached_access_token, clear_default_team,
@@ -74,6 +75,11 @@ enum Command {
     /// Execute a command inside a sandbox
     Exec(ExecArgs),
 
+    /// Change environment variables inside running sandboxes (picked up by
+    /// shells on their next prompt)
+    #[command(subcommand)]
+    Env(EnvCommand),
+
     /// Start a proxy server for the sandbox
     #[command(alias = "p")]
     Proxy {
@@ -170,6 +176,56 @@ enum Command {
     Prune(PruneArgs),
 }
 
+#[derive(Subcommand, Debug)]
+enum EnvCommand {
+    /// Set variables: `cmux env set <sandbox> KEY=VALUE...`
+    Set {
+        #[command(flatten)]
+        target: EnvTargetArgs,
+        /// Mark the values as secret (guessed from key names by default)
+        #[arg(long, conflicts_with = "no_secret")]
+        secret: bool,
+        /// Never treat the values as secret
+        #[arg(long)]
+        no_secret: bool,
+        /// Sandbox ID (unless --all), then KEY=VALUE pairs
+        #[arg(required = true, value_name = "ARGS")]
+        args: Vec<String>,
+    },
+    /// Remove variables: `cmux env unset <sandbox> KEY...`
+    Unset {
+        #[command(flatten)]
+        target: EnvTargetArgs,
+        /// Sandbox ID (unless --all), then keys
+        #[arg(required = true, value_name = "ARGS")]
+        args: Vec<String>,
+    },
+    /// Load a dotenv file (`-` for stdin): `cmux env load <sandbox> FILE`
+    Load {
+        #[command(flatten)]
+        target: EnvTargetArgs,
+        /// Mark the values as secret (guessed from key names by default)
+        #[arg(long, conflicts_with = "no_secret")]
+        secret: bool,
+        /// Never treat the values as secret
+        #[arg(long)]
+        no_secret: bool,
+        /// Sandbox ID (unless --all), then the file
+        #[arg(required = true, value_name = "ARGS")]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Args, Debug)]
+struct EnvTargetArgs {
+    /// Apply the change to every running sandbox
+    #[arg(long)]
+    all: bool,
+    /// Scope the change to this absolute directory inside the sandbox
+    #[arg(long)]
+    dir: Option<String>,
+}
+
 #[derive(Args, Debug)]
 struct IdeArgs {
     /// Sandbox ID: c_xxx (cloud), l_xxx (local)
@@ -1058,6 +1114,10 @@ async fn run() -> anyhow::Result<()> {
             let api_url = base_url.as_deref().unwrap_or(&cli.base_url);
             handle_ssh_proxy(&id, team.as_deref(), api_url).await?;
         }
+        Command::Env(cmd) => {
+            check_server_reachable(&client, &cli.base_url).await?;
+            handle_env_command(&client, &cli.base_url, cmd).await?;
+        }
         Command::Proxy { id, port } => {
             handle_proxy(cli.base_url, id, port).await?;
         }
@@ -2999,6 +3059,112 @@ async fn handle_exec_request(
     Ok(())
 }
 
+async fn handle_env_command(
+    client: &Client,
+    base_url: &str,
+    cmd: EnvCommand,
+) -> anyhow::Result<()> {
+    let secret_flag = |secret: bool, no_secret: bool| match (secret, no_secret) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    };
+    let (target, mut args, secret) = match &cmd {
+        EnvCommand::Set {
+            target,
+            secret,
+            no_secret,
+            args,
+        }
+        | EnvCommand::Load {
+            target,
+            secret,
+            no_secret,
+            args,
+        } => (target, args.clone(), secret_flag(*secret, *no_secret)),
+        EnvCommand::Unset { target, args } => (target, args.clone(), None),
+    };
+
+    // Without --all the first argument names the sandbox
+    let ids = if target.all {
+        let url = format!("{}/sandboxes", base_url.trim_end_matches('/'));
+        let response = client.get(url).send().await?;
+        let sandboxes: Vec<SandboxSummary> = parse_response(response).await?;
+        sandboxes
+            .into_iter()
+            .filter(|sandbox| matches!(sandbox.status, SandboxStatus::Running))
+            .map(|sandbox| sandbox.id.to_string())
+            .collect()
+    } else {
+        vec![args.remove(0)]
+    };
+
+    let mut request = EnvPatchRequest {
+        dir: target.dir.clone(),
+        secret,
+        ..EnvPatchRequest::default()
+    };
+    match cmd {
+        EnvCommand::Set { .. } => {
+            request.set = args
+                .iter()
+                .map(|arg| parse_env(arg).map_err(|e| anyhow::anyhow!(e)))
+                .collect::<anyhow::Result<_>>()?;
+        }
+        EnvCommand::Unset { .. } => request.unset = args,
+        EnvCommand::Load { .. } => {
+            let [file] = args.as_slice() else {
+                anyhow::bail!("expected exactly one file to load");
+            };
+            let content = if file == "-" {
+                let mut content = String::new();
+                tokio::io::stdin().read_to_string(&mut content).await?;
+                content
+            } else {
+                std::fs::read_to_string(file)
+                    .map_err(|e| anyhow::anyhow!("failed to read {file}: {e}"))?
+            };
+            request.load = Some(content);
+        }
+    }
+    if request.set.is_empty() && request.unset.is_empty() && request.load.is_none() {
+        anyhow::bail!("nothing to change");
+    }
+
+    let mut failed = 0;
+    for id in &ids {
+        let url = format!("{}/sandboxes/{id}/env", base_url.trim_end_matches('/'));
+        let result = match client.patch(url).json(&request).send().await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => {
+                let status = response.status();
+                let text = response
+                    .text()
+                    .await
+                    .unwrap_or_else(|_| String::from("unknown error"));
+                Err(anyhow::anyhow!("request failed: {status} - {text}"))
+            }
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(()) if target.all => println!("{id}: updated"),
+            Ok(()) => {}
+            Err(e) if target.all => {
+                eprintln!("{id}: {e}");
+                failed += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    if target.all && ids.is_empty() {
+        eprintln!("No running sandboxes.");
+    }
+    if failed > 0 {
+        anyhow::bail!("failed to update {failed} of {} sandboxes", ids.len());
+    }
+    Ok(())
+}
+
 // =============================================================================
 // CLI Authentication
 // =============================================================================
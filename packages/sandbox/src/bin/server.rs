@@ -620,6 +620,10 @@ impl SandboxService for UnavailableSandboxService {
         Err(self.error("get sandbox"))
     }
 
+    async fn stats(&self, _id: String) -> SandboxResult<cmux_sandbox::models::SandboxStats> {
+        Err(self.error("sandbox stats"))
+    }
+
     async fn exec(&self, _id: String, _exec: ExecRequest) -> SandboxResult<ExecResponse> {
         Err(self.error("exec sandbox command"))
     }
@@ -3,7 +3,8 @@ use crate::ip_pool::{IpLease, IpPool};
 use crate::models::{
     AwaitReadyRequest, AwaitReadyResponse, CreateSandboxRequest, EnvVar, ExecRequest, ExecResponse,
     HostEvent, MuxClientMessage, MuxServerMessage, PruneRequest, PruneResponse, PrunedItem,
-    PtySessionId, SandboxDisplay, SandboxNetwork, SandboxStatus, SandboxSummary, ServiceReadiness,
+    PtySessionId, SandboxDisplay, SandboxNetwork, SandboxStats, SandboxStatus, SandboxSummary,
+    ServiceReadiness,
 };
 use crate::mux::terminal::{DaFilter, VirtualTerminal};
 use crate::service::SandboxService;
@@ -37,6 +38,12 @@ const HOST_IF_PREFIX: &str = "vethh";
 const NS_IF_PREFIX: &str = "vethn";
 const DOCKER_CONTAINER_SOCKET: &str = "/run/docker.sock";
 const SANDBOX_WORKSPACE_MOUNT: &str = "/workspace";
+/// cmux-pty token file inside the sandbox (/run is a per-sandbox tmpfs;
+/// /run/cmux is shared with the host, so it must not go there)
+const PTY_TOKEN_DIR: &str = "/run/cmux-pty";
+const PTY_TOKEN_FILE: &str = "/run/cmux-pty/token";
+/// Where the host's cmux-pty allow policy (`CMUX_PTY_POLICY_FILE`) is copied inside the sandbox.
+const PTY_POLICY_FILE: &str = "/run/cmux-pty/policy.json";
 
 /// Handle for a multiplexed PTY session.
 struct PtySessionHandle {
@@ -126,6 +133,11 @@ pub struct BubblewrapService {
     readiness: Mutex<HashMap<Uuid, ReadinessWatch>>,
 }
 
+/// Quote a value as a single `sh` word.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
 fn nsenter_args(pid: u32, workdir: Option<&str>, command: &[String]) -> Vec<String> {
     let mut args = vec![
         "--target".to_string(),
@@ -366,26 +378,105 @@ async fn start_vscode_background(
     Ok(())
 }
 
+/// Write the cmux-pty token into the sandbox, piping it through stdin so it
+/// never shows up in a process list.
+async fn write_pty_token(nsenter_path: &str, inner_pid: u32, token: &str) -> Result<(), String> {
+    write_pty_file(
+        nsenter_path,
+        inner_pid,
+        PTY_TOKEN_FILE,
+        token.as_bytes(),
+        "token",
+    )
+    .await
+}
+
+/// Write a private cmux-pty file into the sandbox's `/run/cmux-pty` directory.
+async fn write_pty_file(
+    nsenter_path: &str,
+    inner_pid: u32,
+    path: &str,
+    contents: &[u8],
+    what: &str,
+) -> Result<(), String> {
+    let write_cmd = vec![
+        "/bin/sh".to_string(),
+        "-c".to_string(),
+        format!("umask 077 && mkdir -p {PTY_TOKEN_DIR} && cat > {path}"),
+    ];
+
+    let mut child = Command::new(nsenter_path)
+        .args(nsenter_args(inner_pid, None, &write_cmd))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to write cmux-pty {what}: {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(contents)
+            .await
+            .map_err(|e| format!("failed to write cmux-pty {what}: {e}"))?;
+    }
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("failed to write cmux-pty {what}: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "failed to write cmux-pty {}: {}",
+            what,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
 /// Start cmux-pty server inside the sandbox (background process).
 /// This is the unified PTY server that handles terminal sessions.
 async fn start_cmux_pty_background(
     nsenter_path: &str,
     inner_pid: u32,
     pty_port: u16,
+    pty_token: &str,
 ) -> Result<(), String> {
     use tokio::time::timeout;
     let cmd_timeout = Duration::from_secs(10);
 
+    write_pty_token(nsenter_path, inner_pid, pty_token).await?;
+
+    // Allow policy for shells, commands and cwds, copied in from the host
+    let policy_file = match env::var_os("CMUX_PTY_POLICY_FILE") {
+        Some(host_path) => {
+            let policy = tokio::fs::read(&host_path).await.map_err(|e| {
+                format!(
+                    "failed to read cmux-pty policy {}: {e}",
+                    Path::new(&host_path).display()
+                )
+            })?;
+            write_pty_file(nsenter_path, inner_pid, PTY_POLICY_FILE, &policy, "policy").await?;
+            format!(" --policy-file {PTY_POLICY_FILE}")
+        }
+        None => String::new(),
+    };
+
+    // Browser origins (e.g. the web app) allowed to reach cmux-pty via subdomain routing
+    let allowed_origins = env::var("CMUX_PTY_ALLOWED_ORIGINS")
+        .map(|origins| format!(" --allowed-origin {}", shell_quote(&origins)))
+        .unwrap_or_default();
+
     // Start cmux-pty server
     // --host 0.0.0.0: Listen on all interfaces (needed for proxy access)
     // --port: The port to listen on
+    // --token-file: Requests from outside the sandbox must present this token
+    // --policy-file: Shells, commands and cwds clients may use (CMUX_PTY_POLICY_FILE)
     // Use nohup to prevent SIGHUP when nsenter shell exits
     let pty_cmd = vec![
         "/bin/sh".to_string(),
         "-c".to_string(),
         format!(
-            "nohup /usr/local/bin/cmux-pty server --host 0.0.0.0 --port {} > /tmp/cmux-pty.log 2>&1 &",
-            pty_port
+            "nohup /usr/local/bin/cmux-pty server --host 0.0.0.0 --port {} --token-file {}{}{} > /tmp/cmux-pty.log 2>&1 &",
+            pty_port, PTY_TOKEN_FILE, policy_file, allowed_origins
         ),
     ];
 
@@ -1553,6 +1644,7 @@ impl SandboxService for BubblewrapService {
         let vscode_port = 39378_u16; // Fixed port for cmux-code
         let pty_port = 39383_u16; // Fixed port for cmux-pty
         let worker_port = 39377_u16; // Fixed port for cmux-worker
+        let pty_token = Uuid::new_v4().simple().to_string();
 
         // Display config is set immediately (ports are known upfront)
         // Services start in background - use await_services_ready to wait for VNC/VS Code/PTY
@@ -1564,6 +1656,7 @@ impl SandboxService for BubblewrapService {
             vscode_port,
             pty_port,
             worker_port,
+            pty_token: Some(pty_token.clone()),
         });
 
         // Create readiness watch channel for this sandbox
@@ -1624,7 +1717,7 @@ impl SandboxService for BubblewrapService {
 
                 // Start cmux-pty FIRST (PTY server) - must be ready before VS Code extension activates
                 let pty_result =
-                    start_cmux_pty_background(&nsenter_path, inner_pid, pty_port).await;
+                    start_cmux_pty_background(&nsenter_path, inner_pid, pty_port, &pty_token).await;
 
                 let pty_ready = match pty_result {
                     Ok(()) => {
@@ -1787,6 +1880,34 @@ impl SandboxService for BubblewrapService {
         Ok(None)
     }
 
+    async fn stats(&self, id_str: String) -> SandboxResult<SandboxStats> {
+        let id = self.resolve_id(&id_str).await?;
+
+        let inner_pid = {
+            let sandboxes = self.sandboxes.lock().await;
+            sandboxes.get(&id).map(|entry| entry.inner_pid)
+        }
+        .ok_or(SandboxError::NotFound(id))?;
+
+        let usage = collect_pid_namespace_usage(Path::new("/proc"), inner_pid).await;
+        // SAFETY: sysconf has no preconditions and only reads system configuration
+        let (ticks_per_sec, page_size) = unsafe {
+            (
+                libc::sysconf(libc::_SC_CLK_TCK),
+                libc::sysconf(libc::_SC_PAGESIZE),
+            )
+        };
+        let ticks_per_sec = u64::try_from(ticks_per_sec).unwrap_or(100).max(1);
+        let page_size = u64::try_from(page_size).unwrap_or(4096);
+
+        Ok(SandboxStats {
+            id,
+            cpu_time_ms: usage.cpu_ticks * 1000 / ticks_per_sec,
+            memory_bytes: usage.rss_pages * page_size,
+            process_count: usage.processes,
+        })
+    }
+
     async fn exec(&self, id_str: String, exec: ExecRequest) -> SandboxResult<ExecResponse> {
         let id = self.resolve_id(&id_str).await?;
 
@@ -3118,10 +3239,79 @@ async fn calculate_dir_size(path: &Path) -> u64 {
     total
 }
 
+/// Resource usage summed over every process in a PID namespace.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct NamespaceUsage {
+    cpu_ticks: u64,
+    rss_pages: u64,
+    processes: u32,
+}
+
+/// Sum CPU time and resident memory of all processes sharing `pid`'s PID namespace.
+async fn collect_pid_namespace_usage(proc_root: &Path, pid: u32) -> NamespaceUsage {
+    let mut usage = NamespaceUsage::default();
+
+    let target_ns = match fs::read_link(proc_root.join(pid.to_string()).join("ns/pid")).await {
+        Ok(ns) => ns,
+        Err(_) => return usage,
+    };
+    let mut entries = match fs::read_dir(proc_root).await {
+        Ok(entries) => entries,
+        Err(_) => return usage,
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let is_pid_dir = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit()));
+        if !is_pid_dir {
+            continue;
+        }
+
+        // Processes can exit between listing and reading; skip them quietly
+        let dir = entry.path();
+        match fs::read_link(dir.join("ns/pid")).await {
+            Ok(ns) if ns == target_ns => {}
+            _ => continue,
+        }
+        let Ok(stat) = fs::read_to_string(dir.join("stat")).await else {
+            continue;
+        };
+        if let Some((cpu_ticks, rss_pages)) = parse_proc_stat(&stat) {
+            usage.cpu_ticks += cpu_ticks;
+            usage.rss_pages += rss_pages;
+            usage.processes += 1;
+        }
+    }
+
+    usage
+}
+
+/// Parse CPU ticks (utime + stime) and resident pages from a `/proc/<pid>/stat` line.
+fn parse_proc_stat(stat: &str) -> Option<(u64, u64)> {
+    // The command name is parenthesized and may contain spaces, so split after the last ')'.
+    // Field numbering follows proc(5): state is field 3, utime 14, stime 15, rss 24.
+    let rest = &stat[stat.rfind(')')? + 1..];
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let utime: u64 = fields.get(14 - 3)?.parse().ok()?;
+    let stime: u64 = fields.get(15 - 3)?.parse().ok()?;
+    let rss: u64 = fields.get(24 - 3)?.parse().ok()?;
+    Some((utime + stime, rss))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn parse_proc_stat_handles_spaces_in_comm() {
+        let stat = "4242 (tmux: server) S 1 4242 4242 0 -1 4194560 1200 0 0 0 \
+                    150 50 0 0 20 0 1 0 12345 10000000 2048 18446744073709551615";
+        assert_eq!(parse_proc_stat(stat), Some((200, 2048)));
+        assert_eq!(parse_proc_stat("4242 (sh) S 1"), None);
+    }
+
     #[test]
     fn interface_names_are_short() {
         let id = Uuid::new_v4();
@@ -3146,6 +3336,18 @@ mod tests {
         assert!(double_dash_idx < ls_idx);
     }
 
+    #[test]
+    fn shell_quote_keeps_values_intact() {
+        assert_eq!(shell_quote("https://cmux.app"), "'https://cmux.app'");
+        assert_eq!(shell_quote("a'b; rm -rf /"), r"'a'\''b; rm -rf /'");
+        let output = std::process::Command::new("/bin/sh")
+            .arg("-c")
+            .arg(format!("printf %s {}", shell_quote("it's $HOME `x`")))
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "it's $HOME `x`");
+    }
+
     #[test]
     fn nsenter_args_custom_workdir() {
         let args = nsenter_args(123, Some("/custom"), &["ls".to_string()]);
@@ -13,6 +13,7 @@ pub mod service;
 pub mod settings;
 pub mod sync_files;
 pub mod terminal_guard;
+pub mod theme;
 pub mod timing;
 pub mod vnc_proxy;
 
@@ -62,6 +62,11 @@ pub struct SandboxDisplay {
     pub pty_port: u16,
     /// cmux-worker port (always 39377 inside sandbox, accessed via subdomain routing)
     pub worker_port: u16,
+    /// Token cmux-pty requires (`Authorization: Bearer`, `?token=` or cookie).
+    /// Used by the PTY proxy endpoints only, never sent in sandbox listings.
+    #[serde(default, skip_serializing)]
+    #[schema(ignore)]
+    pub pty_token: Option<String>,
 }
 
 /// Readiness status for sandbox services.
@@ -129,6 +134,19 @@ pub struct SandboxSummary {
     pub correlation_id: Option<String>,
 }
 
+/// Resource usage of all processes running inside a sandbox.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema, PartialEq, Eq)]
+pub struct SandboxStats {
+    pub id: Uuid,
+    /// Total CPU time (user + system) consumed by live sandbox processes, in milliseconds.
+    /// Clients derive utilization from the difference between two samples.
+    pub cpu_time_ms: u64,
+    /// Resident memory of all sandbox processes, in bytes.
+    pub memory_bytes: u64,
+    /// Number of processes in the sandbox PID namespace.
+    pub process_count: u32,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct ExecRequest {
     /// Command arguments executed via nsenter inside the sandbox
@@ -140,6 +158,26 @@ pub struct ExecRequest {
     pub env: Vec<EnvVar>,
 }
 
+/// Change to the variables managed by the cmux-env daemon inside a sandbox.
+/// Operations apply in order: `load`, then `set`, then `unset`. Shells in the
+/// sandbox pick them up on their next prompt.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct EnvPatchRequest {
+    #[serde(default)]
+    pub set: Vec<EnvVar>,
+    #[serde(default)]
+    pub unset: Vec<String>,
+    /// Dotenv content to load; `${VAR}` references resolve inside the sandbox
+    #[schema(example = "API_URL=https://api.example.com")]
+    pub load: Option<String>,
+    /// Absolute directory inside the sandbox to scope the change to
+    /// (global when omitted)
+    #[schema(example = "/workspace")]
+    pub dir: Option<String>,
+    /// Mark set/loaded values as secret; guessed from key names when omitted
+    pub secret: Option<bool>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct ExecResponse {
     pub exit_code: i32,
@@ -153,7 +191,18 @@ pub struct HealthResponse {
 }
 
 #[derive(
-    Clone, Debug, Deserialize, Serialize, ToSchema, PartialEq, Eq, Copy, ValueEnum, Default,
+    Clone,
+    Debug,
+    Deserialize,
+    Serialize,
+    ToSchema,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Copy,
+    ValueEnum,
+    Default,
 )]
 #[serde(rename_all = "lowercase")]
 pub enum NotificationLevel {
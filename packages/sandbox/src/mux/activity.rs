@@ -0,0 +1,287 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::mux::layout::{PaneId, SandboxId};
+
+/// How long a pane with unseen output must stay quiet before it is flagged as
+/// having gone silent (e.g. an agent finished and is waiting for input).
+pub const SILENCE_THRESHOLD: Duration = Duration::from_secs(10);
+
+/// Activity badge shown for a pane or sandbox, ordered by priority.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ActivityBadge {
+    /// New output the user hasn't looked at yet.
+    Output,
+    /// Output stopped after a burst of activity.
+    Silence,
+    /// The pane's process exited.
+    Exited,
+    /// The pane rang the terminal bell.
+    Bell,
+}
+
+impl ActivityBadge {
+    /// Whether this badge should be picked up by "jump to next needing attention".
+    pub fn needs_attention(self) -> bool {
+        !matches!(self, ActivityBadge::Output)
+    }
+
+    /// Sidebar glyph for the badge.
+    pub fn icon(self) -> &'static str {
+        match self {
+            ActivityBadge::Output => "•",
+            ActivityBadge::Silence => "…",
+            ActivityBadge::Exited => "x",
+            ActivityBadge::Bell => "!",
+        }
+    }
+}
+
+/// Activity state for a single pane.
+#[derive(Debug, Clone)]
+struct PaneActivity {
+    sandbox_id: SandboxId,
+    last_output: Option<Instant>,
+    unseen_output: bool,
+    silent: bool,
+    bell: bool,
+    exited: bool,
+}
+
+impl PaneActivity {
+    fn new(sandbox_id: SandboxId) -> Self {
+        Self {
+            sandbox_id,
+            last_output: None,
+            unseen_output: false,
+            silent: false,
+            bell: false,
+            exited: false,
+        }
+    }
+
+    fn badge(&self) -> Option<ActivityBadge> {
+        if self.bell {
+            Some(ActivityBadge::Bell)
+        } else if self.exited {
+            Some(ActivityBadge::Exited)
+        } else if self.silent {
+            Some(ActivityBadge::Silence)
+        } else if self.unseen_output {
+            Some(ActivityBadge::Output)
+        } else {
+            None
+        }
+    }
+
+    fn clear(&mut self) {
+        self.unseen_output = false;
+        self.silent = false;
+        self.bell = false;
+    }
+}
+
+/// Tracks per-pane activity (unseen output, bells, silence, exits) so the
+/// sidebar can show which sandboxes are busy or waiting on the user.
+///
+/// Panes the user is looking at never accumulate badges; everything else is
+/// flagged until the pane (or, for exited panes, its sandbox) is viewed again.
+#[derive(Debug)]
+pub struct ActivityTracker {
+    panes: HashMap<PaneId, PaneActivity>,
+    silence_threshold: Duration,
+}
+
+impl Default for ActivityTracker {
+    fn default() -> Self {
+        Self::new(SILENCE_THRESHOLD)
+    }
+}
+
+impl ActivityTracker {
+    pub fn new(silence_threshold: Duration) -> Self {
+        Self {
+            panes: HashMap::new(),
+            silence_threshold,
+        }
+    }
+
+    fn entry(&mut self, pane_id: PaneId, sandbox_id: SandboxId) -> &mut PaneActivity {
+        let activity = self
+            .panes
+            .entry(pane_id)
+            .or_insert_with(|| PaneActivity::new(sandbox_id));
+        activity.sandbox_id = sandbox_id;
+        activity
+    }
+
+    /// Record output written to a pane.
+    pub fn record_output(
+        &mut self,
+        pane_id: PaneId,
+        sandbox_id: SandboxId,
+        viewed: bool,
+        now: Instant,
+    ) {
+        let activity = self.entry(pane_id, sandbox_id);
+        activity.last_output = Some(now);
+        if !viewed {
+            activity.unseen_output = true;
+            activity.silent = false;
+        }
+    }
+
+    /// Record a terminal bell in a pane.
+    pub fn record_bell(&mut self, pane_id: PaneId, sandbox_id: SandboxId, viewed: bool) {
+        if !viewed {
+            self.entry(pane_id, sandbox_id).bell = true;
+        }
+    }
+
+    /// Record that a pane's process exited.
+    pub fn record_exit(&mut self, pane_id: PaneId, sandbox_id: SandboxId, viewed: bool) {
+        if viewed {
+            self.panes.remove(&pane_id);
+        } else {
+            self.entry(pane_id, sandbox_id).exited = true;
+        }
+    }
+
+    /// Clear badges for panes the user can currently see.
+    ///
+    /// Exited panes no longer exist in any layout, so they are cleared as soon
+    /// as their sandbox is the active one. Returns true if any badge changed.
+    pub fn mark_viewed(&mut self, visible: &[PaneId], active_sandbox: Option<SandboxId>) -> bool {
+        let mut changed = false;
+        self.panes.retain(|pane_id, activity| {
+            if activity.exited {
+                let keep = Some(activity.sandbox_id) != active_sandbox;
+                changed |= !keep;
+                return keep;
+            }
+            if visible.contains(pane_id) && activity.badge().is_some() {
+                activity.clear();
+                changed = true;
+            }
+            true
+        });
+        changed
+    }
+
+    /// Flag panes whose unseen output has gone quiet. Returns true if any badge changed.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let mut changed = false;
+        for activity in self.panes.values_mut() {
+            if !activity.unseen_output || activity.silent {
+                continue;
+            }
+            let quiet = activity
+                .last_output
+                .is_some_and(|last| now.duration_since(last) >= self.silence_threshold);
+            if quiet {
+                activity.silent = true;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Forget live panes that no longer exist (e.g. closed by the user).
+    /// Exited panes are kept until their sandbox is viewed. Returns true if any were dropped.
+    pub fn retain_panes(&mut self, live: &HashSet<PaneId>) -> bool {
+        let before = self.panes.len();
+        self.panes
+            .retain(|pane_id, activity| activity.exited || live.contains(pane_id));
+        self.panes.len() != before
+    }
+
+    /// Forget every pane belonging to a sandbox.
+    pub fn remove_sandbox(&mut self, sandbox_id: SandboxId) {
+        self.panes
+            .retain(|_, activity| activity.sandbox_id != sandbox_id);
+    }
+
+    /// Badge for a single pane.
+    pub fn pane_badge(&self, pane_id: PaneId) -> Option<ActivityBadge> {
+        self.panes.get(&pane_id).and_then(PaneActivity::badge)
+    }
+
+    /// Highest-priority badge across all panes of a sandbox.
+    pub fn sandbox_badge(&self, sandbox_id: SandboxId) -> Option<ActivityBadge> {
+        self.panes
+            .values()
+            .filter(|activity| activity.sandbox_id == sandbox_id)
+            .filter_map(PaneActivity::badge)
+            .max()
+    }
+
+    /// The pane in a sandbox that most needs attention, preferring live panes
+    /// (exited panes can't be focused).
+    pub fn attention_pane(&self, sandbox_id: SandboxId) -> Option<PaneId> {
+        self.panes
+            .iter()
+            .filter(|(_, activity)| activity.sandbox_id == sandbox_id && !activity.exited)
+            .filter_map(|(pane_id, activity)| activity.badge().map(|badge| (badge, *pane_id)))
+            .filter(|(badge, _)| badge.needs_attention())
+            .max_by_key(|(badge, _)| *badge)
+            .map(|(_, pane_id)| pane_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unseen_output_goes_silent_and_clears_when_viewed() {
+        let mut tracker = ActivityTracker::new(Duration::from_secs(5));
+        let sandbox = SandboxId::new();
+        let pane = PaneId::new();
+        let start = Instant::now();
+
+        tracker.record_output(pane, sandbox, false, start);
+        assert_eq!(tracker.sandbox_badge(sandbox), Some(ActivityBadge::Output));
+        assert!(!tracker.tick(start + Duration::from_secs(2)));
+
+        assert!(tracker.tick(start + Duration::from_secs(6)));
+        assert_eq!(tracker.pane_badge(pane), Some(ActivityBadge::Silence));
+        assert_eq!(tracker.attention_pane(sandbox), Some(pane));
+
+        assert!(tracker.mark_viewed(&[pane], Some(sandbox)));
+        assert_eq!(tracker.pane_badge(pane), None);
+    }
+
+    #[test]
+    fn viewed_panes_never_get_badges() {
+        let mut tracker = ActivityTracker::default();
+        let sandbox = SandboxId::new();
+        let pane = PaneId::new();
+
+        tracker.record_output(pane, sandbox, true, Instant::now());
+        tracker.record_bell(pane, sandbox, true);
+        assert_eq!(tracker.sandbox_badge(sandbox), None);
+    }
+
+    #[test]
+    fn bell_outranks_exit_and_exit_clears_with_sandbox() {
+        let mut tracker = ActivityTracker::default();
+        let sandbox = SandboxId::new();
+        let other = SandboxId::new();
+        let ringing = PaneId::new();
+        let exited = PaneId::new();
+
+        tracker.record_exit(exited, sandbox, false);
+        assert_eq!(tracker.sandbox_badge(sandbox), Some(ActivityBadge::Exited));
+        // Exited panes can't be focused, so they are not jump targets
+        assert_eq!(tracker.attention_pane(sandbox), None);
+
+        tracker.record_bell(ringing, sandbox, false);
+        assert_eq!(tracker.sandbox_badge(sandbox), Some(ActivityBadge::Bell));
+        assert_eq!(tracker.attention_pane(sandbox), Some(ringing));
+
+        assert!(!tracker.mark_viewed(&[], Some(other)));
+        assert!(tracker.mark_viewed(&[], Some(sandbox)));
+        assert_eq!(tracker.pane_badge(exited), None);
+        assert_eq!(tracker.pane_badge(ringing), Some(ActivityBadge::Bell));
+    }
+}
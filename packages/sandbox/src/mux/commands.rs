@@ -40,6 +40,9 @@ pub enum MuxCommand {
     ResizeRight,
     ResizeUp,
     ResizeDown,
+    NewFloatingPane,
+    ToggleFloatingPanes,
+    PromoteFloatingPane,
 
     // Tab management
     NewTab,
@@ -53,6 +56,7 @@ pub enum MuxCommand {
     SelectSandbox,
     NextSandbox,
     PrevSandbox,
+    JumpToAttention,
 
     // Sandbox management
     NewSandbox,
@@ -68,6 +72,12 @@ pub enum MuxCommand {
     OpenCommandPalette,
     ToggleHelp,
     ShowNotifications,
+    SelectTheme,
+    SetThemeAuto,
+    SetThemeDark,
+    SetThemeLight,
+    SetThemeHighContrast,
+    CycleTheme,
     Quit,
     ScrollUp,
     ScrollDown,
@@ -140,6 +150,9 @@ impl MuxCommand {
             MuxCommand::ResizeRight,
             MuxCommand::ResizeUp,
             MuxCommand::ResizeDown,
+            MuxCommand::NewFloatingPane,
+            MuxCommand::ToggleFloatingPanes,
+            MuxCommand::PromoteFloatingPane,
             // Tab management
             MuxCommand::NewTab,
             MuxCommand::CloseTab,
@@ -151,6 +164,7 @@ impl MuxCommand {
             MuxCommand::SelectSandbox,
             MuxCommand::NextSandbox,
             MuxCommand::PrevSandbox,
+            MuxCommand::JumpToAttention,
             // Sandbox management
             MuxCommand::NewSandbox,
             MuxCommand::DeleteSandbox,
@@ -163,6 +177,12 @@ impl MuxCommand {
             MuxCommand::OpenCommandPalette,
             MuxCommand::ToggleHelp,
             MuxCommand::ShowNotifications,
+            MuxCommand::SelectTheme,
+            MuxCommand::SetThemeAuto,
+            MuxCommand::SetThemeDark,
+            MuxCommand::SetThemeLight,
+            MuxCommand::SetThemeHighContrast,
+            MuxCommand::CycleTheme,
             MuxCommand::Quit,
             MuxCommand::ScrollUp,
             MuxCommand::ScrollDown,
@@ -216,6 +236,9 @@ impl MuxCommand {
             MuxCommand::SplitVertical => "Split Vertical",
             MuxCommand::ClosePane => "Close Pane",
             MuxCommand::ToggleZoom => "Toggle Zoom",
+            MuxCommand::NewFloatingPane => "New Floating Pane",
+            MuxCommand::ToggleFloatingPanes => "Toggle Floating Panes",
+            MuxCommand::PromoteFloatingPane => "Embed Floating Pane",
             MuxCommand::SwapPaneLeft => "Swap Pane Left",
             MuxCommand::SwapPaneRight => "Swap Pane Right",
             MuxCommand::SwapPaneUp => "Swap Pane Up",
@@ -233,6 +256,7 @@ impl MuxCommand {
             MuxCommand::SelectSandbox => "Select Sandbox",
             MuxCommand::NextSandbox => "Next Sandbox",
             MuxCommand::PrevSandbox => "Previous Sandbox",
+            MuxCommand::JumpToAttention => "Jump to Sandbox Needing Attention",
             MuxCommand::NewSandbox => "New Sandbox",
             MuxCommand::DeleteSandbox => "Delete Sandbox",
             MuxCommand::RefreshSandboxes => "Refresh Sandboxes",
@@ -242,6 +266,12 @@ impl MuxCommand {
             MuxCommand::OpenCommandPalette => "Command Palette",
             MuxCommand::ToggleHelp => "Toggle Help",
             MuxCommand::ShowNotifications => "Show Notifications",
+            MuxCommand::SelectTheme => "Select Theme",
+            MuxCommand::SetThemeAuto => "Auto (follow terminal)",
+            MuxCommand::SetThemeDark => "Dark",
+            MuxCommand::SetThemeLight => "Light",
+            MuxCommand::SetThemeHighContrast => "High Contrast",
+            MuxCommand::CycleTheme => "Next Theme",
             MuxCommand::Quit => "Quit",
             MuxCommand::ScrollUp => "Scroll Up",
             MuxCommand::ScrollDown => "Scroll Down",
@@ -276,6 +306,9 @@ impl MuxCommand {
             MuxCommand::SplitHorizontal => &["divide", "new pane", "hsplit"],
             MuxCommand::SplitVertical => &["divide", "new pane", "vsplit"],
             MuxCommand::ToggleZoom => &["maximize", "fullscreen", "expand"],
+            MuxCommand::NewFloatingPane => &["popup", "overlay", "scratch", "run command"],
+            MuxCommand::ToggleFloatingPanes => &["show popups", "hide popups", "overlay"],
+            MuxCommand::PromoteFloatingPane => &["dock", "tile", "pin popup"],
             MuxCommand::FocusLeft => &["move left", "navigate left", "go left"],
             MuxCommand::FocusRight => &["move right", "navigate right", "go right"],
             MuxCommand::FocusUp => &["move up", "navigate up", "go up"],
@@ -283,6 +316,7 @@ impl MuxCommand {
             MuxCommand::NewTab => &["create tab", "add tab", "open tab"],
             MuxCommand::NewSandbox => &["create sandbox", "add sandbox"],
             MuxCommand::DeleteSandbox => &["remove sandbox", "destroy sandbox", "kill sandbox"],
+            MuxCommand::JumpToAttention => &["activity", "bell", "idle", "waiting", "unread"],
             MuxCommand::OpenCommandPalette => &["search", "find command", "quick open"],
             MuxCommand::ToggleHelp => {
                 &["shortcuts", "keybindings", "keyboard shortcuts", "hotkeys"]
@@ -309,6 +343,12 @@ impl MuxCommand {
             MuxCommand::SetEditorCursor => &["cursor", "default", "preference", "ai"],
             MuxCommand::SetEditorZed => &["zed", "default", "preference"],
             MuxCommand::SetEditorWindsurf => &["windsurf", "default", "preference", "ai"],
+            MuxCommand::SelectTheme => &["colors", "color scheme", "appearance", "dark mode"],
+            MuxCommand::SetThemeAuto => &["theme", "system", "detect"],
+            MuxCommand::SetThemeDark => &["theme", "dark mode"],
+            MuxCommand::SetThemeLight => &["theme", "light mode"],
+            MuxCommand::SetThemeHighContrast => &["theme", "accessibility", "contrast"],
+            MuxCommand::CycleTheme => &["theme", "colors", "switch theme", "custom theme"],
             MuxCommand::OpenBrowser => &["chrome", "firefox", "safari", "web", "http", "preview"],
             _ => &[],
         }
@@ -340,6 +380,9 @@ impl MuxCommand {
             MuxCommand::SplitVertical => "Split the current pane vertically",
             MuxCommand::ClosePane => "Close the current pane",
             MuxCommand::ToggleZoom => "Toggle zoom on the current pane",
+            MuxCommand::NewFloatingPane => "Open a floating pane running a command",
+            MuxCommand::ToggleFloatingPanes => "Show or hide floating panes",
+            MuxCommand::PromoteFloatingPane => "Move the floating pane into the tiled layout",
             MuxCommand::SwapPaneLeft => "Swap current pane with the one on the left",
             MuxCommand::SwapPaneRight => "Swap current pane with the one on the right",
             MuxCommand::SwapPaneUp => "Swap current pane with the one above",
@@ -357,6 +400,9 @@ impl MuxCommand {
             MuxCommand::SelectSandbox => "Select a sandbox from the list",
             MuxCommand::NextSandbox => "Switch to the next sandbox workspace",
             MuxCommand::PrevSandbox => "Switch to the previous sandbox workspace",
+            MuxCommand::JumpToAttention => {
+                "Focus the next pane that rang the bell, went quiet or exited"
+            }
             MuxCommand::NewSandbox => "Create a new sandbox",
             MuxCommand::DeleteSandbox => "Delete the selected sandbox",
             MuxCommand::RefreshSandboxes => "Refresh the sandbox list",
@@ -366,6 +412,12 @@ impl MuxCommand {
             MuxCommand::OpenCommandPalette => "Open the command palette",
             MuxCommand::ToggleHelp => "Show or hide help overlay",
             MuxCommand::ShowNotifications => "Show notifications panel",
+            MuxCommand::SelectTheme => "Choose the color theme (Auto, Dark, Light, High Contrast)",
+            MuxCommand::SetThemeAuto => "Pick dark or light from the terminal background",
+            MuxCommand::SetThemeDark => "Use the dark theme",
+            MuxCommand::SetThemeLight => "Use the light theme",
+            MuxCommand::SetThemeHighContrast => "Use the high-contrast theme",
+            MuxCommand::CycleTheme => "Switch to the next theme, including custom theme files",
             MuxCommand::Quit => "Exit the multiplexer",
             MuxCommand::ScrollUp => "Scroll up one line",
             MuxCommand::ScrollDown => "Scroll down one line",
@@ -427,7 +479,10 @@ impl MuxCommand {
             | MuxCommand::ResizeLeft
             | MuxCommand::ResizeRight
             | MuxCommand::ResizeUp
-            | MuxCommand::ResizeDown => "Panes",
+            | MuxCommand::ResizeDown
+            | MuxCommand::NewFloatingPane
+            | MuxCommand::ToggleFloatingPanes
+            | MuxCommand::PromoteFloatingPane => "Panes",
 
             MuxCommand::NewTab
             | MuxCommand::CloseTab
@@ -438,7 +493,8 @@ impl MuxCommand {
             MuxCommand::ToggleSidebar
             | MuxCommand::SelectSandbox
             | MuxCommand::NextSandbox
-            | MuxCommand::PrevSandbox => "Sidebar",
+            | MuxCommand::PrevSandbox
+            | MuxCommand::JumpToAttention => "Sidebar",
 
             MuxCommand::NewSandbox | MuxCommand::DeleteSandbox | MuxCommand::RefreshSandboxes => {
                 "Sandbox"
@@ -451,6 +507,12 @@ impl MuxCommand {
             MuxCommand::OpenCommandPalette
             | MuxCommand::ToggleHelp
             | MuxCommand::ShowNotifications
+            | MuxCommand::SelectTheme
+            | MuxCommand::SetThemeAuto
+            | MuxCommand::SetThemeDark
+            | MuxCommand::SetThemeLight
+            | MuxCommand::SetThemeHighContrast
+            | MuxCommand::CycleTheme
             | MuxCommand::Quit
             | MuxCommand::ScrollUp
             | MuxCommand::ScrollDown
@@ -551,6 +613,13 @@ impl MuxCommand {
                 Some((KeyModifiers::CONTROL | KeyModifiers::ALT, KeyCode::Down))
             }
 
+            // Floating panes
+            MuxCommand::NewFloatingPane => {
+                Some((KeyModifiers::ALT | KeyModifiers::SHIFT, KeyCode::Char('F')))
+            }
+            MuxCommand::ToggleFloatingPanes => Some((KeyModifiers::ALT, KeyCode::Char('f'))),
+            MuxCommand::PromoteFloatingPane => None,
+
             // Tab management - all Alt-based
             MuxCommand::NewTab => Some((KeyModifiers::ALT, KeyCode::Char('t'))),
             MuxCommand::CloseTab => {
@@ -571,6 +640,7 @@ impl MuxCommand {
             // Alt+Shift+{ and Alt+Shift+} for sandbox switching (accepts Alt+{ / Alt+} too)
             MuxCommand::NextSandbox => Some((KeyModifiers::ALT, KeyCode::Char('}'))),
             MuxCommand::PrevSandbox => Some((KeyModifiers::ALT, KeyCode::Char('{'))),
+            MuxCommand::JumpToAttention => Some((KeyModifiers::ALT, KeyCode::Char('a'))),
 
             // Sandbox management - use Alt
             MuxCommand::NewSandbox => Some((KeyModifiers::ALT, KeyCode::Char('n'))),
@@ -590,6 +660,12 @@ impl MuxCommand {
             MuxCommand::ShowNotifications => {
                 Some((KeyModifiers::ALT | KeyModifiers::SHIFT, KeyCode::Char('n')))
             }
+            MuxCommand::SelectTheme => None, // Access via command palette (opens submenu)
+            MuxCommand::SetThemeAuto => None, // Submenu item
+            MuxCommand::SetThemeDark => None, // Submenu item
+            MuxCommand::SetThemeLight => None, // Submenu item
+            MuxCommand::SetThemeHighContrast => None, // Submenu item
+            MuxCommand::CycleTheme => None,
             MuxCommand::Quit => Some((KeyModifiers::CONTROL, KeyCode::Char('q'))),
             // Scroll - only when NOT focused on terminal (handled separately)
             MuxCommand::ScrollUp => None,
@@ -710,6 +786,12 @@ impl MuxCommand {
                 MuxCommand::SetEditorZed,
                 MuxCommand::SetEditorWindsurf,
             ]),
+            MuxCommand::SelectTheme => Some(&[
+                MuxCommand::SetThemeAuto,
+                MuxCommand::SetThemeDark,
+                MuxCommand::SetThemeLight,
+                MuxCommand::SetThemeHighContrast,
+            ]),
             _ => None,
         }
     }
@@ -726,6 +808,10 @@ impl MuxCommand {
                 | MuxCommand::SetEditorCursor
                 | MuxCommand::SetEditorZed
                 | MuxCommand::SetEditorWindsurf
+                | MuxCommand::SetThemeAuto
+                | MuxCommand::SetThemeDark
+                | MuxCommand::SetThemeLight
+                | MuxCommand::SetThemeHighContrast
         )
     }
 
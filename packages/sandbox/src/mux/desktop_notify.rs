@@ -0,0 +1,396 @@
+//! Forward sandbox notifications and terminal bells to native desktop notifications.
+//!
+//! On Linux notifications go through the freedesktop `org.freedesktop.Notifications`
+//! D-Bus service. Clicking a notification sends [`MuxEvent::OpenNotificationTarget`]
+//! so the mux can focus the originating sandbox, tab and pane. Other platforms
+//! currently drop desktop notifications (they still appear in the TUI overlay).
+//!
+//! Which notifications are forwarded is controlled by
+//! [`DesktopNotificationSettings`] in `settings.json`:
+//!
+//! ```json
+//! "desktop_notifications": {
+//!   "min_level": "warning",
+//!   "max_per_minute": 4,
+//!   "sandboxes": { "scratch": { "enabled": false } }
+//! }
+//! ```
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::models::NotificationLevel;
+use crate::mux::events::MuxEvent;
+
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Where a notification came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationSource {
+    /// `cmux-bridge notify` / `POST /notifications`
+    Api,
+    /// Terminal bell (BEL) in a pane
+    Bell,
+    /// OSC 9 / OSC 777 notification escape sequence in a pane
+    Osc,
+}
+
+/// Per-sandbox override of the global rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxNotificationRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_level: Option<NotificationLevel>,
+}
+
+/// Rules for forwarding notifications to the desktop.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DesktopNotificationSettings {
+    /// Master switch for desktop notifications.
+    pub enabled: bool,
+    /// Lowest level that is forwarded.
+    pub min_level: NotificationLevel,
+    /// Forward terminal bells from panes.
+    pub bell: bool,
+    /// Forward OSC 9 / OSC 777 notifications from panes.
+    pub osc: bool,
+    /// Skip notifications for a pane that's on screen while the terminal has focus.
+    pub only_when_unfocused: bool,
+    /// Maximum desktop notifications per sandbox per minute (0 = unlimited).
+    pub max_per_minute: u32,
+    /// Overrides keyed by sandbox name or ID.
+    pub sandboxes: BTreeMap<String, SandboxNotificationRule>,
+}
+
+impl Default for DesktopNotificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_level: NotificationLevel::Info,
+            bell: true,
+            osc: true,
+            only_when_unfocused: true,
+            max_per_minute: 6,
+            sandboxes: BTreeMap::new(),
+        }
+    }
+}
+
+/// Identifies the sandbox/tab/pane a notification should focus when clicked.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NotificationTarget {
+    pub sandbox_id: Option<String>,
+    pub tab_id: Option<String>,
+    pub pane_id: Option<String>,
+}
+
+/// A notification to show on the desktop.
+#[derive(Debug, Clone)]
+pub struct DesktopNotification {
+    pub summary: String,
+    pub body: String,
+    pub level: NotificationLevel,
+    pub target: NotificationTarget,
+}
+
+/// Context the filter needs beyond the notification itself.
+#[derive(Debug, Clone, Copy)]
+pub struct NotificationContext<'a> {
+    pub source: NotificationSource,
+    pub level: NotificationLevel,
+    pub sandbox_id: Option<&'a str>,
+    pub sandbox_name: Option<&'a str>,
+    /// The terminal has focus and the originating pane is on screen.
+    pub in_view: bool,
+}
+
+/// Applies [`DesktopNotificationSettings`] and per-sandbox rate limiting.
+#[derive(Debug, Default)]
+pub struct NotificationFilter {
+    sent: HashMap<String, VecDeque<Instant>>,
+}
+
+impl NotificationFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decide whether a notification should reach the desktop, recording it
+    /// against the rate limit if so.
+    pub fn allow(
+        &mut self,
+        settings: &DesktopNotificationSettings,
+        context: NotificationContext<'_>,
+        now: Instant,
+    ) -> bool {
+        if !settings.enabled {
+            return false;
+        }
+        let source_enabled = match context.source {
+            NotificationSource::Api => true,
+            NotificationSource::Bell => settings.bell,
+            NotificationSource::Osc => settings.osc,
+        };
+        if !source_enabled || (settings.only_when_unfocused && context.in_view) {
+            return false;
+        }
+
+        // Name rules win over ID rules so users can write readable configs
+        let rule = context
+            .sandbox_name
+            .and_then(|name| settings.sandboxes.get(name))
+            .or_else(|| context.sandbox_id.and_then(|id| settings.sandboxes.get(id)));
+        if rule.and_then(|rule| rule.enabled) == Some(false) {
+            return false;
+        }
+        let min_level = rule
+            .and_then(|rule| rule.min_level)
+            .unwrap_or(settings.min_level);
+        if context.level < min_level {
+            return false;
+        }
+
+        if settings.max_per_minute == 0 {
+            return true;
+        }
+        let key = context.sandbox_id.unwrap_or_default().to_string();
+        let sent = self.sent.entry(key).or_default();
+        while sent
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= RATE_LIMIT_WINDOW)
+        {
+            sent.pop_front();
+        }
+        if sent.len() >= settings.max_per_minute as usize {
+            return false;
+        }
+        sent.push_back(now);
+        true
+    }
+}
+
+/// Handle to the background task that talks to the desktop notification service.
+#[derive(Debug, Clone)]
+pub struct DesktopNotifier {
+    tx: mpsc::UnboundedSender<DesktopNotification>,
+}
+
+impl DesktopNotifier {
+    /// Start the notifier. Clicks on notifications are reported on `event_tx`.
+    pub fn spawn(event_tx: mpsc::UnboundedSender<MuxEvent>) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            if let Err(e) = run_notifier(rx, event_tx).await {
+                tracing::warn!("Desktop notifications unavailable: {}", e);
+            }
+        });
+        Self { tx }
+    }
+
+    /// Queue a notification for display.
+    pub fn notify(&self, notification: DesktopNotification) {
+        let _ = self.tx.send(notification);
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn run_notifier(
+    mut rx: mpsc::UnboundedReceiver<DesktopNotification>,
+    event_tx: mpsc::UnboundedSender<MuxEvent>,
+) -> zbus::Result<()> {
+    use futures::StreamExt;
+    use zbus::zvariant::Value;
+
+    /// Bound on remembered click targets if the server never reports closes
+    const MAX_TRACKED: usize = 64;
+
+    let connection = zbus::Connection::session().await?;
+    let proxy = zbus::Proxy::new(
+        &connection,
+        "org.freedesktop.Notifications",
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications",
+    )
+    .await?;
+    let mut invoked = proxy.receive_signal("ActionInvoked").await?;
+    let mut closed = proxy.receive_signal("NotificationClosed").await?;
+    let mut targets: HashMap<u32, NotificationTarget> = HashMap::new();
+
+    loop {
+        tokio::select! {
+            notification = rx.recv() => {
+                let Some(notification) = notification else {
+                    break;
+                };
+                let urgency: u8 = match notification.level {
+                    NotificationLevel::Info => 1,
+                    NotificationLevel::Warning => 1,
+                    NotificationLevel::Error => 2,
+                };
+                let hints = HashMap::from([("urgency", Value::U8(urgency))]);
+                let result: zbus::Result<u32> = proxy
+                    .call(
+                        "Notify",
+                        &(
+                            "cmux",
+                            0u32,
+                            "utilities-terminal",
+                            notification.summary.as_str(),
+                            notification.body.as_str(),
+                            vec!["default", "Open"],
+                            hints,
+                            -1i32,
+                        ),
+                    )
+                    .await;
+                match result {
+                    Ok(id) => {
+                        if targets.len() >= MAX_TRACKED {
+                            targets.clear();
+                        }
+                        targets.insert(id, notification.target);
+                    }
+                    Err(e) => tracing::warn!("Failed to send desktop notification: {}", e),
+                }
+            }
+            Some(message) = invoked.next() => {
+                let Ok((id, action)) = message.body().deserialize::<(u32, String)>() else {
+                    continue;
+                };
+                if action == "default" {
+                    if let Some(target) = targets.remove(&id) {
+                        let _ = event_tx.send(MuxEvent::OpenNotificationTarget(target));
+                    }
+                }
+            }
+            Some(message) = closed.next() => {
+                if let Ok((id, _reason)) = message.body().deserialize::<(u32, u32)>() {
+                    targets.remove(&id);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn run_notifier(
+    mut rx: mpsc::UnboundedReceiver<DesktopNotification>,
+    _event_tx: mpsc::UnboundedSender<MuxEvent>,
+) -> Result<(), std::convert::Infallible> {
+    // No native backend yet; drain so senders never block or error
+    while rx.recv().await.is_some() {}
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(
+        source: NotificationSource,
+        level: NotificationLevel,
+    ) -> NotificationContext<'static> {
+        NotificationContext {
+            source,
+            level,
+            sandbox_id: Some("5f0c"),
+            sandbox_name: Some("agent"),
+            in_view: false,
+        }
+    }
+
+    #[test]
+    fn filter_applies_level_source_and_sandbox_rules() {
+        let mut settings = DesktopNotificationSettings {
+            min_level: NotificationLevel::Warning,
+            bell: false,
+            max_per_minute: 0,
+            ..DesktopNotificationSettings::default()
+        };
+        let mut filter = NotificationFilter::new();
+        let now = Instant::now();
+
+        let info = context(NotificationSource::Api, NotificationLevel::Info);
+        let error = context(NotificationSource::Api, NotificationLevel::Error);
+        assert!(!filter.allow(&settings, info, now));
+        assert!(filter.allow(&settings, error, now));
+        assert!(!filter.allow(
+            &settings,
+            context(NotificationSource::Bell, NotificationLevel::Error),
+            now
+        ));
+        assert!(!filter.allow(
+            &settings,
+            NotificationContext {
+                in_view: true,
+                ..error
+            },
+            now
+        ));
+
+        settings.sandboxes.insert(
+            "agent".to_string(),
+            SandboxNotificationRule {
+                enabled: None,
+                min_level: Some(NotificationLevel::Info),
+            },
+        );
+        assert!(filter.allow(&settings, info, now));
+
+        settings.sandboxes.insert(
+            "5f0c".to_string(),
+            SandboxNotificationRule {
+                enabled: Some(false),
+                min_level: None,
+            },
+        );
+        // The name rule takes precedence over the ID rule
+        assert!(filter.allow(&settings, info, now));
+        assert!(!filter.allow(
+            &settings,
+            NotificationContext {
+                sandbox_name: None,
+                ..info
+            },
+            now
+        ));
+    }
+
+    #[test]
+    fn filter_rate_limits_per_sandbox() {
+        let settings = DesktopNotificationSettings {
+            max_per_minute: 2,
+            ..DesktopNotificationSettings::default()
+        };
+        let mut filter = NotificationFilter::new();
+        let now = Instant::now();
+        let info = context(NotificationSource::Osc, NotificationLevel::Info);
+        let other = NotificationContext {
+            sandbox_id: Some("other"),
+            sandbox_name: None,
+            ..info
+        };
+
+        assert!(filter.allow(&settings, info, now));
+        assert!(filter.allow(&settings, info, now));
+        assert!(!filter.allow(&settings, info, now));
+        assert!(filter.allow(&settings, other, now));
+        assert!(filter.allow(&settings, info, now + RATE_LIMIT_WINDOW));
+    }
+
+    #[test]
+    fn settings_default_when_missing() {
+        let parsed: DesktopNotificationSettings =
+            serde_json::from_str(r#"{"min_level":"error"}"#).unwrap();
+        assert!(parsed.enabled);
+        assert!(parsed.bell);
+        assert_eq!(parsed.min_level, NotificationLevel::Error);
+        assert_eq!(parsed.max_per_minute, 6);
+    }
+}
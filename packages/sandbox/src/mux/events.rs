@@ -1,7 +1,8 @@
 use std::path::PathBuf;
 
-use crate::models::{NotificationLevel, SandboxSummary};
+use crate::models::{NotificationLevel, SandboxStats, SandboxSummary};
 use crate::mux::colors::TerminalColors;
+use crate::mux::desktop_notify::NotificationTarget;
 use crate::mux::layout::PaneId;
 use crate::mux::onboard::OnboardEvent;
 
@@ -28,8 +29,18 @@ pub enum MuxEvent {
     SandboxDeleted(String),
     /// Connection to a sandbox changed.
     SandboxConnectionChanged { sandbox_id: String, connected: bool },
-    /// Terminal output received.
-    TerminalOutput { pane_id: crate::mux::layout::PaneId },
+    /// Terminal output received (`bell` is set if the output rang the bell).
+    TerminalOutput { pane_id: PaneId, bell: bool },
+    /// A pane requested a notification via OSC 9 / OSC 777.
+    TerminalNotification {
+        pane_id: PaneId,
+        title: Option<String>,
+        body: String,
+    },
+    /// A desktop notification was clicked; focus where it came from.
+    OpenNotificationTarget(NotificationTarget),
+    /// Resource usage samples for running sandboxes.
+    SandboxStatsUpdated(Vec<SandboxStats>),
     /// An error occurred.
     Error(String),
     /// A system notification to display.
@@ -162,6 +162,23 @@ impl Pane {
     }
 }
 
+/// Which child of a split to descend into when walking the layout tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitSide {
+    First,
+    Second,
+}
+
+/// A split border found under a screen position (used for mouse resizing).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitBorder {
+    /// Path from the layout root to the split node owning this border.
+    pub path: Vec<SplitSide>,
+    pub direction: Direction,
+    /// Area covered by both children of the split.
+    pub area: Rect,
+}
+
 /// A node in the layout tree.
 #[derive(Debug, Clone)]
 pub enum LayoutNode {
@@ -454,6 +471,89 @@ impl LayoutNode {
         best_candidate.map(|(id, _)| id)
     }
 
+    /// Bounding rect of all pane areas in this node (None before areas are calculated).
+    pub fn bounds(&self) -> Option<Rect> {
+        match self {
+            LayoutNode::Pane(pane) => pane.area,
+            LayoutNode::Split { first, second, .. } => match (first.bounds(), second.bounds()) {
+                (Some(a), Some(b)) => Some(a.union(b)),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+
+    /// Find the pane whose computed area contains the given screen position.
+    pub fn pane_at(&self, column: u16, row: u16) -> Option<PaneId> {
+        self.panes()
+            .into_iter()
+            .find(|pane| {
+                pane.area
+                    .is_some_and(|area| area.contains((column, row).into()))
+            })
+            .map(|pane| pane.id)
+    }
+
+    /// Find the split border under the given screen position.
+    ///
+    /// Each pane draws its own border, so the divider between two children is the
+    /// last row/column of the first child plus the first row/column of the second.
+    pub fn border_at(&self, column: u16, row: u16) -> Option<SplitBorder> {
+        let LayoutNode::Split {
+            direction,
+            first,
+            second,
+            ..
+        } = self
+        else {
+            return None;
+        };
+
+        let first_area = first.bounds()?;
+        let second_area = second.bounds()?;
+        let area = first_area.union(second_area);
+        if !area.contains((column, row).into()) {
+            return None;
+        }
+
+        let on_border = match direction {
+            Direction::Vertical => column + 1 == first_area.right() || column == second_area.x,
+            Direction::Horizontal => row + 1 == first_area.bottom() || row == second_area.y,
+        };
+        if on_border {
+            return Some(SplitBorder {
+                path: Vec::new(),
+                direction: *direction,
+                area,
+            });
+        }
+
+        let (side, child) = if first_area.contains((column, row).into()) {
+            (SplitSide::First, first)
+        } else {
+            (SplitSide::Second, second)
+        };
+        let mut border = child.border_at(column, row)?;
+        border.path.insert(0, side);
+        Some(border)
+    }
+
+    /// Set the ratio of the split at the given path, clamped like keyboard resizing.
+    pub fn set_split_ratio(&mut self, path: &[SplitSide], new_ratio: f32) -> bool {
+        match (self, path.split_first()) {
+            (LayoutNode::Split { ratio, .. }, None) => {
+                *ratio = new_ratio.clamp(0.1, 0.9);
+                true
+            }
+            (LayoutNode::Split { first, .. }, Some((SplitSide::First, rest))) => {
+                first.set_split_ratio(rest, new_ratio)
+            }
+            (LayoutNode::Split { second, .. }, Some((SplitSide::Second, rest))) => {
+                second.set_split_ratio(rest, new_ratio)
+            }
+            (LayoutNode::Pane(_), _) => false,
+        }
+    }
+
     /// Resize the split containing the given pane in the specified direction.
     pub fn resize_pane(&mut self, pane_id: PaneId, direction: NavDirection, delta: f32) {
         self.resize_pane_internal(pane_id, direction, delta);
@@ -512,6 +612,62 @@ impl LayoutNode {
     }
 }
 
+/// Position and size of a floating pane as fractions of the workspace area.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloatGeometry {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for FloatGeometry {
+    fn default() -> Self {
+        Self {
+            x: 0.15,
+            y: 0.15,
+            width: 0.7,
+            height: 0.7,
+        }
+    }
+}
+
+impl FloatGeometry {
+    const MIN_SIZE: f32 = 0.1;
+
+    /// Resolve the geometry to a screen rect inside the given area.
+    pub fn to_rect(&self, area: Rect) -> Rect {
+        let width = ((area.width as f32 * self.width) as u16).clamp(3.min(area.width), area.width);
+        let height =
+            ((area.height as f32 * self.height) as u16).clamp(3.min(area.height), area.height);
+        let x = area.x + ((area.width as f32 * self.x) as u16).min(area.width - width);
+        let y = area.y + ((area.height as f32 * self.y) as u16).min(area.height - height);
+        Rect::new(x, y, width, height)
+    }
+
+    /// Move by the given fractions, keeping the pane inside the workspace.
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        self.x = (self.x + dx).clamp(0.0, 1.0 - self.width);
+        self.y = (self.y + dy).clamp(0.0, 1.0 - self.height);
+    }
+
+    /// Grow or shrink by the given fractions, keeping the pane inside the workspace.
+    pub fn resize(&mut self, dw: f32, dh: f32) {
+        self.width = (self.width + dw).clamp(Self::MIN_SIZE, 1.0 - self.x);
+        self.height = (self.height + dh).clamp(Self::MIN_SIZE, 1.0 - self.y);
+    }
+}
+
+/// A pane floating above the tiled layout of a tab.
+/// Floating panes own a PTY session like tiled panes and stay attached while hidden.
+#[derive(Debug, Clone)]
+pub struct FloatingPane {
+    pub pane: Pane,
+    /// Command to run instead of the default shell.
+    pub command: Option<Vec<String>>,
+    pub geometry: FloatGeometry,
+}
+
 /// A tab in the workspace.
 #[derive(Debug, Clone)]
 pub struct Tab {
@@ -519,6 +675,12 @@ pub struct Tab {
     pub name: String,
     pub layout: LayoutNode,
     pub active_pane: Option<PaneId>,
+    /// Floating panes, in stacking order (last is on top).
+    pub floating: Vec<FloatingPane>,
+    /// Whether floating panes are currently shown.
+    pub floating_visible: bool,
+    /// Tiled pane to return focus to when floating panes are hidden or closed.
+    pub last_tiled_pane: Option<PaneId>,
 }
 
 impl Tab {
@@ -530,12 +692,16 @@ impl Tab {
             name: name.into(),
             layout,
             active_pane,
+            floating: Vec::new(),
+            floating_visible: false,
+            last_tiled_pane: None,
         }
     }
 
     /// Split the active pane in the given direction.
+    /// When a floating pane is focused, the last focused tiled pane is split instead.
     pub fn split(&mut self, direction: Direction, new_pane: Pane) {
-        let Some(active_id) = self.active_pane else {
+        let Some(active_id) = self.tiled_focus() else {
             return;
         };
 
@@ -576,6 +742,9 @@ impl Tab {
         let Some(active_id) = self.active_pane else {
             return false;
         };
+        if self.is_floating(active_id) {
+            return self.remove_pane_by_id(active_id);
+        }
 
         // Find the sibling pane (the one that will take over in the split)
         let sibling = self.layout.find_sibling(active_id);
@@ -593,6 +762,20 @@ impl Tab {
     pub fn remove_pane_by_id(&mut self, pane_id: PaneId) -> bool {
         let was_active = self.active_pane == Some(pane_id);
 
+        if let Some(index) = self.floating_index(pane_id) {
+            self.floating.remove(index);
+            if self.floating.is_empty() {
+                self.floating_visible = false;
+            }
+            if was_active {
+                self.active_pane = match self.floating.last() {
+                    Some(top) if self.floating_visible => Some(top.pane.id),
+                    _ => self.tiled_focus(),
+                };
+            }
+            return true;
+        }
+
         // Find the sibling pane (the one that will take over in the split)
         let sibling = self.layout.find_sibling(pane_id);
 
@@ -612,9 +795,185 @@ impl Tab {
         true
     }
 
-    /// Check if this tab contains the provided pane ID.
+    /// Check if this tab contains the provided pane ID (tiled or floating).
     pub fn contains_pane(&self, pane_id: PaneId) -> bool {
-        self.layout.contains_pane(pane_id)
+        self.layout.contains_pane(pane_id) || self.is_floating(pane_id)
+    }
+
+    /// IDs of all panes in this tab, tiled first, then floating.
+    pub fn all_pane_ids(&self) -> Vec<PaneId> {
+        let mut ids = self.layout.pane_ids();
+        ids.extend(self.floating.iter().map(|fp| fp.pane.id));
+        ids
+    }
+
+    /// Find a pane (tiled or floating) by ID.
+    pub fn find_pane(&self, pane_id: PaneId) -> Option<&Pane> {
+        self.layout.find_pane(pane_id).or_else(|| {
+            self.floating
+                .iter()
+                .find(|fp| fp.pane.id == pane_id)
+                .map(|fp| &fp.pane)
+        })
+    }
+
+    /// Find a pane (tiled or floating) by ID mutably.
+    pub fn find_pane_mut(&mut self, pane_id: PaneId) -> Option<&mut Pane> {
+        if self.layout.contains_pane(pane_id) {
+            return self.layout.find_pane_mut(pane_id);
+        }
+        self.floating
+            .iter_mut()
+            .find(|fp| fp.pane.id == pane_id)
+            .map(|fp| &mut fp.pane)
+    }
+
+    /// IDs of panes that can take focus: tiled panes, then visible floating panes.
+    fn focusable_pane_ids(&self) -> Vec<PaneId> {
+        self.visible_panes().iter().map(|pane| pane.id).collect()
+    }
+
+    /// Panes that are currently on screen: tiled panes, then visible floating panes.
+    pub fn visible_panes(&self) -> Vec<&Pane> {
+        let mut panes = self.layout.panes();
+        if self.floating_visible {
+            panes.extend(self.floating.iter().map(|fp| &fp.pane));
+        }
+        panes
+    }
+
+    /// Find the on-screen pane at a position, checking floating panes top-down first.
+    pub fn pane_at(&self, column: u16, row: u16) -> Option<PaneId> {
+        if self.floating_visible {
+            let hit = self.floating.iter().rev().find(|fp| {
+                fp.pane
+                    .area
+                    .is_some_and(|area| area.contains((column, row).into()))
+            });
+            if let Some(fp) = hit {
+                return Some(fp.pane.id);
+            }
+        }
+        self.layout.pane_at(column, row)
+    }
+
+    fn floating_index(&self, pane_id: PaneId) -> Option<usize> {
+        self.floating.iter().position(|fp| fp.pane.id == pane_id)
+    }
+
+    /// Check whether a pane is floating.
+    pub fn is_floating(&self, pane_id: PaneId) -> bool {
+        self.floating_index(pane_id).is_some()
+    }
+
+    /// Get a floating pane mutably.
+    pub fn floating_mut(&mut self, pane_id: PaneId) -> Option<&mut FloatingPane> {
+        self.floating.iter_mut().find(|fp| fp.pane.id == pane_id)
+    }
+
+    /// Command a pane should run instead of the default shell (floating panes only).
+    pub fn pane_command(&self, pane_id: PaneId) -> Option<Vec<String>> {
+        self.floating
+            .iter()
+            .find(|fp| fp.pane.id == pane_id)
+            .and_then(|fp| fp.command.clone())
+    }
+
+    /// The tiled pane that should receive focus when leaving floating panes.
+    pub fn tiled_focus(&self) -> Option<PaneId> {
+        self.active_pane
+            .filter(|id| self.layout.contains_pane(*id))
+            .or(self
+                .last_tiled_pane
+                .filter(|id| self.layout.contains_pane(*id)))
+            .or_else(|| self.layout.pane_ids().first().copied())
+    }
+
+    fn remember_tiled_focus(&mut self) {
+        if let Some(active) = self.active_pane.filter(|id| self.layout.contains_pane(*id)) {
+            self.last_tiled_pane = Some(active);
+        }
+    }
+
+    /// Add a floating pane on top of the stack, show floating panes and focus it.
+    pub fn add_floating(&mut self, pane: Pane, command: Option<Vec<String>>) -> PaneId {
+        let pane_id = pane.id;
+        // Cascade new panes so they don't exactly cover each other
+        let offset = (self.floating.len() % 5) as f32 * 0.03;
+        let mut geometry = FloatGeometry::default();
+        geometry.translate(offset, offset);
+        self.remember_tiled_focus();
+        self.floating.push(FloatingPane {
+            pane,
+            command,
+            geometry,
+        });
+        self.floating_visible = true;
+        self.active_pane = Some(pane_id);
+        pane_id
+    }
+
+    /// Show or hide floating panes. Returns false if there are none to toggle.
+    pub fn toggle_floating(&mut self) -> bool {
+        if self.floating.is_empty() {
+            return false;
+        }
+        if self.floating_visible {
+            self.floating_visible = false;
+            if self.active_pane.is_some_and(|id| self.is_floating(id)) {
+                self.active_pane = self.tiled_focus();
+            }
+        } else {
+            self.remember_tiled_focus();
+            self.floating_visible = true;
+            self.active_pane = self.floating.last().map(|fp| fp.pane.id);
+        }
+        true
+    }
+
+    /// Focus a pane, showing and raising it if it's floating.
+    pub fn focus_pane(&mut self, pane_id: PaneId) -> bool {
+        if self.is_floating(pane_id) {
+            self.remember_tiled_focus();
+            self.floating_visible = true;
+            self.raise_floating(pane_id);
+        } else if !self.layout.contains_pane(pane_id) {
+            return false;
+        }
+        self.active_pane = Some(pane_id);
+        true
+    }
+
+    /// Bring a floating pane to the top of the stack.
+    pub fn raise_floating(&mut self, pane_id: PaneId) {
+        if let Some(index) = self.floating_index(pane_id) {
+            let fp = self.floating.remove(index);
+            self.floating.push(fp);
+        }
+    }
+
+    /// Move a floating pane into the tiled layout next to the last focused tiled pane.
+    pub fn promote_floating(&mut self, pane_id: PaneId) -> bool {
+        let Some(index) = self.floating_index(pane_id) else {
+            return false;
+        };
+        let Some(target) = self.tiled_focus() else {
+            return false;
+        };
+        let mut fp = self.floating.remove(index);
+        fp.pane.area = None;
+        if self.floating.is_empty() {
+            self.floating_visible = false;
+        }
+        self.split_at_pane(&target, Direction::Vertical, fp.pane);
+        true
+    }
+
+    /// Compute screen areas for floating panes within the workspace area.
+    pub fn calculate_floating_areas(&mut self, area: Rect) {
+        for fp in &mut self.floating {
+            fp.pane.area = Some(fp.geometry.to_rect(area));
+        }
     }
 
     /// Navigate to a neighbor pane.
@@ -622,6 +981,11 @@ impl Tab {
         let Some(active_id) = self.active_pane else {
             return;
         };
+        // Directional navigation out of a floating pane returns to the tiled layout
+        if self.is_floating(active_id) {
+            self.active_pane = self.tiled_focus();
+            return;
+        }
 
         if let Some(neighbor_id) = self.layout.find_neighbor(active_id, direction) {
             self.active_pane = Some(neighbor_id);
@@ -630,7 +994,7 @@ impl Tab {
 
     /// Cycle to the next pane.
     pub fn next_pane(&mut self) {
-        let pane_ids = self.layout.pane_ids();
+        let pane_ids = self.focusable_pane_ids();
         if pane_ids.is_empty() {
             return;
         }
@@ -646,7 +1010,7 @@ impl Tab {
 
     /// Cycle to the previous pane.
     pub fn prev_pane(&mut self) {
-        let pane_ids = self.layout.pane_ids();
+        let pane_ids = self.focusable_pane_ids();
         if pane_ids.is_empty() {
             return;
         }
@@ -669,6 +1033,15 @@ impl Tab {
         let Some(active_id) = self.active_pane else {
             return;
         };
+        if let Some(fp) = self.floating_mut(active_id) {
+            match direction {
+                NavDirection::Left => fp.geometry.resize(-delta, 0.0),
+                NavDirection::Right => fp.geometry.resize(delta, 0.0),
+                NavDirection::Up => fp.geometry.resize(0.0, -delta),
+                NavDirection::Down => fp.geometry.resize(0.0, delta),
+            }
+            return;
+        }
         self.layout.resize_pane(active_id, direction, delta);
     }
 }
@@ -795,6 +1168,19 @@ impl SandboxWorkspace {
         }
     }
 
+    /// Move the tab at `from` to position `to`, keeping the active tab selected.
+    pub fn move_tab(&mut self, from: usize, to: usize) {
+        if from >= self.tabs.len() || to >= self.tabs.len() || from == to {
+            return;
+        }
+        let active_id = self.active_tab().map(|tab| tab.id);
+        let tab = self.tabs.remove(from);
+        self.tabs.insert(to, tab);
+        if let Some(active_id) = active_id {
+            self.select_tab_by_id(active_id);
+        }
+    }
+
     /// Rename the active tab.
     pub fn rename_active_tab(&mut self, name: impl Into<String>) {
         if let Some(tab) = self.active_tab_mut() {
@@ -1101,6 +1487,13 @@ impl WorkspaceManager {
         }
     }
 
+    /// Move a tab within the active workspace.
+    pub fn move_tab(&mut self, from: usize, to: usize) {
+        if let Some(ws) = self.active_workspace_mut() {
+            ws.move_tab(from, to);
+        }
+    }
+
     /// Rename the active tab in the active workspace.
     pub fn rename_active_tab(&mut self, name: impl Into<String>) {
         if let Some(ws) = self.active_workspace_mut() {
@@ -1157,17 +1550,53 @@ impl WorkspaceManager {
         false
     }
 
+    /// IDs of every pane in every workspace.
+    pub fn all_pane_ids(&self) -> std::collections::HashSet<PaneId> {
+        self.workspaces
+            .values()
+            .flat_map(|ws| ws.tabs.iter().flat_map(|tab| tab.all_pane_ids()))
+            .collect()
+    }
+
+    /// Find the sandbox whose workspace contains a pane.
+    pub fn sandbox_for_pane(&self, pane_id: PaneId) -> Option<SandboxId> {
+        self.workspaces
+            .iter()
+            .find(|(_, ws)| ws.tab_index_for_pane(pane_id).is_some())
+            .map(|(sandbox_id, _)| *sandbox_id)
+    }
+
+    /// Make a pane visible and focused: selects its sandbox and tab, then the pane.
+    pub fn focus_pane(&mut self, pane_id: PaneId) -> bool {
+        let Some(sandbox_id) = self.sandbox_for_pane(pane_id) else {
+            return false;
+        };
+        let Some(ws) = self.workspaces.get_mut(&sandbox_id) else {
+            return false;
+        };
+        let Some(index) = ws.tab_index_for_pane(pane_id) else {
+            return false;
+        };
+        ws.active_tab_index = index;
+        self.active_sandbox_id = Some(sandbox_id);
+        ws.tabs[index].focus_pane(pane_id)
+    }
+
     /// Handle a pane exit by either closing its tab (if it's the only pane) or removing the pane.
     pub fn handle_pane_exit(&mut self, pane_id: PaneId) -> Option<PaneExitOutcome> {
         for (sandbox_id, workspace) in self.workspaces.iter_mut() {
             if let Some(index) = workspace.tab_index_for_pane(pane_id) {
-                let pane_ids = workspace
+                let (pane_ids, is_last_tiled) = workspace
                     .tabs
                     .get(index)
-                    .map(|tab| tab.layout.pane_ids())
+                    .map(|tab| {
+                        let is_last_tiled =
+                            !tab.is_floating(pane_id) && tab.layout.pane_count() == 1;
+                        (tab.all_pane_ids(), is_last_tiled)
+                    })
                     .unwrap_or_default();
                 let was_active_tab = workspace.active_tab_index == index;
-                if pane_ids.len() == 1 {
+                if is_last_tiled {
                     let removed = workspace.remove_tab_at(index)?;
                     return Some(PaneExitOutcome::TabClosed(ClosedTabInfo {
                         sandbox_id: *sandbox_id,
@@ -1380,4 +1809,170 @@ mod tests {
         assert_eq!(tab.layout.pane_count(), 1);
         assert!(tab.contains_pane(tab.active_pane.expect("active pane should exist")));
     }
+
+    #[test]
+    fn border_hit_testing_finds_nested_split() {
+        let mut tab = Tab::new("Test");
+        tab.split(Direction::Vertical, Pane::empty());
+        tab.split(Direction::Horizontal, Pane::empty());
+        tab.layout.calculate_areas(Rect::new(0, 0, 100, 40));
+
+        // Vertical divider between the left pane and the right column
+        let border = tab.layout.border_at(50, 10).expect("vertical border");
+        assert!(border.path.is_empty());
+        assert_eq!(border.direction, Direction::Vertical);
+        assert_eq!(border.area, Rect::new(0, 0, 100, 40));
+
+        // Horizontal divider inside the right column
+        let border = tab.layout.border_at(75, 20).expect("horizontal border");
+        assert_eq!(border.path, vec![SplitSide::Second]);
+        assert_eq!(border.direction, Direction::Horizontal);
+
+        assert!(tab.layout.border_at(25, 10).is_none());
+        assert!(tab.layout.pane_at(25, 10).is_some());
+    }
+
+    #[test]
+    fn set_split_ratio_updates_nested_split() {
+        let mut tab = Tab::new("Test");
+        tab.split(Direction::Vertical, Pane::empty());
+        tab.split(Direction::Horizontal, Pane::empty());
+
+        assert!(tab.layout.set_split_ratio(&[SplitSide::Second], 0.95));
+        let LayoutNode::Split { ratio, second, .. } = &tab.layout else {
+            panic!("expected split");
+        };
+        assert_eq!(*ratio, 0.5);
+        let LayoutNode::Split { ratio, .. } = second.as_ref() else {
+            panic!("expected nested split");
+        };
+        assert_eq!(*ratio, 0.9);
+        assert!(!tab.layout.set_split_ratio(&[SplitSide::First], 0.3));
+    }
+
+    #[test]
+    fn move_tab_keeps_active_tab_selected() {
+        let mut workspace = SandboxWorkspace::new(SandboxId::new(), "Test");
+        workspace.new_tab();
+        workspace.new_tab();
+        let first = workspace.tabs[0].id;
+        let active = workspace.tabs[2].id;
+
+        workspace.move_tab(0, 2);
+        assert_eq!(workspace.tabs[2].id, first);
+        assert_eq!(workspace.active_tab().map(|tab| tab.id), Some(active));
+        assert_eq!(workspace.active_tab_index, 1);
+    }
+
+    #[test]
+    fn floating_panes_toggle_and_restore_focus() {
+        let mut tab = Tab::new("Test");
+        let tiled = tab.active_pane.expect("tiled pane");
+        let floating = tab.add_floating(Pane::empty(), Some(vec!["htop".to_string()]));
+
+        assert!(tab.is_floating(floating));
+        assert!(tab.contains_pane(floating));
+        assert_eq!(tab.active_pane, Some(floating));
+        assert_eq!(tab.pane_command(floating), Some(vec!["htop".to_string()]));
+
+        assert!(tab.toggle_floating());
+        assert!(!tab.floating_visible);
+        assert_eq!(tab.active_pane, Some(tiled));
+
+        assert!(tab.toggle_floating());
+        assert_eq!(tab.active_pane, Some(floating));
+
+        // Splitting while a floating pane is focused splits the tiled layout
+        tab.split(Direction::Vertical, Pane::empty());
+        assert_eq!(tab.layout.pane_count(), 2);
+        assert_eq!(tab.floating.len(), 1);
+    }
+
+    #[test]
+    fn floating_pane_hit_testing_prefers_topmost() {
+        let mut tab = Tab::new("Test");
+        let area = Rect::new(0, 0, 100, 40);
+        tab.layout.calculate_areas(area);
+        let first = tab.add_floating(Pane::empty(), None);
+        let second = tab.add_floating(Pane::empty(), None);
+        tab.calculate_floating_areas(area);
+
+        assert_eq!(tab.pane_at(50, 20), Some(second));
+        tab.raise_floating(first);
+        assert_eq!(tab.pane_at(50, 20), Some(first));
+        assert_eq!(tab.pane_at(1, 1), tab.layout.pane_at(1, 1));
+
+        tab.toggle_floating();
+        assert_eq!(tab.pane_at(50, 20), tab.layout.pane_at(50, 20));
+    }
+
+    #[test]
+    fn promote_floating_pane_joins_layout() {
+        let mut tab = Tab::new("Test");
+        let floating = tab.add_floating(Pane::empty(), None);
+
+        assert!(tab.promote_floating(floating));
+        assert!(!tab.is_floating(floating));
+        assert!(tab.layout.contains_pane(floating));
+        assert_eq!(tab.active_pane, Some(floating));
+        assert!(!tab.floating_visible);
+    }
+
+    #[test]
+    fn floating_pane_exit_keeps_tab_open() {
+        let mut manager = WorkspaceManager::new();
+        let sandbox_id = SandboxId::new();
+        manager.add_sandbox(sandbox_id, "Test");
+        let tab = manager.active_tab_mut().expect("active tab");
+        let tiled = tab.active_pane.expect("tiled pane");
+        let floating = tab.add_floating(Pane::empty(), None);
+
+        assert!(matches!(
+            manager.handle_pane_exit(floating),
+            Some(PaneExitOutcome::PaneRemoved { .. })
+        ));
+        let tab = manager.active_tab().expect("tab should remain");
+        assert!(tab.floating.is_empty());
+        assert_eq!(tab.active_pane, Some(tiled));
+
+        let floating = manager
+            .active_tab_mut()
+            .expect("active tab")
+            .add_floating(Pane::empty(), None);
+        let Some(PaneExitOutcome::TabClosed(info)) = manager.handle_pane_exit(tiled) else {
+            panic!("closing the last tiled pane should close the tab");
+        };
+        assert!(info.pane_ids.contains(&floating));
+    }
+
+    #[test]
+    fn focus_pane_switches_sandbox_and_tab() {
+        let mut manager = WorkspaceManager::new();
+        let first = SandboxId::new();
+        let second = SandboxId::new();
+        manager.add_sandbox(first, "First");
+        manager.add_sandbox(second, "Second");
+
+        manager.select_sandbox(second);
+        manager.new_tab();
+        let hidden = manager
+            .active_tab_mut()
+            .expect("active tab")
+            .add_floating(Pane::empty(), None);
+        manager
+            .active_tab_mut()
+            .expect("active tab")
+            .toggle_floating();
+        manager.prev_tab();
+        manager.select_sandbox(first);
+
+        assert_eq!(manager.sandbox_for_pane(hidden), Some(second));
+        assert!(manager.focus_pane(hidden));
+        assert_eq!(manager.active_sandbox_id, Some(second));
+        let tab = manager.active_tab().expect("active tab");
+        assert!(tab.floating_visible);
+        assert_eq!(tab.active_pane, Some(hidden));
+        assert!(manager.all_pane_ids().contains(&hidden));
+        assert!(!manager.focus_pane(PaneId::new()));
+    }
 }
@@ -1,9 +1,12 @@
+pub mod activity;
 pub mod character;
 pub mod colors;
 pub mod commands;
+pub mod desktop_notify;
 pub mod events;
 pub mod grid;
 pub mod layout;
+pub mod mouse;
 pub mod onboard;
 pub mod palette;
 pub mod runner;
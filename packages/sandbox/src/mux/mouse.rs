@@ -0,0 +1,213 @@
+//! Mouse interaction state for the mux TUI.
+//!
+//! Tracks in-progress drags (split resizing, tab reordering, floating pane
+//! moves, text selection)
+//! and the screen regions recorded during rendering that mouse events are
+//! hit-tested against.
+
+use ratatui::layout::{Position, Rect};
+
+use crate::mux::layout::{PaneId, SplitBorder};
+
+/// A drag gesture that started with a left-button press.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MouseDrag {
+    /// Dragging a split border to change the split ratio.
+    ResizeSplit(SplitBorder),
+    /// Dragging a tab in the tab bar; `index` follows the tab as it moves.
+    MoveTab { index: usize },
+    /// Dragging a floating pane by its title bar; `last` is the previous cursor position.
+    MoveFloating { pane_id: PaneId, last: (u16, u16) },
+    /// Dragging the bottom-right corner of a floating pane.
+    ResizeFloating { pane_id: PaneId, last: (u16, u16) },
+    /// Selecting text inside a pane that does not track the mouse.
+    Select,
+}
+
+/// A text selection inside a pane, in view coordinates (row, col), 0-indexed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextSelection {
+    pub pane_id: PaneId,
+    pub anchor: (usize, usize),
+    pub head: (usize, usize),
+}
+
+impl TextSelection {
+    pub fn new(pane_id: PaneId, row: usize, col: usize) -> Self {
+        Self {
+            pane_id,
+            anchor: (row, col),
+            head: (row, col),
+        }
+    }
+
+    /// Start and end of the selection in reading order (end is inclusive).
+    pub fn ordered(&self) -> ((usize, usize), (usize, usize)) {
+        if self.anchor <= self.head {
+            (self.anchor, self.head)
+        } else {
+            (self.head, self.anchor)
+        }
+    }
+
+    /// True when only a single cell was clicked without dragging.
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.head
+    }
+
+    /// Check whether a cell lies inside the selection (line-wise, like a terminal).
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        let (start, end) = self.ordered();
+        if row < start.0 || row > end.0 {
+            return false;
+        }
+        if row == start.0 && col < start.1 {
+            return false;
+        }
+        if row == end.0 && col > end.1 {
+            return false;
+        }
+        true
+    }
+}
+
+/// Screen regions recorded during the last render, used for hit-testing.
+#[derive(Debug, Clone, Default)]
+pub struct MouseRegions {
+    /// Inner area of the sidebar list (first row is the first sandbox).
+    pub sidebar: Option<Rect>,
+    /// Column ranges `[start, end)` of each tab in the tab bar, in tab order.
+    pub tabs: Vec<(u16, u16)>,
+    /// The tab bar row.
+    pub tab_bar: Option<Rect>,
+    /// Area the tab's panes are laid out in; floating pane geometry is relative to it.
+    pub workspace: Option<Rect>,
+}
+
+impl MouseRegions {
+    /// Index of the tab under the given position, if any.
+    pub fn tab_at(&self, column: u16, row: u16) -> Option<usize> {
+        let bar = self.tab_bar?;
+        if !bar.contains(Position::new(column, row)) {
+            return None;
+        }
+        self.tabs
+            .iter()
+            .position(|(start, end)| column >= *start && column < *end)
+    }
+
+    /// Convert a cursor movement into a fractional offset of the workspace area.
+    pub fn workspace_delta(&self, from: (u16, u16), to: (u16, u16)) -> Option<(f32, f32)> {
+        let area = self.workspace?;
+        if area.width == 0 || area.height == 0 {
+            return None;
+        }
+        let dx = (to.0 as f32 - from.0 as f32) / area.width as f32;
+        let dy = (to.1 as f32 - from.1 as f32) / area.height as f32;
+        Some((dx, dy))
+    }
+
+    /// Index of the sidebar entry under the given position, if any.
+    pub fn sidebar_entry_at(&self, column: u16, row: u16) -> Option<usize> {
+        let area = self.sidebar?;
+        if !area.contains(Position::new(column, row)) {
+            return None;
+        }
+        Some(row.saturating_sub(area.y) as usize)
+    }
+}
+
+/// Inner (content) area of a bordered pane.
+pub fn pane_inner_area(area: Rect) -> Rect {
+    Rect::new(
+        area.x.saturating_add(1),
+        area.y.saturating_add(1),
+        area.width.saturating_sub(2),
+        area.height.saturating_sub(2),
+    )
+}
+
+/// Convert a screen position into a cell inside a pane's content area,
+/// clamping to the area so drags that leave the pane still extend the selection.
+pub fn clamp_to_inner(area: Rect, column: u16, row: u16) -> Option<(usize, usize)> {
+    let inner = pane_inner_area(area);
+    if inner.width == 0 || inner.height == 0 {
+        return None;
+    }
+    let col = column.clamp(inner.x, inner.right() - 1) - inner.x;
+    let row = row.clamp(inner.y, inner.bottom() - 1) - inner.y;
+    Some((row as usize, col as usize))
+}
+
+/// Compute a split ratio from the mouse position while dragging a border.
+pub fn ratio_for_drag(border: &SplitBorder, column: u16, row: u16) -> f32 {
+    use crate::mux::layout::Direction;
+
+    let (offset, extent) = match border.direction {
+        Direction::Vertical => (column.saturating_sub(border.area.x), border.area.width),
+        Direction::Horizontal => (row.saturating_sub(border.area.y), border.area.height),
+    };
+    if extent == 0 {
+        return 0.5;
+    }
+    // The first child ends just after the cell under the cursor.
+    (offset as f32 + 1.0) / extent as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mux::layout::Direction;
+
+    #[test]
+    fn selection_orders_and_contains() {
+        let pane_id = PaneId::new();
+        let mut selection = TextSelection::new(pane_id, 3, 5);
+        assert!(selection.is_empty());
+        selection.head = (1, 2);
+        assert_eq!(selection.ordered(), ((1, 2), (3, 5)));
+        assert!(selection.contains(1, 2));
+        assert!(!selection.contains(1, 1));
+        assert!(selection.contains(2, 0));
+        assert!(selection.contains(3, 5));
+        assert!(!selection.contains(3, 6));
+        assert!(!selection.contains(4, 0));
+    }
+
+    #[test]
+    fn regions_hit_test_tabs_and_sidebar() {
+        let regions = MouseRegions {
+            sidebar: Some(Rect::new(1, 1, 28, 10)),
+            tabs: vec![(30, 38), (39, 47)],
+            tab_bar: Some(Rect::new(30, 0, 50, 1)),
+            workspace: Some(Rect::new(30, 1, 50, 20)),
+        };
+        assert_eq!(regions.tab_at(30, 0), Some(0));
+        assert_eq!(regions.tab_at(38, 0), None);
+        assert_eq!(regions.tab_at(40, 0), Some(1));
+        assert_eq!(regions.tab_at(40, 1), None);
+        assert_eq!(regions.sidebar_entry_at(5, 1), Some(0));
+        assert_eq!(regions.sidebar_entry_at(5, 4), Some(3));
+        assert_eq!(regions.sidebar_entry_at(0, 4), None);
+        assert_eq!(regions.workspace_delta((30, 1), (35, 3)), Some((0.1, 0.1)));
+    }
+
+    #[test]
+    fn drag_ratio_tracks_cursor() {
+        let border = SplitBorder {
+            path: Vec::new(),
+            direction: Direction::Vertical,
+            area: Rect::new(10, 0, 100, 40),
+        };
+        assert!((ratio_for_drag(&border, 59, 5) - 0.5).abs() < f32::EPSILON);
+        assert!((ratio_for_drag(&border, 34, 5) - 0.25).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn clamp_to_inner_stays_inside_pane() {
+        let area = Rect::new(0, 0, 10, 5);
+        assert_eq!(clamp_to_inner(area, 0, 0), Some((0, 0)));
+        assert_eq!(clamp_to_inner(area, 50, 50), Some((2, 7)));
+        assert_eq!(clamp_to_inner(area, 3, 2), Some((1, 2)));
+    }
+}
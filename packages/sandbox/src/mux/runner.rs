@@ -2,9 +2,10 @@ use anyhow::Result;
 use crossterm::{
     cursor::SetCursorStyle,
     event::{
-        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
-        Event, EventStream, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
-        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+        DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture, Event, EventStream, KeyCode, KeyEventKind,
+        KeyModifiers, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
     },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
@@ -19,8 +20,12 @@ use tokio::time::MissedTickBehavior;
 
 use crate::mux::colors::{query_outer_terminal_colors, spawn_theme_change_listener};
 use crate::mux::commands::MuxCommand;
+use crate::mux::desktop_notify::DesktopNotifier;
 use crate::mux::events::MuxEvent;
 use crate::mux::layout::{ClosedTabInfo, PaneContent, PaneExitOutcome, SandboxId, TabId};
+use crate::mux::mouse::{
+    clamp_to_inner, pane_inner_area, ratio_for_drag, MouseDrag, TextSelection,
+};
 use crate::mux::onboard::{
     pull_image_with_progress, run_onboard_check, OnboardEvent, OnboardPhase, OnboardState,
 };
@@ -32,6 +37,7 @@ use crate::mux::terminal::{
 use crate::mux::ui::ui;
 use crate::sync_files::{detect_sync_files, upload_sync_files_with_list};
 use crate::terminal_guard;
+use crate::theme;
 
 /// Run the multiplexer TUI.
 ///
@@ -40,7 +46,13 @@ use crate::terminal_guard;
 pub async fn run_mux_tui(base_url: String, workspace_path: Option<PathBuf>) -> Result<()> {
     // Query outer terminal colors BEFORE entering alternate screen
     // This allows us to inherit the host terminal's theme
-    let _outer_colors = query_outer_terminal_colors();
+    let outer_colors = query_outer_terminal_colors();
+
+    // Resolve the color theme while the terminal can still answer queries
+    theme::init(
+        crate::settings::Settings::load().theme_name(),
+        outer_colors.background,
+    );
 
     let mut stdout = std::io::stdout();
     execute!(
@@ -48,6 +60,7 @@ pub async fn run_mux_tui(base_url: String, workspace_path: Option<PathBuf>) -> R
         EnterAlternateScreen,
         EnableMouseCapture,
         EnableBracketedPaste,
+        EnableFocusChange,
         PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::all())
     )?;
     enable_raw_mode()?;
@@ -71,6 +84,7 @@ pub async fn run_mux_tui(base_url: String, workspace_path: Option<PathBuf>) -> R
     if let Err(e) = execute!(
         terminal.backend_mut(),
         PopKeyboardEnhancementFlags,
+        DisableFocusChange,
         DisableBracketedPaste,
         DisableMouseCapture,
         LeaveAlternateScreen
@@ -104,6 +118,7 @@ async fn run_main_loop<B: ratatui::backend::Backend + std::io::Write>(
     let (event_tx, event_rx) = mpsc::unbounded_channel();
 
     let mut app = MuxApp::new(base_url.clone(), event_tx.clone(), workspace.clone());
+    app.desktop_notifier = Some(DesktopNotifier::spawn(event_tx.clone()));
 
     // Create terminal manager
     let terminal_manager = create_terminal_manager(base_url.clone(), event_tx.clone());
@@ -126,6 +141,13 @@ async fn run_main_loop<B: ratatui::backend::Backend + std::io::Write>(
         refresh_sandboxes_periodically(refresh_url, refresh_tx).await;
     });
 
+    // Sample sandbox CPU/memory for the sidebar sparklines
+    let stats_tx = event_tx.clone();
+    let stats_url = base_url.clone();
+    tokio::spawn(async move {
+        poll_sandbox_stats_periodically(stats_url, stats_tx).await;
+    });
+
     // Always create a new sandbox on startup with the current working directory
     let init_tx = event_tx.clone();
     let init_url = base_url.clone();
@@ -198,6 +220,9 @@ async fn run_app<B: ratatui::backend::Backend + std::io::Write>(
                 if had_status && app.status_message.is_none() {
                     redraw_needed = true;
                 }
+                if app.refresh_activity(std::time::Instant::now()) {
+                    redraw_needed = true;
+                }
             }
             _ = render_tick.tick(), if redraw_needed => {
                 terminal.draw(|f| ui(f, &mut app))?;
@@ -290,6 +315,14 @@ async fn run_app<B: ratatui::backend::Backend + std::io::Write>(
                         // Query colors now that we're in normal screen mode
                         let new_colors = crate::mux::colors::query_outer_terminal_colors();
 
+                        // Follow the new background when the theme is automatic
+                        if app.settings.theme_name() == theme::AUTO_THEME {
+                            if let Some(background) = new_colors.background {
+                                theme::set_background(background);
+                                let _ = theme::apply_theme(theme::AUTO_THEME);
+                            }
+                        }
+
                         // Re-enable raw mode and re-enter alternate screen
                         let _ = enable_raw_mode();
                         let _ = execute!(terminal.backend_mut(), EnterAlternateScreen);
@@ -357,8 +390,18 @@ async fn run_app<B: ratatui::backend::Backend + std::io::Write>(
                 // 1. Bash and most shells don't handle focus tracking and just echo ^[[I
                 // 2. Querying colors on focus is problematic (responses leak or cause flicker)
                 // Colors are queried at startup before entering alt screen.
-                if matches!(event, Event::FocusGained | Event::FocusLost) {
-                    continue;
+                // Focus is still tracked so desktop notifications can be
+                // suppressed while the user is looking at the mux.
+                match event {
+                    Event::FocusGained => {
+                        app.terminal_focused = true;
+                        continue;
+                    }
+                    Event::FocusLost => {
+                        app.terminal_focused = false;
+                        continue;
+                    }
+                    _ => {}
                 }
 
                 if handle_input(&mut app, event, &terminal_manager) {
@@ -429,7 +472,7 @@ fn connect_sandbox_terminal(
     // Update the pane's sandbox_id in the workspace
     if let Some(ws) = app.workspace_manager.get_workspace_mut(sandbox_layout_id) {
         if let Some(tab) = ws.active_tab_mut() {
-            if let Some(pane) = tab.layout.find_pane_mut(pane_id) {
+            if let Some(pane) = tab.find_pane_mut(pane_id) {
                 if let PaneContent::Terminal {
                     sandbox_id: pane_sandbox,
                     ..
@@ -456,7 +499,7 @@ fn connect_sandbox_terminal(
         .workspace_manager
         .get_workspace(sandbox_layout_id)
         .and_then(|ws| ws.active_tab())
-        .and_then(|tab| tab.layout.find_pane(pane_id))
+        .and_then(|tab| tab.find_pane(pane_id))
         .and_then(pane_content_dimensions)
         .unwrap_or_else(fallback_terminal_size);
 
@@ -464,15 +507,24 @@ fn connect_sandbox_terminal(
     let manager = terminal_manager.clone();
     let event_tx = app.event_tx.clone();
     let sandbox_id_owned = sandbox_id.to_string();
-    let tab_id = app
+    let active_tab = app
         .workspace_manager
         .get_workspace(sandbox_layout_id)
-        .and_then(|ws| ws.active_tab())
-        .map(|tab| tab.id);
+        .and_then(|ws| ws.active_tab());
+    let tab_id = active_tab.map(|tab| tab.id);
+    let command = active_tab.and_then(|tab| tab.pane_command(pane_id));
 
     tokio::spawn(async move {
-        if let Err(e) =
-            connect_to_sandbox(manager, pane_id, sandbox_id_owned, tab_id, cols, rows).await
+        if let Err(e) = connect_to_sandbox(
+            manager,
+            pane_id,
+            sandbox_id_owned,
+            tab_id,
+            cols,
+            rows,
+            command,
+        )
+        .await
         {
             let _ = event_tx.send(MuxEvent::Error(format!(
                 "Failed to connect to sandbox: {}",
@@ -503,7 +555,9 @@ fn sync_terminal_sizes(
     };
 
     let mut targets: Vec<(crate::mux::layout::PaneId, u16, u16)> = Vec::new();
-    for pane in tab.layout.panes() {
+    // Floating panes are sized even while hidden so they resume at the right size
+    let floating = tab.floating.iter().map(|fp| &fp.pane);
+    for pane in tab.layout.panes().into_iter().chain(floating) {
         if !matches!(pane.content, PaneContent::Terminal { .. }) {
             continue;
         }
@@ -531,6 +585,7 @@ fn handle_terminal_exit_for_pane(
 ) {
     let mut pane_ids_to_cleanup = vec![pane_id];
 
+    app.record_pane_exit(pane_id);
     match app.workspace_manager.handle_pane_exit(pane_id) {
         Some(PaneExitOutcome::TabClosed(info)) => {
             let ClosedTabInfo {
@@ -613,6 +668,20 @@ fn handle_input(
                 }
             }
 
+            // Handle floating pane command prompt
+            if app.floating_command_input.is_some() {
+                match key.code {
+                    KeyCode::Enter => app.finish_floating_pane_prompt(true),
+                    KeyCode::Esc => app.finish_floating_pane_prompt(false),
+                    _ => {
+                        if let Some(input) = &mut app.floating_command_input {
+                            input.input(key);
+                        }
+                    }
+                }
+                return false;
+            }
+
             // Handle notifications overlay
             if app.notifications.is_open && app.focus == FocusArea::Notifications {
                 match key.code {
@@ -918,155 +987,354 @@ fn handle_input(
             }
         }
         Event::Mouse(mouse_event) => {
-            // Handle mouse events (scrolling, clicking)
-            use crossterm::event::{MouseButton, MouseEventKind};
-
-            // First check if terminal has mouse mode enabled and we should forward
+            handle_mouse_event(app, mouse_event, terminal_manager);
+        }
+        Event::Paste(text) => {
+            // Forward paste to active terminal
             if let Some(pane_id) = app.active_pane_id() {
-                if app.focus == FocusArea::MainArea {
-                    // Get the pane's area to check if mouse is inside and compute relative coords
-                    let pane_area = app
-                        .active_tab()
-                        .and_then(|tab| tab.layout.find_pane(pane_id).and_then(|p| p.area));
-
-                    if let Some(area) = pane_area {
-                        // Account for border (1 cell)
-                        let inner_x = area.x.saturating_add(1);
-                        let inner_y = area.y.saturating_add(1);
-                        let inner_w = area.width.saturating_sub(2);
-                        let inner_h = area.height.saturating_sub(2);
-
-                        // Check if mouse is inside pane content area
-                        if mouse_event.column >= inner_x
-                            && mouse_event.column < inner_x + inner_w
-                            && mouse_event.row >= inner_y
-                            && mouse_event.row < inner_y + inner_h
-                        {
-                            // Compute relative coordinates (0-indexed for URL detection, 1-indexed for protocol)
-                            let rel_col_0 = mouse_event.column.saturating_sub(inner_x) as usize;
-                            let rel_row_0 = mouse_event.row.saturating_sub(inner_y) as usize;
-                            let rel_col = (rel_col_0 + 1) as u16;
-                            let rel_row = (rel_row_0 + 1) as u16;
-
-                            // Handle Cmd+Click (macOS) or Ctrl+Click (other platforms) to open URLs
-                            #[cfg(target_os = "macos")]
-                            let open_url_modifier = KeyModifiers::SUPER;
-                            #[cfg(not(target_os = "macos"))]
-                            let open_url_modifier = KeyModifiers::CONTROL;
-
-                            if matches!(mouse_event.kind, MouseEventKind::Down(MouseButton::Left))
-                                && mouse_event.modifiers.contains(open_url_modifier)
-                            {
-                                tracing::debug!(
-                                    "URL click: row={}, col={}, pane={:?}",
-                                    rel_row_0,
-                                    rel_col_0,
-                                    pane_id
-                                );
-
-                                // Use catch_unwind to prevent panics from crashing the TUI
-                                let url_result =
-                                    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
-                                        if let Ok(guard) = terminal_manager.try_lock() {
-                                            if let Some(buffer) = guard.get_buffer(pane_id) {
-                                                tracing::debug!(
-                                                    "Buffer found, grid size: {}",
-                                                    buffer.rows()
-                                                );
-                                                return buffer
-                                                    .url_at_position(rel_row_0, rel_col_0);
-                                            } else {
-                                                tracing::debug!("No buffer for pane {:?}", pane_id);
-                                            }
-                                        } else {
-                                            tracing::debug!("Failed to lock terminal_manager");
-                                        }
-                                        None
-                                    }));
-
-                                match url_result {
-                                    Ok(Some(url)) => {
-                                        tracing::info!("Opening URL: {}", url);
-                                        let _ = open::that(&url);
-                                        app.set_status(format!("Opening: {}", url));
-                                        return false;
-                                    }
-                                    Ok(None) => {
-                                        tracing::debug!("No URL found at position");
-                                        // No URL found at position, continue
-                                    }
-                                    Err(e) => {
-                                        tracing::error!("URL detection panicked: {:?}", e);
-                                        app.set_status(
-                                            "Error: URL detection failed (internal error)"
-                                                .to_string(),
-                                        );
-                                        return false;
-                                    }
-                                }
-                            }
+                if let Ok(mut guard) = terminal_manager.try_lock() {
+                    guard.send_input(pane_id, text.into_bytes());
+                }
+            }
+        }
+        _ => {}
+    }
 
-                            if let Ok(mut guard) = terminal_manager.try_lock() {
-                                let (mouse_mode, sgr_mode) = guard
-                                    .get_buffer(pane_id)
-                                    .map(|b| (b.mouse_tracking(), b.sgr_mouse_mode()))
-                                    .unwrap_or((None, false));
-
-                                if let Some(mode) = mouse_mode {
-                                    // Forward mouse event to terminal
-                                    if let Some(seq) = encode_mouse_event(
-                                        mouse_event.kind,
-                                        mouse_event.modifiers,
-                                        rel_col,
-                                        rel_row,
-                                        mode,
-                                        sgr_mode,
-                                    ) {
-                                        guard.send_input(pane_id, seq);
-                                        return false; // Event handled, don't process locally
-                                    }
-                                }
+    false
+}
+
+/// Handle a mouse event: pane focus, border/tab drags, sidebar clicks, text
+/// selection, and forwarding to applications that enabled mouse tracking.
+fn handle_mouse_event(
+    app: &mut MuxApp<'_>,
+    mouse_event: crossterm::event::MouseEvent,
+    terminal_manager: &crate::mux::terminal::SharedTerminalManager,
+) {
+    use crossterm::event::{MouseButton, MouseEventKind};
+
+    let (column, row) = (mouse_event.column, mouse_event.row);
+
+    // Overlays own the screen while open
+    if matches!(
+        app.focus,
+        FocusArea::CommandPalette | FocusArea::Notifications | FocusArea::Onboard
+    ) {
+        return;
+    }
+
+    // Continue or finish a drag that started on a border, tab, or pane
+    if let Some(drag) = app.mouse_drag.clone() {
+        match mouse_event.kind {
+            MouseEventKind::Drag(MouseButton::Left) => {
+                match drag {
+                    MouseDrag::ResizeSplit(border) => {
+                        let ratio = ratio_for_drag(&border, column, row);
+                        if let Some(tab) = app.active_tab_mut() {
+                            tab.layout.set_split_ratio(&border.path, ratio);
+                        }
+                    }
+                    MouseDrag::MoveTab { index } => {
+                        let bar_row = app.mouse_regions.tab_bar.map(|bar| bar.y).unwrap_or(row);
+                        if let Some(target) = app.mouse_regions.tab_at(column, bar_row) {
+                            if target != index {
+                                app.workspace_manager.move_tab(index, target);
+                                app.mouse_drag = Some(MouseDrag::MoveTab { index: target });
                             }
                         }
                     }
-                }
-            }
-
-            // Handle locally if not forwarded to terminal
-            match mouse_event.kind {
-                MouseEventKind::ScrollUp => {
-                    if let Some(pane_id) = app.active_pane_id() {
-                        if let Ok(mut guard) = terminal_manager.try_lock() {
-                            if let Some(buffer) = guard.get_buffer_mut(pane_id) {
-                                buffer.scroll_up(3);
+                    MouseDrag::MoveFloating { pane_id, last } => {
+                        let delta = app.mouse_regions.workspace_delta(last, (column, row));
+                        if let (Some((dx, dy)), Some(tab)) = (delta, app.active_tab_mut()) {
+                            if let Some(floating) = tab.floating_mut(pane_id) {
+                                floating.geometry.translate(dx, dy);
                             }
                         }
+                        app.mouse_drag = Some(MouseDrag::MoveFloating {
+                            pane_id,
+                            last: (column, row),
+                        });
                     }
-                }
-                MouseEventKind::ScrollDown => {
-                    if let Some(pane_id) = app.active_pane_id() {
-                        if let Ok(mut guard) = terminal_manager.try_lock() {
-                            if let Some(buffer) = guard.get_buffer_mut(pane_id) {
-                                buffer.scroll_down(3);
+                    MouseDrag::ResizeFloating { pane_id, last } => {
+                        let delta = app.mouse_regions.workspace_delta(last, (column, row));
+                        if let (Some((dw, dh)), Some(tab)) = (delta, app.active_tab_mut()) {
+                            if let Some(floating) = tab.floating_mut(pane_id) {
+                                floating.geometry.resize(dw, dh);
                             }
                         }
+                        app.mouse_drag = Some(MouseDrag::ResizeFloating {
+                            pane_id,
+                            last: (column, row),
+                        });
+                    }
+                    MouseDrag::Select => {
+                        extend_selection(app, column, row);
                     }
                 }
-                _ => {}
+                return;
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                app.mouse_drag = None;
+                if drag == MouseDrag::Select {
+                    finish_selection(app, terminal_manager);
+                }
+                return;
             }
+            _ => {}
         }
-        Event::Paste(text) => {
-            // Forward paste to active terminal
-            if let Some(pane_id) = app.active_pane_id() {
-                if let Ok(mut guard) = terminal_manager.try_lock() {
-                    guard.send_input(pane_id, text.into_bytes());
+    }
+
+    if matches!(mouse_event.kind, MouseEventKind::Down(MouseButton::Left)) {
+        clear_selection(app);
+
+        if let Some(index) = app.mouse_regions.sidebar_entry_at(column, row) {
+            if let Some(id) = app.sidebar.sandboxes.get(index).map(|s| s.id) {
+                app.sidebar.select_by_id(id);
+                select_sidebar_sandbox(app);
+                app.focus = FocusArea::MainArea;
+            }
+            return;
+        }
+
+        if let Some(index) = app.mouse_regions.tab_at(column, row) {
+            app.workspace_manager.go_to_tab(index);
+            app.mouse_drag = Some(MouseDrag::MoveTab { index });
+            return;
+        }
+
+        // Floating panes sit above split borders: raise on click, move by the
+        // title bar, resize by the bottom-right corner
+        let floating_hit = app
+            .active_tab()
+            .filter(|_| app.zoomed_pane.is_none())
+            .and_then(|tab| {
+                let pane_id = tab.pane_at(column, row)?;
+                if !tab.is_floating(pane_id) {
+                    return None;
+                }
+                Some((pane_id, tab.find_pane(pane_id)?.area?))
+            });
+        if let Some((pane_id, area)) = floating_hit {
+            if let Some(tab) = app.active_tab_mut() {
+                tab.raise_floating(pane_id);
+                tab.active_pane = Some(pane_id);
+            }
+            app.focus = FocusArea::MainArea;
+            let last = (column, row);
+            if row == area.y {
+                app.mouse_drag = Some(MouseDrag::MoveFloating { pane_id, last });
+                return;
+            }
+            if column == area.right().saturating_sub(1) && row == area.bottom().saturating_sub(1) {
+                app.mouse_drag = Some(MouseDrag::ResizeFloating { pane_id, last });
+                return;
+            }
+        } else if app.zoomed_pane.is_none() {
+            let border = app
+                .active_tab()
+                .and_then(|tab| tab.layout.border_at(column, row));
+            if let Some(border) = border {
+                app.mouse_drag = Some(MouseDrag::ResizeSplit(border));
+                return;
+            }
+        }
+    }
+
+    // Find the pane under the cursor (only the zoomed pane is visible when zoomed)
+    let target = app.active_tab().and_then(|tab| {
+        let pane_id = match app.zoomed_pane {
+            Some(zoomed) => zoomed,
+            None => tab.pane_at(column, row)?,
+        };
+        let area = tab.find_pane(pane_id)?.area?;
+        Some((pane_id, area))
+    });
+    let Some((pane_id, area)) = target else {
+        return;
+    };
+
+    if matches!(mouse_event.kind, MouseEventKind::Down(_)) {
+        if let Some(tab) = app.active_tab_mut() {
+            tab.active_pane = Some(pane_id);
+        }
+        app.focus = FocusArea::MainArea;
+    }
+
+    let inner = pane_inner_area(area);
+    if inner.contains((column, row).into()) {
+        // Compute relative coordinates (0-indexed for URL detection, 1-indexed for protocol)
+        let rel_col_0 = column.saturating_sub(inner.x) as usize;
+        let rel_row_0 = row.saturating_sub(inner.y) as usize;
+        let rel_col = (rel_col_0 + 1) as u16;
+        let rel_row = (rel_row_0 + 1) as u16;
+
+        // Handle Cmd+Click (macOS) or Ctrl+Click (other platforms) to open URLs
+        #[cfg(target_os = "macos")]
+        let open_url_modifier = KeyModifiers::SUPER;
+        #[cfg(not(target_os = "macos"))]
+        let open_url_modifier = KeyModifiers::CONTROL;
+
+        if matches!(mouse_event.kind, MouseEventKind::Down(MouseButton::Left))
+            && mouse_event.modifiers.contains(open_url_modifier)
+        {
+            tracing::debug!(
+                "URL click: row={}, col={}, pane={:?}",
+                rel_row_0,
+                rel_col_0,
+                pane_id
+            );
+
+            // Use catch_unwind to prevent panics from crashing the TUI
+            let url_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                if let Ok(guard) = terminal_manager.try_lock() {
+                    if let Some(buffer) = guard.get_buffer(pane_id) {
+                        tracing::debug!("Buffer found, grid size: {}", buffer.rows());
+                        return buffer.url_at_position(rel_row_0, rel_col_0);
+                    } else {
+                        tracing::debug!("No buffer for pane {:?}", pane_id);
+                    }
+                } else {
+                    tracing::debug!("Failed to lock terminal_manager");
+                }
+                None
+            }));
+
+            match url_result {
+                Ok(Some(url)) => {
+                    tracing::info!("Opening URL: {}", url);
+                    let _ = open::that(&url);
+                    app.set_status(format!("Opening: {}", url));
+                    return;
+                }
+                Ok(None) => {
+                    tracing::debug!("No URL found at position");
+                    // No URL found at position, continue
+                }
+                Err(e) => {
+                    tracing::error!("URL detection panicked: {:?}", e);
+                    app.set_status("Error: URL detection failed (internal error)".to_string());
+                    return;
+                }
+            }
+        }
+
+        if let Ok(mut guard) = terminal_manager.try_lock() {
+            let (mouse_mode, sgr_mode) = guard
+                .get_buffer(pane_id)
+                .map(|b| (b.mouse_tracking(), b.sgr_mouse_mode()))
+                .unwrap_or((None, false));
+
+            if let Some(mode) = mouse_mode {
+                // Forward mouse event to terminal
+                if let Some(seq) = encode_mouse_event(
+                    mouse_event.kind,
+                    mouse_event.modifiers,
+                    rel_col,
+                    rel_row,
+                    mode,
+                    sgr_mode,
+                ) {
+                    guard.send_input(pane_id, seq);
+                }
+                // The application owns the mouse; don't handle the event locally
+                return;
+            }
+        }
+
+        // No mouse tracking: a left press starts a text selection
+        if matches!(mouse_event.kind, MouseEventKind::Down(MouseButton::Left)) {
+            app.selection = Some(TextSelection::new(pane_id, rel_row_0, rel_col_0));
+            app.mouse_drag = Some(MouseDrag::Select);
+            return;
+        }
+    }
+
+    // Handle locally if not forwarded to terminal
+    match mouse_event.kind {
+        MouseEventKind::ScrollUp => {
+            if let Ok(mut guard) = terminal_manager.try_lock() {
+                if let Some(buffer) = guard.get_buffer_mut(pane_id) {
+                    buffer.scroll_up(3);
+                }
+            }
+        }
+        MouseEventKind::ScrollDown => {
+            if let Ok(mut guard) = terminal_manager.try_lock() {
+                if let Some(buffer) = guard.get_buffer_mut(pane_id) {
+                    buffer.scroll_down(3);
                 }
             }
         }
         _ => {}
     }
+}
 
-    false
+/// Move the head of the active selection to the given screen position.
+fn extend_selection(app: &mut MuxApp<'_>, column: u16, row: u16) {
+    let Some(selection) = app.selection else {
+        return;
+    };
+    let area = app
+        .active_tab()
+        .and_then(|tab| tab.find_pane(selection.pane_id))
+        .and_then(|pane| pane.area);
+    if let Some(head) = area.and_then(|area| clamp_to_inner(area, column, row)) {
+        app.selection = Some(TextSelection { head, ..selection });
+    }
+}
+
+/// Copy the finished selection to the clipboard. A click without a drag clears it.
+fn finish_selection(
+    app: &mut MuxApp<'_>,
+    terminal_manager: &crate::mux::terminal::SharedTerminalManager,
+) {
+    let Some(selection) = app.selection else {
+        return;
+    };
+    if selection.is_empty() {
+        clear_selection(app);
+        return;
+    }
+
+    let height = app
+        .active_tab()
+        .and_then(|tab| tab.find_pane(selection.pane_id))
+        .and_then(|pane| pane.area)
+        .map(|area| pane_inner_area(area).height as usize)
+        .unwrap_or(0);
+    let (start, end) = selection.ordered();
+    let text = terminal_manager
+        .try_lock()
+        .ok()
+        .and_then(|guard| {
+            guard
+                .get_buffer(selection.pane_id)
+                .map(|buffer| buffer.selection_text(height, start, end))
+        })
+        .unwrap_or_default();
+
+    if text.is_empty() {
+        return;
+    }
+    match arboard::Clipboard::new() {
+        Ok(mut clipboard) => match clipboard.set_text(&text) {
+            Ok(()) => {
+                app.set_status(format!("Copied {} characters", text.chars().count()));
+            }
+            Err(e) => {
+                app.set_status(format!("Failed to copy: {}", e));
+            }
+        },
+        Err(e) => {
+            app.set_status(format!("Clipboard not available: {}", e));
+        }
+    }
+}
+
+/// Drop the current selection and force its pane to redraw without highlighting.
+fn clear_selection(app: &mut MuxApp<'_>) {
+    if let Some(selection) = app.selection.take() {
+        app.last_terminal_views.remove(&selection.pane_id);
+    }
 }
 
 /// Select the currently highlighted sandbox in the sidebar and switch to its workspace.
@@ -1389,6 +1657,68 @@ async fn refresh_sandboxes(
     Ok(sandboxes)
 }
 
+/// Periodically sample resource usage of running sandboxes.
+async fn poll_sandbox_stats_periodically(base_url: String, tx: mpsc::UnboundedSender<MuxEvent>) {
+    let client = reqwest::Client::new();
+    let mut interval = tokio::time::interval(Duration::from_secs(2));
+    loop {
+        interval.tick().await;
+        match fetch_sandbox_stats(&client, &base_url).await {
+            Ok(stats) => {
+                if tx.send(MuxEvent::SandboxStatsUpdated(stats)).is_err() {
+                    break;
+                }
+            }
+            Err(e) => tracing::debug!("Failed to fetch sandbox stats: {}", e),
+        }
+    }
+}
+
+/// Fetch resource usage for every running sandbox.
+async fn fetch_sandbox_stats(
+    client: &reqwest::Client,
+    base_url: &str,
+) -> Result<Vec<crate::models::SandboxStats>, anyhow::Error> {
+    let base_url = base_url.trim_end_matches('/');
+    let sandboxes: Vec<crate::models::SandboxSummary> = client
+        .get(format!("{}/sandboxes", base_url))
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let mut stats = Vec::new();
+    for sandbox in sandboxes
+        .iter()
+        .filter(|s| s.status == crate::models::SandboxStatus::Running)
+    {
+        // A sandbox may have been deleted since the list was fetched, or fail
+        // to report; skip it rather than blanking the whole view.
+        match fetch_one_sandbox_stats(client, base_url, sandbox.id).await {
+            Ok(entry) => stats.push(entry),
+            Err(e) => tracing::debug!("skipping stats for sandbox {}: {}", sandbox.id, e),
+        }
+    }
+    Ok(stats)
+}
+
+async fn fetch_one_sandbox_stats(
+    client: &reqwest::Client,
+    base_url: &str,
+    id: uuid::Uuid,
+) -> Result<crate::models::SandboxStats, anyhow::Error> {
+    Ok(client
+        .get(format!("{}/sandboxes/{}/stats", base_url, id))
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
 /// Upload a workspace directory to a sandbox.
 async fn upload_workspace(
     client: &reqwest::Client,
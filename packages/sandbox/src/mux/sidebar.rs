@@ -1,6 +1,79 @@
-use crate::models::{SandboxStatus, SandboxSummary};
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
+
+use crate::models::{SandboxStats, SandboxStatus, SandboxSummary};
+use crate::mux::activity::ActivityBadge;
+use crate::theme::Theme;
 use uuid::Uuid;
 
+/// Number of CPU samples kept per sandbox for the sidebar sparkline.
+pub const USAGE_HISTORY_LEN: usize = 6;
+
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Recent resource usage of a sandbox, derived from successive stats samples.
+#[derive(Debug, Clone, Default)]
+pub struct SandboxUsage {
+    /// CPU utilization samples in percent of one core, oldest first.
+    pub cpu_history: VecDeque<u64>,
+    pub memory_bytes: u64,
+    /// Previous cumulative CPU time and when it was sampled
+    last_sample: Option<(u64, Instant)>,
+}
+
+impl SandboxUsage {
+    /// Add a stats sample. CPU utilization needs two samples, so the first
+    /// sample only records memory.
+    pub fn record(&mut self, stats: &SandboxStats, now: Instant) {
+        if let Some((prev_cpu_ms, prev_at)) = self.last_sample {
+            let elapsed_ms = now.duration_since(prev_at).as_millis() as u64;
+            let used_ms = stats.cpu_time_ms.saturating_sub(prev_cpu_ms);
+            if let Some(percent) = (used_ms * 100).checked_div(elapsed_ms) {
+                self.cpu_history.push_back(percent);
+                while self.cpu_history.len() > USAGE_HISTORY_LEN {
+                    self.cpu_history.pop_front();
+                }
+            }
+        }
+        self.last_sample = Some((stats.cpu_time_ms, now));
+        self.memory_bytes = stats.memory_bytes;
+    }
+
+    /// Latest CPU utilization, if at least two samples were recorded.
+    pub fn cpu_percent(&self) -> Option<u64> {
+        self.cpu_history.back().copied()
+    }
+
+    /// CPU history as a right-aligned sparkline of `USAGE_HISTORY_LEN` cells.
+    /// Scaled to one full core, or to the peak if the sandbox used more.
+    pub fn cpu_sparkline(&self) -> String {
+        let peak = self.cpu_history.iter().copied().max().unwrap_or(0).max(100);
+        let padding = USAGE_HISTORY_LEN.saturating_sub(self.cpu_history.len());
+        let mut line = " ".repeat(padding);
+        for &sample in &self.cpu_history {
+            let level = (sample * (SPARK_LEVELS.len() as u64 - 1) / peak) as usize;
+            line.push(SPARK_LEVELS[level.min(SPARK_LEVELS.len() - 1)]);
+        }
+        line
+    }
+}
+
+/// Format a byte count compactly for the sidebar (e.g. "512M", "1.5G").
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 && unit > 0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
 /// State for the sidebar showing sandbox list.
 ///
 /// ARCHITECTURE: Selection is tracked by ID, not index.
@@ -17,6 +90,8 @@ pub struct Sidebar {
     pub last_error: Option<String>,
     pub visible: bool,
     pub width: u16,
+    /// Resource usage per sandbox, updated from periodic stats polling
+    pub usage: HashMap<Uuid, SandboxUsage>,
 }
 
 impl Default for Sidebar {
@@ -35,6 +110,7 @@ impl Sidebar {
             last_error: None,
             visible: true,
             width: 30,
+            usage: HashMap::new(),
         }
     }
 
@@ -53,6 +129,9 @@ impl Sidebar {
         self.sandboxes = sandboxes;
         self.is_loading = false;
         self.last_error = None;
+        let sandboxes = &self.sandboxes;
+        self.usage
+            .retain(|id, _| sandboxes.iter().any(|s| s.id == *id));
 
         // If selection no longer exists in list, clear it or select first
         if let Some(id) = self.selected_id {
@@ -65,6 +144,13 @@ impl Sidebar {
         }
     }
 
+    /// Record a batch of resource usage samples.
+    pub fn record_stats(&mut self, stats: &[SandboxStats], now: Instant) {
+        for sample in stats {
+            self.usage.entry(sample.id).or_default().record(sample, now);
+        }
+    }
+
     /// Set an error state.
     pub fn set_error(&mut self, error: String) {
         self.is_loading = false;
@@ -141,13 +227,23 @@ impl Sidebar {
     }
 
     /// Get status color for a sandbox.
-    pub fn status_color(status: &SandboxStatus) -> ratatui::style::Color {
+    pub fn status_color(status: &SandboxStatus, theme: &Theme) -> ratatui::style::Color {
         match status {
-            SandboxStatus::Creating => ratatui::style::Color::Cyan,
-            SandboxStatus::Running => ratatui::style::Color::Green,
-            SandboxStatus::Exited => ratatui::style::Color::DarkGray,
-            SandboxStatus::Failed => ratatui::style::Color::Red,
-            SandboxStatus::Unknown => ratatui::style::Color::Yellow,
+            SandboxStatus::Creating => theme.sidebar.creating,
+            SandboxStatus::Running => theme.sidebar.running,
+            SandboxStatus::Exited => theme.sidebar.exited,
+            SandboxStatus::Failed => theme.sidebar.failed,
+            SandboxStatus::Unknown => theme.sidebar.unknown,
+        }
+    }
+
+    /// Get the color for an activity badge.
+    pub fn badge_color(badge: ActivityBadge, theme: &Theme) -> ratatui::style::Color {
+        match badge {
+            ActivityBadge::Output => theme.ui.accent,
+            ActivityBadge::Silence => theme.ui.success,
+            ActivityBadge::Exited => theme.ui.muted,
+            ActivityBadge::Bell => theme.ui.warning,
         }
     }
 
@@ -258,6 +354,30 @@ mod tests {
         assert_eq!(Sidebar::status_icon(&SandboxStatus::Unknown), "?");
     }
 
+    #[test]
+    fn usage_derives_cpu_percent_from_samples() {
+        let id = Uuid::new_v4();
+        let start = Instant::now();
+        let mut usage = SandboxUsage::default();
+        let sample = |cpu_time_ms| SandboxStats {
+            id,
+            cpu_time_ms,
+            memory_bytes: 512 * 1024 * 1024,
+            process_count: 3,
+        };
+
+        usage.record(&sample(1_000), start);
+        assert_eq!(usage.cpu_percent(), None);
+        assert_eq!(usage.cpu_sparkline(), " ".repeat(USAGE_HISTORY_LEN));
+
+        // 1s of CPU over 2s of wall time = 50%
+        usage.record(&sample(2_000), start + std::time::Duration::from_secs(2));
+        assert_eq!(usage.cpu_percent(), Some(50));
+        assert_eq!(usage.cpu_sparkline(), "     ▄");
+        assert_eq!(format_bytes(usage.memory_bytes), "512M");
+        assert_eq!(format_bytes(3 * 1024 * 1024 * 1024 / 2), "1.5G");
+    }
+
     #[test]
     fn name_truncation_works() {
         let sandbox = create_test_sandbox("very-long-sandbox-name", SandboxStatus::Running);
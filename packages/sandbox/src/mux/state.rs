@@ -5,14 +5,21 @@ use chrono::{DateTime, Utc};
 use tokio::sync::mpsc;
 
 use crate::models::{NotificationLevel, SandboxNetwork, SandboxStatus, SandboxSummary};
+use crate::mux::activity::ActivityTracker;
 use crate::mux::commands::MuxCommand;
+use crate::mux::desktop_notify::{
+    DesktopNotification, DesktopNotifier, NotificationContext, NotificationFilter,
+    NotificationSource, NotificationTarget,
+};
 use crate::mux::events::MuxEvent;
 use crate::mux::layout::{Direction, NavDirection, Pane, PaneId, SandboxId, WorkspaceManager};
+use crate::mux::mouse::{MouseDrag, MouseRegions, TextSelection};
 use crate::mux::onboard::OnboardState;
 use crate::mux::palette::CommandPalette;
 use crate::mux::sidebar::Sidebar;
 use crate::mux::terminal::{SharedTerminalManager, TerminalRenderView};
 use crate::settings::{EditorChoice, Settings};
+use crate::theme;
 use uuid::Uuid;
 
 /// Result of ensuring SSH config is set up for sandboxes.
@@ -335,6 +342,9 @@ pub struct MuxApp<'a> {
     pub renaming_tab: bool,
     pub rename_input: Option<tui_textarea::TextArea<'a>>,
 
+    // Command prompt for a new floating pane (open while Some)
+    pub floating_command_input: Option<tui_textarea::TextArea<'a>>,
+
     // Terminal manager for handling sandbox connections
     pub terminal_manager: Option<SharedTerminalManager>,
 
@@ -369,6 +379,23 @@ pub struct MuxApp<'a> {
 
     /// Persistent settings (editor choice, etc.)
     pub settings: Settings,
+
+    /// Screen regions from the last render, used for mouse hit-testing
+    pub mouse_regions: MouseRegions,
+    /// Drag gesture in progress (border resize, tab move, text selection)
+    pub mouse_drag: Option<MouseDrag>,
+    /// Current text selection in a pane that doesn't track the mouse
+    pub selection: Option<TextSelection>,
+
+    /// Per-pane activity (unseen output, bells, silence, exits) for sidebar badges
+    pub activity: ActivityTracker,
+
+    /// Native desktop notifications (None until the runner starts it)
+    pub desktop_notifier: Option<DesktopNotifier>,
+    /// Level/sandbox rules and rate limiting for desktop notifications
+    pub notification_filter: NotificationFilter,
+    /// Whether the outer terminal window has focus (from focus reporting)
+    pub terminal_focused: bool,
 }
 
 impl<'a> MuxApp<'a> {
@@ -395,6 +422,7 @@ impl<'a> MuxApp<'a> {
             status_message: None,
             renaming_tab: false,
             rename_input: None,
+            floating_command_input: None,
             terminal_manager: None,
             pending_connects: std::collections::VecDeque::new(),
             needs_initial_sandbox: false,
@@ -406,6 +434,13 @@ impl<'a> MuxApp<'a> {
             pending_creation_tab_ids: HashSet::new(),
             most_recent_creation_tab_id: None,
             settings: Settings::load(),
+            mouse_regions: MouseRegions::default(),
+            mouse_drag: None,
+            selection: None,
+            activity: ActivityTracker::default(),
+            desktop_notifier: None,
+            notification_filter: NotificationFilter::new(),
+            terminal_focused: true,
         }
     }
 
@@ -470,6 +505,177 @@ impl<'a> MuxApp<'a> {
         self.workspace_manager.active_tab_mut()
     }
 
+    /// Panes currently on screen (the zoomed pane, or the active tab's visible panes).
+    pub fn viewed_pane_ids(&self) -> Vec<PaneId> {
+        let Some(tab) = self.active_tab() else {
+            return Vec::new();
+        };
+        match self.zoomed_pane.filter(|id| tab.contains_pane(*id)) {
+            Some(zoomed) => vec![zoomed],
+            None => tab.visible_panes().iter().map(|pane| pane.id).collect(),
+        }
+    }
+
+    /// Record output (and an optional bell) from a pane for activity badges.
+    pub fn record_pane_output(&mut self, pane_id: PaneId, bell: bool) {
+        let Some(sandbox_id) = self.workspace_manager.sandbox_for_pane(pane_id) else {
+            return;
+        };
+        let viewed = self.viewed_pane_ids().contains(&pane_id);
+        self.activity
+            .record_output(pane_id, sandbox_id, viewed, std::time::Instant::now());
+        if bell {
+            self.activity.record_bell(pane_id, sandbox_id, viewed);
+            let target = self.pane_notification_target(pane_id);
+            let tab_name = self
+                .workspace_manager
+                .get_workspace(sandbox_id)
+                .and_then(|ws| ws.tabs.iter().find(|tab| tab.contains_pane(pane_id)))
+                .map(|tab| tab.name.clone())
+                .unwrap_or_default();
+            self.forward_to_desktop(
+                NotificationSource::Bell,
+                None,
+                format!("Bell in {}", tab_name),
+                NotificationLevel::Info,
+                target,
+            );
+        }
+    }
+
+    /// Sandbox/tab/pane IDs for a pane, used to route notifications back to it.
+    fn pane_notification_target(&self, pane_id: PaneId) -> NotificationTarget {
+        let sandbox_id = self.workspace_manager.sandbox_for_pane(pane_id);
+        let tab_id = sandbox_id
+            .and_then(|id| self.workspace_manager.get_workspace(id))
+            .and_then(|ws| ws.tabs.iter().find(|tab| tab.contains_pane(pane_id)))
+            .map(|tab| tab.id.to_string());
+        NotificationTarget {
+            sandbox_id: sandbox_id.map(|id| id.to_string()),
+            tab_id,
+            pane_id: Some(pane_id.to_string()),
+        }
+    }
+
+    /// Show a notification on the desktop if the configured rules allow it.
+    /// `summary` defaults to the sandbox name.
+    fn forward_to_desktop(
+        &mut self,
+        source: NotificationSource,
+        summary: Option<String>,
+        body: String,
+        level: NotificationLevel,
+        target: NotificationTarget,
+    ) {
+        if self.desktop_notifier.is_none() {
+            return;
+        }
+
+        let sandbox_uuid = target
+            .sandbox_id
+            .as_deref()
+            .and_then(|id| Uuid::parse_str(id).ok());
+        let pane_uuid = target
+            .pane_id
+            .as_deref()
+            .and_then(|id| Uuid::parse_str(id).ok());
+        // Without a pane, a notification is "in view" when its sandbox is shown
+        let in_view = self.terminal_focused
+            && match (pane_uuid, sandbox_uuid) {
+                (Some(pane), _) => self.viewed_pane_ids().contains(&PaneId(pane)),
+                (None, Some(sandbox)) => {
+                    self.selected_sandbox_id() == Some(SandboxId::from_uuid(sandbox))
+                }
+                (None, None) => false,
+            };
+        let sandbox_name = sandbox_uuid
+            .and_then(|id| self.sidebar.sandboxes.iter().find(|s| s.id == id))
+            .map(|s| s.name.as_str());
+
+        let context = NotificationContext {
+            source,
+            level,
+            sandbox_id: target.sandbox_id.as_deref(),
+            sandbox_name,
+            in_view,
+        };
+        let allowed = self.notification_filter.allow(
+            &self.settings.desktop_notifications,
+            context,
+            std::time::Instant::now(),
+        );
+        if !allowed {
+            return;
+        }
+
+        let summary = summary
+            .or_else(|| sandbox_name.map(str::to_string))
+            .unwrap_or_else(|| "cmux".to_string());
+        if let Some(notifier) = &self.desktop_notifier {
+            notifier.notify(DesktopNotification {
+                summary,
+                body,
+                level,
+                target,
+            });
+        }
+    }
+
+    /// Record that a pane's process exited. Call before the pane is removed.
+    pub fn record_pane_exit(&mut self, pane_id: PaneId) {
+        if let Some(sandbox_id) = self.workspace_manager.sandbox_for_pane(pane_id) {
+            let viewed = self.viewed_pane_ids().contains(&pane_id);
+            self.activity.record_exit(pane_id, sandbox_id, viewed);
+        }
+    }
+
+    /// Update activity badges: drop closed panes, detect silence and clear
+    /// whatever is on screen. Returns true if the sidebar needs a redraw.
+    pub fn refresh_activity(&mut self, now: std::time::Instant) -> bool {
+        let live = self.workspace_manager.all_pane_ids();
+        let mut changed = self.activity.retain_panes(&live);
+        changed |= self.activity.tick(now);
+        changed |= self
+            .activity
+            .mark_viewed(&self.viewed_pane_ids(), self.selected_sandbox_id());
+        changed
+    }
+
+    /// Switch to the next sandbox (in sidebar order) with a pane that needs
+    /// attention and focus that pane.
+    pub fn jump_to_next_attention(&mut self) {
+        let order: Vec<SandboxId> = self
+            .sidebar
+            .sandboxes
+            .iter()
+            .map(|sandbox| SandboxId::from_uuid(sandbox.id))
+            .collect();
+        let start = self
+            .selected_sandbox_id()
+            .and_then(|current| order.iter().position(|id| *id == current))
+            .map_or(0, |index| index + 1);
+
+        for offset in 0..order.len() {
+            let sandbox_id = order[(start + offset) % order.len()];
+            let Some(pane_id) = self.activity.attention_pane(sandbox_id) else {
+                continue;
+            };
+            if self.workspace_manager.focus_pane(pane_id) {
+                self.sidebar.select_by_id(sandbox_id.0);
+                self.zoomed_pane = None;
+                self.focus = FocusArea::MainArea;
+                let name = self
+                    .workspace_manager
+                    .get_workspace(sandbox_id)
+                    .map(|ws| ws.name.clone())
+                    .unwrap_or_default();
+                self.set_status(format!("Jumped to {}", name));
+                return;
+            }
+        }
+        self.set_status("No sandboxes need attention");
+    }
+
     /// Set a status message that will be displayed temporarily.
     pub fn set_status(&mut self, message: impl Into<String>) {
         self.status_message = Some((message.into(), std::time::Instant::now()));
@@ -540,6 +746,25 @@ impl<'a> MuxApp<'a> {
         }
     }
 
+    /// Switch the color theme and save it as the default.
+    fn set_theme(&mut self, name: &str) {
+        if let Err(e) = theme::apply_theme(name) {
+            self.set_status(format!("Failed to load theme '{}': {}", name, e));
+            return;
+        }
+        self.settings.theme = (name != theme::AUTO_THEME).then(|| name.to_string());
+
+        // Save to disk
+        if let Err(e) = self.settings.save() {
+            self.set_status(format!(
+                "Theme set to {} (warning: failed to save: {})",
+                name, e
+            ));
+        } else {
+            self.set_status(format!("Theme set to {}", name));
+        }
+    }
+
     pub fn open_notifications(&mut self) {
         self.notifications.is_open = true;
         self.focus = FocusArea::Notifications;
@@ -574,8 +799,17 @@ impl<'a> MuxApp<'a> {
     }
 
     pub fn open_notification_target(&mut self, entry: &NotificationEntry) {
+        self.focus_notification_target(&NotificationTarget {
+            sandbox_id: entry.sandbox_id.clone(),
+            tab_id: entry.tab_id.clone(),
+            pane_id: entry.pane_id.clone(),
+        });
+    }
+
+    /// Select the sandbox, tab and (if known) pane a notification came from.
+    pub fn focus_notification_target(&mut self, target: &NotificationTarget) {
         let mut sandbox_selected = false;
-        if let Some(sandbox_id_str) = &entry.sandbox_id {
+        if let Some(sandbox_id_str) = &target.sandbox_id {
             if let Ok(uuid) = Uuid::parse_str(sandbox_id_str) {
                 let sandbox_id = SandboxId::from_uuid(uuid);
                 if self.workspace_manager.has_sandbox(sandbox_id) {
@@ -587,7 +821,7 @@ impl<'a> MuxApp<'a> {
         }
 
         let mut tab_selected = false;
-        if let Some(tab_id_str) = &entry.tab_id {
+        if let Some(tab_id_str) = &target.tab_id {
             if let Ok(uuid) = Uuid::parse_str(tab_id_str) {
                 let tab_id = crate::mux::layout::TabId::from_uuid(uuid);
                 if sandbox_selected {
@@ -604,6 +838,20 @@ impl<'a> MuxApp<'a> {
             }
         }
 
+        let pane_id = target
+            .pane_id
+            .as_deref()
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .map(PaneId);
+        if let Some(pane_id) = pane_id {
+            if self.workspace_manager.focus_pane(pane_id) {
+                if let Some(active_id) = self.workspace_manager.active_sandbox_id {
+                    self.sidebar.select_by_id(active_id.0);
+                }
+                sandbox_selected = true;
+            }
+        }
+
         if sandbox_selected || tab_selected {
             self.focus = FocusArea::MainArea;
         }
@@ -716,7 +964,22 @@ impl<'a> MuxApp<'a> {
             | MuxCommand::SwapPaneRight
             | MuxCommand::SwapPaneUp
             | MuxCommand::SwapPaneDown => {
-                self.set_status("Pane swapping not yet implemented");
+                // Floating panes move instead of swapping
+                let (dx, dy) = match cmd {
+                    MuxCommand::SwapPaneLeft => (-0.05, 0.0),
+                    MuxCommand::SwapPaneRight => (0.05, 0.0),
+                    MuxCommand::SwapPaneUp => (0.0, -0.05),
+                    _ => (0.0, 0.05),
+                };
+                let floating = self.active_tab_mut().and_then(|tab| {
+                    let pane_id = tab.active_pane?;
+                    tab.floating_mut(pane_id)
+                });
+                if let Some(floating) = floating {
+                    floating.geometry.translate(dx, dy);
+                } else {
+                    self.set_status("Pane swapping not yet implemented");
+                }
             }
             MuxCommand::ResizeLeft => {
                 if let Some(tab) = self.active_tab_mut() {
@@ -738,6 +1001,40 @@ impl<'a> MuxApp<'a> {
                     tab.resize(NavDirection::Down, 0.05);
                 }
             }
+            MuxCommand::NewFloatingPane => {
+                self.start_floating_pane_prompt();
+            }
+            MuxCommand::ToggleFloatingPanes => {
+                let toggled = self.active_tab_mut().map(|tab| {
+                    let toggled = tab.toggle_floating();
+                    (toggled, tab.floating_visible)
+                });
+                match toggled {
+                    Some((true, true)) => self.set_status("Floating panes shown"),
+                    Some((true, false)) => {
+                        // Don't stay zoomed on a pane that is no longer visible
+                        let zoomed_hidden = self.zoomed_pane.is_some_and(|id| {
+                            self.active_tab().is_some_and(|tab| tab.is_floating(id))
+                        });
+                        if zoomed_hidden {
+                            self.zoomed_pane = None;
+                        }
+                        self.set_status("Floating panes hidden");
+                    }
+                    _ => self.set_status("No floating panes"),
+                }
+            }
+            MuxCommand::PromoteFloatingPane => {
+                let promoted = self.active_tab_mut().is_some_and(|tab| {
+                    tab.active_pane
+                        .is_some_and(|pane_id| tab.promote_floating(pane_id))
+                });
+                if promoted {
+                    self.set_status("Floating pane embedded");
+                } else {
+                    self.set_status("Active pane is not floating");
+                }
+            }
 
             // Tab management - tabs belong to the active sandbox workspace
             MuxCommand::NewTab => {
@@ -862,6 +1159,9 @@ impl<'a> MuxApp<'a> {
             MuxCommand::ToggleHelp => {
                 self.show_help = !self.show_help;
             }
+            MuxCommand::JumpToAttention => {
+                self.jump_to_next_attention();
+            }
             MuxCommand::ShowNotifications => {
                 if self.notifications.is_open {
                     self.close_notifications();
@@ -1033,6 +1333,33 @@ impl<'a> MuxApp<'a> {
             MuxCommand::SetEditorWindsurf => {
                 self.set_default_editor(EditorChoice::Windsurf);
             }
+            MuxCommand::SelectTheme => {
+                // This normally opens a submenu in the palette, but if executed directly:
+                let dir = theme::themes_dir()
+                    .map(|dir| dir.display().to_string())
+                    .unwrap_or_default();
+                self.set_status(format!(
+                    "Current theme: {}. Custom themes: {}/<name>.json or .toml",
+                    self.settings.theme_name(),
+                    dir
+                ));
+            }
+            MuxCommand::SetThemeAuto => {
+                self.set_theme(theme::AUTO_THEME);
+            }
+            MuxCommand::SetThemeDark => {
+                self.set_theme("dark");
+            }
+            MuxCommand::SetThemeLight => {
+                self.set_theme("light");
+            }
+            MuxCommand::SetThemeHighContrast => {
+                self.set_theme("high-contrast");
+            }
+            MuxCommand::CycleTheme => {
+                let next = theme::next_theme_name(self.settings.theme_name());
+                self.set_theme(&next);
+            }
             MuxCommand::OpenBrowser => {
                 if let Some(sandbox_id) = self.selected_sandbox_id() {
                     // Get the binary name (cmux or dmux)
@@ -1083,6 +1410,46 @@ impl<'a> MuxApp<'a> {
         }
     }
 
+    /// Start the command prompt for a new floating pane.
+    fn start_floating_pane_prompt(&mut self) {
+        if self.active_tab().is_some() {
+            self.floating_command_input = Some(tui_textarea::TextArea::default());
+        } else {
+            self.set_status("No sandbox selected");
+        }
+    }
+
+    /// Finish the floating pane prompt, opening the pane if applied.
+    /// An empty command opens the default shell.
+    pub fn finish_floating_pane_prompt(&mut self, apply: bool) {
+        let Some(input) = self.floating_command_input.take() else {
+            return;
+        };
+        if !apply {
+            return;
+        }
+
+        let command_line = input.lines().join(" ").trim().to_string();
+        let (title, command) = if command_line.is_empty() {
+            ("Terminal".to_string(), None)
+        } else {
+            let command = vec![
+                "/bin/zsh".to_string(),
+                "-i".to_string(),
+                "-c".to_string(),
+                command_line.clone(),
+            ];
+            (command_line, Some(command))
+        };
+
+        if let Some(tab) = self.active_tab_mut() {
+            tab.add_floating(Pane::terminal(None, title), command);
+            self.set_status("Floating pane opened");
+            // Auto-connect the new pane to the sandbox terminal
+            let _ = self.event_tx.send(MuxEvent::ConnectActivePaneToSandbox);
+        }
+    }
+
     /// Finish tab rename.
     pub fn finish_tab_rename(&mut self, apply: bool) {
         if apply {
@@ -1305,8 +1672,11 @@ impl<'a> MuxApp<'a> {
                 };
                 self.set_status(format!("Sandbox {}: {}", sandbox_id, state));
             }
-            MuxEvent::TerminalOutput { .. } => {
-                // TODO: Forward to appropriate pane
+            MuxEvent::TerminalOutput { pane_id, bell } => {
+                self.record_pane_output(pane_id, bell);
+            }
+            MuxEvent::SandboxStatsUpdated(stats) => {
+                self.sidebar.record_stats(&stats, std::time::Instant::now());
             }
             MuxEvent::Error(msg) => {
                 self.set_status(format!("Error: {}", msg));
@@ -1318,7 +1688,41 @@ impl<'a> MuxApp<'a> {
                 tab_id,
                 pane_id,
             } => {
-                self.record_notification(message, level, sandbox_id, tab_id, pane_id);
+                let target = NotificationTarget {
+                    sandbox_id: sandbox_id.clone(),
+                    tab_id: tab_id.clone(),
+                    pane_id: pane_id.clone(),
+                };
+                self.record_notification(message.clone(), level, sandbox_id, tab_id, pane_id);
+                self.forward_to_desktop(NotificationSource::Api, None, message, level, target);
+            }
+            MuxEvent::TerminalNotification {
+                pane_id,
+                title,
+                body,
+            } => {
+                let target = self.pane_notification_target(pane_id);
+                let message = match &title {
+                    Some(title) => format!("{}: {}", title, body),
+                    None => body.clone(),
+                };
+                self.record_notification(
+                    message,
+                    NotificationLevel::Info,
+                    target.sandbox_id.clone(),
+                    target.tab_id.clone(),
+                    target.pane_id.clone(),
+                );
+                self.forward_to_desktop(
+                    NotificationSource::Osc,
+                    title,
+                    body,
+                    NotificationLevel::Info,
+                    target,
+                );
+            }
+            MuxEvent::OpenNotificationTarget(target) => {
+                self.focus_notification_target(&target);
             }
             MuxEvent::StatusMessage { message } => {
                 self.set_status(message);
@@ -1412,6 +1816,7 @@ impl<'a> MuxApp<'a> {
 
         if let Ok(sandbox_id) = id.parse::<SandboxId>() {
             self.workspace_manager.remove_sandbox(sandbox_id);
+            self.activity.remove_sandbox(sandbox_id);
         }
 
         self.sidebar
@@ -13,6 +13,7 @@ use crate::mux::colors::{get_outer_bg, get_outer_fg};
 use crate::mux::events::MuxEvent;
 use crate::mux::grid::Grid;
 use crate::mux::layout::{PaneId, TabId};
+use crate::theme::AnsiPalette;
 
 /// A single cell in the terminal grid (legacy compatibility type).
 /// This is used for backward compatibility with existing tests and APIs.
@@ -679,6 +680,25 @@ fn find_url_at_column(line: &str, col: usize) -> Option<String> {
     None
 }
 
+/// Maximum OSC notifications buffered per terminal before the oldest are dropped
+const MAX_PENDING_NOTIFICATIONS: usize = 16;
+
+/// A notification requested by a program via OSC 9 or OSC 777.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminalNotification {
+    pub title: Option<String>,
+    pub body: String,
+}
+
+/// Rejoin OSC parameters that were split on ';' (message text may contain semicolons).
+fn join_osc_params(params: &[&[u8]]) -> String {
+    params
+        .iter()
+        .map(|param| String::from_utf8_lossy(param))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
 /// Virtual terminal that properly handles ANSI escape sequences.
 /// Uses the optimized Grid structure internally for efficient storage and scrolling.
 #[derive(Debug, Clone)]
@@ -723,6 +743,8 @@ pub struct VirtualTerminal {
     pub sgr_mouse_mode: bool,
     /// Bell triggered flag (for UI notification)
     pub bell_pending: bool,
+    /// Notifications requested via OSC 9 / OSC 777, oldest first
+    pub pending_notifications: Vec<TerminalNotification>,
     /// Window title (set via OSC)
     pub title: Option<String>,
     /// Last printed character (for REP - repeat)
@@ -737,6 +759,9 @@ pub struct VirtualTerminal {
     pub cursor_color: Option<(u8, u8, u8)>,
     /// 256-color palette (OSC 4) - stores custom colors, None means use default
     color_palette: [Option<(u8, u8, u8)>; 256],
+    /// ANSI colors from the active theme. When set, the 16 ANSI colors are
+    /// rendered as these RGB values instead of the outer terminal's palette.
+    pub ansi_palette: Option<AnsiPalette>,
     /// Flag to signal alt screen was entered/exited (for UI to reset scroll state)
     pub alt_screen_toggled: bool,
     /// DECLRMM - Left/Right Margin Mode (mode 69)
@@ -823,6 +848,7 @@ impl VirtualTerminal {
             mouse_tracking: None,
             sgr_mouse_mode: false,
             bell_pending: false,
+            pending_notifications: Vec::new(),
             title: None,
             last_printed_char: None,
             pending_responses: Vec::new(),
@@ -830,6 +856,7 @@ impl VirtualTerminal {
             default_bg_color: None,     // Use terminal's native color
             cursor_color: None,         // Use terminal's native cursor color
             color_palette: [None; 256], // Use default 256-color palette
+            ansi_palette: crate::theme::current().palette,
             alt_screen_toggled: false,
             enable_left_right_margins: false,
             reverse_wraparound: false,
@@ -890,9 +917,41 @@ impl VirtualTerminal {
     }
 
     /// Get the RGB color for a palette index, considering custom OSC 4 colors.
-    /// Returns the custom color if set, otherwise the default palette color.
+    /// Returns the custom color if set, then the theme's ANSI color, then the default.
     pub fn get_palette_color(&self, index: u8) -> (u8, u8, u8) {
-        self.color_palette[index as usize].unwrap_or_else(|| default_palette_color(index))
+        self.color_palette[index as usize]
+            .or_else(|| {
+                self.ansi_palette
+                    .as_ref()
+                    .and_then(|palette| palette.get(index as usize).copied())
+            })
+            .unwrap_or_else(|| default_palette_color(index))
+    }
+
+    /// Resolve ANSI colors through the theme palette, if one is set.
+    fn themed_color(&self, color: Option<Color>) -> Option<Color> {
+        let color = color?;
+        if self.ansi_palette.is_none() {
+            return Some(color);
+        }
+        match crate::theme::ansi_index(color) {
+            Some(index) => {
+                let (r, g, b) = self.get_palette_color(index as u8);
+                Some(Color::Rgb(r, g, b))
+            }
+            None => Some(color),
+        }
+    }
+
+    /// Apply the theme palette to a rendered line.
+    fn apply_ansi_palette(&self, line: &mut ratatui::text::Line<'static>) {
+        if self.ansi_palette.is_none() {
+            return;
+        }
+        for span in &mut line.spans {
+            span.style.fg = self.themed_color(span.style.fg);
+            span.style.bg = self.themed_color(span.style.bg);
+        }
     }
 
     /// Get a reference to the full color palette for rendering.
@@ -1100,6 +1159,15 @@ impl VirtualTerminal {
         std::mem::take(&mut self.pending_responses)
     }
 
+    /// Queue an OSC notification, dropping the oldest if nobody is draining them
+    fn push_notification(&mut self, title: Option<String>, body: String) {
+        if self.pending_notifications.len() >= MAX_PENDING_NOTIFICATIONS {
+            self.pending_notifications.remove(0);
+        }
+        self.pending_notifications
+            .push(TerminalNotification { title, body });
+    }
+
     /// Scroll the screen up by one line within the scroll region
     fn scroll_up(&mut self) {
         self.internal_grid.scroll_up_in_region(1);
@@ -1785,8 +1853,7 @@ impl Perform for VirtualTerminal {
                                 if index < 256 {
                                     if color_str == "?" {
                                         // Query - respond with current color
-                                        let (r, g, b) = self.color_palette[index]
-                                            .unwrap_or_else(|| default_palette_color(index as u8));
+                                        let (r, g, b) = self.get_palette_color(index as u8);
                                         let response = format!(
                                             "\x1b]4;{};rgb:{:04x}/{:04x}/{:04x}\x1b\\",
                                             index,
@@ -1969,6 +2036,18 @@ impl Perform for VirtualTerminal {
                         }
                     }
                 }
+                // OSC 9 - iTerm2-style notification: OSC 9 ; message ST
+                // ConEmu reuses OSC 9 with numeric subcommands (e.g. 9;4 progress), skip those
+                "9" if params.len() > 1 && !params[1].iter().all(u8::is_ascii_digit) => {
+                    let body = join_osc_params(&params[1..]);
+                    self.push_notification(None, body);
+                }
+                // OSC 777 - rxvt-style notification: OSC 777 ; notify ; title ; body ST
+                "777" if params.len() > 2 && params[1] == b"notify" => {
+                    let title = String::from_utf8_lossy(params[2]).into_owned();
+                    let body = join_osc_params(&params[3..]);
+                    self.push_notification(Some(title), body);
+                }
                 // OSC 112 - Reset cursor color to terminal default
                 "112" => {
                     self.cursor_color = None;
@@ -3010,6 +3089,8 @@ pub struct TerminalBuffer {
     render_cache: Option<RenderCache>,
     generation: u64,
     scroll_offset: usize,
+    /// Theme generation the ANSI palette was last synced from
+    theme_generation: u64,
 }
 
 impl std::fmt::Debug for TerminalBuffer {
@@ -3035,6 +3116,7 @@ impl TerminalBuffer {
             render_cache: None,
             generation: 0,
             scroll_offset: 0,
+            theme_generation: crate::theme::generation(),
         }
     }
 
@@ -3045,6 +3127,7 @@ impl TerminalBuffer {
             render_cache: None,
             generation: 0,
             scroll_offset: 0,
+            theme_generation: crate::theme::generation(),
         }
     }
 
@@ -3238,8 +3321,56 @@ impl TerminalBuffer {
         None
     }
 
+    /// Extract text between two view positions (row, col), both inclusive and 0-indexed.
+    /// Rows are relative to the current view, so scrollback offset is honoured.
+    /// Soft-wrapped rows are joined without a newline.
+    pub fn selection_text(
+        &self,
+        height: usize,
+        start: (usize, usize),
+        end: (usize, usize),
+    ) -> String {
+        let rows = self.terminal.visible_lines(height, self.scroll_offset);
+        let mut text = String::new();
+
+        for (row_idx, row) in rows.iter().enumerate().take(end.0 + 1).skip(start.0) {
+            if row_idx > start.0 && row.is_canonical {
+                text.push('\n');
+            }
+            let first_col = if row_idx == start.0 { start.1 } else { 0 };
+            let last_col = if row_idx == end.0 { end.1 } else { usize::MAX };
+            let line: String = row
+                .columns
+                .iter()
+                .enumerate()
+                .filter(|(col, cell)| *col >= first_col && *col <= last_col && !cell.wide_spacer)
+                .map(|(_, cell)| cell.character)
+                .collect();
+            // Keep trailing spaces on wrapped rows so joined words stay intact
+            let wraps = rows.get(row_idx + 1).is_some_and(|next| !next.is_canonical);
+            if wraps && row_idx < end.0 {
+                text.push_str(&line);
+            } else {
+                text.push_str(line.trim_end());
+            }
+        }
+
+        // Drop trailing blank rows, like get_all_text
+        let trimmed_len = text.trim_end_matches('\n').len();
+        text.truncate(trimmed_len);
+        text
+    }
+
     /// Build a cached render view for the given height.
     pub fn render_view(&mut self, height: usize) -> TerminalRenderView {
+        // Pick up theme changes: new ANSI palette and a full re-render
+        let theme_generation = crate::theme::generation();
+        if self.theme_generation != theme_generation {
+            self.theme_generation = theme_generation;
+            self.terminal.ansi_palette = crate::theme::current().palette;
+            self.render_cache = None;
+        }
+
         if let Some(cache) = &self.render_cache {
             if cache.is_valid(height, self.generation, self.scroll_offset) {
                 return cache.as_view();
@@ -3276,7 +3407,9 @@ impl TerminalBuffer {
                     }
                 }
             }
-            lines.push(row.to_ratatui_line_with_palette(default_fg, default_bg, Some(palette)));
+            let mut line = row.to_ratatui_line_with_palette(default_fg, default_bg, Some(palette));
+            self.terminal.apply_ansi_palette(&mut line);
+            lines.push(line);
         }
 
         let cursor = self.cursor_position();
@@ -3417,6 +3550,21 @@ impl TerminalManager {
         self.buffers.get_mut(&pane_id)
     }
 
+    /// Drain notifications a pane requested via OSC 9 / OSC 777.
+    pub fn take_notifications(&mut self, pane_id: PaneId) -> Vec<TerminalNotification> {
+        self.buffers
+            .get_mut(&pane_id)
+            .map(|buffer| std::mem::take(&mut buffer.terminal.pending_notifications))
+            .unwrap_or_default()
+    }
+
+    /// Check and reset whether a pane rang the bell since the last call.
+    pub fn take_bell(&mut self, pane_id: PaneId) -> bool {
+        self.buffers
+            .get_mut(&pane_id)
+            .is_some_and(|buffer| std::mem::take(&mut buffer.terminal.bell_pending))
+    }
+
     /// Invalidate render caches for all terminal buffers.
     /// Call this when outer terminal colors change to force re-rendering.
     pub fn invalidate_all_render_caches(&mut self) {
@@ -3694,14 +3842,24 @@ pub async fn establish_mux_connection(manager: SharedTerminalManager) -> anyhow:
                                     });
                                 }
                                 MuxServerMessage::Output { session_id, data } => {
-                                    let pane_id = {
+                                    let output = {
                                         let mut mgr = manager_clone.lock().await;
-                                        mgr.handle_output_by_session(&session_id, data)
+                                        mgr.handle_output_by_session(&session_id, data).map(|pane_id| {
+                                            (pane_id, mgr.take_bell(pane_id), mgr.take_notifications(pane_id))
+                                        })
                                     };
-                                    if let Some(pane_id) = pane_id {
+                                    if let Some((pane_id, bell, notifications)) = output {
                                         let _ = event_tx_clone.send(MuxEvent::TerminalOutput {
                                             pane_id,
+                                            bell,
                                         });
+                                        for notification in notifications {
+                                            let _ = event_tx_clone.send(MuxEvent::TerminalNotification {
+                                                pane_id,
+                                                title: notification.title,
+                                                body: notification.body,
+                                            });
+                                        }
                                     }
                                 }
                                 MuxServerMessage::Exited { session_id, .. } => {
@@ -3815,6 +3973,7 @@ pub async fn connect_to_sandbox(
     tab_id: Option<TabId>,
     cols: u16,
     rows: u16,
+    command: Option<Vec<String>>,
 ) -> anyhow::Result<()> {
     // Ensure the multiplexed connection is established
     establish_mux_connection(manager.clone()).await?;
@@ -3840,7 +3999,7 @@ pub async fn connect_to_sandbox(
                 sandbox_id: sandbox_id.clone(),
                 cols,
                 rows,
-                command: None,
+                command,
                 tty: true,
                 tab_id: tab_id_string,
                 pane_id: Some(pane_id_string),
@@ -4629,6 +4788,31 @@ mod tests {
         assert_eq!(term.cursor_color, None);
     }
 
+    #[test]
+    fn osc9_and_osc777_queue_notifications() {
+        let mut term = VirtualTerminal::new(24, 80);
+
+        term.process(b"\x1b]9;Build finished; 3 warnings\x07");
+        term.process(b"\x1b]777;notify;Claude;Waiting for input\x1b\\");
+        // ConEmu progress reports are not notifications
+        term.process(b"\x1b]9;4;1;50\x07");
+
+        assert_eq!(
+            term.pending_notifications,
+            vec![
+                TerminalNotification {
+                    title: None,
+                    body: "Build finished; 3 warnings".to_string(),
+                },
+                TerminalNotification {
+                    title: Some("Claude".to_string()),
+                    body: "Waiting for input".to_string(),
+                },
+            ]
+        );
+        assert!(!term.bell_pending);
+    }
+
     #[test]
     fn osc4_set_palette_color() {
         let mut term = VirtualTerminal::new(24, 80);
@@ -4674,6 +4858,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn selection_text_spans_rows() {
+        use crate::mux::terminal::TerminalBuffer;
+
+        let mut buffer = TerminalBuffer::with_size(5, 20);
+        buffer.process(b"hello world\r\nsecond line\r\nthird");
+
+        assert_eq!(buffer.selection_text(5, (0, 6), (0, 10)), "world");
+        assert_eq!(buffer.selection_text(5, (0, 6), (1, 5)), "world\nsecond");
+        assert_eq!(
+            buffer.selection_text(5, (0, 0), (4, 19)),
+            "hello world\nsecond line\nthird"
+        );
+    }
+
+    #[test]
+    fn theme_palette_recolors_ansi_output() {
+        use crate::mux::terminal::TerminalBuffer;
+
+        let mut palette = [(0, 0, 0); 16];
+        palette[1] = (0xdc, 0x32, 0x2f);
+        let mut buffer = TerminalBuffer::with_size(2, 10);
+        buffer.terminal.ansi_palette = Some(palette);
+        buffer.process(b"\x1b[31mred\x1b[0m");
+
+        let lines = buffer.visible_lines(2);
+        let span = &lines[0].spans[0];
+        assert_eq!(span.content, "red");
+        assert_eq!(span.style.fg, Some(Color::Rgb(0xdc, 0x32, 0x2f)));
+
+        // OSC 4 queries report the themed color
+        buffer.terminal.process(b"\x1b]4;1;?\x1b\\");
+        let response = String::from_utf8(buffer.terminal.pending_responses.pop().unwrap()).unwrap();
+        assert!(response.contains("rgb:dcdc/3232/2f2f"));
+    }
+
     #[test]
     fn osc104_reset_palette_color() {
         let mut term = VirtualTerminal::new(24, 80);
@@ -9,16 +9,19 @@ use ratatui::{
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::mux::commands::MuxCommand;
-use crate::mux::layout::LayoutNode;
+use crate::mux::layout::{LayoutNode, Pane, SandboxId};
+use crate::mux::mouse::MouseRegions;
 use crate::mux::onboard::OnboardPhase;
 use crate::mux::palette::PaletteItem;
-use crate::mux::sidebar::Sidebar;
+use crate::mux::sidebar::{format_bytes, Sidebar};
 use crate::mux::state::{FocusArea, MuxApp};
 use crate::settings::EditorChoice;
+use crate::theme;
 
 /// Main UI rendering function.
 pub fn ui(f: &mut Frame, app: &mut MuxApp) {
     let area = f.area();
+    app.mouse_regions = MouseRegions::default();
 
     // Main layout: sidebar | main area
     let main_chunks = if app.sidebar.visible {
@@ -72,6 +75,10 @@ pub fn ui(f: &mut Frame, app: &mut MuxApp) {
         render_rename_dialog(f, app);
     }
 
+    if app.floating_command_input.is_some() {
+        render_floating_command_dialog(f, app);
+    }
+
     // Onboard overlay (highest priority - blocks other interactions during setup)
     if let Some(onboard) = &app.onboard {
         if onboard.is_visible {
@@ -81,45 +88,47 @@ pub fn ui(f: &mut Frame, app: &mut MuxApp) {
 }
 
 /// Render the sidebar with sandbox list.
-fn render_sidebar(f: &mut Frame, app: &MuxApp, area: Rect) {
+fn render_sidebar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
+    let theme = theme::current();
     let is_focused = app.focus == FocusArea::Sidebar;
 
     let border_style = if is_focused {
-        Style::default().fg(Color::Cyan)
+        Style::default().fg(theme.borders.focused)
     } else {
-        Style::default().fg(Color::DarkGray)
+        Style::default().fg(theme.borders.inactive)
     };
 
     let block = Block::default()
         .title(" Sandboxes ")
         .title_style(if is_focused {
             Style::default()
-                .fg(Color::Cyan)
+                .fg(theme.ui.accent)
                 .add_modifier(Modifier::BOLD)
         } else {
-            Style::default().fg(Color::White)
+            Style::default().fg(theme.ui.text)
         })
         .borders(Borders::ALL)
         .border_style(border_style);
 
     let inner_area = block.inner(area);
     f.render_widget(block, area);
+    app.mouse_regions.sidebar = Some(inner_area);
 
     if app.sidebar.is_loading {
-        let loading = Paragraph::new("Loading...").style(Style::default().fg(Color::Yellow));
+        let loading = Paragraph::new("Loading...").style(Style::default().fg(theme.ui.warning));
         f.render_widget(loading, inner_area);
         return;
     }
 
     if let Some(error) = &app.sidebar.last_error {
         let error_text =
-            Paragraph::new(format!("Error: {}", error)).style(Style::default().fg(Color::Red));
+            Paragraph::new(format!("Error: {}", error)).style(Style::default().fg(theme.ui.error));
         f.render_widget(error_text, inner_area);
         return;
     }
 
     if app.sidebar.sandboxes.is_empty() {
-        let empty_text = Paragraph::new("No sandboxes").style(Style::default().fg(Color::DarkGray));
+        let empty_text = Paragraph::new("No sandboxes").style(Style::default().fg(theme.ui.muted));
         f.render_widget(empty_text, inner_area);
         return;
     }
@@ -129,15 +138,32 @@ fn render_sidebar(f: &mut Frame, app: &MuxApp, area: Rect) {
         let is_selected = idx == app.sidebar.selected_index();
 
         let status_icon = Sidebar::status_icon(&sandbox.status);
-        let status_color = Sidebar::status_color(&sandbox.status);
+        let status_color = Sidebar::status_color(&sandbox.status, &theme);
 
         let prefix = if is_selected { "▶ " } else { "  " };
-        let max_name_width = inner_area.width.saturating_sub(6) as usize;
+
+        // Resource usage only fits once the sidebar is widened a bit
+        let usage = app
+            .sidebar
+            .usage
+            .get(&sandbox.id)
+            .filter(|_| inner_area.width >= 24)
+            .map(|usage| {
+                format!(
+                    " {} {:>4}",
+                    usage.cpu_sparkline(),
+                    format_bytes(usage.memory_bytes)
+                )
+            });
+        let usage_width = usage.as_ref().map_or(0, |text| text.chars().count());
+        let max_name_width = (inner_area.width as usize).saturating_sub(6 + usage_width);
         let name = Sidebar::format_name(sandbox, max_name_width);
+        let padding = " ".repeat(max_name_width.saturating_sub(name.chars().count()));
+        let badge = app.activity.sandbox_badge(SandboxId::from_uuid(sandbox.id));
 
         let style = if is_selected {
             Style::default()
-                .fg(Color::Cyan)
+                .fg(theme.sidebar.selected)
                 .add_modifier(Modifier::BOLD)
         } else {
             Style::default()
@@ -148,6 +174,21 @@ fn render_sidebar(f: &mut Frame, app: &MuxApp, area: Rect) {
             Span::styled(status_icon, Style::default().fg(status_color)),
             Span::raw(" "),
             Span::styled(name, style),
+            Span::raw(padding),
+            Span::raw(" "),
+            match badge {
+                Some(badge) => Span::styled(
+                    badge.icon(),
+                    Style::default()
+                        .fg(Sidebar::badge_color(badge, &theme))
+                        .add_modifier(Modifier::BOLD),
+                ),
+                None => Span::raw(" "),
+            },
+            Span::styled(
+                usage.unwrap_or_default(),
+                Style::default().fg(theme.ui.muted),
+            ),
         ]));
     }
 
@@ -156,19 +197,20 @@ fn render_sidebar(f: &mut Frame, app: &MuxApp, area: Rect) {
 }
 
 /// Render the tab bar.
-fn render_tab_bar(f: &mut Frame, app: &MuxApp, area: Rect) {
+fn render_tab_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
+    let theme = theme::current();
     // Get workspace from workspace manager
     let Some(workspace) = app.workspace_manager.active_workspace() else {
         // No active sandbox, show placeholder
         let placeholder =
-            Paragraph::new(" No sandbox selected ").style(Style::default().fg(Color::DarkGray));
+            Paragraph::new(" No sandbox selected ").style(Style::default().fg(theme.ui.muted));
         f.render_widget(placeholder, area);
         return;
     };
 
     if workspace.tabs.is_empty() {
         let placeholder = Paragraph::new(" No tabs ")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(Style::default().fg(theme.ui.muted))
             .alignment(Alignment::Left);
         f.render_widget(placeholder, area);
         return;
@@ -181,53 +223,74 @@ fn render_tab_bar(f: &mut Frame, app: &MuxApp, area: Rect) {
         .map(|(idx, tab)| {
             let style = if idx == workspace.active_tab_index {
                 Style::default()
-                    .fg(Color::Cyan)
+                    .fg(theme.ui.accent)
                     .add_modifier(Modifier::BOLD)
             } else {
-                Style::default().fg(Color::DarkGray)
+                Style::default().fg(theme.ui.muted)
             };
             Line::styled(format!(" {} ", tab.name), style)
         })
         .collect();
 
+    // Record each tab's column range for mouse hit-testing. This mirrors the
+    // Tabs widget layout: " " padding on both sides and a one-column divider.
+    let mut regions = Vec::with_capacity(tab_titles.len());
+    let mut x = area.x;
+    for title in &tab_titles {
+        let width = title.width() as u16 + 2;
+        let end = x.saturating_add(width).min(area.right());
+        regions.push((x, end));
+        x = end.saturating_add(1);
+    }
+
     let tabs = Tabs::new(tab_titles)
         .select(workspace.active_tab_index)
         .divider(Span::raw("│"))
         .highlight_style(
             Style::default()
-                .fg(Color::Cyan)
+                .fg(theme.ui.accent)
                 .add_modifier(Modifier::BOLD),
         );
 
     f.render_widget(tabs, area);
+    app.mouse_regions.tabs = regions;
+    app.mouse_regions.tab_bar = Some(area);
 }
 
 /// Render the main workspace area with panes.
 fn render_workspace(f: &mut Frame, app: &mut MuxApp, area: Rect) {
+    let theme = theme::current();
     // First, calculate areas (needs mutable borrow)
     // Capture zoomed_pane first to avoid borrow issues
     let zoomed_pane = app.zoomed_pane;
 
     if let Some(tab) = app.active_tab_mut() {
         tab.layout.calculate_areas(area);
+        tab.calculate_floating_areas(area);
         if let Some(zoomed_id) = zoomed_pane {
-            if let Some(pane) = tab.layout.find_pane_mut(zoomed_id) {
+            if let Some(pane) = tab.find_pane_mut(zoomed_id) {
                 pane.area = Some(area);
             }
         }
     }
+    app.mouse_regions.workspace = Some(area);
 
     // Snapshot layout info without holding a long borrow
-    let (layout_snapshot, active_pane_id) = if let Some(tab) = app.active_tab() {
-        (Some(tab.layout.clone()), tab.active_pane)
+    let (layout_snapshot, floating_snapshot, active_pane_id) = if let Some(tab) = app.active_tab() {
+        let floating: Vec<Pane> = if tab.floating_visible {
+            tab.floating.iter().map(|fp| fp.pane.clone()).collect()
+        } else {
+            Vec::new()
+        };
+        (Some(tab.layout.clone()), floating, tab.active_pane)
     } else {
-        (None, None)
+        (None, Vec::new(), None)
     };
 
     let Some(layout) = layout_snapshot else {
         // No active tab, show placeholder
         let placeholder = Paragraph::new("Select a sandbox from the sidebar (Tab to switch)")
-            .style(Style::default().fg(Color::DarkGray))
+            .style(Style::default().fg(theme.ui.muted))
             .alignment(Alignment::Center);
         f.render_widget(placeholder, area);
         return;
@@ -237,7 +300,10 @@ fn render_workspace(f: &mut Frame, app: &mut MuxApp, area: Rect) {
 
     // If zoomed, only render the zoomed pane
     if let Some(zoomed_id) = zoomed_pane {
-        if let Some(pane) = layout.find_pane(zoomed_id) {
+        let zoomed = layout
+            .find_pane(zoomed_id)
+            .or_else(|| floating_snapshot.iter().find(|pane| pane.id == zoomed_id));
+        if let Some(pane) = zoomed {
             render_pane(f, pane, area, true, is_main_focused, app);
             return;
         }
@@ -245,6 +311,15 @@ fn render_workspace(f: &mut Frame, app: &mut MuxApp, area: Rect) {
 
     // Render all panes
     render_layout_node(f, &layout, active_pane_id, is_main_focused, app);
+
+    // Floating panes are drawn on top, bottom of the stack first
+    for pane in &floating_snapshot {
+        if let Some(pane_area) = pane.area {
+            f.render_widget(Clear, pane_area);
+            let is_active = active_pane_id == Some(pane.id);
+            render_pane(f, pane, pane_area, is_active, is_main_focused, app);
+        }
+    }
 }
 
 /// Recursively render layout nodes.
@@ -278,20 +353,21 @@ fn render_pane(
     is_main_focused: bool,
     app: &mut MuxApp,
 ) {
+    let theme = theme::current();
     let border_style = if is_active && is_main_focused {
-        Style::default().fg(Color::Cyan)
+        Style::default().fg(theme.borders.focused)
     } else if is_active {
-        Style::default().fg(Color::White)
+        Style::default().fg(theme.borders.active)
     } else {
-        Style::default().fg(Color::DarkGray)
+        Style::default().fg(theme.borders.inactive)
     };
 
     let title_style = if is_active && is_main_focused {
         Style::default()
-            .fg(Color::Cyan)
+            .fg(theme.ui.accent)
             .add_modifier(Modifier::BOLD)
     } else {
-        Style::default().fg(Color::White)
+        Style::default().fg(theme.ui.text)
     };
 
     let block = Block::default()
@@ -307,7 +383,7 @@ fn render_pane(
     match &pane.content {
         crate::mux::layout::PaneContent::Empty => {
             let text = Paragraph::new("Empty pane\n\nUse Alt+- or Alt+\\ to split")
-                .style(Style::default().fg(Color::DarkGray))
+                .style(Style::default().fg(theme.ui.muted))
                 .alignment(Alignment::Center);
             f.render_widget(text, inner_area);
         }
@@ -315,6 +391,11 @@ fn render_pane(
             // Check if we have terminal output to display
             let height = inner_area.height as usize;
             let view = app.get_terminal_view(pane.id, height);
+            let selection = app.selection.filter(|sel| sel.pane_id == pane.id);
+            if selection.is_some() {
+                // Selection highlighting touches arbitrary rows, so redraw everything
+                app.last_terminal_views.remove(&pane.id);
+            }
             let previous = app.last_terminal_views.get(&pane.id).cloned();
             let render_view = match (view, previous.clone()) {
                 (Some(v), _) if v.has_content => Some(v),
@@ -374,6 +455,22 @@ fn render_pane(
                         }
                     }
 
+                    if let Some(selection) = selection {
+                        for row in 0..visible_rows {
+                            for col in 0..inner_area.width {
+                                if !selection.contains(row, col as usize) {
+                                    continue;
+                                }
+                                let pos = (inner_area.x + col, inner_area.y + row as u16);
+                                if let Some(cell) = buf.cell_mut(pos) {
+                                    cell.set_style(
+                                        Style::default().add_modifier(Modifier::REVERSED),
+                                    );
+                                }
+                            }
+                        }
+                    }
+
                     // Clear leftover rows if the area shrank
                     if let Some(prev) = previous {
                         let prev_rows = prev.lines.len();
@@ -465,7 +562,7 @@ fn render_pane(
             };
 
             let text = Paragraph::new(format!("Terminal\n\n{}\n\n{}", sandbox_info, help_text))
-                .style(Style::default().fg(Color::DarkGray))
+                .style(Style::default().fg(theme.ui.muted))
                 .alignment(Alignment::Center);
             f.render_widget(text, inner_area);
         }
@@ -477,7 +574,7 @@ fn render_pane(
                 "Chat with {}\n\nSandbox: {}\n\n(Chat integration coming soon)",
                 provider, sandbox_id
             ))
-            .style(Style::default().fg(Color::DarkGray))
+            .style(Style::default().fg(theme.ui.muted))
             .alignment(Alignment::Center);
             f.render_widget(text, inner_area);
         }
@@ -486,6 +583,7 @@ fn render_pane(
 
 /// Render the status bar.
 fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
+    let theme = theme::current();
     app.clear_expired_status();
 
     let mut spans = Vec::new();
@@ -501,8 +599,8 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
     spans.push(Span::styled(
         format!(" {} ", mode),
         Style::default()
-            .fg(Color::Black)
-            .bg(Color::Cyan)
+            .fg(theme.ui.on_accent)
+            .bg(theme.ui.accent)
             .add_modifier(Modifier::BOLD),
     ));
     spans.push(Span::raw(" "));
@@ -513,8 +611,8 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
         spans.push(Span::styled(
             "[debug build]",
             Style::default()
-                .fg(Color::Black)
-                .bg(Color::Yellow)
+                .fg(theme.ui.on_accent)
+                .bg(theme.ui.warning)
                 .add_modifier(Modifier::BOLD),
         ));
         spans.push(Span::raw(" "));
@@ -524,7 +622,7 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
     if let Some(workspace) = app.workspace_manager.active_workspace() {
         spans.push(Span::styled(
             format!("[{}] ", workspace.name),
-            Style::default().fg(Color::Green),
+            Style::default().fg(theme.ui.success),
         ));
     }
 
@@ -537,7 +635,7 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
                 pane_count,
                 if pane_count == 1 { "" } else { "s" }
             ),
-            Style::default().fg(Color::DarkGray),
+            Style::default().fg(theme.ui.muted),
         ));
         spans.push(Span::raw(" │ "));
     }
@@ -550,12 +648,12 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
                 workspace.active_tab_index + 1,
                 workspace.tabs.len()
             ),
-            Style::default().fg(Color::DarkGray),
+            Style::default().fg(theme.ui.muted),
         ));
     } else {
         spans.push(Span::styled(
             "No sandbox",
-            Style::default().fg(Color::DarkGray),
+            Style::default().fg(theme.ui.muted),
         ));
     }
 
@@ -564,7 +662,7 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
         spans.push(Span::raw(" │ "));
         spans.push(Span::styled(
             msg.clone(),
-            Style::default().fg(Color::Yellow),
+            Style::default().fg(theme.ui.warning),
         ));
     }
 
@@ -575,10 +673,10 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
         format!("Notifs: {}", unread),
         if unread > 0 {
             Style::default()
-                .fg(Color::Yellow)
+                .fg(theme.ui.warning)
                 .add_modifier(Modifier::BOLD)
         } else {
-            Style::default().fg(Color::DarkGray)
+            Style::default().fg(theme.ui.muted)
         },
     ));
 
@@ -589,7 +687,7 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
     let padding = area.width.saturating_sub(left_width + hints_width);
 
     spans.push(Span::raw(" ".repeat(padding as usize)));
-    spans.push(Span::styled(hints, Style::default().fg(Color::DarkGray)));
+    spans.push(Span::styled(hints, Style::default().fg(theme.ui.muted)));
 
     let line = Line::from(spans);
     let paragraph = Paragraph::new(line);
@@ -598,6 +696,7 @@ fn render_status_bar(f: &mut Frame, app: &mut MuxApp, area: Rect) {
 
 /// Render the command palette overlay.
 fn render_command_palette(f: &mut Frame, app: &mut MuxApp) {
+    let theme = theme::current();
     let area = f.area();
 
     let palette_width = 70u16.min(area.width.saturating_sub(4));
@@ -621,18 +720,18 @@ fn render_command_palette(f: &mut Frame, app: &mut MuxApp) {
         .title(title)
         .title_style(
             Style::default()
-                .fg(Color::Cyan)
+                .fg(theme.ui.accent)
                 .add_modifier(Modifier::BOLD),
         )
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(Style::default().fg(theme.ui.accent));
 
     let inner_area = block.inner(palette_area);
     f.render_widget(block, palette_area);
 
     // Search input
     let search_area = Rect::new(inner_area.x, inner_area.y, inner_area.width, 1);
-    let search_prefix = Paragraph::new(Span::styled(">", Style::default().fg(Color::Cyan)));
+    let search_prefix = Paragraph::new(Span::styled(">", Style::default().fg(theme.ui.accent)));
     f.render_widget(search_prefix, Rect::new(search_area.x, search_area.y, 2, 1));
     f.render_widget(
         app.command_palette.search_input(),
@@ -679,7 +778,7 @@ fn render_command_palette(f: &mut Frame, app: &mut MuxApp) {
                     lines.push(Line::styled(
                         format!("─ {} ─", text),
                         Style::default()
-                            .fg(Color::DarkGray)
+                            .fg(theme.ui.muted)
                             .add_modifier(Modifier::BOLD),
                     ));
                 }
@@ -694,13 +793,13 @@ fn render_command_palette(f: &mut Frame, app: &mut MuxApp) {
 
                 let style = if is_highlighted {
                     Style::default()
-                        .fg(Color::Cyan)
+                        .fg(theme.ui.accent)
                         .add_modifier(Modifier::BOLD)
                 } else {
                     Style::default()
                 };
 
-                let kb_style = Style::default().fg(Color::Yellow);
+                let kb_style = Style::default().fg(theme.ui.keybinding);
 
                 // Calculate padding for right-aligned keybinding
                 let label = command.label();
@@ -732,7 +831,7 @@ fn render_command_palette(f: &mut Frame, app: &mut MuxApp) {
                 if is_default {
                     label_spans.push(Span::styled(
                         default_suffix,
-                        Style::default().fg(Color::DarkGray),
+                        Style::default().fg(theme.ui.muted),
                     ));
                 }
 
@@ -776,14 +875,12 @@ fn render_command_palette(f: &mut Frame, app: &mut MuxApp) {
     } else {
         "↑↓: navigate │ Enter: execute │ Esc: cancel"
     };
-    let help = Paragraph::new(Line::styled(
-        help_text,
-        Style::default().fg(Color::DarkGray),
-    ));
+    let help = Paragraph::new(Line::styled(help_text, Style::default().fg(theme.ui.muted)));
     f.render_widget(help, help_area);
 }
 
 fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
+    let theme = theme::current();
     let area = f.area();
     let overlay_width = 80u16.min(area.width.saturating_sub(4));
     let overlay_height = 24u16.min(area.height.saturating_sub(4));
@@ -801,11 +898,11 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
         .title(title)
         .title_style(
             Style::default()
-                .fg(Color::Cyan)
+                .fg(theme.ui.accent)
                 .add_modifier(Modifier::BOLD),
         )
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(Style::default().fg(theme.ui.accent));
 
     let inner_area = block.inner(overlay_area);
     f.render_widget(block, overlay_area);
@@ -813,7 +910,7 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
     if app.notifications.items.is_empty() {
         let empty = Paragraph::new("No notifications yet")
             .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::DarkGray));
+            .style(Style::default().fg(theme.ui.muted));
         f.render_widget(empty, inner_area);
         return;
     }
@@ -860,7 +957,7 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
         lines.push(Line::styled(
             format!(" {header} "),
             Style::default()
-                .fg(Color::Magenta)
+                .fg(theme.notifications.title)
                 .add_modifier(Modifier::BOLD),
         ));
 
@@ -875,9 +972,15 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
             selection_counter += 1;
 
             let level_style = match item.level {
-                crate::models::NotificationLevel::Info => Style::default().fg(Color::White),
-                crate::models::NotificationLevel::Warning => Style::default().fg(Color::Yellow),
-                crate::models::NotificationLevel::Error => Style::default().fg(Color::Red),
+                crate::models::NotificationLevel::Info => {
+                    Style::default().fg(theme.notifications.info)
+                }
+                crate::models::NotificationLevel::Warning => {
+                    Style::default().fg(theme.notifications.warning)
+                }
+                crate::models::NotificationLevel::Error => {
+                    Style::default().fg(theme.notifications.error)
+                }
             };
 
             let mut spans = Vec::new();
@@ -887,7 +990,7 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
             let time_label = relative_time_string(item.sent_at);
             spans.push(Span::styled(
                 time_label,
-                Style::default().fg(Color::DarkGray),
+                Style::default().fg(theme.ui.muted),
             ));
             spans.push(Span::raw(" "));
             spans.push(Span::styled(item.message.clone(), level_style));
@@ -896,7 +999,7 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
                     spans.push(Span::raw("  "));
                     spans.push(Span::styled(
                         format!("sandbox {}", label),
-                        Style::default().fg(Color::DarkGray),
+                        Style::default().fg(theme.ui.muted),
                     ));
                 }
             }
@@ -904,14 +1007,14 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
                 spans.push(Span::raw("  "));
                 spans.push(Span::styled(
                     format!("tab: {}", tab_id),
-                    Style::default().fg(Color::DarkGray),
+                    Style::default().fg(theme.ui.muted),
                 ));
             }
             if let Some(read_at) = item.read_at {
                 spans.push(Span::raw("  "));
                 spans.push(Span::styled(
                     format!("read {}", relative_time_string(read_at)),
-                    Style::default().fg(Color::DarkGray),
+                    Style::default().fg(theme.ui.muted),
                 ));
             }
 
@@ -919,7 +1022,7 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
             if is_selected {
                 line = line.style(
                     Style::default()
-                        .bg(Color::DarkGray)
+                        .bg(theme.notifications.selected_bg)
                         .add_modifier(Modifier::BOLD),
                 );
             }
@@ -946,7 +1049,7 @@ fn render_notifications_overlay(f: &mut Frame, app: &mut MuxApp) {
     );
     let help = Paragraph::new(Line::styled(
         "↑↓: navigate │ Enter: open target │ Space/r: mark read │ u: mark unread │ Esc: close",
-        Style::default().fg(Color::DarkGray),
+        Style::default().fg(theme.ui.muted),
     ));
     f.render_widget(help, help_area);
 }
@@ -1027,6 +1130,7 @@ fn sandbox_short_label(app: &MuxApp, sandbox_id: &str) -> Option<String> {
 
 /// Render help overlay showing all keybindings.
 fn render_help_overlay(f: &mut Frame, _app: &MuxApp) {
+    let theme = theme::current();
     let area = f.area();
 
     let help_width = 60u16.min(area.width.saturating_sub(4));
@@ -1042,11 +1146,11 @@ fn render_help_overlay(f: &mut Frame, _app: &MuxApp) {
         .title(" Keyboard Shortcuts ")
         .title_style(
             Style::default()
-                .fg(Color::Cyan)
+                .fg(theme.ui.accent)
                 .add_modifier(Modifier::BOLD),
         )
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(Style::default().fg(theme.ui.accent));
 
     let inner_area = block.inner(help_area);
     f.render_widget(block, help_area);
@@ -1063,7 +1167,7 @@ fn render_help_overlay(f: &mut Frame, _app: &MuxApp) {
             lines.push(Line::styled(
                 category,
                 Style::default()
-                    .fg(Color::Yellow)
+                    .fg(theme.ui.warning)
                     .add_modifier(Modifier::BOLD),
             ));
             current_category = Some(category);
@@ -1076,7 +1180,7 @@ fn render_help_overlay(f: &mut Frame, _app: &MuxApp) {
                 Span::raw("  "),
                 Span::styled(
                     format!("{:<width$}", keybinding, width = kb_width),
-                    Style::default().fg(Color::Cyan),
+                    Style::default().fg(theme.ui.accent),
                 ),
                 Span::raw(cmd.label()),
             ]));
@@ -1089,6 +1193,7 @@ fn render_help_overlay(f: &mut Frame, _app: &MuxApp) {
 
 /// Render tab rename dialog.
 fn render_rename_dialog(f: &mut Frame, app: &MuxApp) {
+    let theme = theme::current();
     let area = f.area();
 
     let dialog_width = 40u16.min(area.width.saturating_sub(4));
@@ -1104,11 +1209,11 @@ fn render_rename_dialog(f: &mut Frame, app: &MuxApp) {
         .title(" Rename Tab ")
         .title_style(
             Style::default()
-                .fg(Color::Cyan)
+                .fg(theme.ui.accent)
                 .add_modifier(Modifier::BOLD),
         )
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(Style::default().fg(theme.ui.accent));
 
     let inner_area = block.inner(dialog_area);
     f.render_widget(block, dialog_area);
@@ -1126,13 +1231,59 @@ fn render_rename_dialog(f: &mut Frame, app: &MuxApp) {
     );
     let help = Paragraph::new(Line::styled(
         "Enter: confirm │ Esc: cancel",
-        Style::default().fg(Color::DarkGray),
+        Style::default().fg(theme.ui.muted),
+    ));
+    f.render_widget(help, help_area);
+}
+
+/// Render the command prompt for a new floating pane.
+fn render_floating_command_dialog(f: &mut Frame, app: &MuxApp) {
+    let theme = theme::current();
+    let area = f.area();
+
+    let dialog_width = 60u16.min(area.width.saturating_sub(4));
+    let dialog_height = 5u16;
+
+    let x = (area.width.saturating_sub(dialog_width)) / 2;
+    let y = (area.height.saturating_sub(dialog_height)) / 2;
+
+    let dialog_area = Rect::new(x, y, dialog_width, dialog_height);
+    f.render_widget(Clear, dialog_area);
+
+    let block = Block::default()
+        .title(" New Floating Pane ")
+        .title_style(
+            Style::default()
+                .fg(theme.ui.accent)
+                .add_modifier(Modifier::BOLD),
+        )
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.ui.accent));
+
+    let inner_area = block.inner(dialog_area);
+    f.render_widget(block, dialog_area);
+
+    if let Some(input) = &app.floating_command_input {
+        let input_area = Rect::new(inner_area.x, inner_area.y + 1, inner_area.width, 1);
+        f.render_widget(input, input_area);
+    }
+
+    let help_area = Rect::new(
+        inner_area.x,
+        inner_area.y + inner_area.height - 1,
+        inner_area.width,
+        1,
+    );
+    let help = Paragraph::new(Line::styled(
+        "Enter: run (empty for shell) │ Esc: cancel",
+        Style::default().fg(theme.ui.muted),
     ));
     f.render_widget(help, help_area);
 }
 
 /// Render the onboarding overlay for Docker image setup.
 fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
+    let theme = theme::current();
     let Some(onboard) = &app.onboard else {
         return;
     };
@@ -1163,9 +1314,9 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
     };
 
     let border_color = match onboard.phase {
-        OnboardPhase::Error => Color::Red,
-        OnboardPhase::DownloadComplete | OnboardPhase::ImageExists => Color::Green,
-        _ => Color::Cyan,
+        OnboardPhase::Error => theme.ui.error,
+        OnboardPhase::DownloadComplete | OnboardPhase::ImageExists => theme.ui.success,
+        _ => theme.ui.accent,
     };
 
     let block = Block::default()
@@ -1192,7 +1343,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
                 % spinner.len();
 
             let text = Paragraph::new(Line::from(vec![
-                Span::styled(spinner[idx], Style::default().fg(Color::Cyan)),
+                Span::styled(spinner[idx], Style::default().fg(theme.ui.accent)),
                 Span::raw(" Checking Docker image..."),
             ]))
             .alignment(Alignment::Center);
@@ -1203,16 +1354,16 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
                 Line::raw(""),
                 Line::styled(
                     "The sandbox Docker image is not installed.",
-                    Style::default().fg(Color::Yellow),
+                    Style::default().fg(theme.ui.warning),
                 ),
                 Line::raw(""),
                 Line::from(vec![
                     Span::raw("Image: "),
-                    Span::styled(&onboard.image_name, Style::default().fg(Color::Cyan)),
+                    Span::styled(&onboard.image_name, Style::default().fg(theme.ui.accent)),
                 ]),
                 Line::from(vec![
                     Span::raw("Size:  "),
-                    Span::styled(onboard.format_size(), Style::default().fg(Color::Cyan)),
+                    Span::styled(onboard.format_size(), Style::default().fg(theme.ui.accent)),
                 ]),
                 Line::raw(""),
                 Line::styled("Would you like to download it now?", Style::default()),
@@ -1222,20 +1373,20 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
             // Render buttons
             let download_style = if onboard.is_download_selected() {
                 Style::default()
-                    .fg(Color::Black)
-                    .bg(Color::Cyan)
+                    .fg(theme.ui.on_accent)
+                    .bg(theme.ui.accent)
                     .add_modifier(Modifier::BOLD)
             } else {
-                Style::default().fg(Color::Cyan)
+                Style::default().fg(theme.ui.accent)
             };
 
             let cancel_style = if !onboard.is_download_selected() {
                 Style::default()
-                    .fg(Color::Black)
-                    .bg(Color::DarkGray)
+                    .fg(theme.ui.on_accent)
+                    .bg(theme.ui.muted)
                     .add_modifier(Modifier::BOLD)
             } else {
-                Style::default().fg(Color::DarkGray)
+                Style::default().fg(theme.ui.muted)
             };
 
             lines.push(Line::from(vec![
@@ -1257,7 +1408,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
             );
             let help = Paragraph::new(Line::styled(
                 "Tab/←→: switch │ Enter: confirm │ Esc: cancel",
-                Style::default().fg(Color::DarkGray),
+                Style::default().fg(theme.ui.muted),
             ))
             .alignment(Alignment::Center);
             f.render_widget(help, help_area);
@@ -1268,7 +1419,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
             lines.push(Line::raw(""));
             lines.push(Line::styled(
                 &onboard.download_status,
-                Style::default().fg(Color::White),
+                Style::default().fg(theme.ui.text),
             ));
             lines.push(Line::raw(""));
 
@@ -1284,7 +1435,10 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
                 onboard.download_progress * 100.0
             );
 
-            lines.push(Line::styled(progress_bar, Style::default().fg(Color::Cyan)));
+            lines.push(Line::styled(
+                progress_bar,
+                Style::default().fg(theme.ui.accent),
+            ));
             lines.push(Line::raw(""));
 
             // Layer progress
@@ -1294,7 +1448,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
                         "Layers: {}/{}",
                         onboard.layers_downloaded, onboard.layers_total
                     ),
-                    Style::default().fg(Color::DarkGray),
+                    Style::default().fg(theme.ui.muted),
                 ));
             }
 
@@ -1308,7 +1462,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
                 "Docker image ready! Starting cmux..."
             };
 
-            let text = Paragraph::new(Line::styled(message, Style::default().fg(Color::Green)))
+            let text = Paragraph::new(Line::styled(message, Style::default().fg(theme.ui.success)))
                 .alignment(Alignment::Center);
             f.render_widget(text, inner_area);
         }
@@ -1318,7 +1472,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
             lines.push(Line::raw(""));
             lines.push(Line::styled(
                 "Failed to set up Docker image:",
-                Style::default().fg(Color::Red),
+                Style::default().fg(theme.ui.error),
             ));
             lines.push(Line::raw(""));
 
@@ -1328,7 +1482,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
                 for chunk in error.chars().collect::<Vec<_>>().chunks(max_width) {
                     lines.push(Line::styled(
                         chunk.iter().collect::<String>(),
-                        Style::default().fg(Color::White),
+                        Style::default().fg(theme.ui.text),
                     ));
                 }
             }
@@ -1336,7 +1490,7 @@ fn render_onboard_overlay(f: &mut Frame, app: &MuxApp) {
             lines.push(Line::raw(""));
             lines.push(Line::styled(
                 "Press Esc to exit or Enter to retry",
-                Style::default().fg(Color::DarkGray),
+                Style::default().fg(theme.ui.muted),
             ));
 
             let text = Paragraph::new(lines).alignment(Alignment::Center);
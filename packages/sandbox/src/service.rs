@@ -1,7 +1,7 @@
 use crate::errors::SandboxResult;
 use crate::models::{
     AwaitReadyRequest, AwaitReadyResponse, CreateSandboxRequest, ExecRequest, ExecResponse,
-    GhResponse, HostEvent, PruneRequest, PruneResponse, SandboxSummary,
+    GhResponse, HostEvent, PruneRequest, PruneResponse, SandboxStats, SandboxSummary,
 };
 use crate::notifications::NotificationStore;
 use async_trait::async_trait;
@@ -36,6 +36,8 @@ pub trait SandboxService: Send + Sync + 'static {
     async fn create(&self, request: CreateSandboxRequest) -> SandboxResult<SandboxSummary>;
     async fn list(&self) -> SandboxResult<Vec<SandboxSummary>>;
     async fn get(&self, id: String) -> SandboxResult<Option<SandboxSummary>>;
+    /// Sample CPU and memory usage of the processes inside a sandbox.
+    async fn stats(&self, id: String) -> SandboxResult<SandboxStats>;
     async fn exec(&self, id: String, exec: ExecRequest) -> SandboxResult<ExecResponse>;
     async fn attach(
         &self,
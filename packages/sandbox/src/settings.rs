@@ -5,6 +5,7 @@
 //! - Linux: `~/.config/cmux/settings.json`
 //! - Windows: `%APPDATA%/cmux/settings.json`
 
+use crate::mux::desktop_notify::DesktopNotificationSettings;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
@@ -133,6 +134,13 @@ pub struct Settings {
     /// Default editor for opening sandboxes.
     #[serde(default)]
     pub default_editor: EditorChoice,
+    /// Color theme name (`auto`, a built-in theme, or a custom theme file).
+    /// `None` means `auto`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+    /// Rules for forwarding notifications and bells to the desktop.
+    #[serde(default)]
+    pub desktop_notifications: DesktopNotificationSettings,
 }
 
 impl Settings {
@@ -142,7 +150,7 @@ impl Settings {
     }
 
     /// Load settings from disk, falling back to defaults if not found or invalid.
-    /// Also checks DMUX_EDITOR env var which takes precedence over saved settings.
+    /// Also checks DMUX_EDITOR and DMUX_THEME env vars which take precedence over saved settings.
     pub fn load() -> Self {
         // First, try loading from file
         let mut settings = Self::load_from_file();
@@ -151,6 +159,9 @@ impl Settings {
         if let Ok(val) = std::env::var("DMUX_EDITOR") {
             settings.default_editor = EditorChoice::from_str_loose(&val);
         }
+        if let Ok(val) = std::env::var("DMUX_THEME") {
+            settings.theme = Some(val);
+        }
 
         settings
     }
@@ -191,6 +202,11 @@ impl Settings {
         Ok(())
     }
 
+    /// Name of the configured theme, defaulting to `auto`.
+    pub fn theme_name(&self) -> &str {
+        self.theme.as_deref().unwrap_or(crate::theme::AUTO_THEME)
+    }
+
     /// Get the path where settings are stored (for display to user).
     pub fn settings_path() -> Option<String> {
         Self::path().map(|p| p.to_string_lossy().to_string())
@@ -223,12 +239,19 @@ mod tests {
     fn settings_serialization() {
         let settings = Settings {
             default_editor: EditorChoice::Zed,
+            theme: Some("light".to_string()),
+            ..Settings::default()
         };
 
         let json = serde_json::to_string_pretty(&settings).unwrap();
         let parsed: Settings = serde_json::from_str(&json).unwrap();
 
         assert_eq!(settings.default_editor, parsed.default_editor);
+        assert_eq!(parsed.theme_name(), "light");
+
+        // Settings files written before themes existed default to auto
+        let parsed: Settings = serde_json::from_str(r#"{"default_editor":"zed"}"#).unwrap();
+        assert_eq!(parsed.theme_name(), "auto");
     }
 
     #[test]
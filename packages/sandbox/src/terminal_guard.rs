@@ -124,6 +124,7 @@ pub fn restore_terminal() {
     let _ = stdout.write_all(b"\x1b[?1002l"); // Disable button-event mouse
     let _ = stdout.write_all(b"\x1b[?1003l"); // Disable any-event mouse
     let _ = stdout.write_all(b"\x1b[?1006l"); // Disable SGR extended mouse
+    let _ = stdout.write_all(b"\x1b[?1004l"); // Disable focus reporting
     let _ = stdout.write_all(b"\x1b[?2004l"); // Disable bracketed paste
     let _ = stdout.write_all(b"\x1b[?1049l"); // Leave alternate screen
     let _ = stdout.write_all(b"\x1b[?25h"); // Show cursor
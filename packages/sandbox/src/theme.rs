@@ -0,0 +1,680 @@
+//! Color themes for the mux and chat TUIs.
+//!
+//! Three themes are built in: `dark`, `light` and `high-contrast`. Custom themes
+//! are JSON or TOML files in the `themes` directory next to the settings file,
+//! e.g. `~/.config/cmux/themes/solarized.json` on Linux. A theme file only needs the
+//! colors it changes; everything else comes from the built-in theme named by
+//! `base` (default `dark`):
+//!
+//! ```json
+//! {
+//!   "base": "light",
+//!   "ui": { "accent": "#005f87" },
+//!   "borders": { "focused": "magenta" },
+//!   "palette": ["#073642", "#dc322f", "#859900", "#b58900", "#268bd2", "#d33682",
+//!               "#2aa198", "#eee8d5", "#002b36", "#cb4b16", "#586e75", "#657b83",
+//!               "#839496", "#6c71c4", "#93a1a1", "#fdf6e3"]
+//! }
+//! ```
+//!
+//! The same theme as `solarized.toml`:
+//!
+//! ```toml
+//! base = "light"
+//! palette = ["#073642", "#dc322f", "#859900", "#b58900", "#268bd2", "#d33682",
+//!            "#2aa198", "#eee8d5", "#002b36", "#cb4b16", "#586e75", "#657b83",
+//!            "#839496", "#6c71c4", "#93a1a1", "#fdf6e3"]
+//!
+//! [ui]
+//! accent = "#005f87"
+//!
+//! [borders]
+//! focused = "magenta"
+//! ```
+//!
+//! Colors are ratatui color names (`cyan`, `dark-gray`), `#rrggbb` or a
+//! 256-color index. The optional `palette` replaces the 16 ANSI colors used
+//! inside terminal panes.
+//!
+//! The `auto` theme (the default) picks `dark` or `light` from the terminal
+//! background.
+
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, RwLock};
+
+const APP_NAME: &str = "cmux";
+const THEMES_DIR: &str = "themes";
+/// Custom theme file extensions, in lookup order.
+const THEME_EXTENSIONS: &[&str] = &["json", "toml"];
+
+/// Theme name that follows the terminal background.
+pub const AUTO_THEME: &str = "auto";
+
+/// Names of the built-in themes.
+pub const BUILTIN_THEMES: &[&str] = &["dark", "light", "high-contrast"];
+
+/// RGB values for the 16 ANSI colors (0-7 normal, 8-15 bright).
+pub type AnsiPalette = [(u8, u8, u8); 16];
+
+/// Colors for general UI chrome: titles, hints, status line, dialogs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UiColors {
+    #[serde(with = "color_serde")]
+    pub accent: Color,
+    /// Text drawn on top of an accent-colored background.
+    #[serde(with = "color_serde")]
+    pub on_accent: Color,
+    #[serde(with = "color_serde")]
+    pub text: Color,
+    #[serde(with = "color_serde")]
+    pub muted: Color,
+    #[serde(with = "color_serde")]
+    pub highlight: Color,
+    #[serde(with = "color_serde")]
+    pub keybinding: Color,
+    #[serde(with = "color_serde")]
+    pub success: Color,
+    #[serde(with = "color_serde")]
+    pub warning: Color,
+    #[serde(with = "color_serde")]
+    pub error: Color,
+}
+
+/// Pane and panel border colors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BorderColors {
+    /// Active pane or panel while it has keyboard focus.
+    #[serde(with = "color_serde")]
+    pub focused: Color,
+    /// Active pane while focus is elsewhere (e.g. the sidebar).
+    #[serde(with = "color_serde")]
+    pub active: Color,
+    #[serde(with = "color_serde")]
+    pub inactive: Color,
+}
+
+/// Sidebar selection and sandbox status colors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SidebarColors {
+    #[serde(with = "color_serde")]
+    pub selected: Color,
+    #[serde(with = "color_serde")]
+    pub creating: Color,
+    #[serde(with = "color_serde")]
+    pub running: Color,
+    #[serde(with = "color_serde")]
+    pub exited: Color,
+    #[serde(with = "color_serde")]
+    pub failed: Color,
+    #[serde(with = "color_serde")]
+    pub unknown: Color,
+}
+
+/// Notification panel colors by level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotificationColors {
+    #[serde(with = "color_serde")]
+    pub title: Color,
+    #[serde(with = "color_serde")]
+    pub info: Color,
+    #[serde(with = "color_serde")]
+    pub warning: Color,
+    #[serde(with = "color_serde")]
+    pub error: Color,
+    /// Background of the selected notification.
+    #[serde(with = "color_serde")]
+    pub selected_bg: Color,
+}
+
+/// Chat TUI colors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatColors {
+    #[serde(with = "color_serde")]
+    pub user_message_bg: Color,
+    #[serde(with = "color_serde")]
+    pub model: Color,
+}
+
+/// A complete color theme.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+    #[serde(default)]
+    pub name: String,
+    pub ui: UiColors,
+    pub borders: BorderColors,
+    pub sidebar: SidebarColors,
+    pub notifications: NotificationColors,
+    pub chat: ChatColors,
+    /// Optional override for the 16 ANSI colors inside terminal panes.
+    #[serde(default, with = "palette_serde")]
+    pub palette: Option<AnsiPalette>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    /// The default theme for dark terminals.
+    pub fn dark() -> Self {
+        Self {
+            name: "dark".to_string(),
+            ui: UiColors {
+                accent: Color::Cyan,
+                on_accent: Color::Black,
+                text: Color::White,
+                muted: Color::DarkGray,
+                highlight: Color::Magenta,
+                keybinding: Color::Yellow,
+                success: Color::Green,
+                warning: Color::Yellow,
+                error: Color::Red,
+            },
+            borders: BorderColors {
+                focused: Color::Cyan,
+                active: Color::White,
+                inactive: Color::DarkGray,
+            },
+            sidebar: SidebarColors {
+                selected: Color::Cyan,
+                creating: Color::Cyan,
+                running: Color::Green,
+                exited: Color::DarkGray,
+                failed: Color::Red,
+                unknown: Color::Yellow,
+            },
+            notifications: NotificationColors {
+                title: Color::Magenta,
+                info: Color::White,
+                warning: Color::Yellow,
+                error: Color::Red,
+                selected_bg: Color::DarkGray,
+            },
+            chat: ChatColors {
+                user_message_bg: Color::Rgb(60, 60, 60),
+                model: Color::Magenta,
+            },
+            palette: None,
+        }
+    }
+
+    /// The default theme for light terminals.
+    pub fn light() -> Self {
+        Self {
+            name: "light".to_string(),
+            ui: UiColors {
+                accent: Color::Blue,
+                on_accent: Color::White,
+                text: Color::Black,
+                muted: Color::Gray,
+                highlight: Color::Magenta,
+                keybinding: Color::Rgb(150, 95, 0),
+                success: Color::Rgb(0, 128, 0),
+                warning: Color::Rgb(150, 95, 0),
+                error: Color::Red,
+            },
+            borders: BorderColors {
+                focused: Color::Blue,
+                active: Color::Black,
+                inactive: Color::Gray,
+            },
+            sidebar: SidebarColors {
+                selected: Color::Blue,
+                creating: Color::Blue,
+                running: Color::Rgb(0, 128, 0),
+                exited: Color::Gray,
+                failed: Color::Red,
+                unknown: Color::Rgb(150, 95, 0),
+            },
+            notifications: NotificationColors {
+                title: Color::Magenta,
+                info: Color::Black,
+                warning: Color::Rgb(150, 95, 0),
+                error: Color::Red,
+                selected_bg: Color::Rgb(210, 210, 210),
+            },
+            chat: ChatColors {
+                user_message_bg: Color::Rgb(230, 230, 230),
+                model: Color::Magenta,
+            },
+            palette: None,
+        }
+    }
+
+    /// Maximum contrast on a dark background, using bright colors only.
+    pub fn high_contrast() -> Self {
+        Self {
+            name: "high-contrast".to_string(),
+            ui: UiColors {
+                accent: Color::LightCyan,
+                on_accent: Color::Black,
+                text: Color::White,
+                muted: Color::Gray,
+                highlight: Color::LightMagenta,
+                keybinding: Color::LightYellow,
+                success: Color::LightGreen,
+                warning: Color::LightYellow,
+                error: Color::LightRed,
+            },
+            borders: BorderColors {
+                focused: Color::LightYellow,
+                active: Color::White,
+                inactive: Color::Gray,
+            },
+            sidebar: SidebarColors {
+                selected: Color::LightYellow,
+                creating: Color::LightCyan,
+                running: Color::LightGreen,
+                exited: Color::Gray,
+                failed: Color::LightRed,
+                unknown: Color::LightYellow,
+            },
+            notifications: NotificationColors {
+                title: Color::LightMagenta,
+                info: Color::White,
+                warning: Color::LightYellow,
+                error: Color::LightRed,
+                selected_bg: Color::Blue,
+            },
+            chat: ChatColors {
+                user_message_bg: Color::Rgb(40, 40, 90),
+                model: Color::LightMagenta,
+            },
+            palette: None,
+        }
+    }
+
+    /// Look up a built-in theme by name.
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+
+    /// Parse a JSON theme file, filling missing colors from its `base` theme.
+    pub fn from_json(name: &str, contents: &str) -> Result<Self, String> {
+        let overrides: serde_json::Value =
+            serde_json::from_str(contents).map_err(|e| format!("Invalid theme JSON: {}", e))?;
+        Self::from_overrides(name, overrides)
+    }
+
+    /// Parse a TOML theme file, filling missing colors from its `base` theme.
+    pub fn from_toml(name: &str, contents: &str) -> Result<Self, String> {
+        let table: toml::Table =
+            toml::from_str(contents).map_err(|e| format!("Invalid theme TOML: {}", e))?;
+        let overrides =
+            serde_json::to_value(table).map_err(|e| format!("Invalid theme TOML: {}", e))?;
+        Self::from_overrides(name, overrides)
+    }
+
+    fn from_overrides(name: &str, overrides: serde_json::Value) -> Result<Self, String> {
+        let base_name = overrides
+            .get("base")
+            .and_then(|base| base.as_str())
+            .unwrap_or("dark");
+        let base = Self::builtin(base_name)
+            .ok_or_else(|| format!("Unknown base theme '{}'", base_name))?;
+
+        let mut merged = serde_json::to_value(&base)
+            .map_err(|e| format!("Failed to serialize base theme: {}", e))?;
+        merge_json(&mut merged, overrides);
+
+        let mut theme: Theme =
+            serde_json::from_value(merged).map_err(|e| format!("Invalid theme: {}", e))?;
+        theme.name = name.to_string();
+        Ok(theme)
+    }
+}
+
+/// Recursively overlay `overrides` onto `base`. Objects merge key by key;
+/// anything else replaces the base value.
+fn merge_json(base: &mut serde_json::Value, overrides: serde_json::Value) {
+    match (base, overrides) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// Directory holding custom theme files.
+pub fn themes_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_NAME).join(THEMES_DIR))
+}
+
+/// Names of all selectable themes: built-ins first, then custom theme files.
+pub fn available_themes() -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN_THEMES.iter().map(|s| s.to_string()).collect();
+    let mut custom: Vec<String> = themes_dir()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| THEME_EXTENSIONS.iter().any(|known| ext == *known))
+        })
+        .filter_map(|path| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .filter(|name| !BUILTIN_THEMES.contains(&name.as_str()))
+        .collect();
+    custom.sort();
+    custom.dedup();
+    names.extend(custom);
+    names
+}
+
+/// Resolve a theme by name: `auto`, a built-in, or a custom theme file.
+pub fn load_theme(name: &str) -> Result<Theme, String> {
+    if name == AUTO_THEME {
+        return Ok(if dark_background() {
+            Theme::dark()
+        } else {
+            Theme::light()
+        });
+    }
+    if let Some(theme) = Theme::builtin(name) {
+        return Ok(theme);
+    }
+
+    let dir = themes_dir().ok_or_else(|| "Could not determine config directory".to_string())?;
+    for ext in THEME_EXTENSIONS {
+        let path = dir.join(format!("{}.{}", name, ext));
+        if !path.exists() {
+            continue;
+        }
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read theme {}: {}", path.display(), e))?;
+        return match *ext {
+            "toml" => Theme::from_toml(name, &contents),
+            _ => Theme::from_json(name, &contents),
+        };
+    }
+    Err(format!(
+        "Theme '{}' not found in {} (expected {}.json or {}.toml)",
+        name,
+        dir.display(),
+        name,
+        name
+    ))
+}
+
+static CURRENT_THEME: LazyLock<RwLock<Arc<Theme>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(Theme::dark())));
+static THEME_GENERATION: AtomicU64 = AtomicU64::new(0);
+static DARK_BACKGROUND: AtomicBool = AtomicBool::new(true);
+
+/// Get the active theme.
+pub fn current() -> Arc<Theme> {
+    CURRENT_THEME
+        .read()
+        .map(|theme| theme.clone())
+        .unwrap_or_else(|_| Arc::new(Theme::dark()))
+}
+
+/// Counter bumped on every theme change, so caches can tell when to refresh.
+pub fn generation() -> u64 {
+    THEME_GENERATION.load(Ordering::SeqCst)
+}
+
+/// Make a theme the active theme.
+pub fn set_current(theme: Theme) {
+    if let Ok(mut current) = CURRENT_THEME.write() {
+        *current = Arc::new(theme);
+    }
+    THEME_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Load a theme by name and make it active.
+pub fn apply_theme(name: &str) -> Result<(), String> {
+    set_current(load_theme(name)?);
+    Ok(())
+}
+
+/// Theme that follows `current` when cycling: `auto`, built-ins, then custom themes.
+pub fn next_theme_name(current: &str) -> String {
+    let mut names = vec![AUTO_THEME.to_string()];
+    names.extend(available_themes());
+    let next = names
+        .iter()
+        .position(|name| name == current)
+        .map_or(0, |index| (index + 1) % names.len());
+    names.swap_remove(next)
+}
+
+/// Apply the configured theme at startup.
+///
+/// For `auto`, a known terminal background is used directly; otherwise the
+/// terminal is queried, so this must run before raw mode and the alternate
+/// screen are entered.
+pub fn init(name: &str, background: Option<(u8, u8, u8)>) {
+    if name == AUTO_THEME {
+        match background {
+            Some(rgb) => set_background(rgb),
+            None => {
+                detect_background();
+            }
+        }
+    }
+    if let Err(e) = apply_theme(name) {
+        tracing::warn!("Failed to load theme '{}': {}", name, e);
+    }
+}
+
+/// Whether `auto` currently resolves to the dark theme.
+pub fn dark_background() -> bool {
+    DARK_BACKGROUND.load(Ordering::SeqCst)
+}
+
+/// Record the terminal background color (e.g. from an OSC 11 query).
+pub fn set_background(rgb: (u8, u8, u8)) {
+    DARK_BACKGROUND.store(is_dark(rgb), Ordering::SeqCst);
+}
+
+/// Detect the terminal background with terminal-light.
+///
+/// This queries the terminal, so it must run before raw mode and the
+/// alternate screen are entered.
+pub fn detect_background() -> bool {
+    let dark = match terminal_light::luma() {
+        Ok(luma) => luma <= 0.5,
+        Err(_) => true, // Default to dark if detection fails
+    };
+    DARK_BACKGROUND.store(dark, Ordering::SeqCst);
+    dark
+}
+
+/// Check whether an RGB background counts as dark (relative luminance).
+pub fn is_dark((r, g, b): (u8, u8, u8)) -> bool {
+    let luma = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+    luma / 255.0 <= 0.5
+}
+
+/// Map a ratatui ANSI color (named or indexed 0-15) to its palette slot.
+pub fn ansi_index(color: Color) -> Option<usize> {
+    let index = match color {
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+        Color::Indexed(n) if n < 16 => n as usize,
+        _ => return None,
+    };
+    Some(index)
+}
+
+/// Serialize colors as strings (`cyan`, `#rrggbb`, `42`).
+mod color_serde {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&color.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Color::from_str(&value)
+            .map_err(|_| serde::de::Error::custom(format!("invalid color '{}'", value)))
+    }
+}
+
+/// Serialize the ANSI palette as a list of 16 `#rrggbb` strings.
+mod palette_serde {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        palette: &Option<AnsiPalette>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match palette {
+            Some(palette) => serializer.collect_seq(
+                palette
+                    .iter()
+                    .map(|(r, g, b)| format!("#{:02x}{:02x}{:02x}", r, g, b)),
+            ),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<AnsiPalette>, D::Error> {
+        let Some(values) = Option::<Vec<String>>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        if values.len() != 16 {
+            return Err(serde::de::Error::custom(format!(
+                "palette must have 16 colors, got {}",
+                values.len()
+            )));
+        }
+        let mut palette = [(0, 0, 0); 16];
+        for (slot, value) in palette.iter_mut().zip(&values) {
+            match Color::from_str(value) {
+                Ok(Color::Rgb(r, g, b)) => *slot = (r, g, b),
+                _ => {
+                    return Err(serde::de::Error::custom(format!(
+                        "palette colors must be #rrggbb, got '{}'",
+                        value
+                    )))
+                }
+            }
+        }
+        Ok(Some(palette))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_themes_round_trip() {
+        for name in BUILTIN_THEMES {
+            let theme = Theme::builtin(name).expect("builtin theme");
+            let json = serde_json::to_string(&theme).unwrap();
+            let parsed: Theme = serde_json::from_str(&json).unwrap();
+            assert_eq!(theme, parsed);
+        }
+    }
+
+    #[test]
+    fn theme_file_overrides_base() {
+        let json = r##"{
+            "base": "light",
+            "ui": { "accent": "#005f87" },
+            "borders": { "focused": "light-magenta" }
+        }"##;
+        let theme = Theme::from_json("custom", json).unwrap();
+        assert_eq!(theme.name, "custom");
+        assert_eq!(theme.ui.accent, Color::Rgb(0, 0x5f, 0x87));
+        assert_eq!(theme.borders.focused, Color::LightMagenta);
+        // Untouched colors come from the base theme
+        assert_eq!(theme.ui.text, Theme::light().ui.text);
+        assert_eq!(theme.sidebar, Theme::light().sidebar);
+        assert!(theme.palette.is_none());
+    }
+
+    #[test]
+    fn theme_file_palette_is_validated() {
+        let colors: Vec<String> = (0..16).map(|i| format!("\"#0000{:02x}\"", i)).collect();
+        let json = format!(r#"{{ "palette": [{}] }}"#, colors.join(","));
+        let theme = Theme::from_json("palette", &json).unwrap();
+        let palette = theme.palette.expect("palette");
+        assert_eq!(palette[15], (0, 0, 15));
+
+        assert!(Theme::from_json("short", r##"{ "palette": ["#000000"] }"##).is_err());
+        assert!(Theme::from_json("bad", r#"{ "ui": { "accent": "not-a-color" } }"#).is_err());
+        assert!(Theme::from_json("base", r#"{ "base": "nope" }"#).is_err());
+    }
+
+    #[test]
+    fn toml_theme_matches_json() {
+        let toml = r##"
+base = "light"
+
+[ui]
+accent = "#005f87"
+"##;
+        let from_toml = Theme::from_toml("custom", toml).unwrap();
+        let from_json = Theme::from_json(
+            "custom",
+            r##"{ "base": "light", "ui": { "accent": "#005f87" } }"##,
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(&from_toml).unwrap(),
+            serde_json::to_value(&from_json).unwrap()
+        );
+        assert!(Theme::from_toml("bad", "ui = [").is_err());
+    }
+
+    #[test]
+    fn next_theme_cycles_through_builtins() {
+        assert_eq!(next_theme_name(AUTO_THEME), "dark");
+        assert_eq!(next_theme_name("dark"), "light");
+        assert_eq!(next_theme_name("light"), "high-contrast");
+        assert_eq!(next_theme_name("no-such-theme"), AUTO_THEME);
+    }
+
+    #[test]
+    fn background_luma_picks_variant() {
+        assert!(is_dark((53, 55, 49)));
+        assert!(!is_dark((250, 250, 250)));
+        assert_eq!(ansi_index(Color::LightBlue), Some(12));
+        assert_eq!(ansi_index(Color::Indexed(3)), Some(3));
+        assert_eq!(ansi_index(Color::Indexed(42)), None);
+        assert_eq!(ansi_index(Color::Reset), None);
+    }
+}
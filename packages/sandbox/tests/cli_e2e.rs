@@ -3,7 +3,8 @@ use axum::body::Body;
 use axum::Router;
 use cmux_sandbox::build_router;
 use cmux_sandbox::models::{
-    CreateSandboxRequest, ExecRequest, ExecResponse, SandboxNetwork, SandboxStatus, SandboxSummary,
+    CreateSandboxRequest, ExecRequest, ExecResponse, SandboxNetwork, SandboxStats, SandboxStatus,
+    SandboxSummary,
 };
 use cmux_sandbox::notifications::NotificationStore;
 use cmux_sandbox::service::SandboxService;
@@ -92,6 +93,16 @@ impl SandboxService for MockService {
         Ok(guard.iter().find(|s| s.id.to_string() == id).cloned())
     }
 
+    async fn stats(&self, id: String) -> cmux_sandbox::errors::SandboxResult<SandboxStats> {
+        self.record("stats").await;
+        Ok(SandboxStats {
+            id: Uuid::parse_str(&id).unwrap_or_default(),
+            cpu_time_ms: 1_500,
+            memory_bytes: 64 * 1024 * 1024,
+            process_count: 3,
+        })
+    }
+
     async fn exec(
         &self,
         _id: String,
@@ -1,5 +1,6 @@
 import * as vscode from 'vscode';
 import { randomUUID } from 'node:crypto';
+import { readFileSync } from 'node:fs';
 
 // =============================================================================
 // Types
@@ -73,6 +74,37 @@ function getConfig() {
   };
 }
 
+/** API token cmux-pty requires, read from the configured token file */
+function readPtyToken(): string | undefined {
+  const tokenFile = vscode.workspace
+    .getConfiguration('cmux')
+    .get<string>('ptyTokenFile', '/run/cmux-pty/token');
+  if (!tokenFile) return undefined;
+  try {
+    return readFileSync(tokenFile, 'utf8').trim() || undefined;
+  } catch (error) {
+    // No token file just means the server runs without auth
+    if ((error as NodeJS.ErrnoException).code !== 'ENOENT') {
+      console.error(`[cmux] Failed to read PTY token from ${tokenFile}:`, error);
+    }
+    return undefined;
+  }
+}
+
+/** Headers for cmux-pty HTTP requests, including the API token if there is one */
+function ptyHeaders(headers: Record<string, string> = {}): Record<string, string> {
+  const token = readPtyToken();
+  return token ? { ...headers, Authorization: `Bearer ${token}` } : headers;
+}
+
+/** WebSockets can't set headers, so the token goes in the query string */
+function withPtyToken(url: string): string {
+  const token = readPtyToken();
+  if (!token) return url;
+  const separator = url.includes('?') ? '&' : '?';
+  return `${url}${separator}token=${encodeURIComponent(token)}`;
+}
+
 const RESTORE_FALLBACK_DELAY_MS = 1500;
 const RECONCILE_INTERVAL_MS = 15000;
 
@@ -151,7 +183,7 @@ class CmuxPseudoterminal implements vscode.Pseudoterminal {
     const wsUrl = this.serverUrl.replace(/^http/, 'ws');
     const fullUrl = `${wsUrl}/sessions/${this.ptyId}/ws`;
     console.log(`[cmux] CmuxPseudoterminal connecting to: ${fullUrl}`);
-    const ws = new WebSocket(fullUrl);
+    const ws = new WebSocket(withPtyToken(fullUrl));
     this._ws = ws;
 
     ws.onopen = () => {
@@ -384,7 +416,7 @@ class PtyClient {
       const wsUrl = this.serverUrl.replace(/^http/, 'ws');
       const fullUrl = `${wsUrl}/ws`;
       console.log('[cmux] PtyClient connecting to:', fullUrl);
-      this._ws = new WebSocket(fullUrl);
+      this._ws = new WebSocket(withPtyToken(fullUrl));
 
       // Increased timeout to 20s for Docker containers where the PTY server
       // may take longer to start (especially during initial container boot)
@@ -702,7 +734,7 @@ class CmuxTerminalManager {
     try {
       const config = getConfig();
       const response = await fetch(`${config.serverUrl}/sessions`, {
-        headers: { Accept: 'application/json' },
+        headers: ptyHeaders({ Accept: 'application/json' }),
       });
       if (!response.ok) {
         console.warn(
@@ -951,7 +983,10 @@ class CmuxTerminalManager {
           }
           try {
             console.log(`[cmux] Deleting PTY ${info.id} on server`);
-            await fetch(`${config.serverUrl}/sessions/${info.id}`, { method: 'DELETE' });
+            await fetch(`${config.serverUrl}/sessions/${info.id}`, {
+              method: 'DELETE',
+              headers: ptyHeaders(),
+            });
           } catch (err) {
             console.error(`[cmux] Failed to delete PTY ${info.id}:`, err);
           }
@@ -978,7 +1013,7 @@ class CmuxTerminalManager {
       // Create PTY via HTTP POST with our client ID
       const response = await fetch(`${config.serverUrl}/sessions`, {
         method: 'POST',
-        headers: { 'Content-Type': 'application/json' },
+        headers: ptyHeaders({ 'Content-Type': 'application/json' }),
         body: JSON.stringify({
           shell: config.defaultShell,
           cwd: cwd,
@@ -1206,7 +1241,10 @@ class CmuxTerminalManager {
           }
           try {
             console.log(`[cmux] Deleting PTY ${pending.id} on server`);
-            await fetch(`${config.serverUrl}/sessions/${pending.id}`, { method: 'DELETE' });
+            await fetch(`${config.serverUrl}/sessions/${pending.id}`, {
+              method: 'DELETE',
+              headers: ptyHeaders(),
+            });
           } catch (err) {
             console.error(`[cmux] Failed to delete PTY ${pending.id}:`, err);
           }